@@ -0,0 +1,16 @@
+-- Outstanding quotes, so reservations survive a restart
+CREATE TABLE IF NOT EXISTS quotes (
+    quote_id TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL,
+    swap_id BLOB NOT NULL UNIQUE,
+    direction TEXT NOT NULL CHECK (direction IN ('usdc_to_xmr', 'xmr_to_usdc')),
+    usdc_amount INTEGER NOT NULL,
+    xmr_amount INTEGER NOT NULL,
+    secret_hash BLOB NOT NULL,
+    monero_sub_address TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_quotes_client_id ON quotes(client_id);
+CREATE INDEX IF NOT EXISTS idx_quotes_expires_at ON quotes(expires_at);
@@ -0,0 +1,4 @@
+-- Alice's payout destination: a Monero address (USDC -> XMR) or a Solana
+-- address (XMR -> USDC)
+ALTER TABLE quotes ADD COLUMN destination TEXT;
+ALTER TABLE swaps ADD COLUMN destination TEXT;
@@ -0,0 +1,3 @@
+-- Subaddress index of each swap's Monero deposit address, for matching incoming transfers
+ALTER TABLE quotes ADD COLUMN monero_subaddr_index INTEGER;
+ALTER TABLE swaps ADD COLUMN monero_subaddr_index INTEGER;
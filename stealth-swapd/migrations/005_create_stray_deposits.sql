@@ -0,0 +1,36 @@
+-- XMR received that does not (or no longer) counts towards a swap: short or
+-- excess payments, deposits after expiry, and transfers to retired addresses
+CREATE TABLE IF NOT EXISTS stray_deposits (
+    id TEXT PRIMARY KEY,
+    -- One row per swap and reason, or per transaction for unmatched deposits
+    dedup_key TEXT NOT NULL UNIQUE,
+    swap_id BLOB,
+    subaddr_index INTEGER NOT NULL,
+    txids TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL
+        CHECK (reason IN ('underpaid', 'overpaid', 'late', 'unmatched')),
+    status TEXT NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'refunding', 'refunded', 'credited')),
+    refund_address TEXT,
+    refund_txid TEXT,
+    credited_swap_id BLOB,
+    detected_at DATETIME NOT NULL,
+    resolved_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_stray_deposits_status ON stray_deposits(status);
+CREATE INDEX IF NOT EXISTS idx_stray_deposits_credited ON stray_deposits(credited_swap_id);
+
+-- Append-only log of everything that happens to a stray deposit
+CREATE TABLE IF NOT EXISTS stray_deposit_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    deposit_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    detail TEXT,
+    created_at DATETIME NOT NULL,
+    FOREIGN KEY (deposit_id) REFERENCES stray_deposits(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_stray_deposit_audit_deposit ON stray_deposit_audit(deposit_id);
@@ -0,0 +1,22 @@
+-- Chain events a swap advanced on, kept until they are final so a reorg
+-- that drops one can roll the swap back
+CREATE TABLE IF NOT EXISTS chain_observations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    swap_id BLOB NOT NULL,
+    chain TEXT NOT NULL CHECK (chain IN ('monero', 'solana')),
+    -- Monero txid or Solana transaction signature
+    reference TEXT NOT NULL,
+    height INTEGER NOT NULL,
+    block_hash TEXT NOT NULL,
+    advanced_to TEXT NOT NULL,
+    rollback_to TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'final', 'invalidated')),
+    invalidated_reason TEXT,
+    observed_at DATETIME NOT NULL,
+    verified_at DATETIME,
+    UNIQUE (swap_id, chain, reference)
+);
+
+CREATE INDEX IF NOT EXISTS idx_chain_observations_status ON chain_observations(status);
+CREATE INDEX IF NOT EXISTS idx_chain_observations_swap ON chain_observations(swap_id);
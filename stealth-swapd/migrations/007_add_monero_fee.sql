@@ -0,0 +1,2 @@
+-- Fee paid by each swap's Monero payout, in piconero, for P&L
+ALTER TABLE swaps ADD COLUMN monero_fee INTEGER;
@@ -0,0 +1,20 @@
+-- XMR payouts, written before the wallet builds the transaction and
+-- advanced as it is built and relayed, so a restart never pays twice
+CREATE TABLE IF NOT EXISTS payout_outbox (
+    swap_id BLOB PRIMARY KEY,
+    destination TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    state TEXT NOT NULL DEFAULT 'intent'
+        CHECK (state IN ('intent', 'built', 'relayed', 'confirmed')),
+    tx_hash TEXT,
+    tx_key TEXT,
+    -- Signed transaction, for resubmitting straight to a daemon
+    tx_blob TEXT,
+    -- Wallet metadata that relay_tx takes
+    tx_metadata TEXT,
+    fee INTEGER,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_payout_outbox_state ON payout_outbox(state);
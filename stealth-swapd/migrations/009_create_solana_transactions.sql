@@ -0,0 +1,24 @@
+-- Signed Solana transactions, written before they are first sent and
+-- rebroadcast until confirmed, failed or expired
+CREATE TABLE IF NOT EXISTS solana_transactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    swap_id BLOB NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('refund', 'redeem', 'record_proof')),
+    signature TEXT NOT NULL UNIQUE,
+    tx_base64 TEXT NOT NULL,
+    blockhash TEXT NOT NULL,
+    -- NULL for durable nonce transactions, which expire only when the
+    -- nonce advances
+    last_valid_block_height INTEGER,
+    nonce_account TEXT,
+    state TEXT NOT NULL DEFAULT 'pending'
+        CHECK (state IN ('pending', 'confirmed', 'expired', 'failed')),
+    error TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    confirmed_slot INTEGER,
+    created_at DATETIME NOT NULL,
+    last_sent_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_solana_transactions_swap ON solana_transactions(swap_id, kind);
+CREATE INDEX IF NOT EXISTS idx_solana_transactions_state ON solana_transactions(state);
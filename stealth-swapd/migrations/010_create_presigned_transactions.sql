@@ -0,0 +1,21 @@
+-- Refunds signed when a swap is accepted, each holding a durable nonce
+-- account so it stays valid until broadcast after the deadline
+CREATE TABLE IF NOT EXISTS presigned_transactions (
+    swap_id BLOB NOT NULL,
+    kind TEXT NOT NULL,
+    signature TEXT NOT NULL UNIQUE,
+    -- Signed transaction, encrypted with the presign key
+    sealed_tx BLOB NOT NULL,
+    nonce_account TEXT NOT NULL,
+    -- The nonce the transaction was signed against
+    blockhash TEXT NOT NULL,
+    -- Unix time before which the program rejects the transaction
+    not_before INTEGER NOT NULL,
+    state TEXT NOT NULL DEFAULT 'stored'
+        CHECK (state IN ('stored', 'submitted', 'void')),
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (swap_id, kind)
+);
+
+CREATE INDEX IF NOT EXISTS idx_presigned_transactions_state ON presigned_transactions(state);
@@ -0,0 +1,3 @@
+-- What the simulation before the first send reported
+ALTER TABLE solana_transactions ADD COLUMN compute_units INTEGER;
+ALTER TABLE solana_transactions ADD COLUMN simulation_logs TEXT;
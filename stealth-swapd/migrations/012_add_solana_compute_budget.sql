@@ -0,0 +1,4 @@
+-- Compute budget each transaction was signed with; NULL for pre-signed
+-- transactions, whose budget was set when they were signed
+ALTER TABLE solana_transactions ADD COLUMN compute_unit_limit INTEGER;
+ALTER TABLE solana_transactions ADD COLUMN compute_unit_price INTEGER;
@@ -0,0 +1,8 @@
+-- Adaptor secrets behind each quote's secret_hash, kept so Bob can later
+-- claim the USDC of the swap
+CREATE TABLE IF NOT EXISTS swap_secrets (
+    swap_id BLOB PRIMARY KEY,
+    -- The secret, encrypted with the presign key
+    sealed_secret BLOB NOT NULL,
+    created_at DATETIME NOT NULL
+);
@@ -0,0 +1,43 @@
+-- Deposits of XMR→USDC swaps that ended before Bob escrowed their USDC are
+-- held as 'unsettled' strays for refund. SQLite can't alter a CHECK, so both
+-- tables are rebuilt, the audit log first since it references the deposits.
+CREATE TABLE stray_deposits_new (
+    id TEXT PRIMARY KEY,
+    -- One row per swap and reason, or per transaction for unmatched deposits
+    dedup_key TEXT NOT NULL UNIQUE,
+    swap_id BLOB,
+    subaddr_index INTEGER NOT NULL,
+    txids TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL
+        CHECK (reason IN ('underpaid', 'overpaid', 'late', 'unmatched', 'unsettled')),
+    status TEXT NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'refunding', 'refunded', 'credited')),
+    refund_address TEXT,
+    refund_txid TEXT,
+    credited_swap_id BLOB,
+    detected_at DATETIME NOT NULL,
+    resolved_at DATETIME
+);
+INSERT INTO stray_deposits_new SELECT * FROM stray_deposits;
+
+CREATE TABLE stray_deposit_audit_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    deposit_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    detail TEXT,
+    created_at DATETIME NOT NULL,
+    -- Follows the rename below to stray_deposits
+    FOREIGN KEY (deposit_id) REFERENCES stray_deposits_new(id)
+);
+INSERT INTO stray_deposit_audit_new SELECT * FROM stray_deposit_audit;
+
+DROP TABLE stray_deposit_audit;
+DROP TABLE stray_deposits;
+ALTER TABLE stray_deposits_new RENAME TO stray_deposits;
+ALTER TABLE stray_deposit_audit_new RENAME TO stray_deposit_audit;
+
+CREATE INDEX IF NOT EXISTS idx_stray_deposits_status ON stray_deposits(status);
+CREATE INDEX IF NOT EXISTS idx_stray_deposits_credited ON stray_deposits(credited_swap_id);
+CREATE INDEX IF NOT EXISTS idx_stray_deposit_audit_deposit ON stray_deposit_audit(deposit_id);
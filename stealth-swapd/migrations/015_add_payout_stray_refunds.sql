@@ -0,0 +1,3 @@
+-- Refunds of stray deposits go through the payout outbox too. Their row is
+-- keyed by a hash of the deposit id and carries the id itself here.
+ALTER TABLE payout_outbox ADD COLUMN stray_id TEXT;
@@ -1,31 +1,69 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Json,
     routing::{get, post},
     Router,
 };
 use std::error::Error;
 use serde::{Deserialize, Serialize};
-use crate::swap_engine::{SwapEngine, QuoteRequest, Direction};
+use crate::swap_engine::{SwapEngine, QuoteRequest, AcceptRequest, Direction, XmrDeposit, StrayDeposit, StrayAuditEntry, SwapConfirmations, RecoveryBundle};
 use crate::metrics::MetricsCollector;
+use crate::clients::monero::WalletRefresh;
 
 use std::sync::Arc;
 use std::collections::HashMap;
 use uuid::Uuid;
 use hex;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
 
 #[derive(Deserialize)]
 struct QuoteRequestBody {
     direction: String,
     usdc_amount: u64,
     xmr_amount: u64,
+    destination: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct AcceptRequestBody {
     quote_id: String,
-    counterparty_pubkey: Option<String>,
+    swap_id: String,
+    direction: String,
+    usdc_amount: u64,
+    xmr_amount: u64,
+    secret_hash: String,
+    counterparty_pubkey: String,
+    expiry: i64,
+    destination: Option<String>,
+    quote_signature: String,
+}
+
+#[derive(Deserialize)]
+struct NotifyQuery {
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StrayDepositQuery {
+    status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RefundStrayBody {
+    address: String,
+}
+
+#[derive(Deserialize)]
+struct CreditStrayBody {
+    swap_id: String,
+}
+
+#[derive(Serialize)]
+struct StrayDepositDetail {
+    deposit: StrayDeposit,
+    audit: Vec<StrayAuditEntry>,
 }
 
 #[derive(Serialize)]
@@ -39,7 +77,12 @@ struct ApiResponse<T> {
 struct HealthResponse {
     solana_connected: bool,
     monero_connected: bool,
+    /// Wallet sync height.
     last_block_height: u64,
+    daemon_connected: Option<bool>,
+    daemon_height: Option<u64>,
+    wallet_lag_blocks: Option<u64>,
+    quoting_paused: bool,
 }
 
 #[derive(Serialize)]
@@ -49,17 +92,29 @@ struct SwapStatusResponse {
     xmr_amount: u64,
     expiry: String,
     failure_reason: Option<String>,
+    xmr_deposit: Option<XmrDeposit>,
+    confirmations: Option<SwapConfirmations>,
 }
 
 pub struct AppState {
     swap_engine: SwapEngine,
     metrics: Arc<MetricsCollector>,
+    /// Operator names and their admin tokens.
+    admin_tokens: Vec<(String, SecretString)>,
+    notify_token: Option<SecretString>,
 }
 
-pub fn create_app(swap_engine: SwapEngine, metrics: Arc<MetricsCollector>) -> Router {
+pub fn create_app(
+    swap_engine: SwapEngine,
+    metrics: Arc<MetricsCollector>,
+    admin_tokens: Vec<(String, SecretString)>,
+    notify_token: Option<SecretString>,
+) -> Router {
     let state = Arc::new(AppState {
         swap_engine,
         metrics,
+        admin_tokens,
+        notify_token,
     });
 
     Router::new()
@@ -68,33 +123,50 @@ pub fn create_app(swap_engine: SwapEngine, metrics: Arc<MetricsCollector>) -> Ro
         .route("/v1/swap/:swap_id", get(get_swap_status))
         .route("/health", get(health_check))
         .route("/metrics", get(get_metrics))
+        .route("/notify/monero/tx/:txid", post(notify_monero_tx))
+        .route("/notify/monero/block/:hash", post(notify_monero_block))
+        .route("/admin/wallet/refresh", post(refresh_wallet))
+        .route("/admin/recovery-bundle", get(recovery_bundle))
+        .route("/admin/stray-deposits", get(list_stray_deposits))
+        .route("/admin/stray-deposits/:id", get(get_stray_deposit))
+        .route("/admin/stray-deposits/:id/refund", post(refund_stray_deposit))
+        .route("/admin/stray-deposits/:id/credit", post(credit_stray_deposit))
         .with_state(state)
 }
 
-pub async fn start_server(addr: String, swap_engine: SwapEngine, metrics: Arc<MetricsCollector>) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let app = create_app(swap_engine, metrics);
+pub async fn start_server(
+    addr: String,
+    swap_engine: SwapEngine,
+    metrics: Arc<MetricsCollector>,
+    admin_tokens: Vec<(String, SecretString)>,
+    notify_token: Option<SecretString>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let app = create_app(swap_engine, metrics, admin_tokens, notify_token);
     let addr: std::net::SocketAddr = addr.parse()?;
-    axum::Server::bind(&addr).serve(app.into_make_service()).await?;
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .await?;
     Ok(())
 }
 
 async fn generate_quote(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<std::net::SocketAddr>,
     Json(payload): Json<QuoteRequestBody>,
 ) -> Result<Json<ApiResponse<crate::swap_engine::QuoteResponse>>, StatusCode> {
-    let direction = match payload.direction.as_str() {
-        "usdc_to_xmr" => Direction::UsdcToXmr,
-        "xmr_to_usdc" => Direction::XmrToUsdc,
-        _ => return Err(StatusCode::BAD_REQUEST),
+    let direction = match Direction::parse(&payload.direction) {
+        Some(direction) => direction,
+        None => return Err(StatusCode::BAD_REQUEST),
     };
 
     let request = QuoteRequest {
         direction,
         usdc_amount: payload.usdc_amount,
         xmr_amount: payload.xmr_amount,
+        destination: payload.destination,
     };
 
-    match state.swap_engine.generate_quote(request).await {
+    match state.swap_engine.generate_quote(request, &client.ip().to_string()).await {
         Ok(quote) => Ok(Json(ApiResponse {
             success: true,
             data: Some(quote),
@@ -116,8 +188,29 @@ async fn accept_swap(
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
+    let (swap_id, secret_hash) = match (decode_hash(&payload.swap_id), decode_hash(&payload.secret_hash)) {
+        (Some(swap_id), Some(secret_hash)) => (swap_id, secret_hash),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let direction = match Direction::parse(&payload.direction) {
+        Some(direction) => direction,
+        None => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let request = AcceptRequest {
+        quote_id,
+        swap_id,
+        direction,
+        usdc_amount: payload.usdc_amount,
+        xmr_amount: payload.xmr_amount,
+        secret_hash,
+        counterparty_pubkey: payload.counterparty_pubkey,
+        expiry: payload.expiry,
+        destination: payload.destination,
+        quote_signature: payload.quote_signature,
+    };
 
-    match state.swap_engine.accept_swap(quote_id, payload.counterparty_pubkey).await {
+    match state.swap_engine.accept_swap(request).await {
         Ok(swap_id) => Ok(Json(ApiResponse {
             success: true,
             data: Some(hex::encode(swap_id)),
@@ -135,7 +228,7 @@ async fn get_swap_status(
     State(state): State<Arc<AppState>>,
     Path(swap_id): Path<String>,
 ) -> Result<Json<ApiResponse<SwapStatusResponse>>, StatusCode> {
-    let swap_id_bytes = match hex::decode(swap_id).ok().and_then(|v| v.try_into().ok()) {
+    let swap_id_bytes = match decode_hash(&swap_id) {
         Some(bytes) => bytes,
         None => return Err(StatusCode::BAD_REQUEST),
     };
@@ -148,6 +241,8 @@ async fn get_swap_status(
                 xmr_amount: swap.xmr_amount,
                 expiry: swap.expires_at.to_rfc3339(),
                 failure_reason: swap.failure_reason.clone(),
+                xmr_deposit: swap.xmr_deposit.clone(),
+                confirmations: swap.confirmations.clone(),
             };
             
             Ok(Json(ApiResponse {
@@ -160,17 +255,179 @@ async fn get_swap_status(
     }
 }
 
+/// Admin routes take `Authorization: Bearer <token>` and don't exist at
+/// all unless a token is configured. Returns the operator the token
+/// belongs to, who is recorded as the actor of what they do.
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<String, StatusCode> {
+    if state.admin_tokens.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let given = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    // Compare digests so the comparison time doesn't leak a token
+    let given = Sha256::digest(given.as_bytes());
+    state
+        .admin_tokens
+        .iter()
+        .find(|(_, expected)| given == Sha256::digest(expected.expose_secret().as_bytes()))
+        .map(|(name, _)| name.clone())
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+/// Notification hooks take `?token=` when a notify token is configured,
+/// and are otherwise limited to loopback callers.
+fn authorize_notify(state: &AppState, client: &std::net::SocketAddr, token: Option<&str>) -> Result<(), StatusCode> {
+    let expected = match &state.notify_token {
+        Some(expected) => expected,
+        None if client.ip().is_loopback() => return Ok(()),
+        None => return Err(StatusCode::FORBIDDEN),
+    };
+    let given = token.ok_or(StatusCode::UNAUTHORIZED)?;
+    if Sha256::digest(given.as_bytes()) != Sha256::digest(expected.expose_secret().as_bytes()) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+/// `monero-wallet-rpc --tx-notify "curl -sf -X POST http://127.0.0.1:3000/notify/monero/tx/%s"`
+async fn notify_monero_tx(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<std::net::SocketAddr>,
+    Path(txid): Path<String>,
+    Query(query): Query<NotifyQuery>,
+) -> StatusCode {
+    if let Err(status) = authorize_notify(&state, &client, query.token.as_deref()) {
+        return status;
+    }
+    if decode_hash(&txid).is_none() {
+        return StatusCode::BAD_REQUEST;
+    }
+
+    state.swap_engine.notify_monero_tx(&txid).await;
+    StatusCode::ACCEPTED
+}
+
+/// `monerod --block-notify "curl -sf -X POST http://127.0.0.1:3000/notify/monero/block/%s"`
+async fn notify_monero_block(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<std::net::SocketAddr>,
+    Path(hash): Path<String>,
+    Query(query): Query<NotifyQuery>,
+) -> StatusCode {
+    if let Err(status) = authorize_notify(&state, &client, query.token.as_deref()) {
+        return status;
+    }
+    if decode_hash(&hash).is_none() {
+        return StatusCode::BAD_REQUEST;
+    }
+
+    state.swap_engine.notify_monero_block().await;
+    StatusCode::ACCEPTED
+}
+
+fn respond<T>(result: anyhow::Result<T>) -> Json<ApiResponse<T>> {
+    match result {
+        Ok(data) => Json(ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+        }),
+        Err(e) => Json(ApiResponse {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
+
+async fn refresh_wallet(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<WalletRefresh>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+    Ok(respond(state.swap_engine.refresh_wallet().await))
+}
+
+async fn recovery_bundle(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<RecoveryBundle>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+    Ok(respond(state.swap_engine.recovery_bundle().await))
+}
+
+async fn list_stray_deposits(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<StrayDepositQuery>,
+) -> Result<Json<ApiResponse<Vec<StrayDeposit>>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+    Ok(respond(state.swap_engine.list_stray_deposits(query.status.as_deref()).await))
+}
+
+async fn get_stray_deposit(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<StrayDepositDetail>>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+    let id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let detail = state.swap_engine
+        .get_stray_deposit(id)
+        .await
+        .map(|(deposit, audit)| StrayDepositDetail { deposit, audit });
+    Ok(respond(detail))
+}
+
+async fn refund_stray_deposit(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(payload): Json<RefundStrayBody>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let actor = authorize_admin(&state, &headers)?;
+    let id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    Ok(respond(state.swap_engine.refund_stray_deposit(id, &payload.address, &actor).await))
+}
+
+async fn credit_stray_deposit(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(payload): Json<CreditStrayBody>,
+) -> Result<Json<ApiResponse<StrayDeposit>>, StatusCode> {
+    let actor = authorize_admin(&state, &headers)?;
+    let id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let swap_id = decode_hash(&payload.swap_id).ok_or(StatusCode::BAD_REQUEST)?;
+
+    Ok(respond(state.swap_engine.credit_stray_deposit(id, swap_id, &actor).await))
+}
+
+fn decode_hash(value: &str) -> Option<[u8; 32]> {
+    hex::decode(value).ok().and_then(|v| v.try_into().ok())
+}
+
 async fn health_check(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
 ) -> Json<ApiResponse<HealthResponse>> {
-    let (solana_connected, monero_connected, height) = (true, true, 1234567);
-    
+    let health = state.swap_engine.health().await;
+
     Json(ApiResponse {
         success: true,
         data: Some(HealthResponse {
-            solana_connected,
-            monero_connected,
-            last_block_height: height,
+            solana_connected: health.solana_connected,
+            monero_connected: health.monero_connected,
+            last_block_height: health.wallet_height.unwrap_or(0),
+            daemon_connected: health.daemon_connected,
+            daemon_height: health.daemon_height,
+            wallet_lag_blocks: health.wallet_lag_blocks,
+            quoting_paused: health.quoting_paused,
         }),
         error: None,
     })
@@ -187,7 +444,57 @@ async fn get_metrics(
     })
 }
 
-async fn _check_health(_engine: &SwapEngine) -> (bool, bool, u64) {
-    // Mock health check
-    (true, true, 1234567)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::mock_rpc::{self, MockRpc};
+    use axum::body::Body;
+    use axum::extract::connect_info::MockConnectInfo;
+    use axum::http::Request;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    const TXID: &str = "7a0f5b2c4d6e8f1a3b5c7d9e0f2a4b6c8d0e2f4a6b8c0d2e4f6a8b0c2d4e6f8a";
+
+    async fn app(notify_token: Option<&str>, client: &str) -> (Router, MockRpc, MockRpc) {
+        let wallet = MockRpc::wallet(|_, _| Err((-8, "Transaction not found.".to_string()))).await;
+        let solana = MockRpc::start(|method, _| Err((-32601, format!("unexpected {}", method)))).await;
+        let engine = mock_rpc::swap_engine(&wallet, &solana).await;
+        let app = create_app(
+            engine,
+            Arc::new(MetricsCollector::new()),
+            Vec::new(),
+            notify_token.map(|token| SecretString::new(token.to_string())),
+        )
+        .layer(MockConnectInfo(client.parse::<SocketAddr>().unwrap()));
+        (app, wallet, solana)
+    }
+
+    async fn notify(app: Router, uri: &str) -> StatusCode {
+        let request = Request::post(uri).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn notify_token_is_required_from_any_caller_when_configured() {
+        let (app, wallet, _solana) = app(Some("hook-token"), "127.0.0.1:40000").await;
+
+        assert_eq!(notify(app.clone(), &format!("/notify/monero/tx/{}", TXID)).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(notify(app.clone(), &format!("/notify/monero/tx/{}?token=wrong", TXID)).await, StatusCode::UNAUTHORIZED);
+        assert!(wallet.calls("get_transfer_by_txid").is_empty());
+
+        assert_eq!(notify(app.clone(), &format!("/notify/monero/tx/{}?token=hook-token", TXID)).await, StatusCode::ACCEPTED);
+        assert_eq!(wallet.calls("get_transfer_by_txid"), vec![serde_json::json!({ "txid": TXID })]);
+        assert_eq!(notify(app, "/notify/monero/tx/not-a-hash?token=hook-token").await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn without_a_notify_token_only_loopback_callers_are_heard() {
+        let (local, _wallet, _solana) = app(None, "127.0.0.1:40000").await;
+        assert_eq!(notify(local, &format!("/notify/monero/block/{}", TXID)).await, StatusCode::ACCEPTED);
+
+        let (remote, _wallet, _solana) = app(None, "203.0.113.7:40000").await;
+        assert_eq!(notify(remote.clone(), &format!("/notify/monero/block/{}", TXID)).await, StatusCode::FORBIDDEN);
+        assert_eq!(notify(remote, &format!("/notify/monero/tx/{}?token=guess", TXID)).await, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,9 @@
+mod view;
+mod simulated;
+mod observations;
+mod solana_subscriber;
+
+pub use view::*;
+pub use simulated::*;
+pub use observations::*;
+pub use solana_subscriber::*;
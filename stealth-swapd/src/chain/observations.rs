@@ -0,0 +1,329 @@
+use super::{Chain, ChainView, Inclusion};
+use crate::swap_engine::SwapState;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use anyhow::Result;
+
+/// Slots after which a Solana observation is treated as final even if the
+/// RPC node has not reported it `finalized` yet.
+pub const SOLANA_FINALITY_SLOTS: u64 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationStatus {
+    Pending,
+    Final,
+    Invalidated,
+}
+
+impl ObservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObservationStatus::Pending => "pending",
+            ObservationStatus::Final => "final",
+            ObservationStatus::Invalidated => "invalidated",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ObservationStatus::Pending),
+            "final" => Some(ObservationStatus::Final),
+            "invalidated" => Some(ObservationStatus::Invalidated),
+            _ => None,
+        }
+    }
+}
+
+/// A transaction a swap advanced on, and where the swap goes if it turns
+/// out not to be on the best chain after all.
+#[derive(Debug, Clone, Serialize)]
+pub struct Observation {
+    pub id: i64,
+    pub swap_id: [u8; 32],
+    pub chain: Chain,
+    pub reference: String,
+    pub height: u64,
+    pub block_hash: String,
+    pub advanced_to: SwapState,
+    pub rollback_to: SwapState,
+    pub status: ObservationStatus,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Still in the same block, not yet deep enough.
+    Pending,
+    /// Deep enough that a reorg is no longer a concern.
+    Final,
+    /// Re-mined into a different block; the observation must be updated and
+    /// its depth counted again.
+    Moved(Inclusion),
+    /// No longer on the best chain.
+    Invalidated(String),
+}
+
+/// Re-check an observation against the chain as it is now.
+pub async fn reverify<C: ChainView>(view: &C, observation: &Observation, finality_depth: u64) -> Result<Verdict> {
+    let inclusion = match view.locate(&observation.reference).await? {
+        Some(inclusion) => inclusion,
+        None => {
+            return Ok(Verdict::Invalidated(format!(
+                "{} {} is no longer in a block",
+                view.chain().as_str(),
+                observation.reference
+            )));
+        }
+    };
+
+    let hash_changed = !observation.block_hash.is_empty()
+        && !inclusion.block_hash.is_empty()
+        && inclusion.block_hash != observation.block_hash;
+    if inclusion.height != observation.height || hash_changed {
+        return Ok(Verdict::Moved(inclusion));
+    }
+
+    if inclusion.finalized || inclusion.confirmations >= finality_depth {
+        Ok(Verdict::Final)
+    } else {
+        Ok(Verdict::Pending)
+    }
+}
+
+/// SQLite-backed record of the observations swaps advanced on.
+#[derive(Clone)]
+pub struct ObservationStore {
+    db: SqlitePool,
+}
+
+impl ObservationStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    pub async fn record(
+        &self,
+        swap_id: [u8; 32],
+        chain: Chain,
+        reference: &str,
+        inclusion: &Inclusion,
+        advanced_to: SwapState,
+        rollback_to: SwapState,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO chain_observations \
+             (swap_id, chain, reference, height, block_hash, advanced_to, rollback_to, observed_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT (swap_id, chain, reference) DO UPDATE SET \
+             height = excluded.height, block_hash = excluded.block_hash, \
+             advanced_to = excluded.advanced_to, rollback_to = excluded.rollback_to, \
+             status = 'pending', invalidated_reason = NULL, observed_at = excluded.observed_at",
+        )
+        .bind(swap_id.to_vec())
+        .bind(chain.as_str())
+        .bind(reference)
+        .bind(inclusion.height as i64)
+        .bind(&inclusion.block_hash)
+        .bind(advanced_to.as_str())
+        .bind(rollback_to.as_str())
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Observations not yet final or invalidated, oldest first.
+    pub async fn pending(&self) -> Result<Vec<Observation>> {
+        let rows = sqlx::query(
+            "SELECT id, swap_id, chain, reference, height, block_hash, advanced_to, rollback_to, status, observed_at \
+             FROM chain_observations WHERE status = 'pending' ORDER BY id",
+        )
+        .fetch_all(&self.db)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// Whether every observation the swap advanced on is final.
+    pub async fn all_final(&self, swap_id: [u8; 32]) -> Result<bool> {
+        let row = sqlx::query("SELECT COUNT(*) AS open FROM chain_observations WHERE swap_id = ? AND status = 'pending'")
+            .bind(swap_id.to_vec())
+            .fetch_one(&self.db)
+            .await?;
+        Ok(row.try_get::<i64, _>("open")? == 0)
+    }
+
+    pub async fn mark_verified(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE chain_observations SET verified_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn mark_final(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE chain_observations SET status = 'final', verified_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_inclusion(&self, id: i64, inclusion: &Inclusion) -> Result<()> {
+        sqlx::query("UPDATE chain_observations SET height = ?, block_hash = ?, verified_at = ? WHERE id = ?")
+            .bind(inclusion.height as i64)
+            .bind(&inclusion.block_hash)
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Invalidate an observation and every later pending one for the same
+    /// swap, since those were made on top of it.
+    pub async fn invalidate(&self, observation: &Observation, reason: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE chain_observations SET status = 'invalidated', invalidated_reason = ?, verified_at = ? \
+             WHERE swap_id = ? AND status = 'pending' AND id >= ?",
+        )
+        .bind(reason)
+        .bind(Utc::now())
+        .bind(observation.swap_id.to_vec())
+        .bind(observation.id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Observation> {
+        let swap_id: Vec<u8> = row.try_get("swap_id")?;
+        let chain: String = row.try_get("chain")?;
+        let advanced_to: String = row.try_get("advanced_to")?;
+        let rollback_to: String = row.try_get("rollback_to")?;
+        let status: String = row.try_get("status")?;
+        let state = |value: &str| SwapState::parse(value).ok_or_else(|| anyhow::anyhow!("Unknown swap state {}", value));
+
+        Ok(Observation {
+            id: row.try_get("id")?,
+            swap_id: swap_id.try_into().map_err(|_| anyhow::anyhow!("Corrupt swap_id in chain_observations"))?,
+            chain: Chain::parse(&chain).ok_or_else(|| anyhow::anyhow!("Unknown chain {}", chain))?,
+            reference: row.try_get("reference")?,
+            height: row.try_get::<i64, _>("height")? as u64,
+            block_hash: row.try_get("block_hash")?,
+            advanced_to: state(&advanced_to)?,
+            rollback_to: state(&rollback_to)?,
+            status: ObservationStatus::parse(&status).ok_or_else(|| anyhow::anyhow!("Unknown status {}", status))?,
+            observed_at: row.try_get("observed_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::SimulatedChain;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const SWAP: [u8; 32] = [1; 32];
+
+    async fn store() -> ObservationStore {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        ObservationStore::new(db)
+    }
+
+    /// Record `reference` where the chain has it now and return it as stored.
+    async fn observe(store: &ObservationStore, chain: &SimulatedChain, swap_id: [u8; 32], reference: &str) -> Observation {
+        let inclusion = chain.locate(reference).await.unwrap().unwrap();
+        store
+            .record(swap_id, chain.chain(), reference, &inclusion, SwapState::LockedXmr, SwapState::LockedUsdc)
+            .await
+            .unwrap();
+        store.pending().await.unwrap().into_iter().find(|o| o.reference == reference).unwrap()
+    }
+
+    #[tokio::test]
+    async fn observation_becomes_final_at_depth() {
+        let store = store().await;
+        let chain = SimulatedChain::new(Chain::Monero);
+        chain.mine(&["tx1"]);
+        let observation = observe(&store, &chain, SWAP, "tx1").await;
+
+        assert_eq!(reverify(&chain, &observation, 10).await.unwrap(), Verdict::Pending);
+        chain.advance(9);
+        assert_eq!(reverify(&chain, &observation, 10).await.unwrap(), Verdict::Final);
+
+        store.mark_final(observation.id).await.unwrap();
+        assert!(store.all_final(SWAP).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn reorg_that_drops_the_transaction_invalidates_it() {
+        let store = store().await;
+        let chain = SimulatedChain::new(Chain::Monero);
+        chain.advance(5);
+        chain.mine(&["tx1"]);
+        let observation = observe(&store, &chain, SWAP, "tx1").await;
+        assert!(!store.all_final(SWAP).await.unwrap());
+
+        assert_eq!(chain.reorg(1), vec!["tx1".to_string()]);
+        chain.advance(2);
+
+        let verdict = reverify(&chain, &observation, 10).await.unwrap();
+        assert!(matches!(verdict, Verdict::Invalidated(_)), "{:?}", verdict);
+    }
+
+    #[tokio::test]
+    async fn transaction_re_mined_in_another_block_has_moved() {
+        let store = store().await;
+        let chain = SimulatedChain::new(Chain::Solana);
+        chain.advance(3);
+        chain.mine(&["sig1"]);
+        let observation = observe(&store, &chain, SWAP, "sig1").await;
+
+        // Same height, new fork
+        chain.reorg(1);
+        chain.mine(&["sig1"]);
+        let Verdict::Moved(inclusion) = reverify(&chain, &observation, 32).await.unwrap() else {
+            panic!("expected the observation to have moved");
+        };
+        assert_eq!(inclusion.height, observation.height);
+        assert_ne!(inclusion.block_hash, observation.block_hash);
+
+        // Later height
+        chain.reorg(1);
+        chain.advance(1);
+        chain.mine(&["sig1"]);
+        let Verdict::Moved(inclusion) = reverify(&chain, &observation, 32).await.unwrap() else {
+            panic!("expected the observation to have moved");
+        };
+        assert_eq!(inclusion.height, observation.height + 1);
+
+        store.update_inclusion(observation.id, &inclusion).await.unwrap();
+        let updated = store.pending().await.unwrap().remove(0);
+        assert_eq!(reverify(&chain, &updated, 32).await.unwrap(), Verdict::Pending);
+    }
+
+    #[tokio::test]
+    async fn invalidating_an_observation_invalidates_later_ones_for_the_swap() {
+        let store = store().await;
+        let chain = SimulatedChain::new(Chain::Monero);
+        chain.mine(&["tx1", "other"]);
+        chain.mine(&["tx2"]);
+        let first = observe(&store, &chain, SWAP, "tx1").await;
+        observe(&store, &chain, SWAP, "tx2").await;
+        observe(&store, &chain, [2; 32], "other").await;
+
+        store.invalidate(&first, "tx1 is no longer in a block").await.unwrap();
+
+        let pending = store.pending().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].swap_id, [2; 32]);
+        assert!(store.all_final(SWAP).await.unwrap());
+    }
+}
@@ -0,0 +1,108 @@
+use super::{Chain, ChainView, Inclusion};
+
+use std::sync::Mutex;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone)]
+struct SimulatedBlock {
+    hash: String,
+    transactions: Vec<String>,
+}
+
+/// In-memory chain whose blocks can be mined and reorganised at will, for
+/// exercising re-verification and rollback without a node.
+///
+/// Heights start at 1. Every block gets a hash derived from its parent, so
+/// a block re-mined at the same height after [`SimulatedChain::reorg`] has a
+/// different hash even if it holds the same transactions.
+pub struct SimulatedChain {
+    chain: Chain,
+    blocks: Mutex<Vec<SimulatedBlock>>,
+    nonce: Mutex<u64>,
+}
+
+impl SimulatedChain {
+    pub fn new(chain: Chain) -> Self {
+        Self {
+            chain,
+            blocks: Mutex::new(Vec::new()),
+            nonce: Mutex::new(0),
+        }
+    }
+
+    /// Append a block holding `transactions`; returns its height and hash.
+    pub fn mine(&self, transactions: &[&str]) -> (u64, String) {
+        let mut blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut nonce = self.nonce.lock().unwrap_or_else(|e| e.into_inner());
+        *nonce += 1;
+
+        let parent = blocks.last().map(|b| b.hash.clone()).unwrap_or_default();
+        let height = blocks.len() as u64 + 1;
+
+        let mut hasher = Sha256::new();
+        hasher.update(parent.as_bytes());
+        hasher.update(height.to_le_bytes());
+        hasher.update(nonce.to_le_bytes());
+        let hash = hex::encode(hasher.finalize());
+
+        blocks.push(SimulatedBlock {
+            hash: hash.clone(),
+            transactions: transactions.iter().map(|t| t.to_string()).collect(),
+        });
+        (height, hash)
+    }
+
+    /// Mine `count` empty blocks.
+    pub fn advance(&self, count: u64) {
+        for _ in 0..count {
+            self.mine(&[]);
+        }
+    }
+
+    /// Drop the top `depth` blocks, as a reorg to a fork that has not yet
+    /// caught up would. Mine on afterwards to build the new fork.
+    pub fn reorg(&self, depth: u64) -> Vec<String> {
+        let mut blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        let keep = blocks.len().saturating_sub(depth as usize);
+        blocks
+            .drain(keep..)
+            .flat_map(|block| block.transactions)
+            .collect()
+    }
+
+    pub fn height(&self) -> u64 {
+        self.blocks.lock().unwrap_or_else(|e| e.into_inner()).len() as u64
+    }
+
+    pub fn block_hash(&self, height: u64) -> Option<String> {
+        let blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        let index = usize::try_from(height.checked_sub(1)?).ok()?;
+        blocks.get(index).map(|b| b.hash.clone())
+    }
+}
+
+impl ChainView for SimulatedChain {
+    fn chain(&self) -> Chain {
+        self.chain
+    }
+
+    async fn locate(&self, reference: &str) -> Result<Option<Inclusion>> {
+        let blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        let tip = blocks.len() as u64;
+
+        Ok(blocks
+            .iter()
+            .enumerate()
+            .find(|(_, block)| block.transactions.iter().any(|t| t == reference))
+            .map(|(index, block)| {
+                let height = index as u64 + 1;
+                Inclusion {
+                    height,
+                    block_hash: block.hash.clone(),
+                    confirmations: tip - height + 1,
+                    finalized: false,
+                }
+            }))
+    }
+}
@@ -0,0 +1,489 @@
+use crate::clients::SolanaClient;
+use crate::clients::solana::ProgramTransaction;
+use crate::clients::solana_program::{self, ProgramInstruction, SwapAccount};
+
+use std::collections::HashMap;
+use std::time::Duration;
+use anyhow::Result;
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Most signatures fetched when backfilling after a disconnect.
+const BACKFILL_LIMIT: usize = 1000;
+
+const LOGS_REQUEST_ID: u64 = 1;
+
+/// Something the program did that may advance a swap.
+#[derive(Debug, Clone)]
+pub enum ProgramEvent {
+    /// A transaction to the program, from `logsSubscribe` or the backfill.
+    Transaction(ProgramTransaction),
+    /// A watched `Swap` account changed. `None` if it was closed.
+    SwapAccount {
+        swap_id: [u8; 32],
+        slot: u64,
+        swap: Option<Box<SwapAccount>>,
+    },
+}
+
+impl ProgramEvent {
+    pub fn swap_ids(&self) -> Vec<[u8; 32]> {
+        match self {
+            ProgramEvent::Transaction(tx) => tx.instructions.iter().filter_map(|(_, swap_id)| *swap_id).collect(),
+            ProgramEvent::SwapAccount { swap_id, .. } => vec![*swap_id],
+        }
+    }
+}
+
+enum Command {
+    Watch([u8; 32]),
+    Unwatch([u8; 32]),
+}
+
+/// Handle for choosing which `Swap` accounts the subscriber follows.
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl SubscriptionHandle {
+    pub fn watch(&self, swap_id: [u8; 32]) {
+        let _ = self.commands.send(Command::Watch(swap_id));
+    }
+
+    pub fn unwatch(&self, swap_id: [u8; 32]) {
+        let _ = self.commands.send(Command::Unwatch(swap_id));
+    }
+}
+
+/// Websocket subscriber for the `stealth_swap` program: `logsSubscribe` on
+/// the program id plus `accountSubscribe` on each watched `Swap` PDA.
+/// Resubscribes after a disconnect and backfills the transactions missed
+/// meanwhile through `getSignaturesForAddress`.
+pub struct SolanaSubscriber {
+    client: SolanaClient,
+    ws_url: String,
+    program_id: String,
+    commands: mpsc::UnboundedReceiver<Command>,
+    /// Watched swaps and their PDA addresses.
+    watched: HashMap<[u8; 32], String>,
+    /// Newest program signature seen; the backfill resumes after it.
+    cursor: Option<String>,
+}
+
+/// Per-connection subscription bookkeeping.
+#[derive(Default)]
+struct Session {
+    next_request_id: u64,
+    /// accountSubscribe request id → swap.
+    pending: HashMap<u64, [u8; 32]>,
+    /// Subscription id → swap.
+    accounts: HashMap<u64, [u8; 32]>,
+}
+
+impl SolanaSubscriber {
+    pub fn new(client: SolanaClient) -> Result<(Self, SubscriptionHandle)> {
+        let (sender, commands) = mpsc::unbounded_channel();
+        let subscriber = Self {
+            ws_url: client.ws_url(),
+            program_id: solana_program::encode_pubkey(&client.program_id()?),
+            client,
+            commands,
+            watched: HashMap::new(),
+            cursor: None,
+        };
+        Ok((subscriber, SubscriptionHandle { commands: sender }))
+    }
+
+    /// Deliver program events to `events` until the receiver is dropped,
+    /// reconnecting with backoff whenever the websocket fails.
+    pub async fn run(mut self, events: mpsc::Sender<ProgramEvent>) {
+        let mut backoff = Duration::from_secs(1);
+        while !events.is_closed() {
+            let started = tokio::time::Instant::now();
+            match self.session(&events).await {
+                Ok(()) => tracing::warn!("Solana websocket {} closed", self.ws_url),
+                Err(e) => tracing::warn!("Solana websocket {} failed: {}", self.ws_url, e),
+            }
+            if started.elapsed() > Duration::from_secs(60) {
+                backoff = Duration::from_secs(1);
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(Duration::from_secs(60));
+        }
+    }
+
+    async fn session(&mut self, events: &mpsc::Sender<ProgramEvent>) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.ws_url.as_str()).await?;
+        let (mut sink, mut stream) = socket.split();
+        let mut session = Session { next_request_id: LOGS_REQUEST_ID + 1, ..Session::default() };
+
+        sink.send(self.request(LOGS_REQUEST_ID, "logsSubscribe", serde_json::json!([
+            { "mentions": [self.program_id] },
+            { "commitment": self.client.commitment() }
+        ]))).await?;
+        let watched: Vec<[u8; 32]> = self.watched.keys().copied().collect();
+        for swap_id in watched {
+            sink.send(self.account_subscribe(&mut session, swap_id)).await?;
+        }
+        tracing::info!("Subscribed to program {} over {}", self.program_id, self.ws_url);
+
+        self.backfill(events).await?;
+
+        loop {
+            tokio::select! {
+                message = stream.next() => match message {
+                    None | Some(Ok(Message::Close(_))) => return Ok(()),
+                    Some(Err(e)) => return Err(e.into()),
+                    Some(Ok(Message::Ping(payload))) => sink.send(Message::Pong(payload)).await?,
+                    Some(Ok(Message::Text(text))) => self.handle_message(&mut session, &text, events).await?,
+                    Some(Ok(_)) => {}
+                },
+                Some(command) = self.commands.recv() => match command {
+                    Command::Watch(swap_id) => {
+                        if !self.watched.contains_key(&swap_id) {
+                            let address = solana_program::swap_address(&self.client.program_id()?, &swap_id)
+                                .ok_or_else(|| anyhow::anyhow!("No swap address for {}", hex::encode(swap_id)))?;
+                            self.watched.insert(swap_id, solana_program::encode_pubkey(&address));
+                            sink.send(self.account_subscribe(&mut session, swap_id)).await?;
+                        }
+                    }
+                    Command::Unwatch(swap_id) => {
+                        self.watched.remove(&swap_id);
+                        let subscription = session.accounts.iter().find(|(_, id)| **id == swap_id).map(|(sub, _)| *sub);
+                        if let Some(subscription) = subscription {
+                            session.accounts.remove(&subscription);
+                            let id = session.next_request_id;
+                            session.next_request_id += 1;
+                            sink.send(self.request(id, "accountUnsubscribe", serde_json::json!([subscription]))).await?;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Replay program transactions missed since the cursor, oldest first.
+    /// On the first connection there is nothing to replay; the cursor is
+    /// just set to the newest signature.
+    async fn backfill(&mut self, events: &mpsc::Sender<ProgramEvent>) -> Result<()> {
+        let cursor = match &self.cursor {
+            Some(cursor) => cursor.clone(),
+            None => {
+                let newest = self.client.get_signatures_until(&self.program_id, None, 1).await?;
+                self.cursor = newest.into_iter().next().map(|(signature, _)| signature);
+                return Ok(());
+            }
+        };
+
+        let missed = self.client.get_signatures_until(&self.program_id, Some(&cursor), BACKFILL_LIMIT).await?;
+        if missed.len() == BACKFILL_LIMIT {
+            tracing::warn!("Backfill hit {} signatures; older program events were skipped", BACKFILL_LIMIT);
+        }
+        if !missed.is_empty() {
+            tracing::info!("Backfilling {} program transactions", missed.len());
+        }
+        for (signature, _) in missed.into_iter().rev() {
+            if let Some(tx) = self.client.get_program_transaction(&signature).await? {
+                events.send(ProgramEvent::Transaction(tx)).await?;
+            }
+            self.cursor = Some(signature);
+        }
+        Ok(())
+    }
+
+    async fn handle_message(
+        &mut self,
+        session: &mut Session,
+        text: &str,
+        events: &mpsc::Sender<ProgramEvent>,
+    ) -> Result<()> {
+        let message: serde_json::Value = serde_json::from_str(text)?;
+
+        if let Some(error) = message.get("error") {
+            tracing::warn!("Solana subscription error: {}", error);
+            return Ok(());
+        }
+
+        // Subscription confirmations
+        if let (Some(id), Some(subscription)) = (message["id"].as_u64(), message["result"].as_u64()) {
+            if let Some(swap_id) = session.pending.remove(&id) {
+                session.accounts.insert(subscription, swap_id);
+            }
+            return Ok(());
+        }
+
+        let result = &message["params"]["result"];
+        match message["method"].as_str() {
+            Some("logsNotification") => {
+                let signature = match result["value"]["signature"].as_str() {
+                    Some(signature) => signature.to_string(),
+                    None => return Ok(()),
+                };
+                let slot = result["context"]["slot"].as_u64().unwrap_or(0);
+                // The logs name the instructions but not the swap; the
+                // transaction has both
+                let tx = match self.client.get_program_transaction(&signature).await {
+                    Ok(Some(tx)) => tx,
+                    Ok(None) | Err(_) => ProgramTransaction {
+                        signature: signature.clone(),
+                        slot,
+                        instructions: result["value"]["logs"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|line| ProgramInstruction::from_log(line.as_str()?))
+                            .map(|instruction| (instruction, None))
+                            .collect(),
+                        failed: !result["value"]["err"].is_null(),
+                    },
+                };
+                self.cursor = Some(signature);
+                events.send(ProgramEvent::Transaction(tx)).await?;
+            }
+            Some("accountNotification") => {
+                let subscription = message["params"]["subscription"].as_u64().unwrap_or_default();
+                let swap_id = match session.accounts.get(&subscription) {
+                    Some(swap_id) => *swap_id,
+                    None => return Ok(()),
+                };
+                let swap = result["value"]["data"][0]
+                    .as_str()
+                    .and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+                    .and_then(|data| SwapAccount::decode(&data).ok())
+                    .map(Box::new);
+                events.send(ProgramEvent::SwapAccount {
+                    swap_id,
+                    slot: result["context"]["slot"].as_u64().unwrap_or(0),
+                    swap,
+                }).await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn account_subscribe(&self, session: &mut Session, swap_id: [u8; 32]) -> Message {
+        let id = session.next_request_id;
+        session.next_request_id += 1;
+        session.pending.insert(id, swap_id);
+        self.request(id, "accountSubscribe", serde_json::json!([
+            self.watched[&swap_id],
+            { "encoding": "base64", "commitment": self.client.commitment() }
+        ]))
+    }
+
+    fn request(&self, id: u64, method: &str, params: serde_json::Value) -> Message {
+        Message::Text(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::mock_rpc::MockRpc;
+    use crate::clients::solana_program::{decode_pubkey, refund_instruction, DEFAULT_PROGRAM_ID};
+    use crate::swap_engine::Direction;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    const SWAP_ID: [u8; 32] = [1; 32];
+
+    /// `getTransaction` of a program refund of `SWAP_ID`.
+    fn refund_transaction(slot: u64) -> Value {
+        let program_id = decode_pubkey(DEFAULT_PROGRAM_ID).unwrap();
+        let refund = refund_instruction(&program_id, SWAP_ID, [2; 32], [3; 32]).unwrap();
+        json!({
+            "slot": slot,
+            "transaction": { "message": {
+                "accountKeys": [solana_program::encode_pubkey(&[2; 32]), DEFAULT_PROGRAM_ID],
+                "instructions": [{ "programIdIndex": 1, "data": bs58::encode(refund.data).into_string() }],
+            } },
+            "meta": { "err": null },
+        })
+    }
+
+    async fn subscriber(rpc: &MockRpc, ws_url: Option<String>) -> SolanaSubscriber {
+        let mut client = rpc.solana_client().await;
+        client.config.ws_url = ws_url;
+        SolanaSubscriber::new(client).unwrap().0
+    }
+
+    fn swap_account() -> SwapAccount {
+        SwapAccount {
+            direction: Direction::UsdcToXmr,
+            swap_id: SWAP_ID,
+            alice: [2; 32],
+            bob: [3; 32],
+            secret_hash: [4; 32],
+            expiry: 1_700_086_400,
+            relayer_fee: 0,
+            is_redeemed: true,
+            is_refunded: false,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            monero_sub_address: [0; 64],
+            monero_lock_txid: [5; 32],
+            alice_solana: [2; 32],
+            bump: 254,
+            vtc_opened: false,
+            bob_collateral_locked: false,
+            alice_collateral_locked: false,
+            bounty_claimed: false,
+        }
+    }
+
+    fn logs_notification(signature: &str, logs: &[&str], err: Value) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "logsNotification",
+            "params": { "subscription": 0, "result": {
+                "context": { "slot": 77 },
+                "value": { "signature": signature, "logs": logs, "err": err },
+            } },
+        }).to_string()
+    }
+
+    #[tokio::test]
+    async fn log_notifications_are_resolved_through_the_transaction() {
+        let rpc = MockRpc::start(|method, _| match method {
+            "getTransaction" => Ok(refund_transaction(76)),
+            other => Err((-32601, other.to_string())),
+        }).await;
+        let mut subscriber = subscriber(&rpc, None).await;
+        let (events, mut received) = mpsc::channel(8);
+
+        let message = logs_notification("sig-1", &["Program log: Instruction: Refund"], Value::Null);
+        subscriber.handle_message(&mut Session::default(), &message, &events).await.unwrap();
+        let Some(ProgramEvent::Transaction(tx)) = received.try_recv().ok() else { panic!("no transaction") };
+        assert_eq!((tx.signature.as_str(), tx.slot, tx.failed), ("sig-1", 76, false));
+        assert_eq!(tx.instructions, vec![(ProgramInstruction::Refund, Some(SWAP_ID))]);
+        assert_eq!(subscriber.cursor.as_deref(), Some("sig-1"));
+    }
+
+    #[tokio::test]
+    async fn log_notifications_fall_back_to_the_logs_when_the_transaction_is_unavailable() {
+        let rpc = MockRpc::start(|_, _| Err((-32007, "not yet available".to_string()))).await;
+        let mut subscriber = subscriber(&rpc, None).await;
+        let (events, mut received) = mpsc::channel(8);
+
+        let logs = ["Program log: Instruction: RedeemUsdc", "Program log: something else"];
+        let message = logs_notification("sig-2", &logs, json!({ "InstructionError": [0, { "Custom": 6000 }] }));
+        subscriber.handle_message(&mut Session::default(), &message, &events).await.unwrap();
+        let Some(ProgramEvent::Transaction(tx)) = received.try_recv().ok() else { panic!("no transaction") };
+        assert_eq!((tx.signature.as_str(), tx.slot, tx.failed), ("sig-2", 77, true));
+        assert_eq!(tx.instructions, vec![(ProgramInstruction::RedeemUsdc, None)]);
+        assert!(ProgramEvent::Transaction(tx).swap_ids().is_empty());
+
+        // Nothing without a signature, and subscription errors are skipped
+        let unsigned = json!({ "method": "logsNotification", "params": { "result": { "value": {} } } }).to_string();
+        subscriber.handle_message(&mut Session::default(), &unsigned, &events).await.unwrap();
+        let error = json!({ "id": 1, "error": { "code": -32602, "message": "Invalid params" } }).to_string();
+        subscriber.handle_message(&mut Session::default(), &error, &events).await.unwrap();
+        assert!(received.try_recv().is_err());
+        assert_eq!(subscriber.cursor.as_deref(), Some("sig-2"));
+    }
+
+    #[tokio::test]
+    async fn account_notifications_decode_the_watched_swap() {
+        let rpc = MockRpc::start(|_, _| Ok(Value::Null)).await;
+        let mut subscriber = subscriber(&rpc, None).await;
+        let (events, mut received) = mpsc::channel(8);
+        let mut session = Session::default();
+        session.pending.insert(5, SWAP_ID);
+
+        // The subscription id arrives in the reply to accountSubscribe
+        let confirmed = json!({ "jsonrpc": "2.0", "id": 5, "result": 42 }).to_string();
+        subscriber.handle_message(&mut session, &confirmed, &events).await.unwrap();
+        assert_eq!(session.accounts.get(&42), Some(&SWAP_ID));
+        assert!(session.pending.is_empty());
+
+        let notification = |subscription: u64, data: &[u8]| json!({
+            "jsonrpc": "2.0",
+            "method": "accountNotification",
+            "params": { "subscription": subscription, "result": {
+                "context": { "slot": 90 },
+                "value": { "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"] },
+            } },
+        }).to_string();
+
+        subscriber.handle_message(&mut session, &notification(42, &swap_account().encode()), &events).await.unwrap();
+        match received.try_recv().unwrap() {
+            ProgramEvent::SwapAccount { swap_id, slot, swap } => {
+                assert_eq!((swap_id, slot), (SWAP_ID, 90));
+                assert_eq!(swap.as_deref(), Some(&swap_account()));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // A closed account leaves no data to decode
+        subscriber.handle_message(&mut session, &notification(42, &[]), &events).await.unwrap();
+        assert!(matches!(received.try_recv().unwrap(), ProgramEvent::SwapAccount { swap: None, .. }));
+
+        // Another subscription's notification is not this swap's
+        subscriber.handle_message(&mut session, &notification(43, &swap_account().encode()), &events).await.unwrap();
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn transactions_missed_while_the_websocket_was_down_are_polled_for() {
+        // sig-0 is the newest when the subscriber first connects; sig-1 and
+        // sig-2 land while it is disconnected
+        let rpc = MockRpc::start(|method, params| match method {
+            "getSignaturesForAddress" => Ok(match params[1]["until"].as_str() {
+                None => json!([{ "signature": "sig-0", "slot": 10, "err": null }]),
+                Some("sig-0") => json!([
+                    { "signature": "sig-2", "slot": 12, "err": null },
+                    { "signature": "sig-1", "slot": 11, "err": null },
+                ]),
+                Some(_) => json!([]),
+            }),
+            "getTransaction" => Ok(refund_transaction(11)),
+            other => Err((-32601, other.to_string())),
+        }).await;
+
+        // The first connection is dropped once subscribed, the second stays up
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            for first in [true, false] {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let subscribe = socket.next().await.unwrap().unwrap();
+                assert!(subscribe.to_text().unwrap().contains("logsSubscribe"));
+                if first {
+                    socket.close(None).await.unwrap();
+                } else {
+                    connections.push(socket);
+                }
+            }
+            std::future::pending::<()>().await;
+            drop(connections);
+        });
+
+        let subscriber = subscriber(&rpc, Some(ws_url)).await;
+        let (events, mut received) = mpsc::channel(8);
+        let running = tokio::spawn(subscriber.run(events));
+
+        let mut backfilled = Vec::new();
+        while backfilled.len() < 2 {
+            match tokio::time::timeout(Duration::from_secs(10), received.recv()).await.unwrap().unwrap() {
+                ProgramEvent::Transaction(tx) => backfilled.push(tx.signature),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(backfilled, vec!["sig-1", "sig-2"]);
+        assert_eq!(rpc.calls("getSignaturesForAddress").len(), 2);
+
+        running.abort();
+        server.abort();
+    }
+}
@@ -0,0 +1,130 @@
+use crate::clients::{MonerodClient, MoneroClient, SolanaClient};
+
+use std::future::Future;
+use std::sync::Arc;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Chain {
+    Monero,
+    Solana,
+}
+
+impl Chain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Chain::Monero => "monero",
+            Chain::Solana => "solana",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "monero" => Some(Chain::Monero),
+            "solana" => Some(Chain::Solana),
+            _ => None,
+        }
+    }
+}
+
+/// Where a transaction currently sits on a chain's best fork.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inclusion {
+    /// Block height (Monero) or slot (Solana).
+    pub height: u64,
+    /// Empty when the chain can't report block hashes, in which case only
+    /// the height is compared.
+    pub block_hash: String,
+    pub confirmations: u64,
+    /// The chain itself considers the block irreversible.
+    pub finalized: bool,
+}
+
+/// Read-only view of a chain, enough to re-check past observations.
+pub trait ChainView: Send + Sync {
+    fn chain(&self) -> Chain;
+
+    /// Locate a transaction on the best chain. `None` if it is not in a
+    /// block, including when it has dropped back to the mempool.
+    fn locate(&self, reference: &str) -> impl Future<Output = Result<Option<Inclusion>>> + Send;
+}
+
+/// Monero through the wallet RPC, with block hashes from monerod when
+/// `monero.daemon_url` is configured.
+#[derive(Clone)]
+pub struct MoneroChain {
+    wallet: Arc<MoneroClient>,
+    daemon: Option<MonerodClient>,
+}
+
+impl MoneroChain {
+    pub fn new(wallet: Arc<MoneroClient>, daemon: Option<MonerodClient>) -> Self {
+        Self { wallet, daemon }
+    }
+}
+
+impl ChainView for MoneroChain {
+    fn chain(&self) -> Chain {
+        Chain::Monero
+    }
+
+    async fn locate(&self, reference: &str) -> Result<Option<Inclusion>> {
+        let transfer = match self.wallet.get_transfers(reference).await? {
+            Some(transfer) => transfer,
+            None => return Ok(None),
+        };
+        let height = transfer.height;
+        if height == 0 {
+            return Ok(None);
+        }
+
+        let block_hash = match &self.daemon {
+            Some(daemon) => daemon.get_block_header_by_height(height).await?.hash,
+            None => String::new(),
+        };
+
+        Ok(Some(Inclusion {
+            height,
+            block_hash,
+            confirmations: transfer.confirmations,
+            finalized: false,
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct SolanaChain {
+    client: SolanaClient,
+}
+
+impl SolanaChain {
+    pub fn new(client: SolanaClient) -> Self {
+        Self { client }
+    }
+}
+
+impl ChainView for SolanaChain {
+    fn chain(&self) -> Chain {
+        Chain::Solana
+    }
+
+    async fn locate(&self, reference: &str) -> Result<Option<Inclusion>> {
+        let (slot, finalized) = match self.client.get_signature_status(reference).await? {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+        let block_hash = match self.client.get_block_hash(slot).await? {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        let tip = self.client.get_slot("confirmed").await?;
+
+        Ok(Some(Inclusion {
+            height: slot,
+            block_hash,
+            confirmations: tip.saturating_sub(slot),
+            finalized,
+        }))
+    }
+}
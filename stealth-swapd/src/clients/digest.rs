@@ -0,0 +1,160 @@
+use anyhow::Result;
+use digest_auth::{AuthContext, WwwAuthenticateHeader};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// `--rpc-login` credentials for monerod or monero-wallet-rpc.
+#[derive(Clone)]
+pub struct DigestCredentials {
+    pub username: String,
+    pub password: SecretString,
+}
+
+/// HTTP client that answers the HTTP digest challenges the Monero RPCs
+/// send. The last challenge is reused, so only the first request (and any
+/// after the server rotates its nonce) takes an extra round trip.
+#[derive(Clone)]
+pub struct DigestClient {
+    http_client: reqwest::Client,
+    credentials: Option<DigestCredentials>,
+    challenge: Arc<Mutex<Option<WwwAuthenticateHeader>>>,
+}
+
+impl DigestClient {
+    pub fn new(credentials: Option<DigestCredentials>, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            http_client: reqwest::Client::builder().timeout(timeout).build()?,
+            credentials,
+            challenge: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub async fn post_json<T: Serialize + ?Sized>(&self, url: &str, body: &T) -> Result<reqwest::Response> {
+        let body = serde_json::to_vec(body)?;
+
+        let response = self.send(url, &body).await?;
+        if response.status() != StatusCode::UNAUTHORIZED || self.credentials.is_none() {
+            return Ok(response);
+        }
+
+        let prompt = response
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| anyhow::anyhow!("{} answered 401 without a digest challenge", url))?;
+        let challenge = digest_auth::parse(prompt).map_err(|e| anyhow::anyhow!("Bad digest challenge from {}: {}", url, e))?;
+        *self.challenge.lock().unwrap_or_else(|e| e.into_inner()) = Some(challenge);
+
+        self.send(url, &body).await
+    }
+
+    async fn send(&self, url: &str, body: &[u8]) -> Result<reqwest::Response> {
+        let mut request = self.http_client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_vec());
+        if let Some(authorization) = self.authorization(url, body)? {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        Ok(request.send().await?)
+    }
+
+    fn authorization(&self, url: &str, body: &[u8]) -> Result<Option<String>> {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => return Ok(None),
+        };
+        let mut challenge = self.challenge.lock().unwrap_or_else(|e| e.into_inner());
+        let challenge = match challenge.as_mut() {
+            Some(challenge) => challenge,
+            None => return Ok(None),
+        };
+
+        let uri = reqwest::Url::parse(url)?.path().to_string();
+        let context = AuthContext::new_post(
+            credentials.username.as_str(),
+            credentials.password.expose_secret().as_str(),
+            uri,
+            Some(body),
+        );
+        let answer = challenge
+            .respond(&context)
+            .map_err(|e| anyhow::anyhow!("Failed to answer digest challenge: {}", e))?;
+        Ok(Some(answer.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digest_auth::{AuthorizationHeader, HttpMethod, Qop};
+
+    /// The challenge from RFC 2617 §3.5.
+    const RFC_2617_CHALLENGE: &str = "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+        nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"";
+
+    fn client(credentials: Option<(&str, &str)>) -> DigestClient {
+        let credentials = credentials.map(|(username, password)| DigestCredentials {
+            username: username.to_string(),
+            password: SecretString::new(password.to_string()),
+        });
+        DigestClient::new(credentials, Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn answers_the_rfc_2617_example() {
+        let mut challenge = digest_auth::parse(RFC_2617_CHALLENGE).unwrap();
+        let mut context = AuthContext::new("Mufasa", "Circle Of Life", "/dir/index.html");
+        context.set_custom_cnonce("0a4f113b");
+
+        let answer = challenge.respond(&context).unwrap();
+        assert_eq!(answer.response, "6629fae49393a05397450978507c4ef1");
+        assert_eq!(answer.nc, 1);
+        assert_eq!(answer.qop, Some(Qop::AUTH));
+        assert_eq!(answer.opaque.as_deref(), Some("5ccc069c403ebaf9f0171e9517f40e41"));
+    }
+
+    #[test]
+    fn posts_are_signed_for_the_rpc_path_with_a_rising_nonce_count() {
+        let client = client(Some(("Mufasa", "Circle Of Life")));
+        let body = br#"{"jsonrpc":"2.0","id":"0","method":"get_height"}"#;
+        let url = "http://127.0.0.1:18082/json_rpc";
+
+        // Nothing to answer before the server has challenged us
+        assert_eq!(client.authorization(url, body).unwrap(), None);
+        *client.challenge.lock().unwrap() = Some(digest_auth::parse(RFC_2617_CHALLENGE).unwrap());
+
+        for nc in 1..=2 {
+            let header = client.authorization(url, body).unwrap().unwrap();
+            let answer = AuthorizationHeader::parse(&header).unwrap();
+            assert_eq!(answer.username, "Mufasa");
+            assert_eq!(answer.realm, "testrealm@host.com");
+            assert_eq!(answer.nonce, "dcd98b7102dd2f0e8b11d0f600bfb0c093");
+            assert_eq!(answer.uri, "/json_rpc");
+            assert_eq!(answer.nc, nc);
+
+            // The same answer computed independently for a POST of this body
+            let mut expected = digest_auth::parse(RFC_2617_CHALLENGE).unwrap();
+            expected.nc = nc - 1;
+            let mut context = AuthContext::new_with_method(
+                "Mufasa",
+                "Circle Of Life",
+                "/json_rpc",
+                Some(&body[..]),
+                HttpMethod::POST,
+            );
+            context.set_custom_cnonce(answer.cnonce.clone().unwrap());
+            assert_eq!(answer.response, expected.respond(&context).unwrap().response);
+        }
+    }
+
+    #[test]
+    fn no_credentials_send_no_authorization() {
+        let client = client(None);
+        *client.challenge.lock().unwrap() = Some(digest_auth::parse(RFC_2617_CHALLENGE).unwrap());
+        assert_eq!(client.authorization("http://127.0.0.1:18081/json_rpc", b"{}").unwrap(), None);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::clients::{MoneroClient, SolanaClient};
+use crate::config::AppConfig;
+use crate::metrics::MetricsCollector;
+use crate::swap_engine::SwapEngine;
+
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use secrecy::SecretString;
+use serde_json::Value;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::{Arc, Mutex};
+
+/// Seed of the keypair [`MockRpc::solana_client`] signs with.
+const SOLANA_SEED: u8 = 7;
+
+type Respond = dyn Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync;
+
+#[derive(Clone)]
+struct Shared {
+    respond: Arc<Respond>,
+    calls: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+/// JSON-RPC server on a loopback port for client tests. Every request, on
+/// any path, is answered by `respond` with its method and params: `Ok` is
+/// the `result`, `Err` an `error` with that code and message. A body that
+/// isn't JSON-RPC, as monerod's other endpoints take, is passed whole with
+/// the path as the method; `Ok` is the response body and `Err` an HTTP
+/// status with that code.
+pub(crate) struct MockRpc {
+    pub url: String,
+    calls: Arc<Mutex<Vec<(String, Value)>>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl MockRpc {
+    pub async fn start(respond: impl Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync + 'static) -> Self {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new().fallback(handle).with_state(Shared {
+            respond: Arc::new(respond),
+            calls: calls.clone(),
+        });
+        let server = tokio::spawn(async move {
+            axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()).await.unwrap();
+        });
+        Self { url, calls, server }
+    }
+
+    /// A wallet-rpc server that answers the calls `MoneroClient::new` makes
+    /// and passes every other one to `respond`.
+    pub async fn wallet(respond: impl Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync + 'static) -> Self {
+        Self::start(move |method, params| match method {
+            "get_version" => Ok(serde_json::json!({ "version": 65562 })),
+            "open_wallet" => Ok(serde_json::json!({})),
+            _ => respond(method, params),
+        })
+        .await
+    }
+
+    /// A wallet-rpc client against this server, with the default config
+    /// otherwise. The server must have been started with [`MockRpc::wallet`].
+    pub async fn monero_client(&self) -> MoneroClient {
+        let mut config = AppConfig::default().monero;
+        config.wallet_rpc_url = self.url.clone();
+        MoneroClient::new(&config, SecretString::new(String::new())).await.unwrap()
+    }
+
+    /// A Solana client against this server, with the default config
+    /// otherwise, signing with the keypair of [`solana_authority`].
+    pub async fn solana_client(&self) -> SolanaClient {
+        let keypair_path = std::env::temp_dir().join(format!("bob-{}.json", uuid::Uuid::new_v4()));
+        let mut bytes = vec![SOLANA_SEED; 32];
+        bytes.extend_from_slice(&solana_authority());
+        std::fs::write(&keypair_path, serde_json::to_string(&bytes).unwrap()).unwrap();
+
+        let mut config = AppConfig::default().solana;
+        config.rpc_url = self.url.clone();
+        config.keypair_path = keypair_path.clone();
+        let client = SolanaClient::new(&config).await.unwrap();
+        std::fs::remove_file(keypair_path).unwrap();
+        client
+    }
+
+    /// Params of each call to `method`, oldest first.
+    pub fn calls(&self, method: &str) -> Vec<Value> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(called, _)| called == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+}
+
+impl Drop for MockRpc {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// A swap engine on the default config and an in-memory database, with
+/// its wallet-rpc served by `wallet` and its Solana RPC by `solana`.
+pub(crate) async fn swap_engine(wallet: &MockRpc, solana: &MockRpc) -> SwapEngine {
+    let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations").run(&db).await.unwrap();
+    let metrics = MetricsCollector::new();
+    SwapEngine::new(AppConfig::default(), solana.solana_client().await, wallet.monero_client().await, metrics, db)
+        .await
+        .unwrap()
+}
+
+/// Public key of the keypair [`MockRpc::solana_client`] signs with.
+pub(crate) fn solana_authority() -> [u8; 32] {
+    let pair = ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::new([SOLANA_SEED; 32]));
+    pair.pk.as_ref().try_into().unwrap()
+}
+
+async fn handle(State(shared): State<Shared>, uri: Uri, Json(request): Json<Value>) -> Response {
+    let (method, params) = match request["method"].as_str() {
+        Some(method) => (method.to_string(), request["params"].clone()),
+        None => (uri.path().trim_start_matches('/').to_string(), request.clone()),
+    };
+    shared.calls.lock().unwrap().push((method.clone(), params.clone()));
+    let answer = (shared.respond)(&method, &params);
+
+    if request.get("method").is_none() {
+        return match answer {
+            Ok(body) => Json(body).into_response(),
+            Err((code, message)) => (StatusCode::from_u16(code as u16).unwrap(), message).into_response(),
+        };
+    }
+    let mut response = serde_json::json!({ "jsonrpc": "2.0", "id": request["id"] });
+    match answer {
+        Ok(result) => response["result"] = result,
+        Err((code, message)) => response["error"] = serde_json::json!({ "code": code, "message": message }),
+    }
+    Json(response).into_response()
+}
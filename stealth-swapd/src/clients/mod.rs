@@ -1,5 +1,12 @@
 pub mod solana;
+pub mod solana_program;
+pub mod solana_tx;
 pub mod monero;
+pub mod monerod;
+pub mod digest;
+#[cfg(test)]
+pub(crate) mod mock_rpc;
 
 pub use solana::SolanaClient;
-pub use monero::MoneroClient;
\ No newline at end of file
+pub use monero::MoneroClient;
+pub use monerod::MonerodClient;
\ No newline at end of file
@@ -1,15 +1,161 @@
-use crate::config::MoneroConfig;
+use crate::config::{MoneroConfig, WalletRestoreConfig};
+use super::digest::{DigestClient, DigestCredentials};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use rand::Rng;
 use std::time::Duration;
 use secrecy::{SecretString, ExposeSecret};
 
+type Result<T> = std::result::Result<T, MoneroRpcError>;
+
+/// A failed monero-wallet-rpc call, with the wallet's error codes that the
+/// engine reacts to broken out.
+#[derive(Debug, thiserror::Error)]
+pub enum MoneroRpcError {
+    #[error("Monero wallet RPC unreachable: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    #[error("Monero wallet RPC answered HTTP {0}")]
+    Http(reqwest::StatusCode),
+
+    #[error("Monero wallet RPC rejected the credentials")]
+    Unauthorized,
+
+    #[error("Invalid Monero address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Monero wallet busy: {0}")]
+    WalletBusy(String),
+
+    #[error("Transaction not found: {0}")]
+    TransactionNotFound(String),
+
+    #[error("No Monero wallet open")]
+    WalletNotOpen,
+
+    #[error("Monero wallet file already exists")]
+    WalletAlreadyExists,
+
+    #[error("Not enough money: {0}")]
+    NotEnoughMoney(String),
+
+    #[error("Transaction not possible: {0}")]
+    TxNotPossible(String),
+
+    #[error("Invalid Monero wallet password")]
+    InvalidPassword,
+
+    #[error("Monero RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("Unexpected Monero RPC response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("Monero wallet RPC client error: {0}")]
+    Client(anyhow::Error),
+}
+
+impl MoneroRpcError {
+    /// Unwrap transport failures from the digest client so they can be
+    /// retried.
+    fn from_client(error: anyhow::Error) -> Self {
+        match error.downcast::<reqwest::Error>() {
+            Ok(error) => MoneroRpcError::Transport(error),
+            Err(error) => MoneroRpcError::Client(error),
+        }
+    }
+
+    /// The error for an HTTP status other than success.
+    fn from_status(status: reqwest::StatusCode) -> Option<Self> {
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED => Some(MoneroRpcError::Unauthorized),
+            status if !status.is_success() => Some(MoneroRpcError::Http(status)),
+            _ => None,
+        }
+    }
+
+    fn from_code(code: i64, message: String) -> Self {
+        match code {
+            -2 => MoneroRpcError::InvalidAddress(message),
+            -3 => MoneroRpcError::WalletBusy(message),
+            -8 => MoneroRpcError::TransactionNotFound(message),
+            -13 => MoneroRpcError::WalletNotOpen,
+            -16 => MoneroRpcError::TxNotPossible(message),
+            -17 | -37 => MoneroRpcError::NotEnoughMoney(message),
+            -21 => MoneroRpcError::WalletAlreadyExists,
+            -22 => MoneroRpcError::InvalidPassword,
+            _ => MoneroRpcError::Rpc { code, message },
+        }
+    }
+
+    /// Whether the daemon refused a relayed transaction because its inputs
+    /// are already spent.
+    pub fn is_double_spend(&self) -> bool {
+        match self {
+            MoneroRpcError::Rpc { message, .. } | MoneroRpcError::TxNotPossible(message) => {
+                message.to_lowercase().contains("double spend")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the call may succeed if repeated. Calls that spend are only
+    /// retried when the wallet can't have acted on them: it was never
+    /// reached, or it refused because it was busy.
+    pub fn is_transient(&self, idempotent: bool) -> bool {
+        match self {
+            MoneroRpcError::Transport(e) => e.is_connect() || (idempotent && e.is_timeout()),
+            MoneroRpcError::Http(status) => idempotent && status.is_server_error(),
+            MoneroRpcError::WalletBusy(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Exponential backoff with jitter for transient wallet RPC failures.
+#[derive(Debug, Clone)]
+struct RetryPolicy {
+    max_retries: u32,
+    base: Duration,
+    max: Duration,
+}
+
+impl RetryPolicy {
+    fn new(config: &MoneroConfig) -> Self {
+        Self {
+            max_retries: config.rpc_max_retries.unwrap_or(3),
+            base: Duration::from_millis(config.rpc_retry_base_ms.unwrap_or(250)),
+            max: Duration::from_millis(config.rpc_retry_max_ms.unwrap_or(5_000)),
+        }
+    }
+
+    /// Delay before retry `retry` (1-based): the doubled base, capped, then
+    /// drawn uniformly from its upper half so clients don't retry in step.
+    fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self.base
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max);
+        let floor = ceiling / 2;
+        floor + ceiling.saturating_sub(floor).mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
 #[derive(Clone)]
 pub struct MoneroClient {
     rpc_url: String,
     wallet_name: String,
     password: SecretString,
-    http_client: reqwest::Client,
+    http_client: DigestClient,
+    retry: RetryPolicy,
+    create_if_missing: bool,
+    restore: Option<WalletRestoreConfig>,
+}
+
+/// Outcome of a `refresh`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletRefresh {
+    pub blocks_fetched: u64,
+    pub received_money: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,212 +163,775 @@ pub struct MoneroBalance {
     pub unlocked: u64,
     pub locked: u64,
     pub total: u64,
+    /// Blocks until the most recently received (or change) output unlocks.
+    pub blocks_to_unlock: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct RpcRequest {
-    jsonrpc: String,
-    id: String,
-    method: String,
-    params: serde_json::Value,
+pub struct AddressValidation {
+    pub valid: bool,
+    pub integrated: bool,
+    pub subaddress: bool,
+    /// "mainnet", "stagenet" or "testnet".
+    pub nettype: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Subaddress {
+    pub address: String,
+    /// Minor index within account 0.
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SubaddressIndex {
+    pub major: u32,
+    pub minor: u32,
 }
 
+/// A wallet transfer as returned by `get_transfer_by_txid`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct RpcResponse<T> {
+pub struct Transfer {
+    pub txid: String,
+    pub amount: u64,
+    #[serde(default)]
+    pub fee: u64,
+    #[serde(default)]
+    pub confirmations: u64,
+    /// 0 while in the pool.
+    #[serde(default)]
+    pub height: u64,
+    #[serde(default)]
+    pub subaddr_index: SubaddressIndex,
+    #[serde(default)]
+    pub timestamp: i64,
+    /// "in", "out", "pending", "failed" or "pool".
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub double_spend_seen: bool,
+    #[serde(default)]
+    pub unlock_time: u64,
+}
+
+/// An incoming transfer to one of the wallet's subaddresses, confirmed or
+/// still in the txpool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncomingTransfer {
+    pub txid: String,
+    pub amount: u64,
+    pub confirmations: u64,
+    /// 0 while in the pool.
+    pub height: u64,
+    pub subaddr_index: u32,
+    pub in_pool: bool,
+    pub timestamp: i64,
+    pub double_spend_seen: bool,
+}
+
+/// A transfer built and signed with `do_not_relay`, ready for `relay_tx`.
+/// Nothing is broadcast and the wallet marks no outputs spent until then.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuiltTransfer {
+    pub tx_hash: String,
+    pub tx_key: String,
+    /// Signed transaction hex.
+    pub tx_blob: String,
+    /// Wallet metadata hex that `relay_tx` takes.
+    pub tx_metadata: String,
+    /// Piconero.
+    pub fee: u64,
+}
+
+/// A relayed transfer and the fee it paid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentTransfer {
+    pub tx_hash: String,
+    /// Piconero.
+    pub fee: u64,
+}
+
+/// An unspent output of the wallet, from `incoming_transfers`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletOutput {
+    pub amount: u64,
+    pub key_image: String,
+    pub tx_hash: String,
+    #[serde(default)]
+    pub global_index: u64,
+    /// Height of the block that mined it.
+    #[serde(default)]
+    pub block_height: u64,
+    pub unlocked: bool,
+    #[serde(default)]
+    pub frozen: bool,
+    #[serde(default)]
+    pub subaddr_index: SubaddressIndex,
+}
+
+#[derive(Serialize)]
+struct RpcRequest<'a> {
+    jsonrpc: &'static str,
     id: String,
+    method: &'a str,
+    params: &'a serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
     result: Option<T>,
     error: Option<RpcError>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Deserialize)]
 struct RpcError {
     code: i64,
     message: String,
 }
 
+#[derive(Deserialize)]
+struct VersionResult {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct HeightResult {
+    height: u64,
+}
+
+#[derive(Deserialize)]
+struct BalanceResult {
+    balance: u64,
+    unlocked_balance: u64,
+    #[serde(default)]
+    blocks_to_unlock: u64,
+}
+
+#[derive(Deserialize)]
+struct CreateAddressResult {
+    address: String,
+    address_index: u32,
+}
+
+#[derive(Deserialize)]
+struct TransferResult {
+    #[serde(default)]
+    tx_hash: String,
+    #[serde(default)]
+    tx_key: String,
+    #[serde(default)]
+    tx_blob: String,
+    #[serde(default)]
+    tx_metadata: String,
+    #[serde(default)]
+    fee: u64,
+}
+
+#[derive(Deserialize)]
+struct ValidateAddressResult {
+    valid: bool,
+    #[serde(default)]
+    integrated: bool,
+    #[serde(default)]
+    subaddress: bool,
+    #[serde(default)]
+    nettype: String,
+}
+
+#[derive(Deserialize)]
+struct TransferByTxidResult {
+    transfer: Transfer,
+}
+
+#[derive(Deserialize)]
+struct TransfersResult {
+    #[serde(default)]
+    r#in: Vec<Transfer>,
+    #[serde(default)]
+    pool: Vec<Transfer>,
+}
+
+#[derive(Deserialize)]
+struct IncomingTransfersResult {
+    #[serde(default)]
+    transfers: Vec<WalletOutput>,
+}
+
+#[derive(Deserialize)]
+struct AddressResult {
+    address: String,
+}
+
+#[derive(Deserialize)]
+struct Empty {}
+
 impl MoneroClient {
     pub async fn new(
         config: &MoneroConfig,
         password: SecretString,
-    ) -> Result<Self> {
+    ) -> anyhow::Result<Self> {
+        let credentials = config.wallet_rpc_username.as_ref().map(|username| DigestCredentials {
+            username: username.clone(),
+            password: SecretString::new(
+                config.wallet_rpc_password_env
+                    .as_ref()
+                    .and_then(|env| std::env::var(env).ok())
+                    .unwrap_or_default(),
+            ),
+        });
+
+        let rpc_url = config.wallet_rpc_url.trim_end_matches('/');
         let client = Self {
-            rpc_url: config.wallet_rpc_url.clone(),
+            rpc_url: if rpc_url.ends_with("/json_rpc") {
+                rpc_url.to_string()
+            } else {
+                format!("{}/json_rpc", rpc_url)
+            },
             wallet_name: config.wallet_file.clone(),
             password,
-            http_client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()?
+            http_client: DigestClient::new(credentials, Duration::from_secs(30))?,
+            retry: RetryPolicy::new(config),
+            create_if_missing: config.create_if_missing.unwrap_or(false),
+            restore: config.restore.clone(),
         };
         
         // Test connection
         client.health_check().await?;
+        client.ensure_wallet().await?;
         
         Ok(client)
     }
 
     pub async fn health_check(&self) -> Result<bool> {
-        let response: serde_json::Value = 
-            self.call_rpc("get_version", serde_json::json!([])).await?;
+        let response: VersionResult =
+            self.call_rpc("get_version", serde_json::json!({})).await?;
         
-        match response.get("error") {
-            Some(_) => Ok(false),
-            None => Ok(true),
-        }
+        Ok(response.version > 0)
     }
 
     pub async fn get_height(&self) -> Result<u64> {
-        let response: serde_json::Value = 
-            self.call_rpc("get_height", serde_json::json!([])).await?;
-            
-        let result = response["result"]["height"]
-            .as_u64()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get height"))?;
+        let response: HeightResult =
+            self.call_rpc("get_height", serde_json::json!({})).await?;
             
-        Ok(result)
+        Ok(response.height)
     }
 
     pub async fn get_balance(&self) -> Result<MoneroBalance> {
-        let response: serde_json::Value = 
+        let balance: BalanceResult =
             self.call_rpc("get_balance", serde_json::json!({"account_index": 0})).await?;
-            
-        let balance = &response["result"];
         
         Ok(MoneroBalance {
-            unlocked: balance["unlocked_balance"]
-                .as_str()
-                .unwrap_or("0")
-                .parse::<u64>()
-                .unwrap_or(0),
-            locked: balance["locked_balance"]
-                .as_str()
-                .unwrap_or("0")
-                .parse::<u64>()
-                .unwrap_or(0),
-            total: balance["balance"]
-                .as_str()
-                .unwrap_or("0")
-                .parse::<u64>()
-                .unwrap_or(0),
+            unlocked: balance.unlocked_balance,
+            locked: balance.balance.saturating_sub(balance.unlocked_balance),
+            total: balance.balance,
+            blocks_to_unlock: balance.blocks_to_unlock,
         })
     }
 
-    pub async fn create_subaddress(&self, label: &str) -> Result<(String, [u8; 64])> {
+    pub async fn create_subaddress(&self, label: &str) -> Result<Subaddress> {
         let params = serde_json::json!({
             "account_index": 0,
             "label": label
         });
         
-        let response: serde_json::Value = 
+        let response: CreateAddressResult =
             self.call_rpc("create_address", params).await?;
-            
-        let address = response["result"]["address"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Failed to create subaddress"))?.to_string();
-        
-        // Convert address to 64-byte array
-        let mut bytes = [0u8; 64];
-        let address_bytes = address.as_bytes();
-        let len = std::cmp::min(address_bytes.len(), 64);
-        bytes[..len].copy_from_slice(&address_bytes[..len]);
-        
-        Ok((address, bytes))
+
+        Ok(Subaddress { address: response.address, index: response.address_index })
+    }
+
+    /// Build and sign a payout of `amount` without relaying it, to learn
+    /// its fee and keep it for `relay_tx`.
+    pub async fn build_transfer(
+        &self,
+        destination: &str,
+        amount: u64,
+        priority: u32,
+    ) -> Result<BuiltTransfer> {
+        let mut params = Self::transfer_params(destination, amount, priority, true);
+        params["get_tx_hex"] = serde_json::json!(true);
+        params["get_tx_metadata"] = serde_json::json!(true);
+        let response: TransferResult =
+            self.call_rpc("transfer", params).await?;
+
+        Ok(BuiltTransfer {
+            tx_hash: response.tx_hash,
+            tx_key: response.tx_key,
+            tx_blob: response.tx_blob,
+            tx_metadata: response.tx_metadata,
+            fee: response.fee,
+        })
+    }
+
+    /// Relay a transaction built by `build_transfer`. Relaying the same
+    /// transaction again is harmless: it has the same hash.
+    pub async fn relay_tx(&self, tx_metadata: &str) -> Result<String> {
+        let response: TransferResult = self
+            .call_rpc("relay_tx", serde_json::json!({ "hex": tx_metadata }))
+            .await?;
+        Ok(response.tx_hash)
     }
 
     pub async fn send_transfer(
         &self,
         destination: &str,
         amount: u64,
-    ) -> Result<String> {
-        let params = serde_json::json!({
+        priority: u32,
+    ) -> Result<SentTransfer> {
+        let params = Self::transfer_params(destination, amount, priority, false);
+        let response: TransferResult =
+            self.call_rpc_once("transfer", params).await?;
+            
+        Ok(SentTransfer { tx_hash: response.tx_hash, fee: response.fee })
+    }
+
+    fn transfer_params(destination: &str, amount: u64, priority: u32, do_not_relay: bool) -> serde_json::Value {
+        serde_json::json!({
             "destinations": [{
                 "address": destination,
-                "amount": amount.to_string()
+                "amount": amount
             }],
             "account_index": 0,
-            "priority": 1, // Normal priority
+            "priority": priority,
             "get_tx_key": true,
-            "unlock_time": 0
-        });
-        
-        let response: serde_json::Value = 
+            "unlock_time": 0,
+            "do_not_relay": do_not_relay
+        })
+    }
+
+    /// Build and sign, without relaying, a transfer of `amount` with the
+    /// network fee taken out of it rather than added on top, for returning
+    /// funds that were never Bob's.
+    pub async fn build_refund(
+        &self,
+        destination: &str,
+        amount: u64,
+        priority: u32,
+    ) -> Result<BuiltTransfer> {
+        let mut params = Self::transfer_params(destination, amount, priority, true);
+        params["subtract_fee_from_outputs"] = serde_json::json!([0]);
+        params["get_tx_hex"] = serde_json::json!(true);
+        params["get_tx_metadata"] = serde_json::json!(true);
+        let response: TransferResult =
             self.call_rpc("transfer", params).await?;
-            
-        let tx_hash = response["result"]["tx_hash"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get transaction hash"))?.to_string();
-            
-        Ok(tx_hash)
+
+        Ok(BuiltTransfer {
+            tx_hash: response.tx_hash,
+            tx_key: response.tx_key,
+            tx_blob: response.tx_blob,
+            tx_metadata: response.tx_metadata,
+            fee: response.fee,
+        })
+    }
+
+    /// Send the whole of one output, identified by its key image, to
+    /// `destination` as `outputs` equal outputs. Nothing else is spent, so
+    /// only that output's funds are locked while the sweep confirms.
+    pub async fn sweep_single(
+        &self,
+        key_image: &str,
+        destination: &str,
+        outputs: usize,
+    ) -> Result<String> {
+        let params = serde_json::json!({
+            "key_image": key_image,
+            "address": destination,
+            "outputs": outputs,
+            "account_index": 0,
+            "priority": 1
+        });
+
+        let response: TransferResult =
+            self.call_rpc_once("sweep_single", params).await?;
+
+        Ok(response.tx_hash)
+    }
+
+    /// Primary address of account 0.
+    pub async fn get_primary_address(&self) -> Result<String> {
+        let response: AddressResult = self
+            .call_rpc("get_address", serde_json::json!({"account_index": 0, "address_index": [0]}))
+            .await?;
+        Ok(response.address)
     }
 
-    pub async fn validate_address(&self, address: &str) -> Result<bool> {
+    /// Unspent outputs of account 0, locked and unlocked.
+    pub async fn get_outputs(&self) -> Result<Vec<WalletOutput>> {
+        let response: IncomingTransfersResult = self
+            .call_rpc("incoming_transfers", serde_json::json!({
+                "transfer_type": "available",
+                "account_index": 0
+            }))
+            .await?;
+        Ok(response.transfers)
+    }
+
+    pub async fn validate_address(&self, address: &str) -> Result<AddressValidation> {
         let params = serde_json::json!({
-            "address": address
+            "address": address,
+            "any_net_type": true
         });
 
-        let response: serde_json::Value = 
+        let response: ValidateAddressResult =
             self.call_rpc("validate_address", params).await?;
 
-        let valid = response["result"]["valid"].as_bool().unwrap_or(false);
-        Ok(valid)
+        Ok(AddressValidation {
+            valid: response.valid,
+            integrated: response.integrated,
+            subaddress: response.subaddress,
+            nettype: response.nettype,
+        })
     }
 
-    pub async fn get_transfers(&self, txid: &str) -> Result<Option<serde_json::Value>> {
+    pub async fn get_transfers(&self, txid: &str) -> Result<Option<Transfer>> {
         let params = serde_json::json!({
             "txid": txid
         });
         
-        let response: serde_json::Value = 
-            self.call_rpc("get_transfer_by_txid", params).await?;
-        
-        if response["result"].is_null() {
-            Ok(None)
+        // The wallet forgets a transaction that a reorg dropped from both
+        // the chain and the pool, and reports it as not found
+        match self.call_rpc::<TransferByTxidResult>("get_transfer_by_txid", params).await {
+            Ok(response) => Ok(Some(response.transfer)),
+            Err(MoneroRpcError::TransactionNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Incoming transfers, confirmed and in the pool, to the given
+    /// subaddress indices of account 0.
+    pub async fn get_incoming_transfers(&self, subaddr_indices: &[u32]) -> Result<Vec<IncomingTransfer>> {
+        if subaddr_indices.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.fetch_incoming_transfers(serde_json::json!({
+            "in": true,
+            "pool": true,
+            "account_index": 0,
+            "subaddr_indices": subaddr_indices,
+        })).await
+    }
+
+    /// Confirmed incoming transfers to any subaddress of account 0 mined at
+    /// or above `min_height`.
+    pub async fn get_incoming_transfers_since(&self, min_height: u64) -> Result<Vec<IncomingTransfer>> {
+        self.fetch_incoming_transfers(serde_json::json!({
+            "in": true,
+            "account_index": 0,
+            "filter_by_height": true,
+            "min_height": min_height,
+        })).await
+    }
+
+    async fn fetch_incoming_transfers(&self, params: serde_json::Value) -> Result<Vec<IncomingTransfer>> {
+        let response: TransfersResult =
+            self.call_rpc("get_transfers", params).await?;
+
+        let confirmed = response.r#in.into_iter().map(|transfer| (transfer, false));
+        let pooled = response.pool.into_iter().map(|transfer| (transfer, true));
+        Ok(confirmed
+            .chain(pooled)
+            .map(|(transfer, in_pool)| IncomingTransfer {
+                txid: transfer.txid,
+                amount: transfer.amount,
+                confirmations: transfer.confirmations,
+                height: transfer.height,
+                subaddr_index: transfer.subaddr_index.minor,
+                in_pool,
+                timestamp: transfer.timestamp,
+                double_spend_seen: transfer.double_spend_seen,
+            })
+            .collect())
+    }
+
+    /// Open `wallet_file`. If wallet-rpc can't open it, restore it from
+    /// keys or create it when configured to.
+    pub async fn ensure_wallet(&self) -> Result<()> {
+        let error = match self.open_wallet().await {
+            Ok(()) => return Ok(()),
+            // wallet2 reports a missing file as a generic failure
+            Err(e @ MoneroRpcError::Rpc { code: -1, .. }) => e,
+            Err(e) => return Err(e),
+        };
+
+        let created = if let Some(restore) = &self.restore {
+            tracing::info!("Restoring Monero wallet {} from keys", self.wallet_name);
+            self.restore_wallet(restore).await
+        } else if self.create_if_missing {
+            tracing::info!("Creating Monero wallet {}", self.wallet_name);
+            self.create_wallet().await
         } else {
-            Ok(Some(response["result"].clone()))
+            return Err(error);
+        };
+
+        match created {
+            Ok(()) => Ok(()),
+            // The file is there; opening it failed for another reason
+            Err(MoneroRpcError::WalletAlreadyExists) => Err(error),
+            Err(e) => Err(e),
         }
     }
 
     pub async fn open_wallet(&self) -> Result<()> {
+        self.call_rpc::<Empty>("open_wallet", self.open_params()).await?;
+        Ok(())
+    }
+
+    pub async fn close_wallet(&self) -> Result<()> {
+        self.call_rpc::<Empty>("close_wallet", serde_json::json!({})).await?;
+        Ok(())
+    }
+
+    async fn create_wallet(&self) -> Result<()> {
         let params = serde_json::json!({
             "filename": self.wallet_name,
-            "password": self.password.expose_secret()
+            "password": self.password.expose_secret(),
+            "language": "English"
         });
-        
-        self.call_rpc("open_wallet", params).await?;
+
+        self.call_rpc_once::<Empty>("create_wallet", params).await?;
         Ok(())
     }
 
-    pub async fn close_wallet(&self) -> Result<()> {
-        self.call_rpc("close_wallet", serde_json::json!([])).await?;
+    async fn restore_wallet(&self, restore: &WalletRestoreConfig) -> Result<()> {
+        let key = |env: &str| std::env::var(env).map_err(|_| {
+            MoneroRpcError::Client(anyhow::anyhow!("Missing wallet key environment variable {}", env))
+        });
+        let params = serde_json::json!({
+            "filename": self.wallet_name,
+            "password": self.password.expose_secret(),
+            "address": restore.address,
+            "spendkey": key(&restore.spend_key_env)?,
+            "viewkey": key(&restore.view_key_env)?,
+            "restore_height": restore.restore_height.unwrap_or(0),
+            "autosave_current": false
+        });
+
+        self.call_rpc_once::<Empty>("generate_from_keys", params).await?;
+        Ok(())
+    }
+
+    /// Save the wallet to disk.
+    pub async fn store(&self) -> Result<()> {
+        self.call_rpc::<Empty>("store", serde_json::json!({})).await?;
         Ok(())
     }
 
-    async fn call_rpc(
+    /// Sync the wallet with its daemon now rather than at its next
+    /// auto-refresh.
+    pub async fn refresh(&self) -> Result<WalletRefresh> {
+        self.call_rpc("refresh", serde_json::json!({})).await
+    }
+
+    /// Save the wallet every `interval`, for as long as the task runs.
+    pub async fn run_autosave(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.store().await {
+                tracing::warn!("Failed to store Monero wallet {}: {}", self.wallet_name, e);
+            }
+        }
+    }
+
+    fn open_params(&self) -> serde_json::Value {
+        serde_json::json!({
+            "filename": self.wallet_name,
+            "password": self.password.expose_secret()
+        })
+    }
+
+    /// Call a method that is safe to repeat, retrying transient failures.
+    async fn call_rpc<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        self.call_with_retry(method, params, true).await
+    }
+
+    /// Call a method that spends, retrying only when the wallet can't have
+    /// acted on the request.
+    async fn call_rpc_once<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        self.call_with_retry(method, params, false).await
+    }
+
+    async fn call_with_retry<T: DeserializeOwned>(
         &self,
         method: &str,
         params: serde_json::Value,
-    ) -> Result<serde_json::Value> {
+        idempotent: bool,
+    ) -> Result<T> {
+        let mut retries = 0;
+        let mut reopened = false;
+        loop {
+            match self.send(method, &params).await {
+                // wallet-rpc restarted and came back without a wallet; nothing
+                // was done, so reopen and repeat the call
+                Err(MoneroRpcError::WalletNotOpen) if !reopened && method != "open_wallet" => {
+                    reopened = true;
+                    tracing::warn!("Monero wallet {} not open; reopening", self.wallet_name);
+                    self.send::<Empty>("open_wallet", &self.open_params()).await?;
+                }
+                Err(e) if retries < self.retry.max_retries && e.is_transient(idempotent) => {
+                    retries += 1;
+                    let delay = self.retry.delay(retries);
+                    tracing::debug!("Monero RPC {} failed ({}); retry {} in {:?}", method, e, retries, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<T> {
         let request = RpcRequest {
-            jsonrpc: "2.0".to_string(),
+            jsonrpc: "2.0",
             id: uuid::Uuid::new_v4().to_string(),
-            method: method.to_string(),
+            method,
             params,
         };
 
         let response = self.http_client
-            .post(&self.rpc_url)
-            .json(&request)
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
+            .post_json(&self.rpc_url, &request)
+            .await
+            .map_err(MoneroRpcError::from_client)?;
+        if let Some(error) = MoneroRpcError::from_status(response.status()) {
+            return Err(error);
+        }
+        decode_response(method, &response.bytes().await?)
+    }
+}
 
-        let response_json: serde_json::Value = response.json().await?;
-        
-        if let Some(error) = response_json.get("error") {
-            let code = error["code"].as_i64().unwrap_or(-1);
-            let message = error["message"].as_str().unwrap_or("Unknown error");
-            return Err(anyhow::anyhow!("Monero RPC error {}: {}", code, message));
+/// The result of a JSON-RPC response body, or the wallet's error.
+fn decode_response<T: DeserializeOwned>(method: &str, body: &[u8]) -> Result<T> {
+    let response: RpcResponse<T> = serde_json::from_slice(body)?;
+    if let Some(error) = response.error {
+        return Err(MoneroRpcError::from_code(error.code, error.message));
+    }
+
+    response.result.ok_or_else(|| {
+        MoneroRpcError::Rpc { code: 0, message: format!("{} returned no result", method) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn error(code: i64, message: &str) -> MoneroRpcError {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "0",
+            "error": { "code": code, "message": message },
+        });
+        decode_response::<serde_json::Value>("transfer", body.to_string().as_bytes()).unwrap_err()
+    }
+
+    #[test]
+    fn wallet_error_codes_map_to_variants() {
+        assert!(matches!(error(-2, "Invalid address"), MoneroRpcError::InvalidAddress(m) if m == "Invalid address"));
+        assert!(matches!(error(-3, "busy"), MoneroRpcError::WalletBusy(_)));
+        assert!(matches!(error(-8, "not found"), MoneroRpcError::TransactionNotFound(_)));
+        assert!(matches!(error(-13, "No wallet file"), MoneroRpcError::WalletNotOpen));
+        assert!(matches!(error(-16, "tx not possible"), MoneroRpcError::TxNotPossible(_)));
+        assert!(matches!(error(-17, "not enough money"), MoneroRpcError::NotEnoughMoney(_)));
+        assert!(matches!(error(-37, "not enough unlocked money"), MoneroRpcError::NotEnoughMoney(_)));
+        assert!(matches!(error(-21, "exists"), MoneroRpcError::WalletAlreadyExists));
+        assert!(matches!(error(-22, "bad password"), MoneroRpcError::InvalidPassword));
+        assert!(matches!(error(-4, "generic"), MoneroRpcError::Rpc { code: -4, message } if message == "generic"));
+    }
+
+    #[test]
+    fn responses_decode_to_their_result() {
+        let body = br#"{"jsonrpc":"2.0","id":"0","result":{"height":3100000}}"#;
+        let result: serde_json::Value = decode_response("get_height", body).unwrap();
+        assert_eq!(result["height"], 3_100_000);
+
+        let empty = br#"{"jsonrpc":"2.0","id":"0"}"#;
+        let missing = decode_response::<serde_json::Value>("get_height", empty).unwrap_err();
+        assert!(matches!(missing, MoneroRpcError::Rpc { code: 0, message } if message.contains("get_height")));
+
+        let garbage = decode_response::<serde_json::Value>("get_height", b"<html>").unwrap_err();
+        assert!(matches!(garbage, MoneroRpcError::Decode(_)));
+    }
+
+    #[test]
+    fn http_statuses_map_to_errors() {
+        assert!(MoneroRpcError::from_status(StatusCode::OK).is_none());
+        assert!(matches!(MoneroRpcError::from_status(StatusCode::UNAUTHORIZED), Some(MoneroRpcError::Unauthorized)));
+        for status in [StatusCode::INTERNAL_SERVER_ERROR, StatusCode::BAD_GATEWAY, StatusCode::NOT_FOUND] {
+            assert!(matches!(MoneroRpcError::from_status(status), Some(MoneroRpcError::Http(s)) if s == status));
+        }
+    }
+
+    #[test]
+    fn only_calls_the_wallet_cannot_have_acted_on_are_retried_when_spending() {
+        // (error, retried when idempotent, retried when spending)
+        let table = [
+            (MoneroRpcError::WalletBusy("busy".to_string()), true, true),
+            (MoneroRpcError::Http(StatusCode::INTERNAL_SERVER_ERROR), true, false),
+            (MoneroRpcError::Http(StatusCode::SERVICE_UNAVAILABLE), true, false),
+            (MoneroRpcError::Http(StatusCode::NOT_FOUND), false, false),
+            (MoneroRpcError::Unauthorized, false, false),
+            (MoneroRpcError::NotEnoughMoney("short".to_string()), false, false),
+            (MoneroRpcError::InvalidAddress("bad".to_string()), false, false),
+            (MoneroRpcError::WalletNotOpen, false, false),
+            (MoneroRpcError::Rpc { code: -4, message: "generic".to_string() }, false, false),
+        ];
+        for (error, idempotent, spending) in table {
+            assert_eq!(error.is_transient(true), idempotent, "{}", error);
+            assert_eq!(error.is_transient(false), spending, "{}", error);
         }
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_retried_and_timeouts_only_when_idempotent() {
+        let client = reqwest::Client::builder().timeout(Duration::from_millis(100)).build().unwrap();
+
+        // Nothing listens on a port just released
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let refused = client.post(format!("http://127.0.0.1:{}/json_rpc", port)).send().await.unwrap_err();
+        let refused = MoneroRpcError::from_client(refused.into());
+        assert!(matches!(refused, MoneroRpcError::Transport(_)));
+        assert!(refused.is_transient(true) && refused.is_transient(false));
 
-        Ok(response_json)
+        // A listener that accepts but never answers
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/json_rpc", silent.local_addr().unwrap());
+        let timeout = MoneroRpcError::from_client(client.post(url).send().await.unwrap_err().into());
+        assert!(timeout.is_transient(true));
+        assert!(!timeout.is_transient(false));
+
+        let other = MoneroRpcError::from_client(anyhow::anyhow!("bad digest challenge"));
+        assert!(matches!(other, MoneroRpcError::Client(_)));
+        assert!(!other.is_transient(true));
+    }
+
+    #[test]
+    fn double_spends_are_recognised_from_the_message() {
+        assert!(error(-4, "Transaction was rejected by daemon: double spend").is_double_spend());
+        assert!(error(-16, "tx not possible: Double spend seen").is_double_spend());
+        assert!(!error(-17, "not enough money").is_double_spend());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn retry_delay_doubles_within_its_cap() {
+        let policy = RetryPolicy { max_retries: 5, base: Duration::from_millis(200), max: Duration::from_millis(1_000) };
+        for (retry, ceiling) in [(1, 200), (2, 400), (3, 800), (4, 1_000), (10, 1_000)] {
+            let delay = policy.delay(retry);
+            let ceiling = Duration::from_millis(ceiling);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "retry {}: {:?}", retry, delay);
+        }
+    }
+}
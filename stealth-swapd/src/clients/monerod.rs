@@ -0,0 +1,317 @@
+use crate::config::MoneroConfig;
+use super::digest::{DigestClient, DigestCredentials};
+
+use anyhow::Result;
+use secrecy::SecretString;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Header of a block on the daemon's best chain.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockHeader {
+    pub height: u64,
+    pub hash: String,
+}
+
+/// Subset of `get_info`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonInfo {
+    /// Number of blocks in the chain, i.e. the tip's height plus one.
+    pub height: u64,
+    pub top_block_hash: String,
+    /// Height the daemon is syncing towards; 0 once synchronized.
+    #[serde(default)]
+    pub target_height: u64,
+    #[serde(default)]
+    pub synchronized: bool,
+}
+
+/// A transaction as the daemon knows it, from `/get_transactions`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonTransaction {
+    pub tx_hash: String,
+    pub in_pool: bool,
+    /// 0 while in the pool.
+    #[serde(default)]
+    pub block_height: u64,
+    #[serde(default)]
+    pub double_spend_seen: bool,
+}
+
+/// An entry of the daemon's transaction pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolTransaction {
+    pub id_hash: String,
+    pub fee: u64,
+    pub weight: u64,
+    pub receive_time: i64,
+    #[serde(default)]
+    pub double_spend_seen: bool,
+    #[serde(default)]
+    pub relayed: bool,
+}
+
+/// `get_fee_estimate`: piconero per byte of weight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    pub fee: u64,
+    /// Per-byte fee for each priority, lowest first, when the daemon
+    /// reports them.
+    #[serde(default)]
+    pub fees: Vec<u64>,
+    #[serde(default)]
+    pub quantization_mask: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockHeaderResult {
+    block_header: BlockHeader,
+}
+
+#[derive(Deserialize)]
+struct TransactionsResult {
+    #[serde(default)]
+    txs: Vec<DaemonTransaction>,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct TransactionPoolResult {
+    #[serde(default)]
+    transactions: Vec<PoolTransaction>,
+    status: String,
+}
+
+/// JSON-RPC client for monerod, for chain data the wallet RPC doesn't expose.
+#[derive(Clone)]
+pub struct MonerodClient {
+    daemon_url: String,
+    http_client: DigestClient,
+}
+
+impl MonerodClient {
+    pub fn new(daemon_url: &str, credentials: Option<DigestCredentials>) -> Result<Self> {
+        Ok(Self {
+            daemon_url: daemon_url.trim_end_matches('/').to_string(),
+            http_client: DigestClient::new(credentials, Duration::from_secs(30))?,
+        })
+    }
+
+    /// Client for `monero.daemon_url`, or `None` when it isn't configured.
+    pub fn from_config(config: &MoneroConfig) -> Result<Option<Self>> {
+        let daemon_url = match &config.daemon_url {
+            Some(daemon_url) => daemon_url,
+            None => return Ok(None),
+        };
+        let credentials = config.daemon_username.as_ref().map(|username| DigestCredentials {
+            username: username.clone(),
+            password: SecretString::new(config.daemon_password.clone().unwrap_or_default()),
+        });
+        Self::new(daemon_url, credentials).map(Some)
+    }
+
+    pub async fn get_info(&self) -> Result<DaemonInfo> {
+        self.call_rpc("get_info", serde_json::json!({})).await
+    }
+
+    /// Number of blocks in the daemon's chain.
+    pub async fn get_height(&self) -> Result<u64> {
+        Ok(self.get_info().await?.height)
+    }
+
+    pub async fn get_tip(&self) -> Result<BlockHeader> {
+        let result: BlockHeaderResult = self.call_rpc("get_last_block_header", serde_json::json!({})).await?;
+        Ok(result.block_header)
+    }
+
+    pub async fn get_block_header_by_height(&self, height: u64) -> Result<BlockHeader> {
+        let result: BlockHeaderResult = self
+            .call_rpc("get_block_header_by_height", serde_json::json!({ "height": height }))
+            .await?;
+        Ok(result.block_header)
+    }
+
+    /// Transactions the daemon knows, in the pool or mined. Hashes it
+    /// doesn't know are left out.
+    pub async fn get_transactions(&self, tx_hashes: &[&str]) -> Result<Vec<DaemonTransaction>> {
+        let result: TransactionsResult = self
+            .call_other("get_transactions", &serde_json::json!({ "txs_hashes": tx_hashes, "decode_as_json": false }))
+            .await?;
+        check_status("get_transactions", &result.status)?;
+        Ok(result.txs)
+    }
+
+    pub async fn get_transaction_pool(&self) -> Result<Vec<PoolTransaction>> {
+        let result: TransactionPoolResult = self
+            .call_other("get_transaction_pool", &serde_json::json!({}))
+            .await?;
+        check_status("get_transaction_pool", &result.status)?;
+        Ok(result.transactions)
+    }
+
+    pub async fn get_fee_estimate(&self) -> Result<FeeEstimate> {
+        self.call_rpc("get_fee_estimate", serde_json::json!({})).await
+    }
+
+    async fn call_rpc<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "0",
+            "method": method,
+            "params": params,
+        });
+
+        let response: serde_json::Value = self.http_client
+            .post_json(&format!("{}/json_rpc", self.daemon_url), &request)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            let code = error["code"].as_i64().unwrap_or(-1);
+            let message = error["message"].as_str().unwrap_or("Unknown error");
+            return Err(anyhow::anyhow!("Monero daemon RPC error {}: {}", code, message));
+        }
+
+        Ok(serde_json::from_value(response["result"].clone())?)
+    }
+
+    /// Call one of the daemon's non-JSON-RPC endpoints, e.g. `/get_transactions`.
+    async fn call_other<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> Result<T> {
+        Ok(self.http_client
+            .post_json(&format!("{}/{}", self.daemon_url, path), body)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+fn check_status(method: &str, status: &str) -> Result<()> {
+    if status != "OK" {
+        return Err(anyhow::anyhow!("Monero daemon {} failed: {}", method, status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::mock_rpc::MockRpc;
+    use serde_json::{json, Value};
+
+    async fn daemon(respond: impl Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync + 'static) -> (MonerodClient, MockRpc) {
+        let rpc = MockRpc::start(respond).await;
+        let client = MonerodClient::new(&format!("{}/", rpc.url), None).unwrap();
+        (client, rpc)
+    }
+
+    #[tokio::test]
+    async fn height_and_sync_state_come_from_get_info() {
+        let (client, _rpc) = daemon(|method, _| match method {
+            "get_info" => Ok(json!({
+                "height": 3_100_001,
+                "top_block_hash": "ab".repeat(32),
+                "target_height": 0,
+                "synchronized": true,
+                "status": "OK",
+            })),
+            other => Err((-32601, other.to_string())),
+        }).await;
+
+        let info = client.get_info().await.unwrap();
+        assert_eq!((info.height, info.target_height, info.synchronized), (3_100_001, 0, true));
+        assert_eq!(client.get_height().await.unwrap(), 3_100_001);
+    }
+
+    #[tokio::test]
+    async fn a_daemon_still_syncing_may_omit_the_sync_fields() {
+        let (client, _rpc) = daemon(|_, _| Ok(json!({ "height": 10, "top_block_hash": "cd".repeat(32) }))).await;
+        let info = client.get_info().await.unwrap();
+        assert_eq!((info.target_height, info.synchronized), (0, false));
+    }
+
+    #[tokio::test]
+    async fn block_headers_are_read_by_height_and_at_the_tip() {
+        let (client, rpc) = daemon(|method, params| {
+            let height = match method {
+                "get_last_block_header" => 500,
+                "get_block_header_by_height" => params["height"].as_u64().unwrap(),
+                other => return Err((-32601, other.to_string())),
+            };
+            Ok(json!({ "block_header": { "height": height, "hash": format!("hash-{}", height), "nonce": 7 }, "status": "OK" }))
+        }).await;
+
+        let tip = client.get_tip().await.unwrap();
+        assert_eq!((tip.height, tip.hash.as_str()), (500, "hash-500"));
+        let header = client.get_block_header_by_height(42).await.unwrap();
+        assert_eq!((header.height, header.hash.as_str()), (42, "hash-42"));
+        assert_eq!(rpc.calls("get_block_header_by_height"), vec![json!({ "height": 42 })]);
+    }
+
+    #[tokio::test]
+    async fn transactions_and_the_pool_come_from_their_own_endpoints() {
+        let (client, rpc) = daemon(|method, _| match method {
+            "get_transactions" => Ok(json!({
+                "txs": [
+                    { "tx_hash": "aa", "in_pool": false, "block_height": 99 },
+                    { "tx_hash": "bb", "in_pool": true, "double_spend_seen": true },
+                ],
+                "status": "OK",
+            })),
+            // An empty pool leaves the list out
+            "get_transaction_pool" => Ok(json!({ "status": "OK" })),
+            other => Err((404, other.to_string())),
+        }).await;
+
+        let txs = client.get_transactions(&["aa", "bb", "cc"]).await.unwrap();
+        assert_eq!(txs.len(), 2);
+        assert_eq!((txs[0].in_pool, txs[0].block_height, txs[0].double_spend_seen), (false, 99, false));
+        assert_eq!((txs[1].in_pool, txs[1].block_height, txs[1].double_spend_seen), (true, 0, true));
+        assert_eq!(rpc.calls("get_transactions")[0]["txs_hashes"], json!(["aa", "bb", "cc"]));
+        assert!(client.get_transaction_pool().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_bad_statuses_and_malformed_results_are_errors() {
+        let (client, _rpc) = daemon(|method, _| match method {
+            "get_info" => Err((-9, "Core is busy".to_string())),
+            "get_last_block_header" => Ok(json!({ "status": "OK" })),
+            "get_transactions" => Ok(json!({ "status": "Failed" })),
+            "get_transaction_pool" => Err((500, "internal error".to_string())),
+            other => Err((-32601, other.to_string())),
+        }).await;
+
+        let error = client.get_height().await.unwrap_err().to_string();
+        assert!(error.contains("-9") && error.contains("Core is busy"), "{}", error);
+        assert!(client.get_tip().await.is_err());
+        let error = client.get_transactions(&["aa"]).await.unwrap_err().to_string();
+        assert!(error.contains("Failed"), "{}", error);
+        assert!(client.get_transaction_pool().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_daemon_is_an_error() {
+        // Nothing listens on a port just released
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let client = MonerodClient::new(&format!("http://127.0.0.1:{}", port), None).unwrap();
+        assert!(client.get_height().await.is_err());
+    }
+
+    #[test]
+    fn no_client_without_a_daemon_url() {
+        let mut config = crate::config::AppConfig::default().monero;
+        config.daemon_url = None;
+        assert!(MonerodClient::from_config(&config).unwrap().is_none());
+
+        config.daemon_url = Some("http://127.0.0.1:18081/".to_string());
+        let client = MonerodClient::from_config(&config).unwrap().unwrap();
+        assert_eq!(client.daemon_url, "http://127.0.0.1:18081");
+    }
+}
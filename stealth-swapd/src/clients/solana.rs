@@ -1,13 +1,117 @@
 use crate::config::SolanaConfig;
+use crate::security::SolanaKeypair;
+use super::solana_program::{self, ErrorCode, ProgramInstruction, Pubkey, RedeemProof, SwapAccount};
+use super::solana_tx::{Instruction, NonceAccount};
 use anyhow::Result;
+use base64::Engine;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// SPL token account holding a swap's escrowed USDC.
+#[derive(Debug, Clone)]
+pub struct VaultInfo {
+    pub address: String,
+    pub mint: String,
+    pub owner: String,
+    pub amount: u64,
+}
 
 #[derive(Debug, Clone)]
 pub struct OnchainSwapInfo {
-    pub swap_id: [u8; 32],
-    pub secret_hash: [u8; 32],
-    pub usdc_amount: u64,
-    pub is_redeemed: bool,
-    pub is_refunded: bool,
+    /// Address of the `Swap` PDA.
+    pub address: String,
+    /// Slot the RPC node had reached when it answered.
+    pub slot: u64,
+    pub swap: SwapAccount,
+    /// `None` until a vault has been created for the swap.
+    pub vault: Option<VaultInfo>,
+}
+
+/// A transaction that invoked the program, with its top-level program
+/// instructions decoded.
+#[derive(Debug, Clone)]
+pub struct ProgramTransaction {
+    pub signature: String,
+    pub slot: u64,
+    /// Each instruction and, where it takes one, its `swap_id` argument.
+    pub instructions: Vec<(ProgramInstruction, Option<[u8; 32]>)>,
+    pub failed: bool,
+}
+
+/// Why a transaction failed, decoded from the RPC's `TransactionError`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TransactionFailure {
+    #[error("{code} (error {}) in instruction {index}", code.code())]
+    Program { index: u64, code: ErrorCode },
+
+    #[error("Custom error {code} in instruction {index}")]
+    Custom { index: u64, code: u32 },
+
+    #[error("{error} in instruction {index}")]
+    Instruction { index: u64, error: String },
+
+    #[error("{0}")]
+    Transaction(String),
+}
+
+impl TransactionFailure {
+    /// Decode `"BlockhashNotFound"`, `{"InstructionError": [1, {"Custom":
+    /// 6002}]}` and the like.
+    pub fn decode(err: &serde_json::Value) -> Self {
+        let Some([index, error]) = err["InstructionError"].as_array().map(Vec::as_slice) else {
+            return match err.as_str() {
+                Some(error) => TransactionFailure::Transaction(error.to_string()),
+                None => TransactionFailure::Transaction(err.to_string()),
+            };
+        };
+        let index = index.as_u64().unwrap_or_default();
+
+        match error["Custom"].as_u64().map(|code| code as u32) {
+            Some(code) => match ErrorCode::from_code(code) {
+                Some(code) => TransactionFailure::Program { index, code },
+                None => TransactionFailure::Custom { index, code },
+            },
+            None => TransactionFailure::Instruction {
+                index,
+                error: error.as_str().map(String::from).unwrap_or_else(|| error.to_string()),
+            },
+        }
+    }
+
+    /// The program error, if the program raised one.
+    pub fn program_error(&self) -> Option<ErrorCode> {
+        match self {
+            TransactionFailure::Program { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Whether sending again later may succeed: a program error that
+    /// clears with time, or a blockhash the node hasn't seen yet.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TransactionFailure::Program { code, .. } => code.is_retryable(),
+            TransactionFailure::Transaction(error) => error == "BlockhashNotFound",
+            _ => false,
+        }
+    }
+}
+
+/// `getSignatureStatuses` entry for one transaction.
+#[derive(Debug, Clone)]
+pub struct TransactionStatus {
+    pub slot: u64,
+    /// `processed`, `confirmed` or `finalized`.
+    pub confirmation_status: Option<String>,
+    pub err: Option<TransactionFailure>,
+}
+
+/// Outcome of `simulateTransaction`.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub err: Option<TransactionFailure>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
 }
 
 #[derive(Clone)]
@@ -16,6 +120,8 @@ pub struct SolanaClient {
     pub rpc_url: String,
     pub keypair_path: String,
     pub usdc_mint: String,
+    keypair: Arc<SolanaKeypair>,
+    http_client: reqwest::Client,
 }
 
 impl SolanaClient {
@@ -25,37 +131,496 @@ impl SolanaClient {
             rpc_url: config.rpc_url.clone(),
             keypair_path: config.keypair_path.to_string_lossy().into(),
             usdc_mint: config.usdc_mint.clone(),
+            keypair: Arc::new(SolanaKeypair::load(&config.keypair_path)?),
+            http_client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()?,
         })
     }
 
     pub fn pubkey(&self) -> String {
-        // Return a mock pubkey for demo purposes
-        "G1BVSiFojnXFaPG1WUgJAcYaB7aGKLKWtSqhMreKgA82".to_string()
+        self.keypair.pubkey_base58()
     }
 
-    pub async fn refund_usdc(&self, _swap_id: [u8; 32]) -> Result<String> {
-        Ok("refund_tx_placeholder".to_string())
+    pub fn keypair(&self) -> &SolanaKeypair {
+        &self.keypair
     }
 
     pub async fn health_check(&self) -> Result<bool> {
         Ok(true)
     }
 
+    /// Sum of Bob's USDC token accounts, in base units (6 decimals).
+    pub async fn get_usdc_balance(&self) -> Result<u64> {
+        let params = serde_json::json!([
+            self.pubkey(),
+            { "mint": self.usdc_mint },
+            { "encoding": "jsonParsed", "commitment": self.commitment() }
+        ]);
+
+        let response = self.call_rpc("getTokenAccountsByOwner", params).await?;
+
+        let accounts = response["value"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Failed to get token accounts"))?;
+
+        let balance = accounts
+            .iter()
+            .filter_map(|account| {
+                account["account"]["data"]["parsed"]["info"]["tokenAmount"]["amount"]
+                    .as_str()
+                    .and_then(|amount| amount.parse::<u64>().ok())
+            })
+            .sum();
+
+        Ok(balance)
+    }
+
     pub async fn get_block_height(&self) -> Result<u64> {
-        Ok(123456)
+        let response = self.call_rpc("getBlockHeight", serde_json::json!([{ "commitment": self.commitment() }])).await?;
+        response.as_u64().ok_or_else(|| anyhow::anyhow!("Failed to get block height"))
+    }
+
+    /// Latest blockhash and the last block height a transaction carrying it
+    /// can land in.
+    pub async fn get_latest_blockhash(&self) -> Result<([u8; 32], u64)> {
+        let response = self
+            .call_rpc("getLatestBlockhash", serde_json::json!([{ "commitment": self.commitment() }]))
+            .await?;
+        let blockhash = response["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Failed to get latest blockhash"))?;
+        let last_valid = response["value"]["lastValidBlockHeight"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Failed to get last valid block height"))?;
+        Ok((solana_program::decode_pubkey(blockhash)?, last_valid))
+    }
+
+    /// State of a durable nonce account, or `None` if it doesn't exist.
+    pub async fn get_nonce_account(&self, address: &str) -> Result<Option<NonceAccount>> {
+        match self.get_account_data(address, self.commitment()).await? {
+            Some((_, data)) => Ok(Some(NonceAccount::decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Send a signed transaction once, without preflight. Resending is left
+    /// to the caller, which knows whether the blockhash is still valid.
+    pub async fn send_transaction(&self, bytes: &[u8]) -> Result<String> {
+        let params = serde_json::json!([
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+            { "encoding": "base64", "skipPreflight": true, "maxRetries": 0 }
+        ]);
+        let response = self.call_rpc("sendTransaction", params).await?;
+        response
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow::anyhow!("Malformed sendTransaction response"))
+    }
+
+    /// Status of a transaction we sent, failed or not, or `None` if the
+    /// cluster hasn't seen it.
+    pub async fn get_transaction_status(&self, signature: &str) -> Result<Option<TransactionStatus>> {
+        let params = serde_json::json!([[signature], { "searchTransactionHistory": true }]);
+        let response = self.call_rpc("getSignatureStatuses", params).await?;
+
+        let status = &response["value"][0];
+        if status.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(TransactionStatus {
+            slot: status["slot"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("Malformed status for {}", signature))?,
+            confirmation_status: status["confirmationStatus"].as_str().map(String::from),
+            err: (!status["err"].is_null()).then(|| TransactionFailure::decode(&status["err"])),
+        }))
+    }
+
+    /// Run a signed transaction against current state without sending it.
+    /// Its blockhash is kept, so a durable nonce transaction is checked
+    /// against its nonce.
+    pub async fn simulate_transaction(&self, bytes: &[u8]) -> Result<Simulation> {
+        let params = serde_json::json!([
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+            {
+                "encoding": "base64",
+                "commitment": self.commitment(),
+                "sigVerify": true,
+                "replaceRecentBlockhash": false
+            }
+        ]);
+        let response = self.call_rpc("simulateTransaction", params).await?;
+
+        let value = &response["value"];
+        Ok(Simulation {
+            err: (!value["err"].is_null()).then(|| TransactionFailure::decode(&value["err"])),
+            logs: value["logs"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|line| line.as_str().map(String::from))
+                .collect(),
+            units_consumed: value["unitsConsumed"].as_u64(),
+        })
+    }
+
+    /// Prioritization fees, in micro-lamports per compute unit, paid in
+    /// recent slots by transactions writing any of `accounts`.
+    pub async fn get_recent_prioritization_fees(&self, accounts: &[String]) -> Result<Vec<u64>> {
+        let response = self.call_rpc("getRecentPrioritizationFees", serde_json::json!([accounts])).await?;
+        Ok(response
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry["prioritizationFee"].as_u64())
+            .collect())
+    }
+
+    /// `refund` of an expired swap Bob funded, paying the USDC back to him.
+    pub fn refund_instruction(&self, swap_id: [u8; 32]) -> Result<Instruction> {
+        solana_program::refund_instruction(
+            &self.program_id()?,
+            swap_id,
+            self.keypair.public_key(),
+            solana_program::decode_pubkey(&self.usdc_mint)?,
+        )
+    }
+
+    /// `record_monero_lock_proof` of the XMR Bob paid for a USDC-to-XMR swap.
+    pub fn record_monero_lock_proof_instruction(&self, swap_id: [u8; 32], monero_txid: &str) -> Result<Instruction> {
+        let monero_lock_txid: [u8; 32] = hex::decode(monero_txid)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Monero txid {} is not 32 bytes", monero_txid))?;
+        solana_program::record_monero_lock_proof_instruction(
+            &self.program_id()?,
+            swap_id,
+            self.keypair.public_key(),
+            monero_lock_txid,
+        )
+    }
+
+    /// `redeem_usdc` of a USDC-to-XMR swap, paying the vault, relayer fee
+    /// included, to Bob.
+    pub fn redeem_usdc_instruction(&self, swap_id: [u8; 32], proof: &RedeemProof) -> Result<Instruction> {
+        let bob = self.keypair.public_key();
+        solana_program::redeem_usdc_instruction(
+            &self.program_id()?,
+            swap_id,
+            bob,
+            bob,
+            solana_program::decode_pubkey(&self.usdc_mint)?,
+            proof,
+        )
     }
 
     pub async fn create_usdc_to_xmr_swap(&self, _swap_id: [u8; 32], _secret_hash: [u8; 32], _usdc_amount: u64) -> Result<String> {
         Ok("create_swap_tx_placeholder".to_string())
     }
 
-    pub async fn get_swap(&self, _swap_id: [u8; 32]) -> Result<Option<OnchainSwapInfo>, anyhow::Error> {
-        // Mock implementation - would fetch from Solana program
-        Ok(None)
+    pub fn program_id(&self) -> Result<Pubkey> {
+        solana_program::decode_pubkey(
+            self.config.program_id.as_deref().unwrap_or(solana_program::DEFAULT_PROGRAM_ID),
+        )
     }
 
-    pub async fn trigger_onchain_refund(&self, _swap_id: [u8; 32]) -> Result<String> {
-        // Mock implementation - would trigger refund on Solana
-        Ok("refund_triggered".to_string())
+    pub async fn get_swap(&self, swap_id: [u8; 32]) -> Result<Option<OnchainSwapInfo>, anyhow::Error> {
+        self.get_swap_at(swap_id, self.commitment()).await
     }
-}
\ No newline at end of file
+
+    /// Fetch and decode the `Swap` PDA for `swap_id` at `commitment`, together
+    /// with its USDC vault. Returns `None` if the account has not been
+    /// created yet.
+    pub async fn get_swap_at(&self, swap_id: [u8; 32], commitment: &str) -> Result<Option<OnchainSwapInfo>> {
+        let address = solana_program::swap_address(&self.program_id()?, &swap_id)
+            .ok_or_else(|| anyhow::anyhow!("No valid swap PDA for {}", hex::encode(swap_id)))?;
+        let address = solana_program::encode_pubkey(&address);
+
+        let (slot, data) = match self.get_account_data(&address, commitment).await? {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let swap = SwapAccount::decode(&data)?;
+
+        let vault = self.get_vault(&address, commitment).await?;
+
+        Ok(Some(OnchainSwapInfo { address, slot, swap, vault }))
+    }
+
+    async fn get_vault(&self, swap_address: &str, commitment: &str) -> Result<Option<VaultInfo>> {
+        let owner = solana_program::decode_pubkey(swap_address)?;
+        let mint = solana_program::decode_pubkey(&self.usdc_mint)?;
+        let address = solana_program::associated_token_address(&owner, &mint)
+            .ok_or_else(|| anyhow::anyhow!("No valid vault address for {}", swap_address))?;
+        let address = solana_program::encode_pubkey(&address);
+
+        let params = serde_json::json!([
+            address,
+            { "encoding": "jsonParsed", "commitment": commitment }
+        ]);
+        let response = self.call_rpc("getAccountInfo", params).await?;
+        if response["value"].is_null() {
+            return Ok(None);
+        }
+        let info = &response["value"]["data"]["parsed"]["info"];
+
+        Ok(Some(VaultInfo {
+            address,
+            mint: info["mint"].as_str().unwrap_or_default().to_string(),
+            owner: info["owner"].as_str().unwrap_or_default().to_string(),
+            amount: info["tokenAmount"]["amount"]
+                .as_str()
+                .and_then(|amount| amount.parse().ok())
+                .unwrap_or(0),
+        }))
+    }
+
+    /// Raw account data and the context slot it was read at.
+    async fn get_account_data(&self, address: &str, commitment: &str) -> Result<Option<(u64, Vec<u8>)>> {
+        let params = serde_json::json!([
+            address,
+            { "encoding": "base64", "commitment": commitment }
+        ]);
+        let response = self.call_rpc("getAccountInfo", params).await?;
+        if response["value"].is_null() {
+            return Ok(None);
+        }
+
+        let encoded = response["value"]["data"][0]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Malformed account data for {}", address))?;
+        let slot = response["context"]["slot"].as_u64().unwrap_or(0);
+        Ok(Some((slot, base64::engine::general_purpose::STANDARD.decode(encoded)?)))
+    }
+
+    pub async fn get_slot(&self, commitment: &str) -> Result<u64> {
+        let response = self.call_rpc("getSlot", serde_json::json!([{ "commitment": commitment }])).await?;
+        response.as_u64().ok_or_else(|| anyhow::anyhow!("Failed to get slot"))
+    }
+
+    /// Signatures touching `address`, newest first, with their slots.
+    pub async fn get_signatures_for_address(&self, address: &str, limit: usize) -> Result<Vec<(String, u64)>> {
+        self.get_signatures_until(address, None, limit).await
+    }
+
+    /// Like [`Self::get_signatures_for_address`], but stopping before
+    /// `until`, so only signatures newer than it are returned.
+    pub async fn get_signatures_until(&self, address: &str, until: Option<&str>, limit: usize) -> Result<Vec<(String, u64)>> {
+        let mut options = serde_json::json!({ "limit": limit, "commitment": self.commitment() });
+        if let Some(until) = until {
+            options["until"] = serde_json::json!(until);
+        }
+        let params = serde_json::json!([address, options]);
+        let response = self.call_rpc("getSignaturesForAddress", params).await?;
+
+        Ok(response
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry["err"].is_null())
+            .filter_map(|entry| Some((entry["signature"].as_str()?.to_string(), entry["slot"].as_u64()?)))
+            .collect())
+    }
+
+    /// The oldest successful signature touching `address`, paging back
+    /// through its whole history.
+    pub async fn get_oldest_signature(&self, address: &str) -> Result<Option<(String, u64)>> {
+        const PAGE: usize = 1000;
+        let mut before: Option<String> = None;
+        let mut oldest = None;
+        loop {
+            let mut options = serde_json::json!({ "limit": PAGE, "commitment": self.commitment() });
+            if let Some(before) = &before {
+                options["before"] = serde_json::json!(before);
+            }
+            let params = serde_json::json!([address, options]);
+            let response = self.call_rpc("getSignaturesForAddress", params).await?;
+            let page = response.as_array().cloned().unwrap_or_default();
+
+            if let Some(entry) = page.iter().rev().find(|entry| entry["err"].is_null()) {
+                if let (Some(signature), Some(slot)) = (entry["signature"].as_str(), entry["slot"].as_u64()) {
+                    oldest = Some((signature.to_string(), slot));
+                }
+            }
+            // Failed transactions still page, so the cursor is the last entry
+            match page.last().and_then(|entry| entry["signature"].as_str()) {
+                Some(signature) if page.len() == PAGE => before = Some(signature.to_string()),
+                _ => return Ok(oldest),
+            }
+        }
+    }
+
+    /// Slot a transaction landed in and whether it is finalized, or `None`
+    /// if the cluster no longer knows it or it failed.
+    pub async fn get_signature_status(&self, signature: &str) -> Result<Option<(u64, bool)>> {
+        let params = serde_json::json!([[signature], { "searchTransactionHistory": true }]);
+        let response = self.call_rpc("getSignatureStatuses", params).await?;
+
+        let status = &response["value"][0];
+        if status.is_null() || !status["err"].is_null() {
+            return Ok(None);
+        }
+        let slot = status["slot"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Malformed status for {}", signature))?;
+        Ok(Some((slot, status["confirmationStatus"].as_str() == Some("finalized"))))
+    }
+
+    /// Blockhash of the block produced in `slot`, or `None` if it was skipped.
+    pub async fn get_block_hash(&self, slot: u64) -> Result<Option<String>> {
+        let params = serde_json::json!([
+            slot,
+            {
+                "commitment": "confirmed",
+                "transactionDetails": "none",
+                "rewards": false,
+                "maxSupportedTransactionVersion": 0
+            }
+        ]);
+        let response = match self.call_rpc("getBlock", params).await {
+            Ok(response) => response,
+            // -32007: slot skipped, -32009: slot missing in long-term storage
+            Err(e) if e.to_string().contains("-32007") || e.to_string().contains("-32009") => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(response["blockhash"].as_str().map(String::from))
+    }
+
+    /// Fetch a transaction and decode its instructions to the program, or
+    /// `None` if the cluster doesn't have it at our commitment.
+    pub async fn get_program_transaction(&self, signature: &str) -> Result<Option<ProgramTransaction>> {
+        let params = serde_json::json!([
+            signature,
+            { "encoding": "json", "commitment": self.commitment(), "maxSupportedTransactionVersion": 0 }
+        ]);
+        let response = self.call_rpc("getTransaction", params).await?;
+        if response.is_null() {
+            return Ok(None);
+        }
+
+        // Lookup-table addresses follow the static keys, writable first
+        let keys: Vec<&str> = response["transaction"]["message"]["accountKeys"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .chain(response["meta"]["loadedAddresses"]["writable"].as_array().into_iter().flatten())
+            .chain(response["meta"]["loadedAddresses"]["readonly"].as_array().into_iter().flatten())
+            .filter_map(|key| key.as_str())
+            .collect();
+        let program_id = solana_program::encode_pubkey(&self.program_id()?);
+
+        let instructions = response["transaction"]["message"]["instructions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|ix| {
+                ix["programIdIndex"].as_u64().and_then(|i| keys.get(i as usize)) == Some(&program_id.as_str())
+            })
+            .filter_map(|ix| bs58::decode(ix["data"].as_str()?).into_vec().ok())
+            .filter_map(|data| ProgramInstruction::decode(&data))
+            .collect();
+
+        Ok(Some(ProgramTransaction {
+            signature: signature.to_string(),
+            slot: response["slot"].as_u64().unwrap_or(0),
+            instructions,
+            failed: !response["meta"]["err"].is_null(),
+        }))
+    }
+
+    /// Websocket endpoint for subscriptions: `solana.ws_url`, or the RPC
+    /// URL with a `ws`/`wss` scheme.
+    pub fn ws_url(&self) -> String {
+        match &self.config.ws_url {
+            Some(url) => url.clone(),
+            None => match self.rpc_url.split_once("://") {
+                Some(("https", rest)) => format!("wss://{}", rest),
+                Some((_, rest)) => format!("ws://{}", rest),
+                None => self.rpc_url.clone(),
+            },
+        }
+    }
+
+    pub fn commitment(&self) -> &str {
+        self.config.commitment.as_deref().unwrap_or("confirmed")
+    }
+
+    async fn call_rpc(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response: serde_json::Value = self.http_client
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            let code = error["code"].as_i64().unwrap_or(-1);
+            let message = error["message"].as_str().unwrap_or("Unknown error");
+            return Err(anyhow::anyhow!("Solana RPC error {}: {}", code, message));
+        }
+
+        Ok(response["result"].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn program_errors_decode_from_custom_codes() {
+        let failure = TransactionFailure::decode(&json!({ "InstructionError": [1, { "Custom": 6002 }] }));
+        assert_eq!(failure, TransactionFailure::Program { index: 1, code: ErrorCode::NotYetExpired });
+        assert_eq!(failure.program_error(), Some(ErrorCode::NotYetExpired));
+        assert!(failure.is_retryable());
+
+        let failure = TransactionFailure::decode(&json!({ "InstructionError": [2, { "Custom": 6001 }] }));
+        assert_eq!(failure, TransactionFailure::Program { index: 2, code: ErrorCode::AlreadyFinalized });
+        assert!(!failure.is_retryable());
+    }
+
+    #[test]
+    fn unknown_custom_codes_and_builtin_errors_are_not_retried() {
+        // An Anchor or SPL token error, not one the program declares
+        let failure = TransactionFailure::decode(&json!({ "InstructionError": [0, { "Custom": 1 }] }));
+        assert_eq!(failure, TransactionFailure::Custom { index: 0, code: 1 });
+        assert_eq!(failure.program_error(), None);
+        assert!(!failure.is_retryable());
+
+        let failure = TransactionFailure::decode(&json!({ "InstructionError": [3, "InvalidAccountData"] }));
+        assert_eq!(failure, TransactionFailure::Instruction { index: 3, error: "InvalidAccountData".to_string() });
+        assert!(!failure.is_retryable());
+
+        let failure = TransactionFailure::decode(&json!({ "InstructionError": [0, { "BorshIoError": "Unknown" }] }));
+        assert!(matches!(failure, TransactionFailure::Instruction { index: 0, .. }));
+        assert!(!failure.is_retryable());
+    }
+
+    #[test]
+    fn transaction_errors_decode_by_name() {
+        let failure = TransactionFailure::decode(&json!("BlockhashNotFound"));
+        assert_eq!(failure, TransactionFailure::Transaction("BlockhashNotFound".to_string()));
+        assert!(failure.is_retryable());
+
+        let failure = TransactionFailure::decode(&json!("AccountInUse"));
+        assert_eq!(failure, TransactionFailure::Transaction("AccountInUse".to_string()));
+        assert!(!failure.is_retryable());
+
+        let failure = TransactionFailure::decode(&json!({ "InsufficientFundsForRent": { "account_index": 1 } }));
+        assert!(matches!(failure, TransactionFailure::Transaction(_)));
+        assert!(!failure.is_retryable());
+    }
+}
@@ -0,0 +1,590 @@
+//! Off-chain mirror of the `stealth_swap` Anchor program in
+//! `solana-program/src/lib.rs`: addresses, account layout and decoding.
+
+use crate::swap_engine::Direction;
+use super::solana_tx::{AccountMeta, Instruction, SYSTEM_PROGRAM_ID};
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use sha2::{Digest, Sha256};
+
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+/// `declare_id!` of the deployed program.
+pub const DEFAULT_PROGRAM_ID: &str = "G1BVSiFojnXFaPG1WUgJAcYaB7aGKLKWtSqhMreKgA82";
+
+pub type Pubkey = [u8; 32];
+
+pub fn decode_pubkey(address: &str) -> anyhow::Result<Pubkey> {
+    bs58::decode(address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid Solana address: {}", address))
+}
+
+pub fn encode_pubkey(pubkey: &Pubkey) -> String {
+    bs58::encode(pubkey).into_string()
+}
+
+/// `Pubkey::find_program_address`: the first bump, from 255 down, whose
+/// derived address is off the ed25519 curve.
+pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<(Pubkey, u8)> {
+    (0..=u8::MAX).rev().find_map(|bump| {
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update([bump]);
+        hasher.update(program_id);
+        hasher.update(b"ProgramDerivedAddress");
+        let candidate: Pubkey = hasher.finalize().into();
+
+        let on_curve = CompressedEdwardsY(candidate).decompress().is_some();
+        (!on_curve).then_some((candidate, bump))
+    })
+}
+
+/// The `Swap` PDA: seeds `[b"swap", swap_id]`.
+pub fn swap_address(program_id: &Pubkey, swap_id: &[u8; 32]) -> Option<Pubkey> {
+    find_program_address(&[b"swap", swap_id], program_id).map(|(address, _)| address)
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Option<Pubkey> {
+    let token_program = decode_pubkey(TOKEN_PROGRAM_ID).ok()?;
+    let ata_program = decode_pubkey(ASSOCIATED_TOKEN_PROGRAM_ID).ok()?;
+    find_program_address(&[owner, &token_program, mint], &ata_program).map(|(address, _)| address)
+}
+
+/// Anchor's 8-byte discriminator: `sha256("<namespace>:<name>")[..8]`.
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash: [u8; 32] = Sha256::digest(format!("{}:{}", namespace, name).as_bytes()).into();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Anchor instruction data: the discriminator, then the Borsh arguments.
+fn instruction_data(instruction: ProgramInstruction, args: &[&[u8]]) -> Vec<u8> {
+    let mut data = discriminator("global", instruction.name()).to_vec();
+    for arg in args {
+        data.extend_from_slice(arg);
+    }
+    data
+}
+
+fn swap_pda(program_id: &Pubkey, swap_id: &[u8; 32]) -> anyhow::Result<Pubkey> {
+    swap_address(program_id, swap_id).ok_or_else(|| anyhow::anyhow!("No swap address for {}", hex::encode(swap_id)))
+}
+
+fn token_account(owner: &Pubkey, mint: &Pubkey) -> anyhow::Result<Pubkey> {
+    associated_token_address(owner, mint)
+        .ok_or_else(|| anyhow::anyhow!("No token account for {}", encode_pubkey(owner)))
+}
+
+/// Token, associated token and system programs, which close the account
+/// lists of the instructions that move USDC.
+fn token_programs() -> anyhow::Result<[AccountMeta; 3]> {
+    Ok([
+        AccountMeta::readonly(decode_pubkey(TOKEN_PROGRAM_ID)?, false),
+        AccountMeta::readonly(decode_pubkey(ASSOCIATED_TOKEN_PROGRAM_ID)?, false),
+        AccountMeta::readonly(decode_pubkey(SYSTEM_PROGRAM_ID)?, false),
+    ])
+}
+
+/// `refund`: return an expired swap's vault and collateral to `funder`,
+/// who signs.
+pub fn refund_instruction(program_id: &Pubkey, swap_id: [u8; 32], funder: Pubkey, usdc_mint: Pubkey) -> anyhow::Result<Instruction> {
+    let swap = swap_pda(program_id, &swap_id)?;
+    let vault = token_account(&swap, &usdc_mint)?;
+    let funder_token = token_account(&funder, &usdc_mint)?;
+
+    let mut accounts = vec![
+        AccountMeta::writable(swap, false),
+        AccountMeta::writable(funder, true),
+        AccountMeta::writable(vault, false),
+        AccountMeta::writable(vault, false),
+        AccountMeta::writable(funder_token, false),
+        AccountMeta::writable(funder_token, false),
+        AccountMeta::readonly(usdc_mint, false),
+    ];
+    accounts.extend(token_programs()?);
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: instruction_data(ProgramInstruction::Refund, &[&swap_id]),
+    })
+}
+
+/// `record_monero_lock_proof`: Bob records the txid of his XMR lock.
+pub fn record_monero_lock_proof_instruction(
+    program_id: &Pubkey,
+    swap_id: [u8; 32],
+    bob: Pubkey,
+    monero_lock_txid: [u8; 32],
+) -> anyhow::Result<Instruction> {
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::writable(swap_pda(program_id, &swap_id)?, false),
+            AccountMeta::readonly(bob, true),
+        ],
+        data: instruction_data(ProgramInstruction::RecordMoneroLockProof, &[&swap_id, &monero_lock_txid]),
+    })
+}
+
+/// `redeem_usdc` arguments after the `swap_id`.
+#[derive(Debug, Clone, Copy)]
+pub struct RedeemProof {
+    pub adaptor_sig: [u8; 64],
+    pub parity: u8,
+    pub curve_point: [u8; 32],
+}
+
+/// `redeem_usdc`: Bob claims the vault of a USDC-to-XMR swap with an
+/// adaptor signature that reveals the swap secret. The relayer fee goes
+/// to `relayer`'s token account.
+pub fn redeem_usdc_instruction(
+    program_id: &Pubkey,
+    swap_id: [u8; 32],
+    bob: Pubkey,
+    relayer: Pubkey,
+    usdc_mint: Pubkey,
+    proof: &RedeemProof,
+) -> anyhow::Result<Instruction> {
+    let swap = swap_pda(program_id, &swap_id)?;
+
+    let mut accounts = vec![
+        AccountMeta::writable(swap, false),
+        AccountMeta::writable(bob, true),
+        AccountMeta::writable(token_account(&swap, &usdc_mint)?, false),
+        AccountMeta::writable(token_account(&bob, &usdc_mint)?, false),
+        AccountMeta::writable(token_account(&relayer, &usdc_mint)?, false),
+        AccountMeta::readonly(relayer, false),
+        AccountMeta::readonly(usdc_mint, false),
+    ];
+    accounts.extend(token_programs()?);
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: instruction_data(
+            ProgramInstruction::RedeemUsdc,
+            &[&swap_id, &proof.adaptor_sig, &[proof.parity], &proof.curve_point],
+        ),
+    })
+}
+
+/// Anchor numbers a program's own errors from here.
+const ERROR_CODE_OFFSET: u32 = 6000;
+
+/// Mirror of the program's `ErrorCode`, in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ErrorCode {
+    #[error("Invalid adaptor signature")]
+    InvalidAdaptorSig,
+    #[error("Already finalized")]
+    AlreadyFinalized,
+    #[error("Not yet expired")]
+    NotYetExpired,
+    #[error("Wrong direction")]
+    WrongDirection,
+    #[error("Invalid preimage")]
+    InvalidPreimage,
+    #[error("Invalid secret hash")]
+    InvalidSecretHash,
+    #[error("Invalid expiry")]
+    InvalidExpiry,
+    #[error("Excessive relayer fee")]
+    ExcessiveRelayerFee,
+    #[error("Monero lock not recorded")]
+    MoneroLockNotRecorded,
+    #[error("Insufficient confirmations")]
+    InsufficientConfirmations,
+    #[error("VTC not ready")]
+    VtcNotReady,
+    #[error("VTC already opened")]
+    VtcAlreadyOpened,
+    #[error("Commitment expiry invalid")]
+    CommitmentExpiryInvalid,
+    #[error("Bounty already claimed")]
+    BountyAlreadyClaimed,
+    #[error("No collateral available for bounty")]
+    NoCollateralAvailable,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 15] = [
+        ErrorCode::InvalidAdaptorSig,
+        ErrorCode::AlreadyFinalized,
+        ErrorCode::NotYetExpired,
+        ErrorCode::WrongDirection,
+        ErrorCode::InvalidPreimage,
+        ErrorCode::InvalidSecretHash,
+        ErrorCode::InvalidExpiry,
+        ErrorCode::ExcessiveRelayerFee,
+        ErrorCode::MoneroLockNotRecorded,
+        ErrorCode::InsufficientConfirmations,
+        ErrorCode::VtcNotReady,
+        ErrorCode::VtcAlreadyOpened,
+        ErrorCode::CommitmentExpiryInvalid,
+        ErrorCode::BountyAlreadyClaimed,
+        ErrorCode::NoCollateralAvailable,
+    ];
+
+    /// The error behind a `Custom` instruction error code.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code.checked_sub(ERROR_CODE_OFFSET)? as usize).copied()
+    }
+
+    pub fn code(&self) -> u32 {
+        ERROR_CODE_OFFSET + Self::ALL.iter().position(|code| code == self).unwrap_or_default() as u32
+    }
+
+    /// Errors that clear with time, so the same transaction may succeed
+    /// later. The rest will fail however often it is sent.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::NotYetExpired
+                | ErrorCode::MoneroLockNotRecorded
+                | ErrorCode::InsufficientConfirmations
+                | ErrorCode::VtcNotReady
+        )
+    }
+}
+
+/// The `Swap` account's `monero_sub_address` field for `address`: its first
+/// 64 bytes, zero-padded.
+pub fn sub_address_field(address: &str) -> [u8; 64] {
+    let mut field = [0u8; 64];
+    let len = address.len().min(64);
+    field[..len].copy_from_slice(&address.as_bytes()[..len]);
+    field
+}
+
+/// Decoded `Swap` account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapAccount {
+    pub direction: Direction,
+    pub swap_id: [u8; 32],
+    pub alice: Pubkey,
+    pub bob: Pubkey,
+    pub secret_hash: [u8; 32],
+    pub expiry: i64,
+    pub relayer_fee: u64,
+    pub is_redeemed: bool,
+    pub is_refunded: bool,
+    pub usdc_amount: u64,
+    pub xmr_amount: u64,
+    pub monero_sub_address: [u8; 64],
+    pub monero_lock_txid: [u8; 32],
+    pub alice_solana: Pubkey,
+    pub bump: u8,
+    pub vtc_opened: bool,
+    pub bob_collateral_locked: bool,
+    pub alice_collateral_locked: bool,
+    pub bounty_claimed: bool,
+}
+
+impl SwapAccount {
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader { data, offset: 0 };
+        if reader.bytes::<8>()? != discriminator("account", "Swap") {
+            return Err(anyhow::anyhow!("Account is not a stealth_swap Swap"));
+        }
+
+        Ok(Self {
+            direction: match reader.u8()? {
+                0 => Direction::UsdcToXmr,
+                1 => Direction::XmrToUsdc,
+                other => return Err(anyhow::anyhow!("Unknown swap direction {}", other)),
+            },
+            swap_id: reader.bytes()?,
+            alice: reader.bytes()?,
+            bob: reader.bytes()?,
+            secret_hash: reader.bytes()?,
+            expiry: i64::from_le_bytes(reader.bytes()?),
+            relayer_fee: u64::from_le_bytes(reader.bytes()?),
+            is_redeemed: reader.u8()? != 0,
+            is_refunded: reader.u8()? != 0,
+            usdc_amount: u64::from_le_bytes(reader.bytes()?),
+            xmr_amount: u64::from_le_bytes(reader.bytes()?),
+            monero_sub_address: reader.bytes()?,
+            monero_lock_txid: reader.bytes()?,
+            alice_solana: reader.bytes()?,
+            bump: reader.u8()?,
+            vtc_opened: reader.u8()? != 0,
+            bob_collateral_locked: reader.u8()? != 0,
+            alice_collateral_locked: reader.u8()? != 0,
+            bounty_claimed: reader.u8()? != 0,
+        })
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let end = self.offset + N;
+        let slice = self.data
+            .get(self.offset..end)
+            .ok_or_else(|| anyhow::anyhow!("Account data too short"))?;
+        self.offset = end;
+        let mut out = [0u8; N];
+        out.copy_from_slice(slice);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+}
+
+/// Instructions of the `stealth_swap` program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgramInstruction {
+    CreateUsdcToXmrSwap,
+    RecordMoneroLockProof,
+    RedeemUsdc,
+    CreateXmrToUsdcSwap,
+    RedeemUsdcAlice,
+    Refund,
+    ClaimBountyForSecret,
+    AdaptorVerify,
+    ForceOpenVtc,
+    CreateCommitment,
+}
+
+impl ProgramInstruction {
+    pub const ALL: [ProgramInstruction; 10] = [
+        ProgramInstruction::CreateUsdcToXmrSwap,
+        ProgramInstruction::RecordMoneroLockProof,
+        ProgramInstruction::RedeemUsdc,
+        ProgramInstruction::CreateXmrToUsdcSwap,
+        ProgramInstruction::RedeemUsdcAlice,
+        ProgramInstruction::Refund,
+        ProgramInstruction::ClaimBountyForSecret,
+        ProgramInstruction::AdaptorVerify,
+        ProgramInstruction::ForceOpenVtc,
+        ProgramInstruction::CreateCommitment,
+    ];
+
+    /// Rust name of the instruction handler.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgramInstruction::CreateUsdcToXmrSwap => "create_usdc_to_xmr_swap",
+            ProgramInstruction::RecordMoneroLockProof => "record_monero_lock_proof",
+            ProgramInstruction::RedeemUsdc => "redeem_usdc",
+            ProgramInstruction::CreateXmrToUsdcSwap => "create_xmr_to_usdc_swap",
+            ProgramInstruction::RedeemUsdcAlice => "redeem_usdc_alice",
+            ProgramInstruction::Refund => "refund",
+            ProgramInstruction::ClaimBountyForSecret => "claim_bounty_for_secret",
+            ProgramInstruction::AdaptorVerify => "adaptor_verify",
+            ProgramInstruction::ForceOpenVtc => "force_open_vtc",
+            ProgramInstruction::CreateCommitment => "create_commitment",
+        }
+    }
+
+    /// Whether the instruction's first argument is the `swap_id`.
+    pub fn takes_swap_id(&self) -> bool {
+        !matches!(self, ProgramInstruction::CreateCommitment)
+    }
+
+    /// Decode instruction data into the instruction and, where it takes
+    /// one, the swap it concerns.
+    pub fn decode(data: &[u8]) -> Option<(Self, Option<[u8; 32]>)> {
+        let (tag, args) = data.split_at_checked(8)?;
+        let instruction = Self::ALL
+            .into_iter()
+            .find(|instruction| discriminator("global", instruction.name()) == tag)?;
+        let swap_id = instruction
+            .takes_swap_id()
+            .then(|| args.get(..32).and_then(|id| id.try_into().ok()))
+            .flatten();
+        Some((instruction, swap_id))
+    }
+
+    /// Parse Anchor's `Program log: Instruction: <Name>` log line.
+    pub fn from_log(line: &str) -> Option<Self> {
+        let name = line.strip_prefix("Program log: Instruction: ")?;
+        Self::ALL.into_iter().find(|instruction| {
+            instruction
+                .name()
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or(String::new(), |c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                })
+                .collect::<String>()
+                == name.trim()
+        })
+    }
+}
+
+#[cfg(test)]
+impl SwapAccount {
+    /// A `Swap` account as Anchor serializes it, Borsh fields in order.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut data = discriminator("account", "Swap").to_vec();
+        data.push(match self.direction {
+            Direction::UsdcToXmr => 0,
+            Direction::XmrToUsdc => 1,
+        });
+        data.extend_from_slice(&self.swap_id);
+        data.extend_from_slice(&self.alice);
+        data.extend_from_slice(&self.bob);
+        data.extend_from_slice(&self.secret_hash);
+        data.extend_from_slice(&self.expiry.to_le_bytes());
+        data.extend_from_slice(&self.relayer_fee.to_le_bytes());
+        data.push(self.is_redeemed as u8);
+        data.push(self.is_refunded as u8);
+        data.extend_from_slice(&self.usdc_amount.to_le_bytes());
+        data.extend_from_slice(&self.xmr_amount.to_le_bytes());
+        data.extend_from_slice(&self.monero_sub_address);
+        data.extend_from_slice(&self.monero_lock_txid);
+        data.extend_from_slice(&self.alice_solana);
+        data.push(self.bump);
+        data.push(self.vtc_opened as u8);
+        data.push(self.bob_collateral_locked as u8);
+        data.push(self.alice_collateral_locked as u8);
+        data.push(self.bounty_claimed as u8);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_id() -> Pubkey {
+        decode_pubkey(DEFAULT_PROGRAM_ID).unwrap()
+    }
+
+    /// Variant names of the program's `ErrorCode`, in declaration order.
+    fn program_error_codes() -> Vec<String> {
+        let source = include_str!("../../../solana-program/src/lib.rs");
+        let body = source
+            .split_once("pub enum ErrorCode {")
+            .and_then(|(_, rest)| rest.split_once('}'))
+            .map(|(body, _)| body)
+            .unwrap();
+        body.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("#["))
+            .map(|line| line.trim_end_matches(',').to_string())
+            .collect()
+    }
+
+    #[test]
+    fn error_codes_follow_the_program_enum_from_6000() {
+        let names: Vec<String> = ErrorCode::ALL.iter().map(|code| format!("{:?}", code)).collect();
+        assert_eq!(names, program_error_codes());
+
+        for (i, code) in ErrorCode::ALL.iter().enumerate() {
+            assert_eq!(code.code(), 6000 + i as u32);
+            assert_eq!(ErrorCode::from_code(6000 + i as u32), Some(*code));
+        }
+    }
+
+    #[test]
+    fn error_code_table() {
+        let table = [
+            (6000, Some(ErrorCode::InvalidAdaptorSig), false),
+            (6001, Some(ErrorCode::AlreadyFinalized), false),
+            (6002, Some(ErrorCode::NotYetExpired), true),
+            (6003, Some(ErrorCode::WrongDirection), false),
+            (6006, Some(ErrorCode::InvalidExpiry), false),
+            (6008, Some(ErrorCode::MoneroLockNotRecorded), true),
+            (6009, Some(ErrorCode::InsufficientConfirmations), true),
+            (6010, Some(ErrorCode::VtcNotReady), true),
+            (6014, Some(ErrorCode::NoCollateralAvailable), false),
+        ];
+        for (code, expected, retryable) in table {
+            assert_eq!(ErrorCode::from_code(code), expected, "code {}", code);
+            assert_eq!(expected.unwrap().is_retryable(), retryable, "code {}", code);
+        }
+
+        // Anchor's own errors sit below 6000; nothing is declared past 6014
+        for code in [0, 100, 2006, 3012, 5999, 6015, u32::MAX] {
+            assert_eq!(ErrorCode::from_code(code), None, "code {}", code);
+        }
+    }
+
+    fn swap_account() -> SwapAccount {
+        SwapAccount {
+            direction: Direction::XmrToUsdc,
+            swap_id: [1; 32],
+            alice: [2; 32],
+            bob: [3; 32],
+            secret_hash: [4; 32],
+            expiry: 1_700_086_400,
+            relayer_fee: 250_000,
+            is_redeemed: false,
+            is_refunded: true,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            monero_sub_address: sub_address_field(&"8".repeat(95)),
+            monero_lock_txid: [5; 32],
+            alice_solana: [6; 32],
+            bump: 254,
+            vtc_opened: false,
+            bob_collateral_locked: true,
+            alice_collateral_locked: false,
+            bounty_claimed: true,
+        }
+    }
+
+    #[test]
+    fn swap_account_round_trips() {
+        let swap = swap_account();
+        let mut data = swap.encode();
+        assert_eq!(SwapAccount::decode(&data).unwrap(), swap);
+
+        // The program allocates `8 + Swap::LEN`, a byte more than it writes
+        data.push(0);
+        assert_eq!(data.len(), 8 + 297);
+        assert_eq!(SwapAccount::decode(&data).unwrap(), swap);
+
+        let usdc_to_xmr = SwapAccount { direction: Direction::UsdcToXmr, ..swap_account() };
+        assert_eq!(SwapAccount::decode(&usdc_to_xmr.encode()).unwrap(), usdc_to_xmr);
+    }
+
+    #[test]
+    fn malformed_swap_accounts_are_rejected() {
+        let data = swap_account().encode();
+
+        assert!(SwapAccount::decode(&data[..data.len() - 1]).is_err());
+
+        let mut other_account = data.clone();
+        other_account[..8].copy_from_slice(&discriminator("account", "RelayerCommitment"));
+        assert!(SwapAccount::decode(&other_account).is_err());
+
+        let mut bad_direction = data;
+        bad_direction[8] = 2;
+        assert!(SwapAccount::decode(&bad_direction).is_err());
+    }
+
+    #[test]
+    fn redeem_usdc_carries_the_proof_after_the_swap_id() {
+        let proof = RedeemProof { adaptor_sig: [7; 64], parity: 1, curve_point: [8; 32] };
+        let instruction = redeem_usdc_instruction(&program_id(), [1; 32], [2; 32], [3; 32], [4; 32], &proof).unwrap();
+
+        assert_eq!(ProgramInstruction::decode(&instruction.data), Some((ProgramInstruction::RedeemUsdc, Some([1; 32]))));
+        assert_eq!(instruction.data.len(), 8 + 32 + 64 + 1 + 32);
+        assert_eq!(&instruction.data[40..104], &[7; 64]);
+        assert_eq!(instruction.data[104], 1);
+        assert_eq!(&instruction.data[105..], &[8; 32]);
+
+        // The swap PDA, then Bob, who signs and pays for token accounts
+        assert_eq!(instruction.accounts[0].pubkey, swap_address(&program_id(), &[1; 32]).unwrap());
+        assert!(instruction.accounts[1].is_signer && instruction.accounts[1].is_writable);
+        assert_eq!(instruction.accounts.iter().filter(|meta| meta.is_signer).count(), 1);
+        assert_eq!(instruction.accounts.len(), 10);
+    }
+}
@@ -0,0 +1,196 @@
+//! Legacy Solana transaction encoding and signing, and the system program's
+//! durable nonce instruction and account layout.
+
+use super::solana_program::{decode_pubkey, Pubkey};
+use crate::security::SolanaKeypair;
+
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+pub const SYSVAR_RECENT_BLOCKHASHES_ID: &str = "SysvarRecentB1ockHashes11111111111111111111";
+pub const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Most compute units a transaction may request.
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+/// Fee per signature, in lamports.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// `SystemInstruction::AdvanceNonceAccount`.
+const ADVANCE_NONCE_ACCOUNT: u32 = 4;
+/// `ComputeBudgetInstruction` tags.
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+#[derive(Debug, Clone)]
+pub struct AccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl AccountMeta {
+    pub fn writable(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: true }
+    }
+
+    pub fn readonly(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: false }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+/// First instruction of a durable nonce transaction: advances the nonce so
+/// the transaction can't be replayed.
+pub fn advance_nonce_instruction(nonce_account: Pubkey, authority: Pubkey) -> anyhow::Result<Instruction> {
+    Ok(Instruction {
+        program_id: decode_pubkey(SYSTEM_PROGRAM_ID)?,
+        accounts: vec![
+            AccountMeta::writable(nonce_account, false),
+            AccountMeta::readonly(decode_pubkey(SYSVAR_RECENT_BLOCKHASHES_ID)?, false),
+            AccountMeta::readonly(authority, true),
+        ],
+        data: ADVANCE_NONCE_ACCOUNT.to_le_bytes().to_vec(),
+    })
+}
+
+pub fn set_compute_unit_limit(units: u32) -> anyhow::Result<Instruction> {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Ok(Instruction {
+        program_id: decode_pubkey(COMPUTE_BUDGET_PROGRAM_ID)?,
+        accounts: Vec::new(),
+        data,
+    })
+}
+
+/// Priority fee, in micro-lamports per requested compute unit.
+pub fn set_compute_unit_price(micro_lamports: u64) -> anyhow::Result<Instruction> {
+    let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Ok(Instruction {
+        program_id: decode_pubkey(COMPUTE_BUDGET_PROGRAM_ID)?,
+        accounts: Vec::new(),
+        data,
+    })
+}
+
+/// Initialized nonce account state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceAccount {
+    pub authority: Pubkey,
+    /// Blockhash a transaction using this nonce must carry.
+    pub nonce: [u8; 32],
+}
+
+impl NonceAccount {
+    /// Decode `nonce::state::Versions`: version and state tags, then the
+    /// authority, the durable nonce and the fee calculator.
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < 80 {
+            return Err(anyhow::anyhow!("Nonce account data too short"));
+        }
+        if u32::from_le_bytes(data[4..8].try_into()?) != 1 {
+            return Err(anyhow::anyhow!("Nonce account is not initialized"));
+        }
+        Ok(Self {
+            authority: data[8..40].try_into()?,
+            nonce: data[40..72].try_into()?,
+        })
+    }
+}
+
+/// Compact-u16 length prefix.
+fn push_short_len(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.push(byte);
+            return;
+        }
+        byte |= 0x80;
+        out.push(byte);
+    }
+}
+
+/// A legacy message: accounts ordered signer-writable, signer-readonly,
+/// writable, readonly, with the fee payer first.
+fn compile_message(payer: Pubkey, instructions: &[Instruction], blockhash: [u8; 32]) -> anyhow::Result<(Vec<u8>, usize)> {
+    let mut metas: Vec<AccountMeta> = vec![AccountMeta::writable(payer, true)];
+    let mut add = |meta: AccountMeta| match metas.iter_mut().find(|known| known.pubkey == meta.pubkey) {
+        Some(known) => {
+            known.is_signer |= meta.is_signer;
+            known.is_writable |= meta.is_writable;
+        }
+        None => metas.push(meta),
+    };
+    for instruction in instructions {
+        for meta in &instruction.accounts {
+            add(meta.clone());
+        }
+        add(AccountMeta::readonly(instruction.program_id, false));
+    }
+
+    // Stable sort keeps the payer first among signer-writable accounts
+    metas.sort_by_key(|meta| (!meta.is_signer, !meta.is_writable));
+    let signers = metas.iter().filter(|meta| meta.is_signer).count();
+    let readonly_signed = metas.iter().filter(|meta| meta.is_signer && !meta.is_writable).count();
+    let readonly_unsigned = metas.iter().filter(|meta| !meta.is_signer && !meta.is_writable).count();
+    if metas.len() > u8::MAX as usize {
+        return Err(anyhow::anyhow!("Too many accounts in transaction"));
+    }
+    let index = |pubkey: &Pubkey| metas.iter().position(|meta| &meta.pubkey == pubkey).unwrap_or_default() as u8;
+
+    let mut message = vec![signers as u8, readonly_signed as u8, readonly_unsigned as u8];
+    push_short_len(&mut message, metas.len());
+    for meta in &metas {
+        message.extend_from_slice(&meta.pubkey);
+    }
+    message.extend_from_slice(&blockhash);
+    push_short_len(&mut message, instructions.len());
+    for instruction in instructions {
+        message.push(index(&instruction.program_id));
+        push_short_len(&mut message, instruction.accounts.len());
+        for meta in &instruction.accounts {
+            message.push(index(&meta.pubkey));
+        }
+        push_short_len(&mut message, instruction.data.len());
+        message.extend_from_slice(&instruction.data);
+    }
+    Ok((message, signers))
+}
+
+/// A signed transaction, ready to send.
+#[derive(Debug, Clone)]
+pub struct SignedTransaction {
+    /// Base58 fee payer signature, which identifies the transaction.
+    pub signature: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Compile and sign a transaction paid for and signed only by `payer`.
+pub fn sign_transaction(
+    payer: &SolanaKeypair,
+    instructions: &[Instruction],
+    blockhash: [u8; 32],
+) -> anyhow::Result<SignedTransaction> {
+    let (message, signers) = compile_message(payer.public_key(), instructions, blockhash)?;
+    if signers != 1 {
+        return Err(anyhow::anyhow!("Transaction needs {} signers; only the payer can sign", signers));
+    }
+
+    let signature = payer.sign(&message);
+    let mut bytes = Vec::with_capacity(1 + 64 + message.len());
+    push_short_len(&mut bytes, 1);
+    bytes.extend_from_slice(&signature);
+    bytes.extend_from_slice(&message);
+
+    Ok(SignedTransaction {
+        signature: bs58::encode(signature).into_string(),
+        bytes,
+    })
+}
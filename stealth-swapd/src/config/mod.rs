@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use secrecy::SecretString;
 
@@ -11,6 +12,9 @@ pub struct AppConfig {
     pub logging: LoggingConfig,
     pub server: ServerConfig,
     pub database: DatabaseConfig,
+    /// Finality required before the engine acts on a chain observation.
+    pub confirmations: Option<ConfirmationConfig>,
+    pub engine: Option<EngineConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +23,40 @@ pub struct SolanaConfig {
     pub keypair_path: PathBuf,
     pub usdc_mint: String,
     pub commitment: Option<String>,
+    pub program_id: Option<String>,
+    /// Websocket endpoint for program subscriptions; derived from `rpc_url`
+    /// when unset. `solana-test-validator` serves it on port 8900.
+    pub ws_url: Option<String>,
+    /// Durable nonce accounts with Bob's key as authority. Refund and redeem
+    /// transactions take their blockhash from one of these, so they stay
+    /// valid until sent however long that takes.
+    pub nonce_accounts: Option<Vec<String>>,
+    /// How often unconfirmed transactions are sent again.
+    pub rebroadcast_seconds: Option<u64>,
+    /// Environment variable holding the passphrase that encrypts swap
+    /// secrets and refunds pre-signed once Bob's USDC escrow exists.
+    /// Pre-signing is off, and secrets are not stored, while it is unset.
+    pub presign_key_env: Option<String>,
+    /// Compute budget and priority fees; total cost per transaction is
+    /// capped at `relayer.max_gas_lamports`.
+    pub priority_fees: Option<PriorityFeeConfig>,
+}
+
+/// Compute-unit limits and priority fees for Solana transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityFeeConfig {
+    /// Percentile of recent prioritization fees on the transaction's
+    /// writable accounts to pay.
+    pub percentile: Option<u8>,
+    /// Headroom added to the compute units simulation consumed.
+    pub compute_unit_margin_percent: Option<u64>,
+    /// Limit for transactions signed before they can be simulated.
+    pub default_compute_units: Option<u32>,
+    /// A transaction whose swap is this close to its deadline, or past it,
+    /// counts as urgent.
+    pub urgent_within_minutes: Option<u64>,
+    /// Raise on the price per retry of an urgent transaction.
+    pub escalation_percent: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,9 +64,71 @@ pub struct MoneroConfig {
     pub wallet_rpc_url: String,
     pub wallet_file: String,
     pub password_env: String,
+    /// monero-wallet-rpc `--rpc-login` user, sent with HTTP digest auth.
+    pub wallet_rpc_username: Option<String>,
+    /// Environment variable holding the `--rpc-login` password.
+    pub wallet_rpc_password_env: Option<String>,
+    /// Retries of a wallet RPC call after a transient failure (connection
+    /// errors, 5xx responses, a busy wallet).
+    pub rpc_max_retries: Option<u32>,
+    pub rpc_retry_base_ms: Option<u64>,
+    pub rpc_retry_max_ms: Option<u64>,
+    /// How often the wallet is saved to disk; it is also saved on shutdown.
+    pub store_interval_seconds: Option<u64>,
+    /// Create `wallet_file` on first run if wallet-rpc can't open it.
+    pub create_if_missing: Option<bool>,
+    /// Restore `wallet_file` from keys, rather than creating a fresh wallet,
+    /// when it can't be opened.
+    pub restore: Option<WalletRestoreConfig>,
+    /// Keep large outputs split into payout-sized ones; off when unset.
+    pub outputs: Option<OutputConfig>,
+    pub fees: Option<FeeConfig>,
     pub daemon_url: Option<String>,
     pub daemon_username: Option<String>,
     pub daemon_password: Option<String>,
+    /// "mainnet", "stagenet" or "testnet"; destination addresses must match.
+    pub network: Option<String>,
+    /// Environment variable holding the token `/notify/monero` hooks must
+    /// pass as `?token=`. Without one, only loopback callers are accepted.
+    pub notify_token_env: Option<String>,
+    /// Quoting pauses while the wallet is more than this many blocks behind
+    /// the daemon at `daemon_url`.
+    pub max_wallet_lag_blocks: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletRestoreConfig {
+    /// Primary address of the wallet being restored.
+    pub address: String,
+    /// Environment variables holding the hex private keys.
+    pub spend_key_env: String,
+    pub view_key_env: String,
+    /// Height to scan from; older blocks are skipped.
+    pub restore_height: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// Size, in piconero, of the outputs kept ready for payouts; roughly a
+    /// typical swap.
+    pub denomination: u64,
+    /// Outputs of at least `denomination`, unlocked or unlocking, to keep.
+    pub target_count: usize,
+    /// Most outputs one split creates, at most 16.
+    pub max_split_outputs: Option<usize>,
+}
+
+/// Transfer priority (wallet-rpc's 1 = unimportant to 4 = priority) and
+/// fee limits for Monero payouts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeConfig {
+    pub normal_priority: Option<u32>,
+    /// Priority for payouts close to the swap's expiry.
+    pub urgent_priority: Option<u32>,
+    /// A payout this close to expiry counts as urgent.
+    pub urgent_within_minutes: Option<u64>,
+    /// Most one swap's payout may pay in fees, in piconero.
+    pub max_fee_per_swap: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,7 +136,61 @@ pub struct QuotingConfig {
     pub min_usdc: u64,
     pub max_usdc: u64,
     pub spread_bps: u64,
+    /// Reference rate quotes are priced at, in USDC base units per XMR.
+    pub usdc_per_xmr: u64,
     pub expiry_minutes: Option<u64>,
+    pub max_quotes_per_client: Option<usize>,
+    pub reap_interval_seconds: Option<u64>,
+    /// Shrink quotes to available inventory instead of rejecting them.
+    pub partial_fills: Option<bool>,
+    pub inventory_refresh_seconds: Option<u64>,
+    pub skew: Option<SkewConfig>,
+    /// Rhai pricing script; takes precedence over `skew` and `spread_bps`.
+    pub script_path: Option<PathBuf>,
+    /// How long accept waits for Alice's `Swap` PDA to appear on-chain.
+    pub accept_wait_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkewConfig {
+    /// Desired XMR share of total inventory value, in basis points.
+    pub target_xmr_ratio_bps: u64,
+    /// Skew applied when inventory is entirely one asset.
+    pub max_skew_bps: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationConfig {
+    /// Tiers by swap size; a swap uses the first tier whose `max_usdc` covers it.
+    pub tiers: Vec<ConfirmationTier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationTier {
+    /// Largest swap, in USDC base units, this tier applies to. `None` for the top tier.
+    pub max_usdc: Option<u64>,
+    pub monero_confirmations: u64,
+    /// "processed", "confirmed" or "finalized".
+    pub solana_commitment: String,
+    /// Slots that must be built on the transaction behind an on-chain
+    /// observation before it counts.
+    pub solana_slot_depth: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineConfig {
+    /// Consecutive failed attempts at a swap before it is marked failed.
+    pub max_attempts: Option<u32>,
+    /// Delay after the first failure; doubles with each further failure.
+    pub retry_base_seconds: Option<u64>,
+    pub retry_max_seconds: Option<u64>,
+    /// How often a swap waiting on Monero confirmations is checked.
+    pub monero_poll_seconds: Option<u64>,
+    /// How often a swap waiting on a Solana account change is checked.
+    pub solana_poll_seconds: Option<u64>,
+    /// Interval of the engine-wide deposit scan and re-verification.
+    pub sweep_interval_seconds: Option<u64>,
+    pub max_concurrent_swaps: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +213,13 @@ pub struct ServerConfig {
     pub bind_address: String,
     pub timeout_seconds: Option<u64>,
     pub max_connections: Option<u32>,
+    /// Environment variable holding the bearer token for `/admin` routes.
+    /// The admin API is disabled when unset and no operator is configured.
+    pub admin_token_env: Option<String>,
+    /// Operators by name, each with the environment variable holding their
+    /// own admin token. Admin actions are audited under the name whose
+    /// token authenticated them, or `admin` for `admin_token_env`'s.
+    pub admin_operators: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,20 +238,58 @@ impl Default for AppConfig {
                 keypair_path: PathBuf::from("/secrets/bob.json"),
                 usdc_mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
                 commitment: Some("confirmed".to_string()),
+                program_id: Some("G1BVSiFojnXFaPG1WUgJAcYaB7aGKLKWtSqhMreKgA82".to_string()),
+                ws_url: None,
+                nonce_accounts: None,
+                rebroadcast_seconds: Some(2),
+                presign_key_env: Some("STEALTH_SWAP_PRESIGN_KEY".to_string()),
+                priority_fees: Some(PriorityFeeConfig {
+                    percentile: Some(75),
+                    compute_unit_margin_percent: Some(20),
+                    default_compute_units: Some(200_000),
+                    urgent_within_minutes: Some(30),
+                    escalation_percent: Some(50),
+                }),
             },
             monero: MoneroConfig {
                 wallet_rpc_url: "http://127.0.0.1:18083".to_string(),
                 wallet_file: "bob_swap".to_string(),
                 password_env: "MONERO_WALLET_PASSWORD".to_string(),
+                wallet_rpc_username: None,
+                wallet_rpc_password_env: Some("MONERO_WALLET_RPC_PASSWORD".to_string()),
+                rpc_max_retries: Some(3),
+                rpc_retry_base_ms: Some(250),
+                rpc_retry_max_ms: Some(5_000),
+                store_interval_seconds: Some(300),
+                create_if_missing: Some(false),
+                restore: None,
+                outputs: None,
+                fees: Some(FeeConfig {
+                    normal_priority: Some(1),
+                    urgent_priority: Some(3),
+                    urgent_within_minutes: Some(30),
+                    max_fee_per_swap: Some(1_000_000_000),  // 0.001 XMR
+                }),
                 daemon_url: None,
                 daemon_username: None,
                 daemon_password: None,
+                network: Some("mainnet".to_string()),
+                notify_token_env: Some("STEALTH_SWAP_NOTIFY_TOKEN".to_string()),
+                max_wallet_lag_blocks: Some(3),
             },
             quoting: QuotingConfig {
                 min_usdc: 100_000_000,  // 100 USDC
                 max_usdc: 10_000_000_000,  // 10,000 USDC
                 spread_bps: 50,
+                usdc_per_xmr: 150_000_000,  // 150 USDC
                 expiry_minutes: Some(30),
+                max_quotes_per_client: Some(5),
+                reap_interval_seconds: Some(30),
+                partial_fills: Some(false),
+                inventory_refresh_seconds: Some(30),
+                skew: None,
+                script_path: None,
+                accept_wait_seconds: Some(30),
             },
             relayer: RelayerConfig {
                 enabled: true,
@@ -107,6 +306,8 @@ impl Default for AppConfig {
                 bind_address: "0.0.0.0:3000".to_string(),
                 timeout_seconds: Some(30),
                 max_connections: Some(100),
+                admin_token_env: Some("STEALTH_SWAP_ADMIN_TOKEN".to_string()),
+                admin_operators: None,
             },
             database: DatabaseConfig {
                 path: PathBuf::from("./data/stealth-swap.db"),
@@ -114,6 +315,31 @@ impl Default for AppConfig {
                 max_connections: Some(10),
                 checkpoint_interval: Some(300),
             },
+            confirmations: Some(ConfirmationConfig {
+                tiers: vec![
+                    ConfirmationTier {
+                        max_usdc: Some(1_000_000_000),  // up to 1,000 USDC
+                        monero_confirmations: 10,
+                        solana_commitment: "confirmed".to_string(),
+                        solana_slot_depth: Some(0),
+                    },
+                    ConfirmationTier {
+                        max_usdc: None,
+                        monero_confirmations: 20,
+                        solana_commitment: "finalized".to_string(),
+                        solana_slot_depth: Some(32),
+                    },
+                ],
+            }),
+            engine: Some(EngineConfig {
+                max_attempts: Some(10),
+                retry_base_seconds: Some(5),
+                retry_max_seconds: Some(600),
+                monero_poll_seconds: Some(30),
+                solana_poll_seconds: Some(5),
+                sweep_interval_seconds: Some(30),
+                max_concurrent_swaps: Some(64),
+            }),
         }
     }
 }
@@ -146,6 +372,69 @@ impl AppConfig {
             return Err(ConfigError::EmptyPasswordEnv(self.monero.password_env.clone()));
         }
 
+        if self.monero.rpc_retry_base_ms.unwrap_or(250) > self.monero.rpc_retry_max_ms.unwrap_or(5_000) {
+            return Err(ConfigError::InvalidRetryPolicy("rpc_retry_base_ms exceeds rpc_retry_max_ms".to_string()));
+        }
+
+        if self.solana.rebroadcast_seconds == Some(0) {
+            return Err(ConfigError::InvalidRebroadcastInterval);
+        }
+
+        for account in self.solana.nonce_accounts.iter().flatten() {
+            if crate::clients::solana_program::decode_pubkey(account).is_err() {
+                return Err(ConfigError::InvalidNonceAccount(account.clone()));
+            }
+        }
+
+        if let Some(fees) = &self.solana.priority_fees {
+            if fees.percentile.unwrap_or(75) > 100 {
+                return Err(ConfigError::InvalidPriorityFeeConfig("percentile exceeds 100".to_string()));
+            }
+            if !(1..=1_400_000).contains(&fees.default_compute_units.unwrap_or(200_000)) {
+                return Err(ConfigError::InvalidPriorityFeeConfig(
+                    "default_compute_units must be between 1 and 1400000".to_string(),
+                ));
+            }
+        }
+
+        if self.monero.store_interval_seconds == Some(0) {
+            return Err(ConfigError::InvalidStoreInterval);
+        }
+
+        if let Some(restore) = &self.monero.restore {
+            for env in [&restore.spend_key_env, &restore.view_key_env] {
+                if std::env::var(env).unwrap_or_default().is_empty() {
+                    return Err(ConfigError::MissingKeyEnv(env.clone()));
+                }
+            }
+        }
+
+        if let Some(outputs) = &self.monero.outputs {
+            if outputs.denomination == 0 || outputs.target_count == 0 {
+                return Err(ConfigError::InvalidOutputConfig("denomination and target_count must be positive".to_string()));
+            }
+            if !(2..=16).contains(&outputs.max_split_outputs.unwrap_or(16)) {
+                return Err(ConfigError::InvalidOutputConfig("max_split_outputs must be between 2 and 16".to_string()));
+            }
+        }
+
+        if let Some(fees) = &self.monero.fees {
+            let normal = fees.normal_priority.unwrap_or(1);
+            let urgent = fees.urgent_priority.unwrap_or(3);
+            if !(1..=4).contains(&normal) || !(1..=4).contains(&urgent) {
+                return Err(ConfigError::InvalidFeeConfig("priorities must be between 1 and 4".to_string()));
+            }
+            if urgent < normal {
+                return Err(ConfigError::InvalidFeeConfig("urgent_priority is below normal_priority".to_string()));
+            }
+        }
+
+        if let Some(network) = &self.monero.network {
+            if !matches!(network.as_str(), "mainnet" | "stagenet" | "testnet") {
+                return Err(ConfigError::InvalidMoneroNetwork(network.clone()));
+            }
+        }
+
         // Validate quoting parameters
         if self.quoting.min_usdc >= self.quoting.max_usdc {
             return Err(ConfigError::InvalidQuotingRange);
@@ -155,6 +444,52 @@ impl AppConfig {
             return Err(ConfigError::InvalidSpread(self.quoting.spread_bps));
         }
 
+        if self.quoting.expiry_minutes == Some(0) {
+            return Err(ConfigError::InvalidQuoteExpiry);
+        }
+
+        if self.quoting.usdc_per_xmr == 0 {
+            return Err(ConfigError::InvalidReferencePrice);
+        }
+
+        if let Some(skew) = &self.quoting.skew {
+            if skew.target_xmr_ratio_bps > 10000 || skew.max_skew_bps > 10000 {
+                return Err(ConfigError::InvalidSkew);
+            }
+        }
+
+        if let Some(script_path) = &self.quoting.script_path {
+            if !script_path.exists() {
+                return Err(ConfigError::FileNotFound(script_path.clone()));
+            }
+        }
+
+        if let Some(confirmations) = &self.confirmations {
+            if confirmations.tiers.is_empty() {
+                return Err(ConfigError::InvalidConfirmationTiers("at least one tier is required".to_string()));
+            }
+            if confirmations.tiers.iter().filter(|tier| tier.max_usdc.is_none()).count() > 1 {
+                return Err(ConfigError::InvalidConfirmationTiers("only one tier may omit max_usdc".to_string()));
+            }
+            for tier in &confirmations.tiers {
+                if !matches!(tier.solana_commitment.as_str(), "processed" | "confirmed" | "finalized") {
+                    return Err(ConfigError::InvalidConfirmationTiers(format!(
+                        "unknown Solana commitment {}",
+                        tier.solana_commitment
+                    )));
+                }
+            }
+        }
+
+        if let Some(engine) = &self.engine {
+            if engine.max_attempts == Some(0) {
+                return Err(ConfigError::InvalidRetryPolicy("max_attempts must be at least 1".to_string()));
+            }
+            if engine.retry_base_seconds.unwrap_or(5) > engine.retry_max_seconds.unwrap_or(600) {
+                return Err(ConfigError::InvalidRetryPolicy("retry_base_seconds exceeds retry_max_seconds".to_string()));
+            }
+        }
+
         // Validate relayer config
         if self.relayer.fee_bps > 10000 {
             return Err(ConfigError::InvalidFeeBps(self.relayer.fee_bps));
@@ -163,6 +498,41 @@ impl AppConfig {
         Ok(())
     }
 
+    /// Admin API tokens configured and present in the environment, each
+    /// with the name of the operator it authenticates.
+    pub fn get_admin_tokens(&self) -> Vec<(String, SecretString)> {
+        let shared = self.server.admin_token_env.iter().map(|env| ("admin".to_string(), env));
+        let operators = self.server.admin_operators.iter().flatten().map(|(name, env)| (name.clone(), env));
+        shared
+            .chain(operators)
+            .filter_map(|(name, env)| {
+                std::env::var(env)
+                    .ok()
+                    .filter(|token| !token.is_empty())
+                    .map(|token| (name, SecretString::new(token)))
+            })
+            .collect()
+    }
+
+    /// Passphrase for pre-signed transactions, if configured and present in
+    /// the environment.
+    pub fn get_presign_key(&self) -> Option<SecretString> {
+        let env = self.solana.presign_key_env.as_ref()?;
+        std::env::var(env)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(SecretString::new)
+    }
+
+    /// Token for the Monero notification hooks, if present in the environment.
+    pub fn get_notify_token(&self) -> Option<SecretString> {
+        let env = self.monero.notify_token_env.as_ref()?;
+        std::env::var(env)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .map(SecretString::new)
+    }
+
     pub fn get_monero_password(&self) -> Result<SecretString, ConfigError> {
         let password = std::env::var(&self.monero.password_env)
             .map_err(|_| ConfigError::MissingPasswordEnv(self.monero.password_env.clone()))?;
@@ -188,6 +558,30 @@ pub enum ConfigError {
     #[error("Empty password environment variable: {0}")]
     EmptyPasswordEnv(String),
     
+    #[error("Missing or empty wallet key environment variable: {0}")]
+    MissingKeyEnv(String),
+
+    #[error("Rebroadcast interval must be at least one second")]
+    InvalidRebroadcastInterval,
+
+    #[error("Invalid nonce account: {0}")]
+    InvalidNonceAccount(String),
+
+    #[error("Invalid priority fee configuration: {0}")]
+    InvalidPriorityFeeConfig(String),
+
+    #[error("Wallet store interval must be at least one second")]
+    InvalidStoreInterval,
+
+    #[error("Invalid output management: {0}")]
+    InvalidOutputConfig(String),
+
+    #[error("Invalid Monero fee configuration: {0}")]
+    InvalidFeeConfig(String),
+
+    #[error("Invalid Monero network: {0}")]
+    InvalidMoneroNetwork(String),
+    
     #[error("Invalid quoting range: min must be less than max")]
     InvalidQuotingRange,
     
@@ -196,6 +590,21 @@ pub enum ConfigError {
     
     #[error("Invalid fee basis points: {0}")]
     InvalidFeeBps(u64),
+    
+    #[error("Quote expiry must be at least one minute")]
+    InvalidQuoteExpiry,
+
+    #[error("Invalid confirmation tiers: {0}")]
+    InvalidConfirmationTiers(String),
+
+    #[error("Invalid engine retry policy: {0}")]
+    InvalidRetryPolicy(String),
+    
+    #[error("Invalid skew: target ratio and max skew must be at most 10000 bps")]
+    InvalidSkew,
+
+    #[error("Invalid reference price: usdc_per_xmr must be positive")]
+    InvalidReferencePrice,
 }
 
 pub fn load_config() -> Result<AppConfig, ConfigError> {
@@ -1,6 +1,8 @@
 pub mod config;
 pub mod clients;
 pub mod swap_engine;
+pub mod quoting;
+pub mod chain;
 pub mod api;
 pub mod metrics;
 pub mod security;
@@ -1,20 +1,15 @@
-mod config;
-mod clients;
-mod swap_engine;
-mod api;
-mod metrics;
-mod security;
-
 use std::sync::Arc;
+use std::time::Duration;
 use anyhow::Result;
 use clap::Parser;
 use tracing::{info, error};
-use tracing_subscriber;
 
-use config::load_config;
-use clients::{SolanaClient, MoneroClient};
-use swap_engine::SwapEngine;
-use metrics::MetricsCollector;
+use stealth_swapd::{api, config};
+use stealth_swapd::chain::SolanaSubscriber;
+use stealth_swapd::config::load_config;
+use stealth_swapd::clients::{SolanaClient, MoneroClient};
+use stealth_swapd::swap_engine::{PresignedStore, SwapEngine};
+use stealth_swapd::metrics::MetricsCollector;
 
 #[derive(Parser)]
 #[command(
@@ -34,6 +29,11 @@ struct Args {
     /// Print configuration and exit
     #[arg(long)]
     print_config: bool,
+
+    /// Write the pre-signed transaction recovery bundle to this file and
+    /// exit, without connecting to Solana or Monero
+    #[arg(long)]
+    export_recovery_bundle: Option<std::path::PathBuf>,
 }
 
 #[tokio::main]
@@ -61,6 +61,14 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    if let Some(path) = &args.export_recovery_bundle {
+        let db = init_database(&config.database).await?;
+        let bundle = PresignedStore::new(db, config.get_presign_key()).recovery_bundle().await?;
+        tokio::fs::write(path, serde_json::to_vec_pretty(&bundle)?).await?;
+        info!("Wrote {} pre-signed transactions to {}", bundle.transactions.len(), path.display());
+        return Ok(());
+    }
+
     info!("Configuration loaded successfully");
 
     // Initialize metrics
@@ -73,10 +81,11 @@ async fn main() -> Result<()> {
     info!("Initializing Monero client...");
     let monero_password = config.get_monero_password()?;
     let monero_client = MoneroClient::new(&config.monero, monero_password).await?;
+    let wallet = monero_client.clone();
 
     // Initialize database
     info!("Initializing database...");
-    let _db = init_database(&config.database).await?;
+    let db = init_database(&config.database).await?;
 
     if args.migrate_only {
         info!("Database migrations completed successfully");
@@ -85,12 +94,15 @@ async fn main() -> Result<()> {
 
     // Initialize swap engine
     info!("Initializing swap engine...");
+    let (subscriber, subscriptions) = SolanaSubscriber::new(solana_client.clone())?;
     let swap_engine = SwapEngine::new(
         config.clone(),
         solana_client,
         monero_client,
         metrics.clone(),
-    ).await?;
+        db,
+    ).await?
+    .with_subscriptions(subscriptions);
 
     info!("Swap engine initialized successfully");
 
@@ -104,6 +116,39 @@ async fn main() -> Result<()> {
         })
     };
 
+    let subscriber_handle = {
+        let swap_engine = swap_engine.clone();
+        let (events, mut received) = tokio::sync::mpsc::channel(1024);
+        tokio::spawn(subscriber.run(events));
+        tokio::spawn(async move {
+            while let Some(event) = received.recv().await {
+                swap_engine.handle_program_event(event).await;
+            }
+        })
+    };
+
+    let autosave_handle = {
+        let wallet = wallet.clone();
+        let interval = Duration::from_secs(config.monero.store_interval_seconds.unwrap_or(300));
+        tokio::spawn(async move {
+            wallet.run_autosave(interval).await;
+        })
+    };
+
+    let rebroadcast_handle = {
+        let transactions = swap_engine.transactions().clone();
+        tokio::spawn(async move {
+            transactions.run().await;
+        })
+    };
+
+    let quote_reaper_handle = {
+        let quotes = swap_engine.quote_manager().clone();
+        tokio::spawn(async move {
+            quotes.run_reaper().await;
+        })
+    };
+
     // Start HTTP server
     let server_handle = {
         let config = config.clone();
@@ -113,9 +158,11 @@ async fn main() -> Result<()> {
         tokio::spawn(async move {
             info!("Starting HTTP server on {}", config.server.bind_address);
             if let Err(e) = api::start_server(
-                config.server.bind_address,
+                config.server.bind_address.clone(),
                 swap_engine,
                 metrics,
+                config.get_admin_tokens(),
+                config.get_notify_token(),
             ).await {
                 error!("HTTP server error: {}", e);
             }
@@ -129,7 +176,15 @@ async fn main() -> Result<()> {
 
     // Gracefully shutdown
     swap_engine_handle.abort();
+    subscriber_handle.abort();
+    quote_reaper_handle.abort();
+    rebroadcast_handle.abort();
     server_handle.abort();
+    autosave_handle.abort();
+
+    if let Err(e) = wallet.store().await {
+        error!("Failed to store Monero wallet: {}", e);
+    }
 
     info!("Gracefully shutdown completed");
     Ok(())
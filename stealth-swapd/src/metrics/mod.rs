@@ -8,10 +8,11 @@ use std::collections::HashMap;
 pub struct MetricsCollector {
     registry: Registry,
     swaps_total: CounterVec,
-    swaps_duration_seconds: GaugeVec,
     monero_wallet_balance_xmr: Gauge,
     solana_wallet_balance_usdc: Gauge,
     relayer_fees_earned_usdc: Gauge,
+    monero_fees_paid: Gauge,
+    chain_reorgs_total: CounterVec,
 }
 
 impl MetricsCollector {
@@ -30,7 +31,7 @@ impl MetricsCollector {
             Opts::new("swaps_duration_seconds", "Duration of swaps by direction and state"),
             &["direction", "state"]
         ).unwrap();
-        registry.register(Box::new(swaps_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(swaps_duration_seconds)).unwrap();
 
         // Wallet balance gauges
         let monero_wallet_balance_xmr = Gauge::new(
@@ -52,13 +53,28 @@ impl MetricsCollector {
         ).unwrap();
         registry.register(Box::new(relayer_fees_earned_usdc.clone())).unwrap();
 
+        // Network fees of Monero payouts
+        let monero_fees_paid = Gauge::new(
+            "monero_fees_paid",
+            "Total Monero network fees paid on swap payouts in atomic units"
+        ).unwrap();
+        registry.register(Box::new(monero_fees_paid.clone())).unwrap();
+
+        // Observations invalidated by a reorg
+        let chain_reorgs_total = CounterVec::new(
+            Opts::new("chain_reorgs_total", "Chain observations rolled back after a reorganisation"),
+            &["chain"]
+        ).unwrap();
+        registry.register(Box::new(chain_reorgs_total.clone())).unwrap();
+
         Self {
             registry,
+            chain_reorgs_total,
             swaps_total,
-            swaps_duration_seconds,
             monero_wallet_balance_xmr,
             solana_wallet_balance_usdc,
             relayer_fees_earned_usdc,
+            monero_fees_paid,
         }
     }
 
@@ -82,6 +98,10 @@ impl MetricsCollector {
         self.swaps_total.with_label_values(&["na", "failed"]).inc();
     }
 
+    pub fn increment_reorgs(&self, chain: &str) {
+        self.chain_reorgs_total.with_label_values(&[chain]).inc();
+    }
+
     pub fn set_monero_balance(&self, balance: u64) {
         self.monero_wallet_balance_xmr.set(balance as f64);
     }
@@ -94,6 +114,10 @@ impl MetricsCollector {
         self.relayer_fees_earned_usdc.add(fee as f64);
     }
 
+    pub fn add_monero_fee(&self, fee: u64) {
+        self.monero_fees_paid.add(fee as f64);
+    }
+
     pub fn export(&self) -> String {
         let encoder = TextEncoder::new();
         let metric_families = self.registry.gather();
@@ -0,0 +1,526 @@
+use crate::clients::{MoneroClient, SolanaClient};
+use crate::clients::monero::{MoneroBalance, WalletOutput};
+use super::outputs::{spendable_after, unlock_schedule, UnlockEstimate};
+use crate::config::QuotingConfig;
+use crate::swap_engine::Direction;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+use anyhow::Result;
+
+/// Blocks a freshly received or change output stays locked on Monero.
+pub const MONERO_LOCK_BLOCKS: u64 = 10;
+
+/// Average Monero block time, used to project when locked outputs unlock.
+pub const MONERO_BLOCK_TIME_SECS: i64 = 120;
+
+#[derive(Debug, thiserror::Error)]
+pub enum InventoryError {
+    #[error("Insufficient XMR inventory: requested {requested}, available {available}")]
+    InsufficientXmr { requested: u64, available: u64 },
+
+    #[error("Insufficient USDC inventory: requested {requested}, available {available}")]
+    InsufficientUsdc { requested: u64, available: u64 },
+}
+
+/// Wallet balances as last observed on both chains.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InventoryBalances {
+    pub xmr_unlocked: u64,
+    pub xmr_locked: u64,
+    pub xmr_blocks_to_unlock: u64,
+    pub usdc: u64,
+    pub refreshed_at: Option<DateTime<Utc>>,
+}
+
+impl InventoryBalances {
+    /// Projected time at which the currently locked XMR becomes spendable.
+    pub fn xmr_unlocks_at(&self) -> Option<DateTime<Utc>> {
+        let refreshed_at = self.refreshed_at?;
+        if self.xmr_locked == 0 {
+            return None;
+        }
+        Some(refreshed_at + Duration::seconds(self.xmr_blocks_to_unlock as i64 * MONERO_BLOCK_TIME_SECS))
+    }
+}
+
+/// Liquidity held back for an outstanding quote or an accepted swap.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reservation {
+    pub quote_id: uuid::Uuid,
+    pub swap_id: Option<[u8; 32]>,
+    pub direction: Direction,
+    pub usdc_amount: u64,
+    pub xmr_amount: u64,
+    /// XMR Bob has to pay out for this trade.
+    pub xmr_reserved: u64,
+    /// USDC Bob has to lock (escrow or collateral) for this trade.
+    pub usdc_reserved: u64,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Reservation {
+    /// A quote's reservation lapses with the quote; one bound to a swap is
+    /// held until the swap settles or is released.
+    fn is_held(&self, now: DateTime<Utc>) -> bool {
+        self.swap_id.is_some() || self.expires_at > now
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InventorySnapshot {
+    pub balances: InventoryBalances,
+    pub xmr_reserved: u64,
+    pub usdc_reserved: u64,
+    pub xmr_available: u64,
+    pub usdc_available: u64,
+    pub xmr_unlocks_at: Option<DateTime<Utc>>,
+    /// Locked outputs and when each is expected to unlock, when the wallet's
+    /// outputs are known.
+    pub xmr_unlocking: Vec<UnlockEstimate>,
+    pub reservations: usize,
+}
+
+#[derive(Debug, Default)]
+struct InventoryState {
+    balances: InventoryBalances,
+    reservations: HashMap<uuid::Uuid, Reservation>,
+    /// Unspent outputs as of the last refresh; `None` until then and once
+    /// a payout has spent some of them.
+    outputs: Option<Vec<WalletOutput>>,
+    wallet_height: u64,
+}
+
+impl InventoryState {
+    fn reserved(&self, now: DateTime<Utc>) -> (u64, u64) {
+        self.reservations
+            .values()
+            .filter(|r| r.is_held(now))
+            .fold((0u64, 0u64), |(xmr, usdc), r| {
+                (xmr.saturating_add(r.xmr_reserved), usdc.saturating_add(r.usdc_reserved))
+            })
+    }
+
+    /// Spendable liquidity net of live reservations. Locked XMR is excluded
+    /// until its 10-block lock has elapsed and the wallet reports it unlocked.
+    /// With the wallet's outputs known, XMR is what remains after fitting each
+    /// reservation to the outputs its payout would lock.
+    fn available(&self, now: DateTime<Utc>) -> (u64, u64) {
+        let (xmr_reserved, usdc_reserved) = self.reserved(now);
+        let xmr_available = match &self.outputs {
+            Some(outputs) => {
+                let reservations: Vec<u64> = self.reservations
+                    .values()
+                    .filter(|r| r.is_held(now))
+                    .map(|r| r.xmr_reserved)
+                    .collect();
+                spendable_after(outputs, &reservations)
+            }
+            None => self.balances.xmr_unlocked.saturating_sub(xmr_reserved),
+        };
+        (xmr_available, self.balances.usdc.saturating_sub(usdc_reserved))
+    }
+}
+
+/// Tracks Bob's XMR and USDC inventory and the liquidity promised to quotes.
+#[derive(Clone)]
+pub struct InventoryManager {
+    state: Arc<RwLock<InventoryState>>,
+    min_usdc: u64,
+    partial_fills: bool,
+    refresh_interval: Duration,
+}
+
+impl InventoryManager {
+    pub fn new(config: &QuotingConfig) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(InventoryState::default())),
+            min_usdc: config.min_usdc,
+            partial_fills: config.partial_fills.unwrap_or(false),
+            refresh_interval: Duration::seconds(config.inventory_refresh_seconds.unwrap_or(30) as i64),
+        }
+    }
+
+    /// Amounts Bob must commit for a trade: the XMR payout in direction A, and
+    /// the USDC escrow (direction B) or matching USDC collateral (direction A).
+    pub fn requirement(direction: Direction, usdc_amount: u64, xmr_amount: u64) -> (u64, u64) {
+        match direction {
+            Direction::UsdcToXmr => (xmr_amount, usdc_amount),
+            Direction::XmrToUsdc => (0, usdc_amount),
+        }
+    }
+
+    pub async fn refresh(&self, monero: &MoneroClient, solana: &SolanaClient) -> Result<InventoryBalances> {
+        let xmr = monero.get_balance().await?;
+        let outputs = monero.get_outputs().await?;
+        let wallet_height = monero.get_height().await?;
+        let usdc = solana.get_usdc_balance().await?;
+        self.set_outputs(outputs, wallet_height).await;
+        Ok(self.set_balances(&xmr, usdc).await)
+    }
+
+    pub async fn set_outputs(&self, outputs: Vec<WalletOutput>, wallet_height: u64) {
+        let mut state = self.state.write().await;
+        state.outputs = Some(outputs);
+        state.wallet_height = wallet_height;
+    }
+
+    /// Unspent outputs as of the last refresh, if still current.
+    pub async fn outputs(&self) -> Option<Vec<WalletOutput>> {
+        self.state.read().await.outputs.clone()
+    }
+
+    pub async fn set_balances(&self, xmr: &MoneroBalance, usdc: u64) -> InventoryBalances {
+        let mut state = self.state.write().await;
+        state.balances = InventoryBalances {
+            xmr_unlocked: xmr.unlocked,
+            xmr_locked: xmr.locked,
+            xmr_blocks_to_unlock: xmr.blocks_to_unlock,
+            usdc,
+            refreshed_at: Some(Utc::now()),
+        };
+        state.balances.clone()
+    }
+
+    pub async fn is_stale(&self) -> bool {
+        let state = self.state.read().await;
+        match state.balances.refreshed_at {
+            Some(refreshed_at) => Utc::now() - refreshed_at > self.refresh_interval,
+            None => true,
+        }
+    }
+
+    /// Force a refresh before the next quote, after funds left the wallet
+    /// outside of a reservation.
+    pub async fn mark_stale(&self) {
+        let mut state = self.state.write().await;
+        state.balances.refreshed_at = None;
+        state.outputs = None;
+    }
+
+    /// Spendable (XMR, USDC) after subtracting live reservations.
+    pub async fn available(&self) -> (u64, u64) {
+        self.state.read().await.available(Utc::now())
+    }
+
+    /// Reserve liquidity for a new quote. If the request exceeds inventory and
+    /// partial fills are enabled, both legs are scaled down to what is
+    /// available, as long as the result still clears `min_usdc`.
+    pub async fn reserve(
+        &self,
+        quote_id: uuid::Uuid,
+        direction: Direction,
+        usdc_amount: u64,
+        xmr_amount: u64,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Reservation, InventoryError> {
+        let mut state = self.state.write().await;
+        let (xmr_available, usdc_available) = state.available(Utc::now());
+        let (xmr_needed, usdc_needed) = Self::requirement(direction, usdc_amount, xmr_amount);
+
+        let (usdc_amount, xmr_amount) = if xmr_needed <= xmr_available && usdc_needed <= usdc_available {
+            (usdc_amount, xmr_amount)
+        } else if self.partial_fills {
+            // Scale by whichever leg is the tighter constraint
+            let (num, den) = if xmr_needed > 0
+                && (xmr_available as u128) * (usdc_needed.max(1) as u128)
+                    <= (usdc_available as u128) * (xmr_needed as u128)
+            {
+                (xmr_available, xmr_needed)
+            } else {
+                (usdc_available, usdc_needed)
+            };
+            let scale = |amount: u64| ((amount as u128) * (num as u128) / (den.max(1) as u128)) as u64;
+            let (usdc_amount, xmr_amount) = (scale(usdc_amount), scale(xmr_amount));
+
+            if usdc_amount < self.min_usdc || xmr_amount == 0 {
+                return Err(Self::shortfall(xmr_needed, xmr_available, usdc_needed, usdc_available));
+            }
+            (usdc_amount, xmr_amount)
+        } else {
+            return Err(Self::shortfall(xmr_needed, xmr_available, usdc_needed, usdc_available));
+        };
+
+        let (xmr_reserved, usdc_reserved) = Self::requirement(direction, usdc_amount, xmr_amount);
+        let reservation = Reservation {
+            quote_id,
+            swap_id: None,
+            direction,
+            usdc_amount,
+            xmr_amount,
+            xmr_reserved,
+            usdc_reserved,
+            expires_at,
+        };
+        state.reservations.insert(quote_id, reservation.clone());
+
+        Ok(reservation)
+    }
+
+    /// Reinstate a reservation recorded before a restart, without checking
+    /// it against balances that have not been refreshed yet.
+    pub async fn restore(
+        &self,
+        quote_id: uuid::Uuid,
+        direction: Direction,
+        usdc_amount: u64,
+        xmr_amount: u64,
+        expires_at: DateTime<Utc>,
+    ) {
+        let (xmr_reserved, usdc_reserved) = Self::requirement(direction, usdc_amount, xmr_amount);
+        self.state.write().await.reservations.insert(quote_id, Reservation {
+            quote_id,
+            swap_id: None,
+            direction,
+            usdc_amount,
+            xmr_amount,
+            xmr_reserved,
+            usdc_reserved,
+            expires_at,
+        });
+    }
+
+    /// Attach a reservation to an accepted swap so it is held until the swap
+    /// settles or is released, rather than until the quote expires.
+    pub async fn bind(&self, quote_id: uuid::Uuid, swap_id: [u8; 32], expires_at: DateTime<Utc>) {
+        let mut state = self.state.write().await;
+        if let Some(reservation) = state.reservations.get_mut(&quote_id) {
+            reservation.swap_id = Some(swap_id);
+            reservation.expires_at = expires_at;
+        }
+    }
+
+    /// Return reserved liquidity to the pool without spending it.
+    pub async fn release(&self, quote_id: uuid::Uuid) -> Option<Reservation> {
+        self.state.write().await.reservations.remove(&quote_id)
+    }
+
+    /// Mark reserved liquidity as spent. Only the asset Bob pays out leaves
+    /// the cached balance: the XMR payout in direction A, whose USDC collateral
+    /// stays Bob's, and the USDC escrow in direction B. The balance is flagged
+    /// stale, since a change output is now locked for another 10 blocks and
+    /// what Bob receives shows up only on a refresh.
+    pub async fn settle(&self, quote_id: uuid::Uuid) -> Option<Reservation> {
+        let mut state = self.state.write().await;
+        let reservation = state.reservations.remove(&quote_id)?;
+        match reservation.direction {
+            Direction::UsdcToXmr => {
+                state.balances.xmr_unlocked = state.balances.xmr_unlocked.saturating_sub(reservation.xmr_reserved);
+            }
+            Direction::XmrToUsdc => {
+                state.balances.usdc = state.balances.usdc.saturating_sub(reservation.usdc_reserved);
+            }
+        }
+        state.balances.refreshed_at = None;
+        state.outputs = None;
+        Some(reservation)
+    }
+
+    /// Drop reservations whose quote lapsed without being accepted. Those
+    /// bound to a swap are left for the swap to settle or release.
+    pub async fn release_expired(&self) -> Vec<uuid::Uuid> {
+        let now = Utc::now();
+        let mut state = self.state.write().await;
+        let expired: Vec<uuid::Uuid> = state.reservations
+            .values()
+            .filter(|r| !r.is_held(now))
+            .map(|r| r.quote_id)
+            .collect();
+        for quote_id in &expired {
+            state.reservations.remove(quote_id);
+        }
+        expired
+    }
+
+    pub async fn snapshot(&self) -> InventorySnapshot {
+        let now = Utc::now();
+        let state = self.state.read().await;
+        let (xmr_reserved, usdc_reserved) = state.reserved(now);
+        let (xmr_available, usdc_available) = state.available(now);
+        let xmr_unlocking = state.outputs
+            .as_deref()
+            .map(|outputs| unlock_schedule(outputs, state.wallet_height, now))
+            .unwrap_or_default();
+        InventorySnapshot {
+            balances: state.balances.clone(),
+            xmr_reserved,
+            usdc_reserved,
+            xmr_available,
+            usdc_available,
+            xmr_unlocks_at: xmr_unlocking
+                .first()
+                .map(|estimate| estimate.at)
+                .or_else(|| state.balances.xmr_unlocks_at()),
+            xmr_unlocking,
+            reservations: state.reservations.len(),
+        }
+    }
+
+    fn shortfall(xmr_needed: u64, xmr_available: u64, usdc_needed: u64, usdc_available: u64) -> InventoryError {
+        if xmr_needed > xmr_available {
+            InventoryError::InsufficientXmr { requested: xmr_needed, available: xmr_available }
+        } else {
+            InventoryError::InsufficientUsdc { requested: usdc_needed, available: usdc_available }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    const XMR: u64 = 1_000_000_000_000;
+    const USDC: u64 = 1_000_000;
+
+    /// 10 XMR unlocked and 3,000 USDC, with a 100 USDC minimum quote.
+    async fn inventory(partial_fills: bool) -> InventoryManager {
+        let mut config = AppConfig::default().quoting;
+        config.min_usdc = 100 * USDC;
+        config.partial_fills = Some(partial_fills);
+        let inventory = InventoryManager::new(&config);
+        let xmr = MoneroBalance { unlocked: 10 * XMR, locked: 0, total: 10 * XMR, blocks_to_unlock: 0 };
+        inventory.set_balances(&xmr, 3_000 * USDC).await;
+        inventory
+    }
+
+    fn in_minutes(minutes: i64) -> DateTime<Utc> {
+        Utc::now() + Duration::minutes(minutes)
+    }
+
+    #[tokio::test]
+    async fn reserve_holds_liquidity_until_released() {
+        let inventory = inventory(false).await;
+        let quote_id = uuid::Uuid::new_v4();
+
+        let reservation = inventory
+            .reserve(quote_id, Direction::UsdcToXmr, 300 * USDC, 2 * XMR, in_minutes(30))
+            .await
+            .unwrap();
+        assert_eq!((reservation.xmr_reserved, reservation.usdc_reserved), (2 * XMR, 300 * USDC));
+        assert_eq!(inventory.available().await, (8 * XMR, 2_700 * USDC));
+
+        assert!(inventory.release(quote_id).await.is_some());
+        assert_eq!(inventory.available().await, (10 * XMR, 3_000 * USDC));
+        assert!(inventory.release(quote_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn xmr_to_usdc_reserves_only_the_usdc_escrow() {
+        let inventory = inventory(false).await;
+        inventory
+            .reserve(uuid::Uuid::new_v4(), Direction::XmrToUsdc, 300 * USDC, 2 * XMR, in_minutes(30))
+            .await
+            .unwrap();
+        assert_eq!(inventory.available().await, (10 * XMR, 2_700 * USDC));
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_inventory_are_rejected_without_partial_fills() {
+        let inventory = inventory(false).await;
+        let result = inventory
+            .reserve(uuid::Uuid::new_v4(), Direction::UsdcToXmr, 1_500 * USDC, 12 * XMR, in_minutes(30))
+            .await;
+        assert!(matches!(
+            result,
+            Err(InventoryError::InsufficientXmr { requested, available }) if requested == 12 * XMR && available == 10 * XMR
+        ));
+        assert_eq!(inventory.snapshot().await.reservations, 0);
+    }
+
+    #[tokio::test]
+    async fn partial_fills_scale_both_legs_by_the_tighter_constraint() {
+        let inventory = inventory(true).await;
+
+        // XMR is the tighter leg: 10 of 20 XMR, so both legs halve
+        let reservation = inventory
+            .reserve(uuid::Uuid::new_v4(), Direction::UsdcToXmr, 2_000 * USDC, 20 * XMR, in_minutes(30))
+            .await
+            .unwrap();
+        assert_eq!((reservation.usdc_amount, reservation.xmr_amount), (1_000 * USDC, 10 * XMR));
+        assert_eq!(inventory.available().await, (0, 2_000 * USDC));
+
+        // Now USDC is the tighter leg: 2,000 of 4,000 USDC
+        let reservation = inventory
+            .reserve(uuid::Uuid::new_v4(), Direction::XmrToUsdc, 4_000 * USDC, 30 * XMR, in_minutes(30))
+            .await
+            .unwrap();
+        assert_eq!((reservation.usdc_amount, reservation.xmr_amount), (2_000 * USDC, 15 * XMR));
+        assert_eq!(inventory.available().await, (0, 0));
+    }
+
+    #[tokio::test]
+    async fn partial_fills_below_the_minimum_are_rejected() {
+        let inventory = inventory(true).await;
+        inventory
+            .reserve(uuid::Uuid::new_v4(), Direction::XmrToUsdc, 2_950 * USDC, 20 * XMR, in_minutes(30))
+            .await
+            .unwrap();
+
+        // 50 USDC left, below the 100 USDC minimum
+        let result = inventory
+            .reserve(uuid::Uuid::new_v4(), Direction::XmrToUsdc, 200 * USDC, XMR, in_minutes(30))
+            .await;
+        assert!(matches!(result, Err(InventoryError::InsufficientUsdc { .. })));
+    }
+
+    #[tokio::test]
+    async fn bind_holds_the_reservation_past_the_quote_expiry() {
+        let inventory = inventory(false).await;
+        let (quoted, accepted) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let past = Utc::now() - Duration::seconds(1);
+        inventory.reserve(quoted, Direction::UsdcToXmr, 300 * USDC, 2 * XMR, past).await.unwrap();
+        inventory.reserve(accepted, Direction::UsdcToXmr, 300 * USDC, 2 * XMR, past).await.unwrap();
+        inventory.bind(accepted, [1; 32], in_minutes(60)).await;
+
+        assert_eq!(inventory.release_expired().await, vec![quoted]);
+        assert_eq!(inventory.available().await, (8 * XMR, 2_700 * USDC));
+        let snapshot = inventory.snapshot().await;
+        assert_eq!(snapshot.reservations, 1);
+        assert_eq!((snapshot.xmr_reserved, snapshot.usdc_reserved), (2 * XMR, 300 * USDC));
+    }
+
+    #[tokio::test]
+    async fn bound_reservation_outlives_the_swap_expiry() {
+        let inventory = inventory(false).await;
+        let quote_id = uuid::Uuid::new_v4();
+        inventory.reserve(quote_id, Direction::UsdcToXmr, 300 * USDC, 2 * XMR, in_minutes(30)).await.unwrap();
+        inventory.bind(quote_id, [1; 32], Utc::now() - Duration::seconds(1)).await;
+
+        // A swap past its expiry still has a refund or payout to finish
+        assert!(inventory.release_expired().await.is_empty());
+        assert_eq!(inventory.available().await, (8 * XMR, 2_700 * USDC));
+
+        assert!(inventory.release(quote_id).await.is_some());
+        assert_eq!(inventory.available().await, (10 * XMR, 3_000 * USDC));
+    }
+
+    #[tokio::test]
+    async fn settle_deducts_the_reservation_and_marks_balances_stale() {
+        let inventory = inventory(false).await;
+        let quote_id = uuid::Uuid::new_v4();
+        inventory.reserve(quote_id, Direction::UsdcToXmr, 300 * USDC, 2 * XMR, in_minutes(30)).await.unwrap();
+        assert!(!inventory.is_stale().await);
+
+        let settled = inventory.settle(quote_id).await.unwrap();
+        assert_eq!(settled.xmr_reserved, 2 * XMR);
+        // Bob paid the XMR; the USDC collateral came back to him
+        assert_eq!(inventory.available().await, (8 * XMR, 3_000 * USDC));
+        assert!(inventory.is_stale().await);
+        assert!(inventory.settle(quote_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn settling_xmr_to_usdc_spends_only_the_usdc_escrow() {
+        let inventory = inventory(false).await;
+        let quote_id = uuid::Uuid::new_v4();
+        inventory.reserve(quote_id, Direction::XmrToUsdc, 300 * USDC, 2 * XMR, in_minutes(30)).await.unwrap();
+
+        inventory.settle(quote_id).await.unwrap();
+        assert_eq!(inventory.available().await, (10 * XMR, 2_700 * USDC));
+        assert!(inventory.is_stale().await);
+    }
+}
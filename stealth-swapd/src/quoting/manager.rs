@@ -0,0 +1,430 @@
+use crate::config::QuotingConfig;
+use crate::swap_engine::{Direction, SwapState, SwapTrade};
+use super::InventoryManager;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Row, SqlitePool};
+use tokio::sync::RwLock;
+use anyhow::Result;
+
+#[derive(Debug, thiserror::Error)]
+pub enum QuoteError {
+    #[error("Quote not found")]
+    NotFound,
+
+    #[error("Quote expired")]
+    Expired,
+
+    #[error("Too many outstanding quotes for client {client_id} (limit {limit})")]
+    ClientLimit { client_id: String, limit: usize },
+}
+
+#[derive(Debug, Clone)]
+struct OutstandingQuote {
+    quote: SwapTrade,
+    client_id: String,
+}
+
+/// Owns outstanding quotes: TTL, per-client limits, persistence to SQLite
+/// and releasing inventory when quotes lapse.
+#[derive(Clone)]
+pub struct QuoteManager {
+    quotes: Arc<RwLock<HashMap<uuid::Uuid, OutstandingQuote>>>,
+    db: SqlitePool,
+    inventory: InventoryManager,
+    ttl: Duration,
+    max_per_client: usize,
+    reap_interval: std::time::Duration,
+}
+
+impl QuoteManager {
+    pub fn new(config: &QuotingConfig, db: SqlitePool, inventory: InventoryManager) -> Self {
+        Self {
+            quotes: Arc::new(RwLock::new(HashMap::new())),
+            db,
+            inventory,
+            ttl: Duration::minutes(config.expiry_minutes.unwrap_or(30) as i64),
+            max_per_client: config.max_quotes_per_client.unwrap_or(5),
+            reap_interval: std::time::Duration::from_secs(config.reap_interval_seconds.unwrap_or(30)),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Restore unexpired quotes after a restart and re-reserve their
+    /// inventory. Expired rows are dropped.
+    pub async fn load(&self) -> Result<usize> {
+        let now = Utc::now();
+        sqlx::query("DELETE FROM quotes WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.db)
+            .await?;
+
+        let rows = sqlx::query(
+            "SELECT quote_id, client_id, swap_id, direction, usdc_amount, xmr_amount, \
+             secret_hash, monero_sub_address, monero_subaddr_index, destination, created_at, expires_at FROM quotes",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut quotes = self.quotes.write().await;
+        for row in rows {
+            let quote_id: String = row.try_get("quote_id")?;
+            let direction: String = row.try_get("direction")?;
+            let swap_id: Vec<u8> = row.try_get("swap_id")?;
+            let secret_hash: Vec<u8> = row.try_get("secret_hash")?;
+
+            let quote = SwapTrade {
+                swap_id: swap_id.try_into().map_err(|_| anyhow::anyhow!("Corrupt swap_id for quote {}", quote_id))?,
+                quote_id: uuid::Uuid::parse_str(&quote_id)?,
+                direction: Direction::parse(&direction)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown direction {}", direction))?,
+                usdc_amount: row.try_get::<i64, _>("usdc_amount")? as u64,
+                xmr_amount: row.try_get::<i64, _>("xmr_amount")? as u64,
+                secret_hash: secret_hash.try_into().map_err(|_| anyhow::anyhow!("Corrupt secret_hash for quote {}", quote_id))?,
+                monero_sub_address: row.try_get("monero_sub_address")?,
+                monero_subaddr_index: row.try_get::<Option<i64>, _>("monero_subaddr_index")?.map(|index| index as u32),
+                destination: row.try_get("destination")?,
+                alice_solana: None,
+                state: SwapState::Quoted,
+                created_at: row.try_get::<DateTime<Utc>, _>("created_at")?,
+                expires_at: row.try_get::<DateTime<Utc>, _>("expires_at")?,
+                monero_txid: None,
+                monero_fee: None,
+                solana_signature: None,
+                failure_reason: None,
+                xmr_deposit: None,
+                confirmations: None,
+            };
+
+            self.inventory.restore(quote.quote_id, quote.direction, quote.usdc_amount, quote.xmr_amount, quote.expires_at).await;
+            quotes.insert(quote.quote_id, OutstandingQuote {
+                quote,
+                client_id: row.try_get("client_id")?,
+            });
+        }
+
+        Ok(quotes.len())
+    }
+
+    /// Early rejection for a client already at its limit, before any work is
+    /// done for the quote. `insert` enforces the limit.
+    pub async fn check_capacity(&self, client_id: &str) -> Result<(), QuoteError> {
+        self.within_limit(&*self.quotes.read().await, client_id)
+    }
+
+    fn within_limit(&self, quotes: &HashMap<uuid::Uuid, OutstandingQuote>, client_id: &str) -> Result<(), QuoteError> {
+        let now = Utc::now();
+        let outstanding = quotes
+            .values()
+            .filter(|q| q.client_id == client_id && q.quote.expires_at > now)
+            .count();
+        if outstanding >= self.max_per_client {
+            return Err(QuoteError::ClientLimit {
+                client_id: client_id.to_string(),
+                limit: self.max_per_client,
+            });
+        }
+        Ok(())
+    }
+
+    /// Store a quote if the client is still under its limit. The count and
+    /// the insert happen under one write lock, so concurrent requests from
+    /// the same client cannot all pass the check.
+    pub async fn insert(&self, quote: SwapTrade, client_id: &str) -> Result<()> {
+        let mut quotes = self.quotes.write().await;
+        self.within_limit(&quotes, client_id)?;
+
+        sqlx::query(
+            "INSERT INTO quotes (quote_id, client_id, swap_id, direction, usdc_amount, xmr_amount, \
+             secret_hash, monero_sub_address, monero_subaddr_index, destination, created_at, expires_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(quote.quote_id.to_string())
+        .bind(client_id)
+        .bind(quote.swap_id.to_vec())
+        .bind(quote.direction.as_str())
+        .bind(quote.usdc_amount as i64)
+        .bind(quote.xmr_amount as i64)
+        .bind(quote.secret_hash.to_vec())
+        .bind(&quote.monero_sub_address)
+        .bind(quote.monero_subaddr_index.map(|index| index as i64))
+        .bind(quote.destination.as_deref())
+        .bind(quote.created_at)
+        .bind(quote.expires_at)
+        .execute(&self.db)
+        .await?;
+
+        quotes.insert(quote.quote_id, OutstandingQuote {
+            quote,
+            client_id: client_id.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Monero subaddress indices handed out with outstanding quotes.
+    pub async fn subaddr_indices(&self) -> Vec<u32> {
+        self.quotes
+            .read()
+            .await
+            .values()
+            .filter_map(|q| q.quote.monero_subaddr_index)
+            .collect()
+    }
+
+    pub async fn get(&self, quote_id: uuid::Uuid) -> Result<SwapTrade, QuoteError> {
+        let quotes = self.quotes.read().await;
+        let outstanding = quotes.get(&quote_id).ok_or(QuoteError::NotFound)?;
+        if Utc::now() > outstanding.quote.expires_at {
+            return Err(QuoteError::Expired);
+        }
+        Ok(outstanding.quote.clone())
+    }
+
+    /// Remove a quote for acceptance. Its inventory reservation is left in
+    /// place for the caller to bind to the swap.
+    pub async fn take(&self, quote_id: uuid::Uuid) -> Result<SwapTrade> {
+        let outstanding = self.quotes.write().await.remove(&quote_id).ok_or(QuoteError::NotFound)?;
+        self.delete(quote_id).await?;
+
+        if Utc::now() > outstanding.quote.expires_at {
+            self.inventory.release(quote_id).await;
+            return Err(QuoteError::Expired.into());
+        }
+        Ok(outstanding.quote)
+    }
+
+    /// Drop expired quotes from memory and SQLite and release their
+    /// inventory. Returns the ids that were reaped.
+    pub async fn reap(&self) -> Result<Vec<uuid::Uuid>> {
+        let now = Utc::now();
+        let expired: Vec<uuid::Uuid> = {
+            let mut quotes = self.quotes.write().await;
+            let expired: Vec<uuid::Uuid> = quotes
+                .values()
+                .filter(|q| q.quote.expires_at <= now)
+                .map(|q| q.quote.quote_id)
+                .collect();
+            for quote_id in &expired {
+                quotes.remove(quote_id);
+            }
+            expired
+        };
+
+        for quote_id in &expired {
+            self.inventory.release(*quote_id).await;
+        }
+        // Reservations restored without a quote, never those bound to a swap
+        self.inventory.release_expired().await;
+
+        sqlx::query("DELETE FROM quotes WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&self.db)
+            .await?;
+
+        Ok(expired)
+    }
+
+    pub async fn run_reaper(&self) {
+        let mut interval = tokio::time::interval(self.reap_interval);
+        loop {
+            interval.tick().await;
+            match self.reap().await {
+                Ok(expired) if !expired.is_empty() => {
+                    tracing::debug!("Reaped {} expired quotes", expired.len());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to reap expired quotes: {}", e),
+            }
+        }
+    }
+
+    async fn delete(&self, quote_id: uuid::Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM quotes WHERE quote_id = ?")
+            .bind(quote_id.to_string())
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn manager(max_per_client: usize) -> QuoteManager {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        let mut config = AppConfig::default().quoting;
+        config.max_quotes_per_client = Some(max_per_client);
+        QuoteManager::new(&config, db, InventoryManager::new(&config))
+    }
+
+    fn quote() -> SwapTrade {
+        quote_expiring(Utc::now() + Duration::minutes(30))
+    }
+
+    fn quote_expiring(expires_at: DateTime<Utc>) -> SwapTrade {
+        let quote_id = uuid::Uuid::new_v4();
+        SwapTrade {
+            swap_id: crate::security::KeyDerivation::generate_swap_id(),
+            quote_id,
+            direction: Direction::UsdcToXmr,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            secret_hash: [9; 32],
+            monero_sub_address: format!("8{}", "a".repeat(94)),
+            monero_subaddr_index: Some(1),
+            destination: None,
+            alice_solana: None,
+            state: SwapState::Quoted,
+            created_at: Utc::now(),
+            expires_at,
+            monero_txid: None,
+            monero_fee: None,
+            solana_signature: None,
+            failure_reason: None,
+            xmr_deposit: None,
+            confirmations: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_quotes_from_one_client_respect_the_limit() {
+        let manager = manager(3).await;
+        let inserts = (0..10).map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.insert(quote(), "10.0.0.1").await })
+        });
+        let results = futures_util::future::join_all(inserts).await;
+
+        let accepted = results.iter().filter(|result| matches!(result, Ok(Ok(())))).count();
+        assert_eq!(accepted, 3);
+        for result in results {
+            if let Ok(Err(e)) = result {
+                assert!(matches!(e.downcast_ref::<QuoteError>(), Some(QuoteError::ClientLimit { limit: 3, .. })));
+            }
+        }
+
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM quotes").fetch_one(&manager.db).await.unwrap();
+        assert_eq!(stored, 3);
+        assert!(manager.check_capacity("10.0.0.1").await.is_err());
+        manager.insert(quote(), "10.0.0.2").await.unwrap();
+    }
+
+    /// Insert a quote along with the reservation `generate_quote` makes for it.
+    async fn quoted(manager: &QuoteManager, quote: &SwapTrade) {
+        manager
+            .inventory
+            .restore(quote.quote_id, quote.direction, quote.usdc_amount, quote.xmr_amount, quote.expires_at)
+            .await;
+        manager.insert(quote.clone(), "10.0.0.1").await.unwrap();
+    }
+
+    async fn stored(manager: &QuoteManager) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM quotes").fetch_one(&manager.db).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn quotes_expire_after_the_configured_ttl() {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        let mut config = AppConfig::default().quoting;
+        config.expiry_minutes = Some(5);
+        assert_eq!(QuoteManager::new(&config, db, InventoryManager::new(&config)).ttl(), Duration::minutes(5));
+
+        // A lapsed quote no longer counts towards the client's limit of one
+        let manager = manager(1).await;
+        let (live, lapsed) = (quote(), quote_expiring(Utc::now() - Duration::seconds(1)));
+        quoted(&manager, &lapsed).await;
+        quoted(&manager, &live).await;
+        assert!(manager.check_capacity("10.0.0.1").await.is_err());
+
+        assert_eq!(manager.get(live.quote_id).await.unwrap().swap_id, live.swap_id);
+        assert!(matches!(manager.get(lapsed.quote_id).await, Err(QuoteError::Expired)));
+        assert!(matches!(manager.get(uuid::Uuid::new_v4()).await, Err(QuoteError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn taking_an_expired_quote_releases_its_reservation() {
+        let manager = manager(5).await;
+        let (live, lapsed) = (quote(), quote_expiring(Utc::now() - Duration::seconds(1)));
+        quoted(&manager, &live).await;
+        quoted(&manager, &lapsed).await;
+
+        let error = manager.take(lapsed.quote_id).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<QuoteError>(), Some(QuoteError::Expired)));
+        assert_eq!(manager.inventory.snapshot().await.reservations, 1);
+
+        // A live quote keeps its reservation for the swap to bind
+        assert_eq!(manager.take(live.quote_id).await.unwrap().quote_id, live.quote_id);
+        assert_eq!(manager.inventory.snapshot().await.reservations, 1);
+        assert_eq!(stored(&manager).await, 0);
+        assert!(manager.take(live.quote_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reap_drops_expired_quotes_and_their_reservations_only() {
+        let manager = manager(5).await;
+        let past = Utc::now() - Duration::seconds(1);
+        let (live, lapsed, accepted) = (quote(), quote_expiring(past), quote_expiring(past));
+        quoted(&manager, &live).await;
+        quoted(&manager, &lapsed).await;
+        quoted(&manager, &accepted).await;
+
+        // Accepted and bound to a swap whose on-chain expiry has also passed
+        manager.take(accepted.quote_id).await.unwrap_err();
+        manager
+            .inventory
+            .restore(accepted.quote_id, accepted.direction, accepted.usdc_amount, accepted.xmr_amount, past)
+            .await;
+        manager.inventory.bind(accepted.quote_id, accepted.swap_id, past).await;
+
+        assert_eq!(manager.reap().await.unwrap(), vec![lapsed.quote_id]);
+        assert!(matches!(manager.get(lapsed.quote_id).await, Err(QuoteError::NotFound)));
+        assert!(manager.get(live.quote_id).await.is_ok());
+        assert_eq!(stored(&manager).await, 1);
+
+        let snapshot = manager.inventory.snapshot().await;
+        assert_eq!(snapshot.reservations, 2);
+        assert_eq!(snapshot.xmr_reserved, live.xmr_amount + accepted.xmr_amount);
+        assert!(manager.inventory.release(lapsed.quote_id).await.is_none());
+        assert!(manager.inventory.release(accepted.quote_id).await.is_some());
+
+        assert!(manager.reap().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn load_restores_live_quotes_and_their_reservations() {
+        let manager = manager(5).await;
+        let (live, lapsed) = (quote(), quote_expiring(Utc::now() - Duration::seconds(1)));
+        quoted(&manager, &live).await;
+        quoted(&manager, &lapsed).await;
+
+        let mut config = AppConfig::default().quoting;
+        config.max_quotes_per_client = Some(5);
+        let restarted = QuoteManager::new(&config, manager.db.clone(), InventoryManager::new(&config));
+        assert_eq!(restarted.load().await.unwrap(), 1);
+        assert_eq!(stored(&restarted).await, 1);
+
+        let loaded = restarted.get(live.quote_id).await.unwrap();
+        assert_eq!(loaded.swap_id, live.swap_id);
+        assert_eq!(loaded.direction, live.direction);
+        assert_eq!((loaded.usdc_amount, loaded.xmr_amount), (live.usdc_amount, live.xmr_amount));
+        assert_eq!(loaded.secret_hash, live.secret_hash);
+        assert_eq!(loaded.monero_sub_address, live.monero_sub_address);
+        assert_eq!(loaded.monero_subaddr_index, live.monero_subaddr_index);
+        assert_eq!(loaded.expires_at, live.expires_at);
+        assert!(matches!(restarted.get(lapsed.quote_id).await, Err(QuoteError::NotFound)));
+        assert_eq!(restarted.subaddr_indices().await, vec![1]);
+
+        let snapshot = restarted.inventory.snapshot().await;
+        assert_eq!(snapshot.reservations, 1);
+        assert_eq!((snapshot.xmr_reserved, snapshot.usdc_reserved), (live.xmr_amount, live.usdc_amount));
+    }
+}
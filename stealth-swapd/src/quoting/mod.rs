@@ -0,0 +1,13 @@
+mod inventory;
+mod manager;
+mod outputs;
+mod pricing;
+mod script;
+mod strategy;
+
+pub use inventory::*;
+pub use manager::*;
+pub use outputs::*;
+pub use pricing::*;
+pub use script::*;
+pub use strategy::*;
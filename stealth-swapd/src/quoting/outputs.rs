@@ -0,0 +1,289 @@
+use crate::clients::monero::WalletOutput;
+use crate::config::OutputConfig;
+use super::inventory::{MONERO_BLOCK_TIME_SECS, MONERO_LOCK_BLOCKS};
+
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Monero caps a transaction at 16 outputs.
+const MAX_TX_OUTPUTS: usize = 16;
+
+/// How long to wait for a split to be mined before planning another.
+const SPLIT_TIMEOUT_MINUTES: i64 = 60;
+
+/// Locked XMR expected to become spendable at `at`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnlockEstimate {
+    pub at: DateTime<Utc>,
+    pub amount: u64,
+}
+
+/// A split that was sent: its transaction and when.
+type SentSplit = (String, DateTime<Utc>);
+
+/// A split to perform: sweep one output back to the wallet as `outputs`
+/// equal outputs.
+#[derive(Debug, Clone)]
+pub struct OutputSplit {
+    pub key_image: String,
+    pub amount: u64,
+    pub outputs: usize,
+}
+
+fn spendable(outputs: &[WalletOutput]) -> impl Iterator<Item = &WalletOutput> {
+    outputs.iter().filter(|output| output.unlocked && !output.frozen)
+}
+
+/// Unlocked XMR left for new quotes once each reservation is assigned the
+/// outputs its payout would spend: the smallest output that covers it, or
+/// else the largest outputs until it is covered. A payout locks the whole
+/// of every output it spends, so one large output funds one swap at a time
+/// however much change it leaves.
+pub fn spendable_after(outputs: &[WalletOutput], reservations: &[u64]) -> u64 {
+    let mut free: Vec<u64> = spendable(outputs).map(|output| output.amount).collect();
+    free.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut reservations: Vec<u64> = reservations.iter().copied().filter(|amount| *amount > 0).collect();
+    reservations.sort_unstable_by(|a, b| b.cmp(a));
+
+    for reserved in reservations {
+        // `free` is sorted largest first, so the last match is the smallest
+        if let Some(index) = free.iter().rposition(|amount| *amount >= reserved) {
+            free.remove(index);
+            continue;
+        }
+        let mut covered = 0u64;
+        while covered < reserved && !free.is_empty() {
+            covered = covered.saturating_add(free.remove(0));
+        }
+    }
+
+    free.iter().sum()
+}
+
+/// When each locked output is expected to unlock, earliest first, given the
+/// wallet's current height.
+pub fn unlock_schedule(outputs: &[WalletOutput], wallet_height: u64, now: DateTime<Utc>) -> Vec<UnlockEstimate> {
+    let mut schedule: Vec<UnlockEstimate> = outputs
+        .iter()
+        .filter(|output| !output.unlocked && !output.frozen)
+        .map(|output| {
+            let unlock_height = output.block_height + MONERO_LOCK_BLOCKS;
+            let blocks = unlock_height.saturating_sub(wallet_height).max(1);
+            UnlockEstimate {
+                at: now + Duration::seconds(blocks as i64 * MONERO_BLOCK_TIME_SECS),
+                amount: output.amount,
+            }
+        })
+        .collect();
+    schedule.sort_by_key(|estimate| estimate.at);
+    schedule
+}
+
+/// Keeps the wallet's outputs split into payout-sized denominations, so
+/// concurrent swaps don't queue behind the 10-block lock on change.
+#[derive(Clone)]
+pub struct OutputManager {
+    config: Option<OutputConfig>,
+    /// The split sent last and when, until its outputs show up.
+    in_flight: Arc<Mutex<Option<SentSplit>>>,
+}
+
+impl OutputManager {
+    pub fn new(config: Option<&OutputConfig>) -> Self {
+        Self {
+            config: config.cloned(),
+            in_flight: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn record_split(&self, txid: String) {
+        *self.in_flight.lock().unwrap_or_else(|e| e.into_inner()) = Some((txid, Utc::now()));
+    }
+
+    /// Whether an earlier split is still unmined. Its outputs aren't listed
+    /// until then, so planning now would split again.
+    fn split_pending(&self, outputs: &[WalletOutput]) -> bool {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let pending = match in_flight.as_ref() {
+            Some((txid, sent_at)) => {
+                !outputs.iter().any(|output| &output.tx_hash == txid)
+                    && Utc::now() - *sent_at < Duration::minutes(SPLIT_TIMEOUT_MINUTES)
+            }
+            None => false,
+        };
+        if !pending {
+            *in_flight = None;
+        }
+        pending
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// The next split to make, if the wallet holds fewer payout-sized
+    /// outputs than the target. Only an unlocked output that live
+    /// reservations can do without is split, since the split locks it.
+    pub fn plan(&self, outputs: &[WalletOutput], xmr_reserved: u64) -> Option<OutputSplit> {
+        let config = self.config.as_ref()?;
+        if self.split_pending(outputs) {
+            return None;
+        }
+        let denomination = config.denomination;
+        let max_outputs = config.max_split_outputs.unwrap_or(MAX_TX_OUTPUTS).min(MAX_TX_OUTPUTS);
+
+        // Outputs big enough for a payout, but not worth splitting further
+        let ready = outputs
+            .iter()
+            .filter(|output| !output.frozen && output.amount >= denomination && output.amount < 2 * denomination)
+            .count();
+        if ready >= config.target_count {
+            return None;
+        }
+
+        let unlocked: u64 = spendable(outputs).map(|output| output.amount).sum();
+        let candidate = spendable(outputs)
+            .filter(|output| output.amount >= 2 * denomination)
+            .filter(|output| unlocked - output.amount >= xmr_reserved)
+            .max_by_key(|output| output.amount)?;
+
+        // Each new output must still be at least one denomination once the
+        // sweep's fee comes out of them
+        let wanted = config.target_count - ready;
+        let fits = candidate.amount.saturating_sub(denomination / 100) / denomination;
+        let outputs = (fits as usize).min(max_outputs).min(wanted.max(2));
+        if outputs < 2 {
+            return None;
+        }
+
+        Some(OutputSplit {
+            key_image: candidate.key_image.clone(),
+            amount: candidate.amount,
+            outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XMR: u64 = 1_000_000_000_000;
+
+    fn output(amount: u64, unlocked: bool) -> WalletOutput {
+        WalletOutput {
+            amount,
+            key_image: format!("ki-{}-{}", amount, unlocked),
+            tx_hash: format!("tx-{}", amount),
+            global_index: 0,
+            block_height: 3_000_000,
+            unlocked,
+            frozen: false,
+            subaddr_index: Default::default(),
+        }
+    }
+
+    fn manager(target_count: usize, max_split_outputs: Option<usize>) -> OutputManager {
+        OutputManager::new(Some(&OutputConfig { denomination: 10 * XMR, target_count, max_split_outputs }))
+    }
+
+    #[test]
+    fn one_large_output_is_split_into_the_missing_denominations() {
+        let outputs = [output(100 * XMR, true), output(12 * XMR, true)];
+
+        // One output is already payout-sized, three more are wanted
+        let split = manager(4, None).plan(&outputs, 0).unwrap();
+        assert_eq!(split.key_image, outputs[0].key_image);
+        assert_eq!((split.amount, split.outputs), (100 * XMR, 3));
+
+        // Bounded by what still covers the sweep fee, and by the config
+        assert_eq!(manager(20, None).plan(&outputs, 0).unwrap().outputs, 9);
+        assert_eq!(manager(20, Some(5)).plan(&outputs, 0).unwrap().outputs, 5);
+        assert_eq!(manager(2, None).plan(&outputs, 0).unwrap().outputs, 2);
+    }
+
+    #[test]
+    fn nothing_is_split_below_the_threshold() {
+        let manager = manager(4, None);
+        // Under two denominations, an output is not worth splitting
+        assert!(manager.plan(&[output(19 * XMR, true)], 0).is_none());
+        // Exactly two denominations leaves too little for the fee
+        assert!(manager.plan(&[output(20 * XMR, true)], 0).is_none());
+        assert_eq!(manager.plan(&[output(21 * XMR, true)], 0).unwrap().outputs, 2);
+
+        // Enough payout-sized outputs already
+        let ready: Vec<WalletOutput> = (0..4).map(|i| output(10 * XMR + i, true)).collect();
+        assert!(manager.plan(&[ready.as_slice(), &[output(100 * XMR, true)]].concat(), 0).is_none());
+
+        // Locked outputs and outputs live reservations depend on stay whole
+        assert!(manager.plan(&[output(100 * XMR, false)], 0).is_none());
+        assert!(manager.plan(&[output(100 * XMR, true), output(5 * XMR, true)], 6 * XMR).is_none());
+        assert!(manager.plan(&[output(100 * XMR, true), output(5 * XMR, true)], 5 * XMR).is_some());
+
+        assert!(OutputManager::new(None).plan(&[output(100 * XMR, true)], 0).is_none());
+    }
+
+    #[test]
+    fn no_new_split_until_the_last_one_is_mined() {
+        let manager = manager(4, None);
+        let outputs = [output(100 * XMR, true)];
+        manager.record_split("split-tx".to_string());
+        assert!(manager.plan(&outputs, 0).is_none());
+
+        let mut mined = output(10 * XMR, false);
+        mined.tx_hash = "split-tx".to_string();
+        assert!(manager.plan(&[outputs[0].clone(), mined], 0).is_some());
+    }
+
+    #[test]
+    fn spendable_balance_excludes_locked_outputs_and_whole_outputs_payouts_lock() {
+        let outputs = [
+            output(50 * XMR, true),
+            output(10 * XMR, true),
+            output(4 * XMR, true),
+            output(30 * XMR, false),
+        ];
+        let mut frozen = output(7 * XMR, true);
+        frozen.frozen = true;
+        let outputs = [outputs.as_slice(), &[frozen]].concat();
+
+        let table: [(&[u64], u64); 6] = [
+            (&[], 64 * XMR),
+            // The smallest covering output is locked whole
+            (&[XMR], 60 * XMR),
+            (&[8 * XMR], 54 * XMR),
+            (&[11 * XMR], 14 * XMR),
+            (&[11 * XMR, 3 * XMR], 10 * XMR),
+            // No single output covers it: the largest are combined
+            (&[55 * XMR], 4 * XMR),
+        ];
+        for (reservations, available) in table {
+            assert_eq!(spendable_after(&outputs, reservations), available, "{:?}", reservations);
+        }
+        assert_eq!(spendable_after(&outputs, &[100 * XMR]), 0);
+    }
+
+    #[test]
+    fn locked_outputs_unlock_ten_blocks_after_they_were_mined() {
+        let now = Utc::now();
+        let mut recent = output(2 * XMR, false);
+        recent.block_height = 3_000_008;
+        let mut overdue = output(3 * XMR, false);
+        overdue.block_height = 2_999_000;
+        let outputs = [recent, output(5 * XMR, false), overdue, output(9 * XMR, true)];
+
+        let schedule = unlock_schedule(&outputs, 3_000_004, now);
+        let expected = [
+            (3 * XMR, 1),
+            (5 * XMR, 6),
+            (2 * XMR, 14),
+        ];
+        assert_eq!(schedule.len(), expected.len());
+        for (estimate, (amount, blocks)) in schedule.iter().zip(expected) {
+            assert_eq!(estimate.amount, amount);
+            assert_eq!(estimate.at, now + Duration::seconds(blocks * MONERO_BLOCK_TIME_SECS));
+        }
+    }
+}
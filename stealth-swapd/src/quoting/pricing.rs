@@ -0,0 +1,253 @@
+use crate::config::{QuotingConfig, SkewConfig};
+use crate::swap_engine::{Direction, QuoteRequest};
+use super::InventorySnapshot;
+
+use serde::Serialize;
+
+pub(crate) const BPS: i64 = 10_000;
+/// Atomic units in one XMR.
+const XMR_UNIT: u64 = 1_000_000_000_000;
+
+/// Bob's holdings as seen by the pricer, net of reserved liquidity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct InventoryPosition {
+    pub xmr: u64,
+    pub usdc: u64,
+}
+
+impl From<&InventorySnapshot> for InventoryPosition {
+    fn from(snapshot: &InventorySnapshot) -> Self {
+        let xmr_total = snapshot.balances.xmr_unlocked.saturating_add(snapshot.balances.xmr_locked);
+        Self {
+            xmr: xmr_total.saturating_sub(snapshot.xmr_reserved),
+            usdc: snapshot.usdc_available,
+        }
+    }
+}
+
+/// Linear skew between a target XMR share of inventory value and the
+/// current share. At the target the skew is zero; with no XMR left it
+/// reaches `+max_skew_bps`, with nothing but XMR it reaches `-max_skew_bps`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewCurve {
+    pub target_xmr_ratio_bps: u64,
+    pub max_skew_bps: u64,
+}
+
+impl From<&SkewConfig> for SkewCurve {
+    fn from(config: &SkewConfig) -> Self {
+        Self {
+            target_xmr_ratio_bps: config.target_xmr_ratio_bps,
+            max_skew_bps: config.max_skew_bps,
+        }
+    }
+}
+
+impl SkewCurve {
+    /// Signed skew in bps. Positive means Bob is short XMR.
+    pub fn skew_bps(&self, position: &InventoryPosition, price: &ReferencePrice) -> i64 {
+        let xmr_value = price.xmr_to_usdc(position.xmr) as i128;
+        let total_value = xmr_value + position.usdc as i128;
+        if total_value == 0 {
+            return 0;
+        }
+
+        let target = self.target_xmr_ratio_bps.min(BPS as u64) as i128;
+        let actual = xmr_value * BPS as i128 / total_value;
+        let max_skew = self.max_skew_bps as i128;
+
+        let skew = if actual < target {
+            max_skew * (target - actual) / target.max(1)
+        } else {
+            -max_skew * (actual - target) / (BPS as i128 - target).max(1)
+        };
+
+        skew.clamp(-max_skew, max_skew) as i64
+    }
+}
+
+/// USDC/XMR rate quotes are priced against, kept as a pair of atomic
+/// amounts to avoid rounding. It comes from `quoting.usdc_per_xmr`, never
+/// from the request, so Alice cannot set the rate she is quoted at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ReferencePrice {
+    pub usdc_amount: u64,
+    pub xmr_amount: u64,
+}
+
+impl From<&QuotingConfig> for ReferencePrice {
+    fn from(config: &QuotingConfig) -> Self {
+        Self {
+            usdc_amount: config.usdc_per_xmr,
+            xmr_amount: XMR_UNIT,
+        }
+    }
+}
+
+impl ReferencePrice {
+    pub fn xmr_to_usdc(&self, xmr: u64) -> u64 {
+        if self.xmr_amount == 0 {
+            return 0;
+        }
+        ((xmr as u128) * (self.usdc_amount as u128) / (self.xmr_amount as u128)).min(u64::MAX as u128) as u64
+    }
+
+    pub fn usdc_to_xmr(&self, usdc: u64) -> u64 {
+        if self.usdc_amount == 0 {
+            return 0;
+        }
+        ((usdc as u128) * (self.xmr_amount as u128) / (self.usdc_amount as u128)).min(u64::MAX as u128) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PricedQuote {
+    pub usdc_amount: u64,
+    pub xmr_amount: u64,
+}
+
+impl PricedQuote {
+    /// Take the leg Alice pays as given and price the leg she receives at
+    /// `reference`, less `discount_bps`. The amount she asked to receive
+    /// plays no part. The discount is clamped so a quote never prices
+    /// through the reference rate.
+    pub fn discounted(request: &QuoteRequest, reference: &ReferencePrice, discount_bps: i64) -> Self {
+        let discount_bps = discount_bps.clamp(0, BPS);
+        let apply = |amount: u64| ((amount as u128) * ((BPS - discount_bps) as u128) / BPS as u128) as u64;
+
+        match request.direction {
+            Direction::UsdcToXmr => Self {
+                usdc_amount: request.usdc_amount,
+                xmr_amount: apply(reference.usdc_to_xmr(request.usdc_amount)),
+            },
+            Direction::XmrToUsdc => Self {
+                usdc_amount: apply(reference.xmr_to_usdc(request.xmr_amount)),
+                xmr_amount: request.xmr_amount,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XMR: u64 = 1_000_000_000_000;
+    const USDC: u64 = 1_000_000;
+
+    /// 150 USDC per XMR.
+    const PRICE: ReferencePrice = ReferencePrice {
+        usdc_amount: 150 * USDC,
+        xmr_amount: XMR,
+    };
+
+    fn curve(target_xmr_ratio_bps: u64) -> SkewCurve {
+        SkewCurve {
+            target_xmr_ratio_bps,
+            max_skew_bps: 200,
+        }
+    }
+
+    /// A position worth 3,000 USDC with `xmr_share_bps` of it in XMR.
+    fn position(xmr_share_bps: u64) -> InventoryPosition {
+        let xmr_value = 3_000 * USDC * xmr_share_bps / BPS as u64;
+        InventoryPosition {
+            xmr: (xmr_value as u128 * XMR as u128 / (150 * USDC) as u128) as u64,
+            usdc: 3_000 * USDC - xmr_value,
+        }
+    }
+
+    #[test]
+    fn skew_is_zero_at_the_target_ratio() {
+        assert_eq!(curve(5_000).skew_bps(&position(5_000), &PRICE), 0);
+        assert_eq!(curve(3_000).skew_bps(&position(3_000), &PRICE), 0);
+    }
+
+    #[test]
+    fn skew_is_positive_when_short_xmr_and_negative_when_long() {
+        let curve = curve(5_000);
+        assert!(curve.skew_bps(&position(4_000), &PRICE) > 0);
+        assert!(curve.skew_bps(&position(6_000), &PRICE) < 0);
+    }
+
+    #[test]
+    fn skew_is_half_the_maximum_midway_to_either_extreme() {
+        // Halfway from a 40% target to 0% and to 100% XMR
+        let curve = curve(4_000);
+        assert_eq!(curve.skew_bps(&position(2_000), &PRICE), 100);
+        assert_eq!(curve.skew_bps(&position(7_000), &PRICE), -100);
+    }
+
+    #[test]
+    fn skew_reaches_max_skew_bps_at_the_extremes() {
+        let curve = curve(5_000);
+        assert_eq!(curve.skew_bps(&position(0), &PRICE), 200);
+        assert_eq!(curve.skew_bps(&position(BPS as u64), &PRICE), -200);
+    }
+
+    #[test]
+    fn skew_stays_within_max_skew_bps_for_out_of_range_targets() {
+        let above = curve(20_000);
+        assert_eq!(above.skew_bps(&position(BPS as u64), &PRICE), 0);
+        assert_eq!(above.skew_bps(&position(0), &PRICE), 200);
+
+        let zero = curve(0);
+        assert_eq!(zero.skew_bps(&position(0), &PRICE), 0);
+        assert_eq!(zero.skew_bps(&position(BPS as u64), &PRICE), -200);
+        for share in (0..=BPS as u64).step_by(500) {
+            assert!(zero.skew_bps(&position(share), &PRICE).abs() <= 200);
+        }
+    }
+
+    #[test]
+    fn empty_inventory_has_no_skew() {
+        assert_eq!(curve(5_000).skew_bps(&InventoryPosition::default(), &PRICE), 0);
+    }
+
+    #[test]
+    fn discount_applies_to_the_leg_alice_receives_and_is_clamped() {
+        let request = |direction| QuoteRequest {
+            direction,
+            usdc_amount: 150 * USDC,
+            xmr_amount: XMR,
+            destination: None,
+        };
+
+        let quote = PricedQuote::discounted(&request(Direction::UsdcToXmr), &PRICE, 100);
+        assert_eq!(quote, PricedQuote { usdc_amount: 150 * USDC, xmr_amount: XMR / 100 * 99 });
+
+        let quote = PricedQuote::discounted(&request(Direction::XmrToUsdc), &PRICE, 100);
+        assert_eq!(quote, PricedQuote { usdc_amount: 148_500_000, xmr_amount: XMR });
+
+        let quote = PricedQuote::discounted(&request(Direction::XmrToUsdc), &PRICE, -50);
+        assert_eq!(quote.usdc_amount, 150 * USDC);
+    }
+
+    #[test]
+    fn the_amount_alice_asks_to_receive_does_not_move_the_price() {
+        let request = |direction, usdc_amount, xmr_amount| QuoteRequest {
+            direction,
+            usdc_amount,
+            xmr_amount,
+            destination: None,
+        };
+
+        // Asking for ten times the XMR still gets the reference rate
+        let fair = PricedQuote::discounted(&request(Direction::UsdcToXmr, 150 * USDC, XMR), &PRICE, 50);
+        let greedy = PricedQuote::discounted(&request(Direction::UsdcToXmr, 150 * USDC, 10 * XMR), &PRICE, 50);
+        assert_eq!(greedy, fair);
+
+        let fair = PricedQuote::discounted(&request(Direction::XmrToUsdc, 150 * USDC, XMR), &PRICE, 50);
+        let greedy = PricedQuote::discounted(&request(Direction::XmrToUsdc, 1_500 * USDC, XMR), &PRICE, 50);
+        assert_eq!(greedy, fair);
+    }
+
+    #[test]
+    fn reference_price_comes_from_the_configured_rate() {
+        let mut config = crate::config::AppConfig::default().quoting;
+        config.usdc_per_xmr = 150 * USDC;
+        assert_eq!(ReferencePrice::from(&config), PRICE);
+        assert_eq!(PRICE.usdc_to_xmr(300 * USDC), 2 * XMR);
+        assert_eq!(PRICE.xmr_to_usdc(2 * XMR), 300 * USDC);
+    }
+}
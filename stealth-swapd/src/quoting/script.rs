@@ -0,0 +1,259 @@
+use super::{PricedQuote, QuoteContext, QuoteDecision, QuoteStrategy};
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use anyhow::Result;
+use rhai::{Dynamic, Engine, ImmutableString, Map, Scope, AST};
+
+/// Upper bound on Rhai operations per quote, so a runaway script can't stall
+/// the quote endpoint.
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000;
+
+/// Prices quotes with a Rhai script loaded from `quoting.script_path`.
+///
+/// The script must define `fn quote(request, inventory, price)` where
+/// `request` is `#{ direction, usdc_amount, xmr_amount }`, `inventory` is
+/// `#{ xmr, usdc }` and `price` is the configured reference rate
+/// (`quoting.usdc_per_xmr`) as `#{ usdc_amount, xmr_amount }`. The
+/// request's amounts are Alice's to choose, so the script should price the
+/// leg she receives from `price` rather than from the request. It returns either
+/// `#{ usdc_amount, xmr_amount }` or `#{ reject: "reason" }`.
+///
+/// The file is recompiled whenever its modification time changes, so
+/// traders can update pricing without restarting the daemon.
+pub struct ScriptStrategy {
+    path: PathBuf,
+    engine: Engine,
+    compiled: RwLock<(Option<SystemTime>, AST)>,
+}
+
+impl ScriptStrategy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+
+        let modified = Self::modified(path);
+        let ast = Self::compile(&engine, path)?;
+        tracing::info!("Loaded quote script from {}", path.display());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            compiled: RwLock::new((modified, ast)),
+        })
+    }
+
+    fn compile(engine: &Engine, path: &Path) -> Result<AST> {
+        engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow::anyhow!("Failed to compile quote script {}: {}", path.display(), e))
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Recompile if the file changed. A broken edit keeps the previous
+    /// script in service rather than taking quoting down.
+    fn reload_if_changed(&self) {
+        let modified = Self::modified(&self.path);
+        {
+            let compiled = self.compiled.read().unwrap_or_else(|e| e.into_inner());
+            if compiled.0 == modified {
+                return;
+            }
+        }
+
+        match Self::compile(&self.engine, &self.path) {
+            Ok(ast) => {
+                let mut compiled = self.compiled.write().unwrap_or_else(|e| e.into_inner());
+                *compiled = (modified, ast);
+                tracing::info!("Reloaded quote script from {}", self.path.display());
+            }
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+
+    fn amount(result: &Map, key: &str) -> Result<u64> {
+        let value = result
+            .get(key)
+            .and_then(|v| v.as_int().ok())
+            .ok_or_else(|| anyhow::anyhow!("Quote script result is missing integer `{}`", key))?;
+        u64::try_from(value).map_err(|_| anyhow::anyhow!("Quote script returned negative `{}`", key))
+    }
+
+    /// Rhai integers are `i64`; amounts beyond that are rejected rather than
+    /// wrapped negative.
+    fn int(value: u64, key: &str) -> Result<Dynamic> {
+        i64::try_from(value)
+            .map(Dynamic::from)
+            .map_err(|_| anyhow::anyhow!("`{}` of {} is too large for the quote script", key, value))
+    }
+}
+
+impl QuoteStrategy for ScriptStrategy {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn quote(&self, context: &QuoteContext) -> Result<QuoteDecision> {
+        self.reload_if_changed();
+
+        let mut request = Map::new();
+        request.insert("direction".into(), Dynamic::from(ImmutableString::from(context.request.direction.as_str())));
+        request.insert("usdc_amount".into(), Self::int(context.request.usdc_amount, "usdc_amount")?);
+        request.insert("xmr_amount".into(), Self::int(context.request.xmr_amount, "xmr_amount")?);
+
+        let mut inventory = Map::new();
+        inventory.insert("xmr".into(), Self::int(context.inventory.xmr, "xmr")?);
+        inventory.insert("usdc".into(), Self::int(context.inventory.usdc, "usdc")?);
+
+        let mut price = Map::new();
+        price.insert("usdc_amount".into(), Self::int(context.reference.usdc_amount, "usdc_amount")?);
+        price.insert("xmr_amount".into(), Self::int(context.reference.xmr_amount, "xmr_amount")?);
+
+        let result: Map = {
+            let compiled = self.compiled.read().unwrap_or_else(|e| e.into_inner());
+            self.engine
+                .call_fn(&mut Scope::new(), &compiled.1, "quote", (request, inventory, price))
+                .map_err(|e| anyhow::anyhow!("Quote script failed: {}", e))?
+        };
+
+        if let Some(reason) = result.get("reject") {
+            return Ok(QuoteDecision::Reject(reason.to_string()));
+        }
+
+        Ok(QuoteDecision::Quote(PricedQuote {
+            usdc_amount: Self::amount(&result, "usdc_amount")?,
+            xmr_amount: Self::amount(&result, "xmr_amount")?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quoting::{InventoryPosition, ReferencePrice};
+    use crate::swap_engine::{Direction, QuoteRequest};
+
+    use std::time::Duration;
+
+    /// Script file in the temp directory, removed on drop.
+    struct ScriptFile(PathBuf);
+
+    impl ScriptFile {
+        fn new(source: &str) -> Self {
+            let file = Self(std::env::temp_dir().join(format!("quote-{}.rhai", uuid::Uuid::new_v4())));
+            file.write(source, SystemTime::now());
+            file
+        }
+
+        /// Rewrite the script with an explicit mtime, so a reload does not
+        /// depend on the filesystem's timestamp resolution.
+        fn write(&self, source: &str, modified: SystemTime) {
+            std::fs::write(&self.0, source).unwrap();
+            std::fs::File::options().write(true).open(&self.0).unwrap().set_modified(modified).unwrap();
+        }
+    }
+
+    impl Drop for ScriptFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    const HALF: &str = "fn quote(request, inventory, price) { #{ usdc_amount: request.usdc_amount, xmr_amount: request.xmr_amount / 2 } }";
+    const QUARTER: &str = "fn quote(request, inventory, price) { #{ usdc_amount: request.usdc_amount, xmr_amount: request.xmr_amount / 4 } }";
+
+    fn context(xmr_amount: u64) -> QuoteContext {
+        let request = QuoteRequest {
+            direction: Direction::UsdcToXmr,
+            usdc_amount: 150_000_000,
+            xmr_amount,
+            destination: None,
+        };
+        // 150 USDC per XMR
+        let price = ReferencePrice { usdc_amount: 150_000_000, xmr_amount: 1_000_000_000_000 };
+        QuoteContext::new(&request, InventoryPosition { xmr: 10_000_000_000_000, usdc: 3_000_000_000 }, price)
+    }
+
+    fn xmr_quoted(strategy: &ScriptStrategy) -> u64 {
+        match strategy.quote(&context(1_000_000_000_000)).unwrap() {
+            QuoteDecision::Quote(priced) => priced.xmr_amount,
+            QuoteDecision::Reject(reason) => panic!("rejected: {}", reason),
+        }
+    }
+
+    #[test]
+    fn example_script_quotes_and_rejects() {
+        let strategy = ScriptStrategy::load(Path::new("quote_strategy.example.rhai")).unwrap();
+        assert_eq!(xmr_quoted(&strategy), 995_000_000_000);
+
+        let mut short = context(1_000_000_000_000);
+        short.inventory.xmr = 1_000_000_000_000;
+        assert!(matches!(strategy.quote(&short).unwrap(), QuoteDecision::Reject(_)));
+    }
+
+    #[test]
+    fn example_script_prices_from_the_reference_rate_not_the_request() {
+        let strategy = ScriptStrategy::load(Path::new("quote_strategy.example.rhai")).unwrap();
+        match strategy.quote(&context(10_000_000_000_000)).unwrap() {
+            QuoteDecision::Quote(priced) => assert_eq!(priced.xmr_amount, 995_000_000_000),
+            QuoteDecision::Reject(reason) => panic!("rejected: {}", reason),
+        }
+
+        let mut cheaper = context(1_000_000_000_000);
+        cheaper.reference.usdc_amount = 300_000_000;
+        match strategy.quote(&cheaper).unwrap() {
+            QuoteDecision::Quote(priced) => assert_eq!(priced.xmr_amount, 497_500_000_000),
+            QuoteDecision::Reject(reason) => panic!("rejected: {}", reason),
+        }
+    }
+
+    #[test]
+    fn runaway_scripts_hit_the_operation_limit() {
+        let file = ScriptFile::new("fn quote(request, inventory, price) { loop { } }");
+        let strategy = ScriptStrategy::load(&file.0).unwrap();
+        let error = strategy.quote(&context(1_000_000_000_000)).unwrap_err();
+        assert!(error.to_string().contains("operations"), "{}", error);
+    }
+
+    #[test]
+    fn script_is_reloaded_when_its_mtime_changes() {
+        let file = ScriptFile::new(HALF);
+        let strategy = ScriptStrategy::load(&file.0).unwrap();
+        assert_eq!(xmr_quoted(&strategy), 500_000_000_000);
+
+        file.write(QUARTER, SystemTime::now() + Duration::from_secs(60));
+        assert_eq!(xmr_quoted(&strategy), 250_000_000_000);
+    }
+
+    #[test]
+    fn broken_edit_keeps_the_previous_script_in_service() {
+        let file = ScriptFile::new(HALF);
+        let strategy = ScriptStrategy::load(&file.0).unwrap();
+
+        file.write("fn quote(request, inventory, price) {", SystemTime::now() + Duration::from_secs(60));
+        assert_eq!(xmr_quoted(&strategy), 500_000_000_000);
+
+        // A fixed edit is picked up again
+        file.write(QUARTER, SystemTime::now() + Duration::from_secs(120));
+        assert_eq!(xmr_quoted(&strategy), 250_000_000_000);
+    }
+
+    #[test]
+    fn amounts_beyond_i64_are_rejected() {
+        let file = ScriptFile::new(HALF);
+        let strategy = ScriptStrategy::load(&file.0).unwrap();
+        let error = strategy.quote(&context(u64::MAX)).unwrap_err();
+        assert!(error.to_string().contains("xmr_amount"), "{}", error);
+    }
+
+    #[test]
+    fn negative_results_are_rejected() {
+        let file = ScriptFile::new("fn quote(request, inventory, price) { #{ usdc_amount: -1, xmr_amount: 1 } }");
+        let strategy = ScriptStrategy::load(&file.0).unwrap();
+        assert!(strategy.quote(&context(1_000_000_000_000)).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::config::QuotingConfig;
+use crate::swap_engine::{Direction, QuoteRequest};
+use super::{InventoryPosition, PricedQuote, ReferencePrice, ScriptStrategy, SkewCurve};
+
+use std::sync::Arc;
+use anyhow::Result;
+
+/// Everything a strategy sees when asked to price a request.
+#[derive(Debug, Clone)]
+pub struct QuoteContext {
+    pub request: QuoteRequest,
+    pub inventory: InventoryPosition,
+    pub reference: ReferencePrice,
+}
+
+impl QuoteContext {
+    pub fn new(request: &QuoteRequest, inventory: InventoryPosition, reference: ReferencePrice) -> Self {
+        Self {
+            request: request.clone(),
+            inventory,
+            reference,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteDecision {
+    Quote(PricedQuote),
+    Reject(String),
+}
+
+/// Pricing logic the swap engine calls for every quote request.
+pub trait QuoteStrategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn quote(&self, context: &QuoteContext) -> Result<QuoteDecision>;
+}
+
+/// Flat `spread_bps` on the leg Alice receives.
+#[derive(Debug, Clone)]
+pub struct StaticSpreadStrategy {
+    spread_bps: u64,
+}
+
+impl StaticSpreadStrategy {
+    pub fn new(spread_bps: u64) -> Self {
+        Self { spread_bps }
+    }
+}
+
+impl QuoteStrategy for StaticSpreadStrategy {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    fn quote(&self, context: &QuoteContext) -> Result<QuoteDecision> {
+        Ok(QuoteDecision::Quote(PricedQuote::discounted(&context.request, &context.reference, self.spread_bps as i64)))
+    }
+}
+
+/// `spread_bps` plus an inventory skew: when Bob is short XMR, selling XMR
+/// gets dearer and buying XMR gets cheaper.
+#[derive(Debug, Clone)]
+pub struct SkewStrategy {
+    spread_bps: u64,
+    curve: SkewCurve,
+}
+
+impl SkewStrategy {
+    pub fn new(spread_bps: u64, curve: SkewCurve) -> Self {
+        Self { spread_bps, curve }
+    }
+}
+
+impl QuoteStrategy for SkewStrategy {
+    fn name(&self) -> &str {
+        "skew"
+    }
+
+    fn quote(&self, context: &QuoteContext) -> Result<QuoteDecision> {
+        let skew_bps = self.curve.skew_bps(&context.inventory, &context.reference);
+        let discount_bps = match context.request.direction {
+            Direction::UsdcToXmr => self.spread_bps as i64 + skew_bps,
+            Direction::XmrToUsdc => self.spread_bps as i64 - skew_bps,
+        };
+        tracing::debug!("Skew strategy: spread {} bps, skew {} bps", self.spread_bps, skew_bps);
+
+        Ok(QuoteDecision::Quote(PricedQuote::discounted(&context.request, &context.reference, discount_bps)))
+    }
+}
+
+/// Pick the strategy from config: a script if `script_path` is set,
+/// otherwise skew pricing if `skew` is set, otherwise a static spread.
+pub fn build_strategy(config: &QuotingConfig) -> Result<Arc<dyn QuoteStrategy>> {
+    if let Some(path) = &config.script_path {
+        return Ok(Arc::new(ScriptStrategy::load(path)?));
+    }
+
+    match &config.skew {
+        Some(skew) => Ok(Arc::new(SkewStrategy::new(config.spread_bps, SkewCurve::from(skew)))),
+        None => Ok(Arc::new(StaticSpreadStrategy::new(config.spread_bps))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, SkewConfig};
+
+    const XMR: u64 = 1_000_000_000_000;
+    const USDC: u64 = 1_000_000;
+
+    /// 150 USDC per XMR.
+    const PRICE: ReferencePrice = ReferencePrice {
+        usdc_amount: 150 * USDC,
+        xmr_amount: XMR,
+    };
+
+    /// 150 USDC for 1 XMR, against `xmr` XMR and `usdc` USDC of inventory.
+    fn context(direction: Direction, xmr: u64, usdc: u64) -> QuoteContext {
+        let request = QuoteRequest {
+            direction,
+            usdc_amount: 150 * USDC,
+            xmr_amount: XMR,
+            destination: None,
+        };
+        QuoteContext::new(&request, InventoryPosition { xmr: xmr * XMR, usdc: usdc * USDC }, PRICE)
+    }
+
+    fn priced(strategy: &dyn QuoteStrategy, context: &QuoteContext) -> PricedQuote {
+        match strategy.quote(context).unwrap() {
+            QuoteDecision::Quote(priced) => priced,
+            QuoteDecision::Reject(reason) => panic!("rejected: {}", reason),
+        }
+    }
+
+    fn skew() -> SkewStrategy {
+        SkewStrategy::new(50, SkewCurve::from(&SkewConfig { target_xmr_ratio_bps: 5_000, max_skew_bps: 200 }))
+    }
+
+    #[test]
+    fn static_spread_discounts_the_leg_alice_receives() {
+        let strategy = StaticSpreadStrategy::new(50);
+
+        let buy = priced(&strategy, &context(Direction::UsdcToXmr, 10, 1_500));
+        assert_eq!(buy, PricedQuote { usdc_amount: 150 * USDC, xmr_amount: XMR / 10_000 * 9_950 });
+
+        let sell = priced(&strategy, &context(Direction::XmrToUsdc, 10, 1_500));
+        assert_eq!(sell, PricedQuote { usdc_amount: 150 * USDC / 10_000 * 9_950, xmr_amount: XMR });
+    }
+
+    #[test]
+    fn skew_matches_the_static_spread_at_the_target_ratio() {
+        // 10 XMR at 150 USDC against 1,500 USDC is a 50% XMR share
+        for direction in [Direction::UsdcToXmr, Direction::XmrToUsdc] {
+            let context = context(direction, 10, 1_500);
+            assert_eq!(priced(&skew(), &context), priced(&StaticSpreadStrategy::new(50), &context));
+        }
+    }
+
+    #[test]
+    fn skew_leans_against_the_short_asset() {
+        // (skewed, flat) amounts Alice receives
+        let receives = |direction, xmr, usdc| {
+            let context = context(direction, xmr, usdc);
+            let (skewed, flat) = (priced(&skew(), &context), priced(&StaticSpreadStrategy::new(50), &context));
+            match direction {
+                Direction::UsdcToXmr => (skewed.xmr_amount, flat.xmr_amount),
+                Direction::XmrToUsdc => (skewed.usdc_amount, flat.usdc_amount),
+            }
+        };
+
+        // Short XMR: selling XMR gets dearer, buying XMR gets cheaper
+        let (skewed, flat) = receives(Direction::UsdcToXmr, 2, 2_700);
+        assert!(skewed < flat);
+        let (skewed, flat) = receives(Direction::XmrToUsdc, 2, 2_700);
+        assert!(skewed > flat);
+
+        // Long XMR: the reverse
+        let (skewed, flat) = receives(Direction::UsdcToXmr, 18, 300);
+        assert!(skewed > flat);
+        let (skewed, flat) = receives(Direction::XmrToUsdc, 18, 300);
+        assert!(skewed < flat);
+    }
+
+    #[test]
+    fn a_skewed_request_does_not_move_the_skewed_price() {
+        // 2 XMR against 2,700 USDC is short XMR at the reference rate.
+        // Asking for 10 XMR per 150 USDC would make Bob look long XMR if
+        // the rate came from the request.
+        let honest = context(Direction::UsdcToXmr, 2, 2_700);
+        let mut skewed = honest.clone();
+        skewed.request.xmr_amount = 10 * XMR;
+        assert_eq!(priced(&skew(), &skewed), priced(&skew(), &honest));
+        assert!(priced(&skew(), &honest).xmr_amount < priced(&StaticSpreadStrategy::new(50), &honest).xmr_amount);
+    }
+
+    #[test]
+    fn build_strategy_follows_the_config() {
+        let mut config = AppConfig::default().quoting;
+        config.script_path = None;
+        config.skew = None;
+        assert_eq!(build_strategy(&config).unwrap().name(), "static");
+
+        config.skew = Some(SkewConfig { target_xmr_ratio_bps: 5_000, max_skew_bps: 200 });
+        assert_eq!(build_strategy(&config).unwrap().name(), "skew");
+
+        config.script_path = Some("quote_strategy.example.rhai".into());
+        assert_eq!(build_strategy(&config).unwrap().name(), "script");
+    }
+}
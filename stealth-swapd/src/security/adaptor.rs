@@ -0,0 +1,247 @@
+use num_bigint_dig::BigUint;
+use num_traits::Zero;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+/// secp256k1 field prime.
+const P: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F";
+/// secp256k1 group order.
+const N: &str = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141";
+const GX: &str = "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+const GY: &str = "483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdaptorError {
+    #[error("Swap secret is not a valid secp256k1 key")]
+    InvalidSecret,
+}
+
+/// What the program's `redeem_usdc` checks: a signature over the swap's
+/// `secret_hash` that `secp256k1_recover` with `recovery_id` recovers to
+/// the key whose x-coordinate is `curve_point`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedeemSignature {
+    /// `r || s`, big-endian, with `s` in the lower half of the order.
+    pub signature: [u8; 64],
+    pub recovery_id: u8,
+    pub curve_point: [u8; 32],
+}
+
+/// Sign `secret_hash` with the swap's adaptor secret as the key, so only
+/// the holder of the secret can produce the redeem. The nonce is derived
+/// from the secret and the hash, so signing the same swap twice gives the
+/// same signature.
+pub fn sign_redeem(secret: &Secret<[u8; 32]>, secret_hash: &[u8; 32]) -> Result<RedeemSignature, AdaptorError> {
+    let curve = Curve::new();
+    let d = BigUint::from_bytes_be(secret.expose_secret());
+    if d.is_zero() || d >= curve.n {
+        return Err(AdaptorError::InvalidSecret);
+    }
+    let public = curve.mul(&d, &curve.g).ok_or(AdaptorError::InvalidSecret)?;
+    let z = BigUint::from_bytes_be(secret_hash) % &curve.n;
+
+    for counter in 0u32.. {
+        let mut hasher = Sha256::new();
+        hasher.update(b"stealth-swap/redeem-nonce/v1");
+        hasher.update(secret.expose_secret());
+        hasher.update(secret_hash);
+        hasher.update(counter.to_be_bytes());
+        let k = BigUint::from_bytes_be(&hasher.finalize());
+        if k.is_zero() || k >= curve.n {
+            continue;
+        }
+
+        let Some(point) = curve.mul(&k, &curve.g) else {
+            continue;
+        };
+        // A recovery id of 2 or 3 would be needed for an x past the order
+        if point.x >= curve.n {
+            continue;
+        }
+        let r = point.x.clone();
+        let k_inv = curve.invert(&k, &curve.n);
+        let mut s = (k_inv * ((&z + &r * &d) % &curve.n)) % &curve.n;
+        if s.is_zero() {
+            continue;
+        }
+
+        let mut recovery_id = u8::from(is_odd(&point.y));
+        if s > &curve.n >> 1 {
+            s = &curve.n - s;
+            recovery_id ^= 1;
+        }
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&to_bytes(&r));
+        signature[32..].copy_from_slice(&to_bytes(&s));
+        return Ok(RedeemSignature {
+            signature,
+            recovery_id,
+            curve_point: to_bytes(&public.x),
+        });
+    }
+    unreachable!("a nonce is found long before the counter wraps")
+}
+
+fn to_bytes(value: &BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(&bytes);
+    out
+}
+
+fn bit(value: &BigUint, index: usize) -> bool {
+    (value >> index).to_bytes_le()[0] & 1 == 1
+}
+
+fn is_odd(value: &BigUint) -> bool {
+    bit(value, 0)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Point {
+    x: BigUint,
+    y: BigUint,
+}
+
+/// Affine secp256k1 arithmetic, `None` standing for the point at infinity.
+/// Not constant time: it only ever handles a swap's own secret, which the
+/// redeem reveals anyway.
+struct Curve {
+    p: BigUint,
+    n: BigUint,
+    g: Point,
+}
+
+impl Curve {
+    fn new() -> Self {
+        let parse = |hex: &str| BigUint::parse_bytes(hex.as_bytes(), 16).expect("curve constant");
+        Self {
+            p: parse(P),
+            n: parse(N),
+            g: Point { x: parse(GX), y: parse(GY) },
+        }
+    }
+
+    /// `value⁻¹ mod modulus` for a prime modulus.
+    fn invert(&self, value: &BigUint, modulus: &BigUint) -> BigUint {
+        value.modpow(&(modulus - BigUint::from(2u8)), modulus)
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        ((a + &self.p) - (b % &self.p)) % &self.p
+    }
+
+    fn add(&self, a: Option<&Point>, b: Option<&Point>) -> Option<Point> {
+        let (a, b) = match (a, b) {
+            (None, b) => return b.cloned(),
+            (a, None) => return a.cloned(),
+            (Some(a), Some(b)) => (a, b),
+        };
+        let slope = if a.x == b.x {
+            if (&a.y + &b.y) % &self.p == BigUint::zero() {
+                return None;
+            }
+            let numerator = (BigUint::from(3u8) * &a.x * &a.x) % &self.p;
+            numerator * self.invert(&((BigUint::from(2u8) * &a.y) % &self.p), &self.p) % &self.p
+        } else {
+            self.sub(&b.y, &a.y) * self.invert(&self.sub(&b.x, &a.x), &self.p) % &self.p
+        };
+        let x = self.sub(&self.sub(&(&slope * &slope), &a.x), &b.x);
+        let y = self.sub(&(slope * self.sub(&a.x, &x)), &a.y);
+        Some(Point { x, y })
+    }
+
+    fn mul(&self, scalar: &BigUint, point: &Point) -> Option<Point> {
+        let mut result: Option<Point> = None;
+        for i in (0..scalar.bits()).rev() {
+            result = self.add(result.as_ref(), result.as_ref());
+            if bit(scalar, i) {
+                result = self.add(result.as_ref(), Some(point));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::One;
+
+    /// What `secp256k1_recover` does on-chain: the public key `r⁻¹(sR − zG)`.
+    fn recover(hash: &[u8; 32], recovery_id: u8, signature: &[u8; 64]) -> Option<Point> {
+        let curve = Curve::new();
+        let r = BigUint::from_bytes_be(&signature[..32]);
+        let s = BigUint::from_bytes_be(&signature[32..]);
+        let z = BigUint::from_bytes_be(hash) % &curve.n;
+        let big_r = lift(&curve, &r, recovery_id == 1)?;
+
+        let r_inv = curve.invert(&r, &curve.n);
+        let u1 = (&curve.n - z) * &r_inv % &curve.n;
+        let u2 = s * r_inv % &curve.n;
+        curve.add(curve.mul(&u1, &curve.g).as_ref(), curve.mul(&u2, &big_r).as_ref())
+    }
+
+    /// The point with x-coordinate `x` and y of the given parity.
+    fn lift(curve: &Curve, x: &BigUint, odd: bool) -> Option<Point> {
+        let rhs = (x.modpow(&BigUint::from(3u8), &curve.p) + BigUint::from(7u8)) % &curve.p;
+        let exponent = (&curve.p + BigUint::one()) >> 2;
+        let y = rhs.modpow(&exponent, &curve.p);
+        if (&y * &y) % &curve.p != rhs {
+            return None;
+        }
+        let y = if is_odd(&y) == odd { y } else { &curve.p - y };
+        Some(Point { x: x.clone(), y })
+    }
+
+    fn secret(byte: u8) -> Secret<[u8; 32]> {
+        Secret::new([byte; 32])
+    }
+
+    #[test]
+    fn doubling_the_generator_matches_the_published_point() {
+        let curve = Curve::new();
+        let two_g = curve.mul(&BigUint::from(2u8), &curve.g).unwrap();
+        assert_eq!(
+            hex::encode(to_bytes(&two_g.x)),
+            "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+        );
+        assert_eq!(curve.add(Some(&curve.g), Some(&curve.g)), Some(two_g));
+        assert_eq!(curve.mul(&curve.n, &curve.g), None);
+    }
+
+    #[test]
+    fn the_signature_recovers_to_the_curve_point() {
+        for byte in [1, 7, 0x42, 0xaa] {
+            let hash = Sha256::digest([byte]).into();
+            let signed = sign_redeem(&secret(byte), &hash).unwrap();
+
+            let recovered = recover(&hash, signed.recovery_id, &signed.signature).unwrap();
+            assert_eq!(to_bytes(&recovered.x), signed.curve_point);
+        }
+    }
+
+    #[test]
+    fn signatures_are_low_s_and_deterministic() {
+        let hash = [9; 32];
+        let signed = sign_redeem(&secret(3), &hash).unwrap();
+        let n = Curve::new().n;
+        assert!(BigUint::from_bytes_be(&signed.signature[32..]) <= &n >> 1);
+        assert!(signed.recovery_id <= 1);
+        assert_eq!(sign_redeem(&secret(3), &hash).unwrap(), signed);
+    }
+
+    #[test]
+    fn another_hash_does_not_recover_to_the_curve_point() {
+        let signed = sign_redeem(&secret(5), &[1; 32]).unwrap();
+        let recovered = recover(&[2; 32], signed.recovery_id, &signed.signature);
+        assert!(recovered.is_none_or(|point| to_bytes(&point.x) != signed.curve_point));
+    }
+
+    #[test]
+    fn secrets_outside_the_group_order_are_rejected() {
+        assert!(matches!(sign_redeem(&Secret::new([0; 32]), &[1; 32]), Err(AdaptorError::InvalidSecret)));
+        assert!(matches!(sign_redeem(&Secret::new([0xff; 32]), &[1; 32]), Err(AdaptorError::InvalidSecret)));
+    }
+}
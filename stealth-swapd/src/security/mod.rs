@@ -1,7 +1,16 @@
+mod signing;
+mod sealing;
+mod adaptor;
+
+pub use signing::*;
+pub use sealing::*;
+pub use adaptor::*;
+
 use sha2::{Sha256, Digest};
 use std::sync::Arc;
 use secrecy::{Secret, SecretString, ExposeSecret};
 
+#[derive(Clone)]
 pub struct KeyDerivation {
     encryption_key: Arc<Secret<[u8; 32]>>,
 }
@@ -14,6 +23,10 @@ impl KeyDerivation {
         }
     }
 
+    pub fn encryption_key(&self) -> &Secret<[u8; 32]> {
+        &self.encryption_key
+    }
+
     fn derive_key_from_passphrase(passphrase: &str) -> [u8; 32] {
         use sha2::{Digest};
         let mut hasher = Sha256::new();
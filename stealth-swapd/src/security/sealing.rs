@@ -0,0 +1,93 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use secrecy::{ExposeSecret, Secret};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SealError {
+    #[error("Sealed data is truncated")]
+    Truncated,
+
+    /// Wrong key, tampered data, or data sealed for another record.
+    #[error("Sealed data does not authenticate")]
+    BadSeal,
+}
+
+fn sealing_key(key: &Secret<[u8; 32]>) -> LessSafeKey {
+    // A 32-byte key is always valid for ChaCha20-Poly1305
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key.expose_secret()).expect("32-byte key"))
+}
+
+/// Encrypt `plaintext` with ChaCha20-Poly1305 under a random nonce, which
+/// is prepended. `context` is authenticated but not stored, so the result
+/// only opens for the same record.
+pub fn seal(key: &Secret<[u8; 32]>, context: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut nonce);
+
+    let mut sealed = plaintext.to_vec();
+    sealing_key(key)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(context), &mut sealed)
+        .expect("plaintext within ChaCha20-Poly1305 limits");
+
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&sealed);
+    out
+}
+
+/// Decrypt what [`seal`] produced with the same key and context.
+pub fn open(key: &Secret<[u8; 32]>, context: &[u8], sealed: &[u8]) -> Result<Vec<u8>, SealError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(SealError::Truncated);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| SealError::Truncated)?;
+
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = sealing_key(key)
+        .open_in_place(nonce, Aad::from(context), &mut buffer)
+        .map_err(|_| SealError::BadSeal)?;
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> Secret<[u8; 32]> {
+        Secret::new([byte; 32])
+    }
+
+    #[test]
+    fn sealed_data_opens_with_the_same_key_and_context() {
+        let sealed = seal(&key(1), b"swap-1refund", b"signed transaction");
+        assert_eq!(sealed.len(), NONCE_LEN + b"signed transaction".len() + CHACHA20_POLY1305.tag_len());
+        assert_eq!(open(&key(1), b"swap-1refund", &sealed).unwrap(), b"signed transaction");
+
+        // A fresh nonce each time
+        assert_ne!(seal(&key(1), b"swap-1refund", b"signed transaction"), sealed);
+        assert_eq!(open(&key(1), b"", &seal(&key(1), b"", b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn wrong_key_or_context_does_not_open() {
+        let sealed = seal(&key(1), b"swap-1refund", b"signed transaction");
+        assert!(matches!(open(&key(2), b"swap-1refund", &sealed), Err(SealError::BadSeal)));
+        assert!(matches!(open(&key(1), b"swap-2refund", &sealed), Err(SealError::BadSeal)));
+    }
+
+    #[test]
+    fn tampered_or_truncated_data_does_not_open() {
+        let sealed = seal(&key(1), b"context", b"signed transaction");
+        // Flip one bit in the nonce, the ciphertext and the tag in turn
+        for index in [0, NONCE_LEN, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[index] ^= 1;
+            assert!(matches!(open(&key(1), b"context", &tampered), Err(SealError::BadSeal)), "byte {}", index);
+        }
+
+        assert!(matches!(open(&key(1), b"context", &sealed[..NONCE_LEN - 1]), Err(SealError::Truncated)));
+        assert!(matches!(open(&key(1), b"context", &sealed[..NONCE_LEN + 4]), Err(SealError::BadSeal)));
+        let mut extended = sealed;
+        extended.push(0);
+        assert!(matches!(open(&key(1), b"context", &extended), Err(SealError::BadSeal)));
+    }
+}
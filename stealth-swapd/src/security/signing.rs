@@ -0,0 +1,230 @@
+use crate::swap_engine::Direction;
+
+use std::path::Path;
+use secrecy::{ExposeSecret, Secret};
+
+/// Domain separator so a quote signature can never be replayed as a Solana
+/// transaction signature or any other message signed by Bob's key.
+const QUOTE_DOMAIN: &[u8] = b"stealth-swap/quote/v1";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningError {
+    #[error("Failed to read keypair file {0}: {1}")]
+    KeypairIo(String, std::io::Error),
+
+    #[error("Invalid keypair file: {0}")]
+    InvalidKeypair(String),
+
+    #[error("Invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("Invalid signature encoding")]
+    InvalidSignatureEncoding,
+
+    #[error("Quote signature does not verify")]
+    BadSignature,
+}
+
+/// Bob's Solana identity, loaded from a `solana-keygen` JSON file. Only the
+/// 32-byte seed is kept, wrapped in `Secret`; the signing key is rebuilt
+/// for each signature.
+pub struct SolanaKeypair {
+    seed: Secret<[u8; 32]>,
+    public_key: [u8; 32],
+}
+
+impl SolanaKeypair {
+    pub fn load(path: &Path) -> Result<Self, SigningError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SigningError::KeypairIo(path.display().to_string(), e))?;
+        let bytes: Vec<u8> = serde_json::from_str(&contents)
+            .map_err(|e| SigningError::InvalidKeypair(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Accepts the 64-byte `secret || public` layout used by Solana tooling.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SigningError> {
+        if bytes.len() != 64 {
+            return Err(SigningError::InvalidKeypair(format!("expected 64 bytes, got {}", bytes.len())));
+        }
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&bytes[..32]);
+
+        let keypair = ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::new(seed));
+        if keypair.pk.as_ref() != &bytes[32..] {
+            return Err(SigningError::InvalidKeypair("public key does not match secret".to_string()));
+        }
+
+        let mut public_key = [0u8; 32];
+        public_key.copy_from_slice(keypair.pk.as_ref());
+        Ok(Self {
+            seed: Secret::new(seed),
+            public_key,
+        })
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    pub fn pubkey_base58(&self) -> String {
+        bs58::encode(self.public_key).into_string()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let keypair = ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::new(*self.seed.expose_secret()));
+        *keypair.sk.sign(message, None)
+    }
+}
+
+/// The terms Bob commits to when he issues a quote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteTerms<'a> {
+    pub quote_id: uuid::Uuid,
+    pub direction: Direction,
+    pub usdc_amount: u64,
+    pub xmr_amount: u64,
+    pub secret_hash: [u8; 32],
+    pub destination: &'a str,
+    /// Unix timestamp, seconds.
+    pub expiry: i64,
+}
+
+impl QuoteTerms<'_> {
+    /// Canonical encoding: domain tag, then each field in order as
+    /// `quote_id[16] | direction u8 | usdc u64le | xmr u64le | secret_hash[32]
+    /// | destination_len u16le | destination | expiry i64le`.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let destination = self.destination.as_bytes();
+        let mut bytes = Vec::with_capacity(QUOTE_DOMAIN.len() + 16 + 1 + 8 + 8 + 32 + 2 + destination.len() + 8);
+        bytes.extend_from_slice(QUOTE_DOMAIN);
+        bytes.extend_from_slice(self.quote_id.as_bytes());
+        bytes.push(match self.direction {
+            Direction::UsdcToXmr => 0,
+            Direction::XmrToUsdc => 1,
+        });
+        bytes.extend_from_slice(&self.usdc_amount.to_le_bytes());
+        bytes.extend_from_slice(&self.xmr_amount.to_le_bytes());
+        bytes.extend_from_slice(&self.secret_hash);
+        bytes.extend_from_slice(&(destination.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(destination);
+        bytes.extend_from_slice(&self.expiry.to_le_bytes());
+        bytes
+    }
+}
+
+pub fn sign_quote(keypair: &SolanaKeypair, terms: &QuoteTerms<'_>) -> [u8; 64] {
+    keypair.sign(&terms.canonical_bytes())
+}
+
+/// Check a quote signature against Bob's public key.
+pub fn verify_quote(public_key: &[u8; 32], terms: &QuoteTerms<'_>, signature: &[u8; 64]) -> Result<(), SigningError> {
+    let public_key = ed25519_compact::PublicKey::from_slice(public_key)
+        .map_err(|e| SigningError::InvalidPublicKey(e.to_string()))?;
+    let signature = ed25519_compact::Signature::from_slice(signature)
+        .map_err(|_| SigningError::InvalidSignatureEncoding)?;
+    public_key
+        .verify(terms.canonical_bytes(), &signature)
+        .map_err(|_| SigningError::BadSignature)
+}
+
+/// Same as [`verify_quote`], taking the base58 Solana address and hex
+/// signature exactly as they appear in a `QuoteResponse`.
+pub fn verify_quote_encoded(solana_address: &str, terms: &QuoteTerms<'_>, signature_hex: &str) -> Result<(), SigningError> {
+    let public_key: [u8; 32] = bs58::decode(solana_address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| SigningError::InvalidPublicKey(solana_address.to_string()))?;
+    let signature: [u8; 64] = hex::decode(signature_hex)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SigningError::InvalidSignatureEncoding)?;
+    verify_quote(&public_key, terms, &signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypair(seed: u8) -> SolanaKeypair {
+        let pair = ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::new([seed; 32]));
+        let mut bytes = [seed; 32].to_vec();
+        bytes.extend_from_slice(pair.pk.as_ref());
+        SolanaKeypair::from_bytes(&bytes).unwrap()
+    }
+
+    fn terms() -> QuoteTerms<'static> {
+        QuoteTerms {
+            quote_id: uuid::Uuid::from_u128(0x00112233_4455_6677_8899_aabbccddeeff),
+            direction: Direction::XmrToUsdc,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            secret_hash: [0xab; 32],
+            destination: "dest",
+            expiry: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn canonical_bytes_match_the_fixed_layout() {
+        let expected = [
+            hex::encode(b"stealth-swap/quote/v1"),
+            "00112233445566778899aabbccddeeff".to_string(),
+            "01".to_string(),
+            "80d1f00800000000".to_string(),
+            "0010a5d4e8000000".to_string(),
+            "ab".repeat(32),
+            "0400".to_string(),
+            hex::encode(b"dest"),
+            "00f1536500000000".to_string(),
+        ]
+        .concat();
+        assert_eq!(hex::encode(terms().canonical_bytes()), expected);
+    }
+
+    #[test]
+    fn signed_quote_verifies() {
+        let keypair = keypair(1);
+        let signature = sign_quote(&keypair, &terms());
+        verify_quote(&keypair.public_key(), &terms(), &signature).unwrap();
+        verify_quote_encoded(&keypair.pubkey_base58(), &terms(), &hex::encode(signature)).unwrap();
+    }
+
+    #[test]
+    fn tampering_with_any_field_breaks_the_signature() {
+        let keypair = keypair(1);
+        let signature = sign_quote(&keypair, &terms());
+
+        let tampered = [
+            QuoteTerms { quote_id: uuid::Uuid::from_u128(1), ..terms() },
+            QuoteTerms { direction: Direction::UsdcToXmr, ..terms() },
+            QuoteTerms { usdc_amount: 150_000_001, ..terms() },
+            QuoteTerms { xmr_amount: 999_999_999_999, ..terms() },
+            QuoteTerms { secret_hash: [0xac; 32], ..terms() },
+            QuoteTerms { destination: "desu", ..terms() },
+            QuoteTerms { expiry: 1_700_000_001, ..terms() },
+        ];
+        for terms in &tampered {
+            assert!(
+                matches!(verify_quote(&keypair.public_key(), terms, &signature), Err(SigningError::BadSignature)),
+                "tampered terms verified: {:?}",
+                terms
+            );
+        }
+    }
+
+    #[test]
+    fn signature_under_another_key_is_rejected() {
+        let signature = sign_quote(&keypair(1), &terms());
+        let other = keypair(2);
+        assert!(matches!(
+            verify_quote(&other.public_key(), &terms(), &signature),
+            Err(SigningError::BadSignature)
+        ));
+        assert!(matches!(
+            verify_quote_encoded(&other.pubkey_base58(), &terms(), &hex::encode(signature)),
+            Err(SigningError::BadSignature)
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::clients::solana_tx::{
+    set_compute_unit_limit, set_compute_unit_price, Instruction, LAMPORTS_PER_SIGNATURE, MAX_COMPUTE_UNITS,
+};
+use crate::config::PriorityFeeConfig;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Price an urgent retry escalates from when recent fees are zero, so it
+/// still outbids the transactions it lost to.
+const ESCALATION_FLOOR_MICRO_LAMPORTS: u64 = 1_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ComputeBudgetError {
+    #[error("Base fee of {fee} lamports exceeds max_gas_lamports of {cap}")]
+    ExceedsCap { fee: u64, cap: u64 },
+}
+
+/// Compute-unit limit and price of one transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub unit_limit: u32,
+    /// Micro-lamports per compute unit.
+    pub unit_price: u64,
+}
+
+impl ComputeBudget {
+    /// Most the transaction can cost with one signature: the base fee plus
+    /// the priority fee, which is charged on the requested limit.
+    pub fn max_cost(&self) -> u64 {
+        LAMPORTS_PER_SIGNATURE + (self.unit_limit as u64 * self.unit_price).div_ceil(1_000_000)
+    }
+
+    pub fn instructions(&self) -> anyhow::Result<Vec<Instruction>> {
+        Ok(vec![set_compute_unit_limit(self.unit_limit)?, set_compute_unit_price(self.unit_price)?])
+    }
+}
+
+/// Sizes compute budgets from simulation and prices them from recent fees,
+/// escalating retries near a deadline, within `relayer.max_gas_lamports`.
+#[derive(Debug, Clone)]
+pub struct PriorityFeePolicy {
+    percentile: u8,
+    unit_margin_percent: u64,
+    default_units: u32,
+    urgent_within: Duration,
+    escalation_percent: u64,
+    max_lamports: u64,
+}
+
+impl PriorityFeePolicy {
+    pub fn new(config: Option<&PriorityFeeConfig>, max_lamports: u64) -> Self {
+        Self {
+            percentile: config.and_then(|c| c.percentile).unwrap_or(75).min(100),
+            unit_margin_percent: config.and_then(|c| c.compute_unit_margin_percent).unwrap_or(20),
+            default_units: config.and_then(|c| c.default_compute_units).unwrap_or(200_000),
+            urgent_within: Duration::minutes(config.and_then(|c| c.urgent_within_minutes).unwrap_or(30) as i64),
+            escalation_percent: config.and_then(|c| c.escalation_percent).unwrap_or(50),
+            max_lamports,
+        }
+    }
+
+    /// Limit for a transaction that consumed `consumed` units in simulation,
+    /// or the default when it couldn't be simulated.
+    pub fn unit_limit(&self, consumed: Option<u64>) -> u32 {
+        match consumed {
+            Some(units) => (units * (100 + self.unit_margin_percent) / 100).clamp(1, MAX_COMPUTE_UNITS as u64) as u32,
+            None => self.default_units,
+        }
+    }
+
+    /// Whether the deadline is close enough, or past, that retries escalate.
+    pub fn is_urgent(&self, deadline: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        deadline - now <= self.urgent_within
+    }
+
+    /// Budget for `unit_limit` units: the chosen percentile of `recent_fees`,
+    /// raised by `escalation_percent` for each of `retries` when urgent, then
+    /// lowered if needed to keep the total within the cap.
+    pub fn budget(&self, unit_limit: u32, recent_fees: &[u64], retries: u32, urgent: bool) -> Result<ComputeBudget, ComputeBudgetError> {
+        if LAMPORTS_PER_SIGNATURE > self.max_lamports {
+            return Err(ComputeBudgetError::ExceedsCap { fee: LAMPORTS_PER_SIGNATURE, cap: self.max_lamports });
+        }
+
+        let mut price = self.percentile_fee(recent_fees);
+        if urgent && retries > 0 {
+            price = price.max(ESCALATION_FLOOR_MICRO_LAMPORTS);
+            for _ in 0..retries {
+                price = price.saturating_mul(100 + self.escalation_percent) / 100;
+            }
+        }
+
+        let cap = (self.max_lamports - LAMPORTS_PER_SIGNATURE).saturating_mul(1_000_000) / unit_limit.max(1) as u64;
+        Ok(ComputeBudget {
+            unit_limit,
+            unit_price: price.min(cap),
+        })
+    }
+
+    fn percentile_fee(&self, recent_fees: &[u64]) -> u64 {
+        if recent_fees.is_empty() {
+            return 0;
+        }
+        let mut fees = recent_fees.to_vec();
+        fees.sort_unstable();
+        let rank = (fees.len() - 1) * self.percentile as usize / 100;
+        fees[rank]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_LAMPORTS: u64 = 100_000;
+
+    fn policy() -> PriorityFeePolicy {
+        PriorityFeePolicy::new(None, MAX_LAMPORTS)
+    }
+
+    #[test]
+    fn max_cost_stays_within_the_cap_for_any_unit_limit() {
+        let policy = policy();
+        let fees = [u64::MAX / 2, 50_000_000, 1];
+        for unit_limit in [1, 7, 1_000, 150_001, 200_000, 999_999, MAX_COMPUTE_UNITS] {
+            for retries in [0, 1, 5, 40] {
+                let budget = policy.budget(unit_limit, &fees, retries, true).unwrap();
+                assert!(
+                    budget.max_cost() <= MAX_LAMPORTS,
+                    "{} units at {} micro-lamports costs {}",
+                    unit_limit,
+                    budget.unit_price,
+                    budget.max_cost()
+                );
+                assert_eq!(budget.unit_limit, unit_limit);
+            }
+        }
+    }
+
+    #[test]
+    fn cap_below_the_base_fee_is_an_error() {
+        let policy = PriorityFeePolicy::new(None, LAMPORTS_PER_SIGNATURE - 1);
+        assert!(matches!(
+            policy.budget(200_000, &[], 0, false),
+            Err(ComputeBudgetError::ExceedsCap { fee: LAMPORTS_PER_SIGNATURE, .. })
+        ));
+    }
+
+    #[test]
+    fn urgent_retry_escalates_from_the_floor_when_fees_are_zero() {
+        let policy = policy();
+        assert_eq!(policy.budget(200_000, &[], 0, true).unwrap().unit_price, 0);
+        assert_eq!(policy.budget(200_000, &[0, 0, 0], 0, true).unwrap().unit_price, 0);
+
+        // 50% per retry from the floor
+        assert_eq!(policy.budget(200_000, &[], 1, true).unwrap().unit_price, 1_500);
+        assert_eq!(policy.budget(200_000, &[0, 0], 2, true).unwrap().unit_price, 2_250);
+    }
+
+    #[test]
+    fn retries_only_escalate_when_urgent() {
+        let policy = policy();
+        assert_eq!(policy.budget(200_000, &[], 3, false).unwrap().unit_price, 0);
+        assert_eq!(policy.budget(200_000, &[10_000], 3, false).unwrap().unit_price, 10_000);
+        assert_eq!(policy.budget(200_000, &[10_000], 1, true).unwrap().unit_price, 15_000);
+    }
+
+    #[test]
+    fn price_is_the_configured_percentile_of_recent_fees() {
+        let fees: Vec<u64> = (1..=101).rev().collect();
+        assert_eq!(policy().budget(1_000, &fees, 0, false).unwrap().unit_price, 76);
+    }
+
+    #[test]
+    fn unit_limit_adds_the_margin_and_is_clamped() {
+        let policy = policy();
+        assert_eq!(policy.unit_limit(Some(100_000)), 120_000);
+        assert_eq!(policy.unit_limit(Some(0)), 1);
+        assert_eq!(policy.unit_limit(Some(5_000_000)), MAX_COMPUTE_UNITS);
+        assert_eq!(policy.unit_limit(None), 200_000);
+    }
+
+    #[test]
+    fn deadline_within_the_window_or_past_is_urgent() {
+        let policy = policy();
+        let now = Utc::now();
+        assert!(!policy.is_urgent(now + Duration::minutes(31), now));
+        assert!(policy.is_urgent(now + Duration::minutes(30), now));
+        assert!(policy.is_urgent(now - Duration::minutes(1), now));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::config::{ConfirmationConfig, ConfirmationTier};
+
+use serde::{Deserialize, Serialize};
+
+/// Finality a swap's chain observations need before the engine acts on them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmationRequirement {
+    pub monero_confirmations: u64,
+    pub solana_commitment: String,
+    pub solana_slot_depth: u64,
+}
+
+/// The requirement fixed for a swap at accept, and how far its current
+/// observations have got towards it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapConfirmations {
+    pub required: ConfirmationRequirement,
+    pub monero_confirmations: u64,
+    /// Slot of the transaction that brought about the on-chain condition
+    /// being waited for.
+    pub solana_observed_slot: Option<u64>,
+    /// Slots built on top of that transaction's.
+    pub solana_slot_depth: u64,
+}
+
+impl SwapConfirmations {
+    pub fn new(required: ConfirmationRequirement) -> Self {
+        Self {
+            required,
+            monero_confirmations: 0,
+            solana_observed_slot: None,
+            solana_slot_depth: 0,
+        }
+    }
+}
+
+/// Maps swap size to a [`ConfirmationRequirement`] using the configured
+/// tiers, smallest first.
+#[derive(Debug, Clone)]
+pub struct ConfirmationPolicy {
+    tiers: Vec<ConfirmationTier>,
+}
+
+impl ConfirmationPolicy {
+    pub fn new(config: Option<&ConfirmationConfig>) -> Self {
+        let mut tiers = config.map(|c| c.tiers.clone()).unwrap_or_default();
+        tiers.sort_by_key(|tier| tier.max_usdc.unwrap_or(u64::MAX));
+        Self { tiers }
+    }
+
+    /// Requirement for a swap of `usdc_amount`. Amounts above every tier
+    /// get the strictest one; with no tiers, 10 Monero confirmations and
+    /// `confirmed` commitment.
+    pub fn requirement(&self, usdc_amount: u64) -> ConfirmationRequirement {
+        let tier = self.tiers
+            .iter()
+            .find(|tier| usdc_amount <= tier.max_usdc.unwrap_or(u64::MAX))
+            .or_else(|| self.tiers.last());
+
+        match tier {
+            Some(tier) => ConfirmationRequirement {
+                monero_confirmations: tier.monero_confirmations,
+                solana_commitment: tier.solana_commitment.clone(),
+                solana_slot_depth: tier.solana_slot_depth.unwrap_or(0),
+            },
+            None => ConfirmationRequirement {
+                monero_confirmations: 10,
+                solana_commitment: "confirmed".to_string(),
+                solana_slot_depth: 0,
+            },
+        }
+    }
+
+    /// The least strict requirement, for observations not tied to a swap.
+    pub fn baseline(&self) -> ConfirmationRequirement {
+        self.requirement(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    const USDC: u64 = 1_000_000;
+
+    fn tier(max_usdc: Option<u64>, monero_confirmations: u64, solana_commitment: &str, solana_slot_depth: u64) -> ConfirmationTier {
+        ConfirmationTier {
+            max_usdc,
+            monero_confirmations,
+            solana_commitment: solana_commitment.to_string(),
+            solana_slot_depth: Some(solana_slot_depth),
+        }
+    }
+
+    fn requirement(monero_confirmations: u64, solana_commitment: &str, solana_slot_depth: u64) -> ConfirmationRequirement {
+        ConfirmationRequirement {
+            monero_confirmations,
+            solana_commitment: solana_commitment.to_string(),
+            solana_slot_depth,
+        }
+    }
+
+    #[test]
+    fn tiers_apply_up_to_and_including_their_edge() {
+        // Listed out of order; the policy sorts them by size
+        let policy = ConfirmationPolicy::new(Some(&ConfirmationConfig {
+            tiers: vec![
+                tier(None, 30, "finalized", 64),
+                tier(Some(100 * USDC), 5, "processed", 0),
+                tier(Some(1_000 * USDC), 15, "confirmed", 16),
+            ],
+        }));
+
+        let small = requirement(5, "processed", 0);
+        let medium = requirement(15, "confirmed", 16);
+        let large = requirement(30, "finalized", 64);
+        let table = [
+            (0, &small),
+            (1, &small),
+            (100 * USDC - 1, &small),
+            (100 * USDC, &small),
+            (100 * USDC + 1, &medium),
+            (1_000 * USDC, &medium),
+            (1_000 * USDC + 1, &large),
+            (u64::MAX, &large),
+        ];
+        for (usdc_amount, expected) in table {
+            assert_eq!(&policy.requirement(usdc_amount), expected, "{} base units", usdc_amount);
+        }
+        assert_eq!(policy.baseline(), small);
+    }
+
+    #[test]
+    fn amounts_above_every_bounded_tier_get_the_strictest() {
+        let policy = ConfirmationPolicy::new(Some(&ConfirmationConfig {
+            tiers: vec![tier(Some(500 * USDC), 20, "finalized", 32), tier(Some(50 * USDC), 10, "confirmed", 0)],
+        }));
+
+        assert_eq!(policy.requirement(50 * USDC), requirement(10, "confirmed", 0));
+        assert_eq!(policy.requirement(500 * USDC), requirement(20, "finalized", 32));
+        assert_eq!(policy.requirement(500 * USDC + 1), requirement(20, "finalized", 32));
+    }
+
+    #[test]
+    fn default_config_switches_tier_above_1000_usdc() {
+        let policy = ConfirmationPolicy::new(AppConfig::default().confirmations.as_ref());
+        assert_eq!(policy.requirement(1_000 * USDC), requirement(10, "confirmed", 0));
+        assert_eq!(policy.requirement(1_000 * USDC + 1), requirement(20, "finalized", 32));
+    }
+
+    #[test]
+    fn no_tiers_fall_back_to_ten_monero_confirmations() {
+        let mut unset_depth = tier(None, 12, "confirmed", 0);
+        unset_depth.solana_slot_depth = None;
+        let policy = ConfirmationPolicy::new(Some(&ConfirmationConfig { tiers: vec![unset_depth] }));
+        assert_eq!(policy.requirement(u64::MAX), requirement(12, "confirmed", 0));
+
+        let fallback = requirement(10, "confirmed", 0);
+        assert_eq!(ConfirmationPolicy::new(None).requirement(0), fallback);
+        assert_eq!(ConfirmationPolicy::new(None).requirement(u64::MAX), fallback);
+        assert_eq!(ConfirmationPolicy::new(Some(&ConfirmationConfig { tiers: Vec::new() })).baseline(), fallback);
+    }
+}
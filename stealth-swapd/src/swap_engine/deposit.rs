@@ -0,0 +1,204 @@
+use crate::clients::monero::IncomingTransfer;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How long after expiry a swap's subaddress is still watched, so late
+/// deposits are recorded rather than silently absorbed.
+pub const LATE_DEPOSIT_WATCH_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepositState {
+    /// Enough XMR has arrived but is not yet past the confirmation threshold.
+    Pending,
+    /// Less than the quoted amount has arrived before expiry.
+    Underpaid,
+    /// The quoted amount, confirmed.
+    Confirmed,
+    /// More than the quoted amount, confirmed. The excess is Alice's.
+    Overpaid,
+    /// XMR arrived only after the swap expired.
+    Late,
+}
+
+/// Alice's XMR deposit to a swap's subaddress, as last observed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XmrDeposit {
+    pub state: DepositState,
+    /// Transfers that count towards the swap, oldest first.
+    pub txids: Vec<String>,
+    /// Sum of on-time transfers.
+    pub amount: u64,
+    /// Amount received after expiry, which never counts towards the swap.
+    pub late_amount: u64,
+    pub late_txids: Vec<String>,
+    /// Confirmations of the least-confirmed counted transfer.
+    pub confirmations: u64,
+    pub required_confirmations: u64,
+}
+
+impl XmrDeposit {
+    /// Classify the transfers seen on a swap's subaddress against the
+    /// quoted amount. Pool transfers flagged as double spends are ignored.
+    pub fn assess(
+        transfers: &[IncomingTransfer],
+        expected: u64,
+        expires_at: DateTime<Utc>,
+        required_confirmations: u64,
+    ) -> Option<Self> {
+        let mut transfers: Vec<&IncomingTransfer> = transfers
+            .iter()
+            .filter(|t| !(t.in_pool && t.double_spend_seen))
+            .collect();
+        if transfers.is_empty() {
+            return None;
+        }
+        transfers.sort_by_key(|t| t.timestamp);
+
+        let (on_time, late): (Vec<_>, Vec<_>) = transfers
+            .into_iter()
+            .partition(|t| t.timestamp <= expires_at.timestamp());
+
+        let amount = on_time.iter().fold(0u64, |sum, t| sum.saturating_add(t.amount));
+        let late_amount = late.iter().fold(0u64, |sum, t| sum.saturating_add(t.amount));
+        let confirmations = on_time.iter().map(|t| t.confirmations).min().unwrap_or(0);
+
+        let state = if on_time.is_empty() {
+            DepositState::Late
+        } else if amount < expected {
+            DepositState::Underpaid
+        } else if confirmations < required_confirmations {
+            DepositState::Pending
+        } else if amount > expected {
+            DepositState::Overpaid
+        } else {
+            DepositState::Confirmed
+        };
+
+        Some(Self {
+            state,
+            txids: on_time.iter().map(|t| t.txid.clone()).collect(),
+            amount,
+            late_amount,
+            late_txids: late.iter().map(|t| t.txid.clone()).collect(),
+            confirmations,
+            required_confirmations,
+        })
+    }
+
+    /// Whether the swap may advance on this deposit.
+    pub fn is_settled(&self) -> bool {
+        matches!(self.state, DepositState::Confirmed | DepositState::Overpaid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    const XMR: u64 = 1_000_000_000_000;
+    const REQUIRED: u64 = 10;
+
+    fn expires_at() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    /// A transfer to the swap's subaddress, `minutes` after (or before) expiry.
+    fn transfer(txid: &str, amount: u64, confirmations: u64, minutes: i64) -> IncomingTransfer {
+        IncomingTransfer {
+            txid: txid.to_string(),
+            amount,
+            confirmations,
+            height: if confirmations == 0 { 0 } else { 3_000_000 },
+            subaddr_index: 7,
+            in_pool: confirmations == 0,
+            timestamp: (expires_at() + Duration::minutes(minutes)).timestamp(),
+            double_spend_seen: false,
+        }
+    }
+
+    #[test]
+    fn deposits_are_classified_against_the_quoted_amount() {
+        let table: Vec<(&str, Vec<IncomingTransfer>, DepositState, u64, u64)> = vec![
+            ("exact", vec![transfer("a", XMR, 10, -60)], DepositState::Confirmed, XMR, 10),
+            ("under", vec![transfer("a", XMR - 1, 12, -60)], DepositState::Underpaid, XMR - 1, 12),
+            ("over", vec![transfer("a", XMR + 1, 12, -60)], DepositState::Overpaid, XMR + 1, 12),
+            ("unconfirmed", vec![transfer("a", XMR, 9, -60)], DepositState::Pending, XMR, 9),
+            ("in the pool", vec![transfer("a", XMR, 0, -60)], DepositState::Pending, XMR, 0),
+            // Outputs summed, the least confirmed one holding the swap back
+            (
+                "multi-output",
+                vec![transfer("b", XMR / 2, 10, -30), transfer("a", XMR / 2, 15, -90)],
+                DepositState::Confirmed,
+                XMR,
+                10,
+            ),
+            (
+                "multi-output unconfirmed",
+                vec![transfer("a", XMR / 2, 15, -90), transfer("b", XMR / 2, 3, -30)],
+                DepositState::Pending,
+                XMR,
+                3,
+            ),
+            (
+                "multi-output under",
+                vec![transfer("a", XMR / 4, 15, -90), transfer("b", XMR / 2, 15, -30)],
+                DepositState::Underpaid,
+                XMR * 3 / 4,
+                15,
+            ),
+        ];
+
+        for (case, transfers, state, amount, confirmations) in table {
+            let deposit = XmrDeposit::assess(&transfers, XMR, expires_at(), REQUIRED).unwrap();
+            assert_eq!(deposit.state, state, "{}", case);
+            assert_eq!(deposit.amount, amount, "{}", case);
+            assert_eq!(deposit.confirmations, confirmations, "{}", case);
+            assert_eq!(deposit.required_confirmations, REQUIRED, "{}", case);
+            assert_eq!(deposit.late_amount, 0, "{}", case);
+            assert_eq!(
+                deposit.is_settled(),
+                matches!(state, DepositState::Confirmed | DepositState::Overpaid),
+                "{}",
+                case
+            );
+        }
+    }
+
+    #[test]
+    fn multi_output_txids_are_listed_oldest_first() {
+        let transfers = [transfer("b", XMR / 2, 10, -30), transfer("a", XMR / 2, 10, -90)];
+        let deposit = XmrDeposit::assess(&transfers, XMR, expires_at(), REQUIRED).unwrap();
+        assert_eq!(deposit.txids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn transfers_after_expiry_never_count() {
+        let transfers = [transfer("a", XMR / 2, 10, -60), transfer("b", XMR, 10, 1)];
+        let deposit = XmrDeposit::assess(&transfers, XMR, expires_at(), REQUIRED).unwrap();
+        assert_eq!(deposit.state, DepositState::Underpaid);
+        assert_eq!((deposit.amount, deposit.late_amount), (XMR / 2, XMR));
+        assert_eq!(deposit.late_txids, vec!["b"]);
+
+        let deposit = XmrDeposit::assess(&transfers[1..], XMR, expires_at(), REQUIRED).unwrap();
+        assert_eq!(deposit.state, DepositState::Late);
+        assert!(!deposit.is_settled());
+
+        // Exactly at expiry is still on time
+        let deposit = XmrDeposit::assess(&[transfer("a", XMR, 10, 0)], XMR, expires_at(), REQUIRED).unwrap();
+        assert_eq!(deposit.state, DepositState::Confirmed);
+    }
+
+    #[test]
+    fn double_spent_pool_transfers_are_ignored() {
+        let mut double_spend = transfer("a", XMR, 0, -60);
+        double_spend.double_spend_seen = true;
+        assert!(XmrDeposit::assess(&[double_spend.clone()], XMR, expires_at(), REQUIRED).is_none());
+        assert!(XmrDeposit::assess(&[], XMR, expires_at(), REQUIRED).is_none());
+
+        let deposit = XmrDeposit::assess(&[double_spend, transfer("b", XMR, 10, -30)], XMR, expires_at(), REQUIRED).unwrap();
+        assert_eq!(deposit.state, DepositState::Confirmed);
+        assert_eq!(deposit.txids, vec!["b"]);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::clients::MoneroClient;
+use crate::clients::monero::MoneroRpcError;
+use crate::swap_engine::Direction;
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha256};
+use tiny_keccak::{Hasher, Keccak};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DestinationError {
+    #[error("Missing destination: {0}")]
+    Missing(&'static str),
+
+    #[error("Invalid Monero address")]
+    InvalidMoneroAddress,
+
+    #[error("Monero payouts need a standard address, not a subaddress or integrated address")]
+    NotStandardMoneroAddress,
+
+    #[error("Monero address is for {actual}, expected {expected}")]
+    WrongMoneroNetwork { expected: String, actual: String },
+
+    #[error("Invalid Solana address: must be a base58 32-byte public key")]
+    InvalidSolanaAddress,
+
+    #[error("Destination does not match the quoted destination")]
+    Mismatch,
+
+    #[error("Failed to validate destination: {0}")]
+    Rpc(#[from] MoneroRpcError),
+}
+
+/// Where Alice wants to be paid: her Monero address when she buys XMR, her
+/// Solana wallet when she buys USDC.
+pub fn destination_kind(direction: Direction) -> &'static str {
+    match direction {
+        Direction::UsdcToXmr => "Monero address",
+        Direction::XmrToUsdc => "Solana address",
+    }
+}
+
+pub fn validate_solana_address(address: &str) -> Result<[u8; 32], DestinationError> {
+    bs58::decode(address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(DestinationError::InvalidSolanaAddress)
+}
+
+/// Validate through monero-wallet-rpc, which knows every address format,
+/// and reject addresses for a different network than the wallet's.
+pub async fn validate_monero_address(
+    monero: &MoneroClient,
+    address: &str,
+    network: &str,
+) -> Result<(), DestinationError> {
+    let validation = monero.validate_address(address).await?;
+    if !validation.valid {
+        return Err(DestinationError::InvalidMoneroAddress);
+    }
+    if validation.nettype != network {
+        return Err(DestinationError::WrongMoneroNetwork {
+            expected: network.to_string(),
+            actual: validation.nettype,
+        });
+    }
+    Ok(())
+}
+
+/// Network bytes of standard addresses on mainnet, testnet and stagenet.
+/// Subaddresses (42, 63, 36) and integrated addresses (19, 54, 25) are
+/// left out.
+const STANDARD_NETWORK_BYTES: [u8; 3] = [18, 53, 24];
+
+/// Reject anything but a standard address, the only kind whose spend key
+/// [`payout_address`] can tag: a subaddress's keys are derived from the
+/// wallet's, and an integrated address carries a payment id.
+pub fn validate_standard_address(address: &str) -> Result<(), DestinationError> {
+    let bytes = decode_monero_address(address)?;
+    if bytes.len() != 65 || !STANDARD_NETWORK_BYTES.contains(&bytes[0]) {
+        return Err(DestinationError::NotStandardMoneroAddress);
+    }
+    Ok(())
+}
+
+pub async fn validate_destination(
+    monero: &MoneroClient,
+    network: &str,
+    direction: Direction,
+    destination: &str,
+) -> Result<(), DestinationError> {
+    match direction {
+        Direction::UsdcToXmr => {
+            validate_standard_address(destination)?;
+            validate_monero_address(monero, destination, network).await
+        }
+        Direction::XmrToUsdc => validate_solana_address(destination).map(|_| ()),
+    }
+}
+
+/// Per-swap tag `H(destination || swap_id)` of protocol.md §4.1 and §5. It
+/// derives Alice's payout address and labels the swap's wallet sub-address.
+pub fn subaddress_tag(destination: &str, swap_id: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(destination.as_bytes());
+    hasher.update(swap_id);
+    hasher.finalize().into()
+}
+
+/// `A_sub = A + H(A || swap_id) * G` (protocol.md §4.1): Alice's Monero
+/// address with the swap's tag added to its public spend key. Her seed's
+/// spend key plus the tag spends it; the view key and network are hers.
+pub fn payout_address(destination: &str, swap_id: &[u8; 32]) -> Result<String, DestinationError> {
+    validate_standard_address(destination)?;
+    let mut bytes = decode_monero_address(destination)?;
+    let spend = CompressedEdwardsY(bytes[1..33].try_into().unwrap_or_default())
+        .decompress()
+        .ok_or(DestinationError::InvalidMoneroAddress)?;
+    let tag = Scalar::from_bytes_mod_order(subaddress_tag(destination, swap_id));
+    let spend = spend + EdwardsPoint::mul_base(&tag);
+    bytes[1..33].copy_from_slice(spend.compress().as_bytes());
+    Ok(encode_monero_address(&bytes))
+}
+
+/// Monero's base58 encodes 8-byte blocks as 11 characters; a shorter final
+/// block of `n` bytes takes `ENCODED_BLOCK_SIZES[n]` characters.
+const ENCODED_BLOCK_SIZES: [usize; 9] = [0, 2, 3, 5, 6, 7, 9, 10, 11];
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Network byte, spend key, view key and any payment id, checksum verified.
+/// Every Monero network byte is below 0x80, so its varint is one byte.
+fn decode_monero_address(address: &str) -> Result<Vec<u8>, DestinationError> {
+    let mut bytes = Vec::new();
+    for block in address.as_bytes().chunks(11) {
+        let size = ENCODED_BLOCK_SIZES
+            .iter()
+            .position(|&encoded| encoded == block.len())
+            .ok_or(DestinationError::InvalidMoneroAddress)?;
+        let mut value: u128 = 0;
+        for c in block {
+            let digit = BASE58_ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or(DestinationError::InvalidMoneroAddress)?;
+            value = value * 58 + digit as u128;
+        }
+        if value >> (8 * size) != 0 {
+            return Err(DestinationError::InvalidMoneroAddress);
+        }
+        bytes.extend_from_slice(&value.to_be_bytes()[16 - size..]);
+    }
+
+    if bytes.len() < 69 || bytes[0] >= 0x80 {
+        return Err(DestinationError::InvalidMoneroAddress);
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - 4);
+    if keccak256(body)[..4] != *checksum {
+        return Err(DestinationError::InvalidMoneroAddress);
+    }
+    bytes.truncate(bytes.len() - 4);
+    Ok(bytes)
+}
+
+fn encode_monero_address(body: &[u8]) -> String {
+    let mut bytes = body.to_vec();
+    bytes.extend_from_slice(&keccak256(body)[..4]);
+
+    let mut address = String::new();
+    for block in bytes.chunks(8) {
+        let mut value = block.iter().fold(0u64, |value, &b| value << 8 | b as u64);
+        let mut encoded = vec![BASE58_ALPHABET[0]; ENCODED_BLOCK_SIZES[block.len()]];
+        for c in encoded.iter_mut().rev() {
+            *c = BASE58_ALPHABET[(value % 58) as usize];
+            value /= 58;
+        }
+        address.extend(encoded.into_iter().map(char::from));
+    }
+    address
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut keccak = Keccak::v256();
+    keccak.update(data);
+    let mut output = [0u8; 32];
+    keccak.finalize(&mut output);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Monero General Fund's mainnet address.
+    const ADDRESS: &str = "44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3A";
+
+    #[test]
+    fn monero_address_round_trips_and_checks_its_checksum() {
+        let body = decode_monero_address(ADDRESS).unwrap();
+        assert_eq!(body.len(), 65);
+        assert_eq!(body[0], 18);
+        assert_eq!(encode_monero_address(&body), ADDRESS);
+
+        let mut corrupted = ADDRESS.to_string();
+        corrupted.replace_range(10..11, if &ADDRESS[10..11] == "A" { "B" } else { "A" });
+        assert!(decode_monero_address(&corrupted).is_err());
+        assert!(decode_monero_address(&ADDRESS[..94]).is_err());
+        assert!(decode_monero_address("0OIl").is_err());
+    }
+
+    #[test]
+    fn payout_address_adds_the_swap_tag_to_the_spend_key() {
+        let swap_id = [7; 32];
+        let derived = payout_address(ADDRESS, &swap_id).unwrap();
+        let (original, body) = (decode_monero_address(ADDRESS).unwrap(), decode_monero_address(&derived).unwrap());
+
+        // Same network and view key, spend key moved by exactly H(A || swap_id) * G
+        assert_eq!(body[0], original[0]);
+        assert_eq!(body[33..], original[33..]);
+        let spend = |bytes: &[u8]| CompressedEdwardsY(bytes[1..33].try_into().unwrap()).decompress().unwrap();
+        let tag = Scalar::from_bytes_mod_order(subaddress_tag(ADDRESS, &swap_id));
+        assert_eq!(spend(&body), spend(&original) + EdwardsPoint::mul_base(&tag));
+
+        assert_eq!(payout_address(ADDRESS, &swap_id).unwrap(), derived);
+        assert_ne!(payout_address(ADDRESS, &[8; 32]).unwrap(), derived);
+        assert!(payout_address("not an address", &swap_id).is_err());
+    }
+
+    /// The test address re-encoded under another network byte, with `extra`
+    /// bytes (a payment id) after the keys.
+    fn reencoded(network_byte: u8, extra: &[u8]) -> String {
+        let mut body = decode_monero_address(ADDRESS).unwrap();
+        body[0] = network_byte;
+        body.extend_from_slice(extra);
+        encode_monero_address(&body)
+    }
+
+    #[test]
+    fn only_standard_addresses_take_payouts() {
+        assert!(validate_standard_address(ADDRESS).is_ok());
+        for network_byte in [53, 24] {
+            assert!(validate_standard_address(&reencoded(network_byte, &[])).is_ok());
+        }
+
+        // Subaddresses on mainnet, testnet and stagenet
+        for network_byte in [42, 63, 36] {
+            let subaddress = reencoded(network_byte, &[]);
+            assert!(matches!(validate_standard_address(&subaddress), Err(DestinationError::NotStandardMoneroAddress)));
+            assert!(matches!(payout_address(&subaddress, &[7; 32]), Err(DestinationError::NotStandardMoneroAddress)));
+        }
+
+        // Integrated addresses, with their 8-byte payment id
+        for network_byte in [19, 54, 25] {
+            let integrated = reencoded(network_byte, &[9; 8]);
+            assert_eq!(integrated.len(), 106);
+            assert!(matches!(validate_standard_address(&integrated), Err(DestinationError::NotStandardMoneroAddress)));
+            assert!(matches!(payout_address(&integrated, &[7; 32]), Err(DestinationError::NotStandardMoneroAddress)));
+        }
+
+        // A payment id tacked onto a standard network byte is no better
+        assert!(validate_standard_address(&reencoded(18, &[9; 8])).is_err());
+        assert!(matches!(validate_standard_address("not an address"), Err(DestinationError::InvalidMoneroAddress)));
+    }
+}
@@ -1,23 +1,67 @@
-use crate::config::AppConfig;
-use crate::clients::{SolanaClient, MoneroClient};
+use crate::config::{AppConfig, EngineConfig};
+use crate::chain::{self, Chain, ChainView, Inclusion, MoneroChain, Observation, ObservationStore, SolanaChain, Verdict, SOLANA_FINALITY_SLOTS};
+use crate::chain::{ProgramEvent, SubscriptionHandle};
+use crate::clients::{SolanaClient, MoneroClient, MonerodClient};
+use crate::clients::solana::OnchainSwapInfo;
+use crate::clients::solana_program::{sub_address_field, RedeemProof};
+use crate::clients::solana_tx::Instruction;
 use crate::metrics::MetricsCollector;
-use crate::security::KeyDerivation;
-use crate::swap_engine::{SwapTrade, SwapState, Direction, QuoteRequest, QuoteResponse};
+use crate::quoting::{build_strategy, InventoryManager, InventoryPosition, OutputManager, QuoteContext, QuoteDecision, QuoteManager, QuoteStrategy, ReferencePrice};
+use crate::security::{self, KeyDerivation, QuoteTerms};
+use crate::swap_engine::{SwapTrade, SwapState, Direction, QuoteRequest, QuoteResponse, DestinationError};
+use crate::swap_engine::{AcceptRequest, AcceptError, SwapExpectations, refunds_to_bob, usdc_locked, verify_accept_request, verify_onchain_swap};
+use crate::swap_engine::{XmrDeposit, LATE_DEPOSIT_WATCH_HOURS};
+use crate::swap_engine::{ConfirmationPolicy, ConfirmationRequirement, SwapConfirmations};
+use crate::swap_engine::{ErrorBudget, RetryDecision, RetryPolicy};
+use crate::swap_engine::{Clock, Scheduler, SystemClock};
+use crate::swap_engine::{EngineHealth, FeePolicy, WalletSync};
+use crate::swap_engine::{reconcile_payout, stray_refund_key, Payout, PayoutOutbox, PayoutState, Reconcile};
+use crate::swap_engine::{PriorityFeePolicy, RecoveryBundle, SolanaTxError, SolanaTxKind, SolanaTxManager, SolanaTxState};
+use crate::swap_engine::{StrayAuditEntry, StrayDeposit, StrayDepositError, StrayDepositStore, StrayReason, STRAY_RESCAN_BLOCKS};
+use crate::swap_engine::{is_unmatched, stray_parts};
+use crate::clients::monero::{BuiltTransfer, IncomingTransfer, WalletRefresh};
+use crate::swap_engine::{SwapSecretStore, SwapStore};
+use crate::swap_engine::destination::{self, destination_kind, subaddress_tag};
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use chrono::{Utc, Duration};
+use chrono::{DateTime, Utc, Duration, Timelike};
 use tokio::sync::RwLock;
+use sqlx::SqlitePool;
 use anyhow::Result;
 
+/// How far past acceptance a swap's expiry may lie (protocol.md §6 Monero
+/// timeout). Accepted inventory is bound to the swap, not to this window,
+/// and stays held until the swap ends.
+const SETTLEMENT_WINDOW_HOURS: i64 = 48;
+
 #[derive(Clone)]
 pub struct SwapEngine {
     config: AppConfig,
     solana_client: SolanaClient,
     monero_client: std::sync::Arc<MoneroClient>,
     metrics: Arc<MetricsCollector>,
+    inventory: InventoryManager,
+    outputs: OutputManager,
+    fee_policy: FeePolicy,
+    outbox: PayoutOutbox,
+    payout_lock: Arc<tokio::sync::Mutex<()>>,
+    transactions: SolanaTxManager,
+    strategy: Arc<dyn QuoteStrategy>,
     active_swaps: Arc<RwLock<HashMap<[u8; 32], SwapTrade>>>,
-    quotes: Arc<RwLock<HashMap<uuid::Uuid, SwapTrade>>>,
+    quotes: QuoteManager,
+    swaps: SwapStore,
+    secrets: SwapSecretStore,
+    strays: StrayDepositStore,
+    confirmation_policy: ConfirmationPolicy,
+    observations: ObservationStore,
+    monero_chain: MoneroChain,
+    solana_chain: SolanaChain,
+    error_budget: ErrorBudget,
+    scheduler: Arc<Scheduler>,
+    subscriptions: Option<SubscriptionHandle>,
+    daemon: Option<MonerodClient>,
+    wallet_sync: Arc<RwLock<Option<WalletSync>>>,
 }
 
 impl SwapEngine {
@@ -26,148 +70,1124 @@ impl SwapEngine {
         solana_client: SolanaClient,
         monero_client: MoneroClient,
         metrics: MetricsCollector,
+        db: SqlitePool,
     ) -> Result<Self> {
+        let inventory = InventoryManager::new(&config.quoting);
+        let monero_client = std::sync::Arc::new(monero_client);
+        let daemon = MonerodClient::from_config(&config.monero)?;
         let client = Self {
+            quotes: QuoteManager::new(&config.quoting, db.clone(), inventory.clone()),
+            swaps: SwapStore::new(db.clone()),
+            secrets: SwapSecretStore::new(db.clone(), config.get_presign_key()),
+            strays: StrayDepositStore::new(db.clone()),
+            outbox: PayoutOutbox::new(db.clone()),
+            payout_lock: Arc::new(tokio::sync::Mutex::new(())),
+            transactions: SolanaTxManager::new(
+                solana_client.clone(),
+                db.clone(),
+                config.get_presign_key(),
+                PriorityFeePolicy::new(config.solana.priority_fees.as_ref(), config.relayer.max_gas_lamports),
+            ),
+            observations: ObservationStore::new(db),
+            confirmation_policy: ConfirmationPolicy::new(config.confirmations.as_ref()),
+            error_budget: ErrorBudget::new(RetryPolicy::new(config.engine.as_ref())),
+            scheduler: Arc::new(Scheduler::new(Arc::new(SystemClock))),
+            subscriptions: None,
+            monero_chain: MoneroChain::new(monero_client.clone(), daemon.clone()),
+            daemon,
+            wallet_sync: Arc::new(RwLock::new(None)),
+            solana_chain: SolanaChain::new(solana_client.clone()),
+            inventory,
+            outputs: OutputManager::new(config.monero.outputs.as_ref()),
+            fee_policy: FeePolicy::new(config.monero.fees.as_ref()),
+            strategy: build_strategy(&config.quoting)?,
             config,
             solana_client,
-            monero_client: std::sync::Arc::new(monero_client),
+            monero_client,
             metrics: Arc::new(metrics),
             active_swaps: Arc::new(RwLock::new(HashMap::new())),
-            quotes: Arc::new(RwLock::new(HashMap::new())),
         };
 
         // Load saved swaps from database if they exist
         client.load_persisted_swaps().await?;
+        client.reconcile_stray_refunds().await?;
+        let restored = client.quotes.load().await?;
+        tracing::info!("Restored {} outstanding quotes", restored);
         
         Ok(client)
     }
 
-    pub async fn generate_quote(&self, request: QuoteRequest) -> Result<QuoteResponse> {
+    /// Drive scheduling from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.scheduler = Arc::new(Scheduler::new(clock));
+        self
+    }
+
+    /// Follow live swaps' accounts through a Solana program subscriber.
+    pub fn with_subscriptions(mut self, subscriptions: SubscriptionHandle) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+
+    pub async fn generate_quote(&self, request: QuoteRequest, client_id: &str) -> Result<QuoteResponse> {
+        if request.direction == Direction::UsdcToXmr && !self.secrets.is_enabled() {
+            return Err(anyhow::anyhow!("USDC→XMR swaps need solana.presign_key_env set to store the secret that redeems their USDC"));
+        }
+        if request.direction == Direction::XmrToUsdc {
+            return Err(AcceptError::EscrowUnsupported.into());
+        }
         self.validate_trade_parameters(request.direction, request.usdc_amount, request.xmr_amount)?;
+        self.quotes.check_capacity(client_id).await?;
+        self.ensure_wallet_synced().await?;
+        if let Some(destination) = &request.destination {
+            self.validate_destination(request.direction, destination).await?;
+        }
         
         let quote_id = uuid::Uuid::new_v4();
-        let secret_hash = KeyDerivation::derive_secret_hash(&KeyDerivation::generate_adaptor_secret());
-        
-        let (_monero_address, monero_sub_address) = self.monero_client
-            .create_subaddress(&format!("swap_{}", quote_id))
+        // Whole seconds, so the signed expiry and the stored one agree
+        let expires_at = Utc::now() + self.quotes.ttl();
+        let expires_at = expires_at.with_nanosecond(0).unwrap_or(expires_at);
+
+        if self.inventory.is_stale().await {
+            self.refresh_inventory().await?;
+        }
+
+        let position = InventoryPosition::from(&self.inventory.snapshot().await);
+        let reference = ReferencePrice::from(&self.config.quoting);
+        let priced = match self.strategy.quote(&QuoteContext::new(&request, position, reference))? {
+            QuoteDecision::Quote(priced) => priced,
+            QuoteDecision::Reject(reason) => {
+                return Err(anyhow::anyhow!("Quote rejected by {} strategy: {}", self.strategy.name(), reason));
+            }
+        };
+        self.validate_trade_parameters(request.direction, priced.usdc_amount, priced.xmr_amount)?;
+
+        let swap_id = KeyDerivation::generate_swap_id();
+        let payout_address = match (request.direction, &request.destination) {
+            (Direction::UsdcToXmr, Some(destination)) => Some(destination::payout_address(destination, &swap_id)?),
+            _ => None,
+        };
+
+        let reservation = self.inventory
+            .reserve(quote_id, request.direction, priced.usdc_amount, priced.xmr_amount, expires_at)
             .await?;
+
+        let secret = KeyDerivation::generate_adaptor_secret();
+        let secret_hash = KeyDerivation::derive_secret_hash(&secret);
+        if self.secrets.is_enabled() {
+            if let Err(e) = self.secrets.insert(swap_id, &secret).await {
+                self.inventory.release(quote_id).await;
+                return Err(e);
+            }
+        }
+
+        // Label the sub-address with the tag of Alice's destination when she
+        // has given one, the tag that derives her payout address
+        let label = match &request.destination {
+            Some(destination) => format!("swap_{}_{}", quote_id, hex::encode(&subaddress_tag(destination, &swap_id)[..8])),
+            None => format!("swap_{}", quote_id),
+        };
         
-        let expires_at = Utc::now() + Duration::minutes(30);
+        let subaddress = match self.monero_client
+            .create_subaddress(&label)
+            .await
+        {
+            Ok(subaddress) => subaddress,
+            Err(e) => {
+                self.inventory.release(quote_id).await;
+                return Err(e.into());
+            }
+        };
         
         let quote = SwapTrade {
-            swap_id: KeyDerivation::generate_swap_id(),
+            swap_id,
             quote_id,
             direction: request.direction,
-            usdc_amount: request.usdc_amount,
-            xmr_amount: request.xmr_amount,
+            usdc_amount: reservation.usdc_amount,
+            xmr_amount: reservation.xmr_amount,
             secret_hash,
-            monero_sub_address,
+            monero_sub_address: subaddress.address,
+            monero_subaddr_index: Some(subaddress.index),
+            destination: request.destination.clone(),
             alice_solana: None,
             state: SwapState::Quoted,
             created_at: Utc::now(),
             expires_at,
             monero_txid: None,
+            monero_fee: None,
             solana_signature: None,
             failure_reason: None,
+            xmr_deposit: None,
+            confirmations: None,
         };
 
-        {
-            let mut quotes = self.quotes.write().await;
-            quotes.insert(quote_id, quote.clone());
+        if let Err(e) = self.quotes.insert(quote.clone(), client_id).await {
+            self.inventory.release(quote_id).await;
+            return Err(e);
         }
 
         self.metrics.increment_quotes_generated();
 
+        let signature = security::sign_quote(self.solana_client.keypair(), &Self::quote_terms(&quote));
+
         Ok(QuoteResponse {
             quote_id,
+            swap_id,
             expires_at,
             usdc_amount: quote.usdc_amount,
             xmr_amount: quote.xmr_amount,
             secret_hash,
+            onchain_sub_address: sub_address_field(&quote.monero_sub_address),
             monero_sub_address: quote.monero_sub_address,
-            solana_address: self.solana_client.pubkey().to_string(),
+            payout_address,
+            destination: quote.destination.clone(),
+            solana_address: self.solana_client.pubkey(),
+            signature: hex::encode(signature),
         })
     }
 
-    pub async fn accept_swap(&self, quote_id: uuid::Uuid, alice_solana: Option<String>) -> Result<[u8; 32]> {
-        let mut quote = {
-            let mut quotes = self.quotes.write().await;
-            quotes.remove(&quote_id)
-                .ok_or_else(|| anyhow::anyhow!("Quote not found"))?
+    /// Accept a quote once Alice's `Swap` PDA is on-chain and matches it.
+    /// Waits up to `quoting.accept_wait_seconds` for the account to appear;
+    /// the quote stays outstanding if it does not, so Alice can retry.
+    pub async fn accept_swap(&self, request: AcceptRequest) -> Result<[u8; 32]> {
+        let quote = self.quotes.get(request.quote_id).await?;
+        security::verify_quote_encoded(
+            &self.solana_client.pubkey(),
+            &Self::quote_terms(&quote),
+            &request.quote_signature,
+        ).map_err(|e| anyhow::anyhow!("Quote was not signed by this daemon: {}", e))?;
+        verify_accept_request(&quote, &request)?;
+
+        let destination = match (&quote.destination, &request.destination) {
+            (Some(quoted), Some(given)) if quoted != given => return Err(DestinationError::Mismatch.into()),
+            (Some(quoted), _) => quoted.clone(),
+            (None, Some(given)) => {
+                self.validate_destination(quote.direction, given).await?;
+                given.clone()
+            }
+            (None, None) => return Err(DestinationError::Missing(destination_kind(quote.direction)).into()),
         };
 
-        if Utc::now() > quote.expires_at {
-            return Err(anyhow::anyhow!("Quote expired"));
+        // Bob's XMR→USDC escrow can't be made safe with this program, so
+        // those swaps are never accepted
+        if quote.direction == Direction::XmrToUsdc {
+            return Err(AcceptError::EscrowUnsupported.into());
         }
 
-        quote.alice_solana = alice_solana;
-        quote.state = match quote.direction {
-            Direction::UsdcToXmr => SwapState::LockedUsdc,
-            Direction::XmrToUsdc => SwapState::LockedXmr,
+        let required = self.confirmation_policy.requirement(quote.usdc_amount);
+        let (onchain, created) = self.wait_for_onchain_swap(request.swap_id, &required).await?;
+        let bob = self.solana_client.pubkey();
+        verify_onchain_swap(&request, &onchain, &SwapExpectations {
+            bob: &bob,
+            usdc_mint: &self.config.solana.usdc_mint,
+            destination: &destination,
+            now: Utc::now().timestamp(),
+            max_expiry_hours: SETTLEMENT_WINDOW_HOURS,
+        })?;
+
+        // Everything that can fail is looked up before the quote is taken, so
+        // a failed accept leaves it outstanding for Alice to retry
+        let expires_at = DateTime::from_timestamp(onchain.swap.expiry, 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid swap expiry {}", onchain.swap.expiry))?;
+        let creation = match &created {
+            Some((signature, _)) => self
+                .solana_chain
+                .locate(signature)
+                .await?
+                .map(|inclusion| (signature.clone(), inclusion)),
+            None => None,
         };
 
+        let mut quote = self.quotes.take(request.quote_id).await?;
+        quote.destination = Some(destination);
+        quote.alice_solana = Some(request.counterparty_pubkey);
+        quote.confirmations = Some(SwapConfirmations {
+            solana_observed_slot: created.as_ref().map(|(_, slot)| *slot),
+            solana_slot_depth: created.as_ref().map_or(0, |(_, slot)| onchain.slot.saturating_sub(*slot)),
+            ..SwapConfirmations::new(required)
+        });
+        quote.expires_at = expires_at;
+        quote.state = SwapState::LockedUsdc;
+
+        self.inventory.bind(quote.quote_id, quote.swap_id, quote.expires_at).await;
         {
             let mut active_swaps = self.active_swaps.write().await;
             active_swaps.insert(quote.swap_id, quote.clone());
         }
 
+        // The swap is live from here; it is persisted again on its next
+        // change if these writes fail
+        if let Err(e) = self.persist_swap(&quote).await {
+            tracing::warn!("Failed to persist accepted swap {}: {}", hex::encode(quote.swap_id), e);
+        }
+        self.presign_escrow_refund(quote.swap_id, &onchain).await;
+        match &creation {
+            Some((signature, inclusion)) => {
+                if let Err(e) = self.observations
+                    .record(quote.swap_id, Chain::Solana, signature, inclusion, quote.state, SwapState::Failed)
+                    .await
+                {
+                    tracing::warn!("Failed to record creation of swap {}: {}", hex::encode(quote.swap_id), e);
+                }
+            }
+            None => tracing::warn!("Creation of swap {} is not in a block", hex::encode(quote.swap_id)),
+        }
+        self.scheduler.wake(quote.swap_id);
+        if let Some(subscriptions) = &self.subscriptions {
+            subscriptions.watch(quote.swap_id);
+        }
+
         Ok(quote.swap_id)
     }
 
+    /// Wait for the swap account to exist at the required commitment, with
+    /// the transaction that created it the required slot depth deep.
+    /// Returns it with that transaction's signature and slot, if the node
+    /// still has the account's history.
+    async fn wait_for_onchain_swap(
+        &self,
+        swap_id: [u8; 32],
+        required: &ConfirmationRequirement,
+    ) -> Result<(OnchainSwapInfo, Option<(String, u64)>)> {
+        let wait = Duration::seconds(self.config.quoting.accept_wait_seconds.unwrap_or(30) as i64);
+        let deadline = Utc::now() + wait;
+        let mut created = None;
+        loop {
+            let onchain = self.solana_client.get_swap_at(swap_id, &required.solana_commitment).await?;
+            let depth = match onchain {
+                Some(onchain) => {
+                    // The create transaction is the oldest touching the swap account
+                    if created.is_none() {
+                        created = self.solana_client.get_oldest_signature(&onchain.address).await?;
+                    }
+                    let depth = created.as_ref().map_or(0, |(_, slot)| onchain.slot.saturating_sub(*slot));
+                    if depth >= required.solana_slot_depth {
+                        return Ok((onchain, created));
+                    }
+                    Some(depth)
+                }
+                None => None,
+            };
+            if Utc::now() >= deadline {
+                return Err(match depth {
+                    Some(depth) => AcceptError::NotFinal { depth, required: required.solana_slot_depth },
+                    None => AcceptError::SwapNotFound(hex::encode(swap_id)),
+                }.into());
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+    }
+
+    /// The finality a swap must reach before acting on chain observations.
+    fn confirmation_requirement(&self, swap: &SwapTrade) -> ConfirmationRequirement {
+        swap.confirmations
+            .as_ref()
+            .map(|c| c.required.clone())
+            .unwrap_or_else(|| self.confirmation_policy.requirement(swap.usdc_amount))
+    }
+
+    /// Read the swap account at the swap's required commitment and return it
+    /// once the transaction that made `condition` hold is the required slot
+    /// depth deep. Progress is recorded on the swap; it resets if the
+    /// condition stops holding.
+    async fn observe_onchain_swap(
+        &self,
+        swap: &SwapTrade,
+        condition: impl Fn(&OnchainSwapInfo) -> bool,
+    ) -> Result<Option<OnchainSwapInfo>> {
+        let required = self.confirmation_requirement(swap);
+        let onchain = self.solana_client
+            .get_swap_at(swap.swap_id, &required.solana_commitment)
+            .await?
+            .filter(|onchain| condition(onchain));
+
+        // The condition came about in the newest transaction touching the
+        // swap account when it was first seen to hold
+        let observed = self.active_swaps
+            .read()
+            .await
+            .get(&swap.swap_id)
+            .and_then(|tracked| tracked.confirmations.as_ref()?.solana_observed_slot);
+        let included = match (&onchain, observed) {
+            (Some(onchain), None) => Some(
+                self.solana_client
+                    .get_signatures_for_address(&onchain.address, 1)
+                    .await?
+                    .first()
+                    .map_or(onchain.slot, |(_, slot)| *slot),
+            ),
+            _ => observed,
+        };
+
+        let mut active_swaps = self.active_swaps.write().await;
+        let confirmations = match active_swaps.get_mut(&swap.swap_id) {
+            Some(tracked) => tracked.confirmations.get_or_insert_with(|| SwapConfirmations::new(required.clone())),
+            None => return Ok(None),
+        };
+
+        let (onchain, included) = match (onchain, included) {
+            (Some(onchain), Some(included)) => (onchain, included),
+            _ => {
+                confirmations.solana_observed_slot = None;
+                confirmations.solana_slot_depth = 0;
+                return Ok(None);
+            }
+        };
+
+        let included = *confirmations.solana_observed_slot.get_or_insert(included);
+        confirmations.solana_slot_depth = onchain.slot.saturating_sub(included);
+        if confirmations.solana_slot_depth < required.solana_slot_depth {
+            return Ok(None);
+        }
+        Ok(Some(onchain))
+    }
+
     pub async fn get_swap_status(&self, swap_id: [u8; 32]) -> Option<SwapTrade> {
         let active_swaps = self.active_swaps.read().await;
         active_swaps.get(&swap_id).cloned()
     }
 
+    pub fn quote_manager(&self) -> &QuoteManager {
+        &self.quotes
+    }
+
+    pub fn transactions(&self) -> &SolanaTxManager {
+        &self.transactions
+    }
+
+    /// Run the engine loop, restarting it with backoff if it ever stops.
     pub async fn run(&self) -> Result<()> {
+        let mut restarts = 0;
         loop {
-            self.process_expired_swaps().await?;
-            self.process_pending_swaps().await?;
-            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            let engine = self.clone();
+            match tokio::spawn(async move { engine.run_loop().await }).await {
+                Ok(()) => tracing::error!("Swap engine loop exited"),
+                Err(e) => tracing::error!("Swap engine loop panicked: {}", e),
+            }
+            restarts += 1;
+            let delay = self.error_budget.policy().backoff(restarts);
+            tracing::warn!("Restarting swap engine loop in {}s", delay.num_seconds());
+            tokio::time::sleep(delay.to_std().unwrap_or_default()).await;
         }
     }
 
-    async fn process_expired_swaps(&self) -> Result<()> {
-        let now = Utc::now();
-        let mut expired_swaps = Vec::new();
-
+    async fn run_loop(&self) {
+        // Pick up every live swap, including after a restart of this loop
         {
+            let now = self.scheduler.now();
             let active_swaps = self.active_swaps.read().await;
-            for (swap_id, swap) in active_swaps.iter() {
-                if now > swap.expires_at 
-                    && (swap.state == SwapState::Quoted || swap.state == SwapState::LockedUsdc || swap.state == SwapState::LockedXmr) {
-                    expired_swaps.push(*swap_id);
+            for swap in active_swaps.values().filter(|swap| !swap.state.is_terminal()) {
+                self.scheduler.schedule(swap.swap_id, now);
+                if let Some(subscriptions) = &self.subscriptions {
+                    subscriptions.watch(swap.swap_id);
                 }
             }
         }
 
-        for swap_id in expired_swaps {
-            self.refund_swap(swap_id).await?;
-            // Also trigger refund on blockchain if necessary
-            self.trigger_onchain_refund(swap_id).await?;
+        let sweep_interval = Duration::seconds(self.engine_setting(|e| e.sweep_interval_seconds, 30) as i64);
+        let mut next_sweep = self.scheduler.now();
+        loop {
+            let now = self.scheduler.now();
+            if now >= next_sweep || self.scheduler.take_sweep_request() {
+                self.sweep().await;
+                next_sweep = self.scheduler.now() + sweep_interval;
+            }
+
+            let due = self.scheduler.pop_due(self.scheduler.now());
+            if !due.is_empty() {
+                self.process_due_swaps(due).await;
+            }
+
+            self.scheduler.wait(next_sweep).await;
+        }
+    }
+
+    /// Engine-wide work that isn't tied to one swap's schedule.
+    async fn sweep(&self) {
+        if let Err(e) = self.check_wallet_sync().await {
+            tracing::warn!("Failed to compare wallet and daemon heights: {}", e);
+        }
+        if let Err(e) = self.refresh_inventory().await {
+            tracing::warn!("Failed to refresh inventory: {}", e);
+        }
+        if let Err(e) = self.reconcile_payouts().await {
+            tracing::warn!("Failed to reconcile XMR payouts: {}", e);
+        }
+        if let Err(e) = self.release_presigned().await {
+            tracing::warn!("Failed to release pre-signed transactions: {}", e);
+        }
+        if let Err(e) = self.maintain_outputs().await {
+            tracing::warn!("Failed to split Monero outputs: {}", e);
+        }
+        if let Err(e) = self.reverify_observations().await {
+            tracing::warn!("Failed to re-verify chain observations: {}", e);
+        }
+        if let Err(e) = self.process_xmr_deposits().await {
+            tracing::warn!("Failed to scan XMR deposits: {}", e);
+        }
+        if let Err(e) = self.scan_unmatched_deposits().await {
+            tracing::warn!("Failed to scan for unmatched XMR deposits: {}", e);
+        }
+    }
+
+    /// Wake a swap because a chain event concerns it.
+    pub fn notify_swap(&self, swap_id: [u8; 32]) {
+        self.scheduler.wake(swap_id);
+    }
+
+    /// Wake the swaps a program event concerns.
+    pub async fn handle_program_event(&self, event: ProgramEvent) {
+        if let ProgramEvent::Transaction(tx) = &event {
+            tracing::debug!(
+                "Program transaction {} at slot {}: {:?}{}",
+                tx.signature,
+                tx.slot,
+                tx.instructions.iter().map(|(instruction, _)| instruction.name()).collect::<Vec<_>>(),
+                if tx.failed { " (failed)" } else { "" }
+            );
+            if tx.failed {
+                return;
+            }
+        }
+
+        let active_swaps = self.active_swaps.read().await;
+        for swap_id in event.swap_ids() {
+            if active_swaps.get(&swap_id).is_some_and(|swap| !swap.state.is_terminal()) {
+                self.scheduler.wake(swap_id);
+            }
+        }
+    }
+
+    /// Run the engine-wide sweeps now, e.g. on a new Monero transaction.
+    pub fn notify_sweep(&self) {
+        self.scheduler.request_sweep();
+    }
+
+    /// A wallet transaction notification: rescan deposits now and wake the
+    /// swap the transaction pays or was sent for, if any.
+    pub async fn notify_monero_tx(&self, txid: &str) {
+        self.scheduler.request_sweep();
+
+        let subaddr_index = match self.monero_client.get_transfers(txid).await {
+            Ok(transfer) => transfer.map(|t| t.subaddr_index.minor),
+            Err(e) => {
+                tracing::warn!("Failed to look up notified Monero transaction {}: {}", txid, e);
+                None
+            }
+        };
+
+        let active_swaps = self.active_swaps.read().await;
+        for swap in active_swaps.values().filter(|swap| !swap.state.is_terminal()) {
+            let ours = swap.monero_txid.as_deref() == Some(txid)
+                || (swap.monero_subaddr_index.is_some() && swap.monero_subaddr_index == subaddr_index);
+            if ours {
+                tracing::debug!("Monero transaction {} concerns swap {}", txid, hex::encode(swap.swap_id));
+                self.scheduler.wake(swap.swap_id);
+            }
+        }
+    }
+
+    /// A new Monero block: confirmations moved for every swap waiting on them.
+    pub async fn notify_monero_block(&self) {
+        // Have the wallet pick up the block now rather than at its next
+        // auto-refresh, without holding up the notifier
+        let wallet = self.monero_client.clone();
+        let scheduler = self.scheduler.clone();
+        tokio::spawn(async move {
+            if let Err(e) = wallet.refresh().await {
+                tracing::warn!("Failed to refresh Monero wallet: {}", e);
+            }
+            scheduler.request_sweep();
+        });
+        self.scheduler.request_sweep();
+
+        let active_swaps = self.active_swaps.read().await;
+        for swap in active_swaps.values() {
+            if swap.direction == Direction::UsdcToXmr && swap.state == SwapState::LockedUsdc && swap.monero_txid.is_some() {
+                self.scheduler.wake(swap.swap_id);
+            }
+        }
+    }
+
+    /// Stored pre-signed transactions, decrypted for broadcast by a
+    /// watchtower or operator after their deadlines.
+    pub async fn recovery_bundle(&self) -> Result<RecoveryBundle> {
+        self.transactions.presigned().recovery_bundle().await
+    }
+
+    /// Sign the refund of the swap's escrow while the daemon is certainly
+    /// up, as soon as the PDA holding it exists, so it can be broadcast
+    /// from the recovery bundle after expiry. Only escrows the daemon would
+    /// refund to Bob itself are signed. A failure is only logged: the
+    /// daemon still refunds at expiry if it is running.
+    async fn presign_escrow_refund(&self, swap_id: [u8; 32], onchain: &OnchainSwapInfo) {
+        if !refunds_to_bob(onchain, &self.solana_client.keypair().public_key()) {
+            return;
+        }
+        if let Err(e) = self.presign_refund(swap_id, onchain.swap.expiry).await {
+            tracing::warn!("Failed to pre-sign refund of {}: {}", hex::encode(swap_id), e);
+        }
+    }
+
+    async fn presign_refund(&self, swap_id: [u8; 32], expiry: i64) -> Result<()> {
+        let instruction = self.solana_client.refund_instruction(swap_id)?;
+        if let Some(signature) = self.transactions.presign(swap_id, SolanaTxKind::Refund, vec![instruction], expiry).await? {
+            tracing::debug!("Refund of {} pre-signed as {}", hex::encode(swap_id), signature);
+        }
+        Ok(())
+    }
+
+    /// Void stored transactions whose swap has settled on-chain, freeing
+    /// their nonce accounts. Swaps are read from the chain, not memory, so
+    /// a restart never voids a refund that is still needed.
+    async fn release_presigned(&self) -> Result<()> {
+        let presigned = self.transactions.presigned();
+        for tx in presigned.stored().await? {
+            let settled = match self.solana_client.get_swap(tx.swap_id).await? {
+                Some(onchain) => onchain.swap.is_redeemed || onchain.swap.is_refunded,
+                None => true,
+            };
+            if settled {
+                tracing::info!("Voiding pre-signed {} of settled swap {}", tx.kind.as_str(), hex::encode(tx.swap_id));
+                presigned.void(tx.swap_id, tx.kind).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn refresh_wallet(&self) -> Result<WalletRefresh> {
+        let refresh = self.monero_client.refresh().await?;
+        self.scheduler.request_sweep();
+        Ok(refresh)
+    }
+
+    fn engine_setting(&self, setting: impl Fn(&EngineConfig) -> Option<u64>, default: u64) -> u64 {
+        self.config.engine.as_ref().and_then(setting).unwrap_or(default)
+    }
+
+    /// When the swap next needs attention, if ever: at expiry, or sooner to
+    /// check on whatever it is waiting for.
+    fn next_action_at(&self, swap: &SwapTrade, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let solana_poll = self.engine_setting(|e| e.solana_poll_seconds, 5);
+        let poll = match (swap.direction, swap.state) {
+            (_, state) if state.is_terminal() => return None,
+            // Waiting on the deposit sweep; only expiry is scheduled
+            (_, SwapState::Quoted) => None,
+            (Direction::UsdcToXmr, SwapState::LockedUsdc) => Some(self.engine_setting(|e| e.monero_poll_seconds, 30)),
+            _ => Some(solana_poll),
+        };
+        // Just past expiry, which is when the swap counts as expired
+        let expiry = swap.expires_at + Duration::seconds(1);
+        if now >= expiry {
+            // Past its deadline a live swap waits on a Solana transaction:
+            // its refund, or the redeem of XMR already paid out
+            return Some(now + Duration::seconds(solana_poll as i64));
+        }
+        let at = poll.map_or(expiry, |seconds| expiry.min(now + Duration::seconds(seconds as i64)));
+        Some(at.max(now))
+    }
+
+    /// Compare the wallet's sync height with the daemon's. Without a
+    /// daemon there is nothing to compare against and quoting never pauses.
+    async fn check_wallet_sync(&self) -> Result<()> {
+        let daemon = match &self.daemon {
+            Some(daemon) => daemon,
+            None => return Ok(()),
+        };
+        let (wallet_height, daemon_height) = tokio::try_join!(
+            async { Ok::<_, anyhow::Error>(self.monero_client.get_height().await?) },
+            daemon.get_height(),
+        )?;
+
+        let sync = WalletSync::new(wallet_height, daemon_height, Utc::now());
+        if sync.is_lagging(self.max_wallet_lag()) {
+            tracing::warn!(
+                "Monero wallet at height {} is {} blocks behind the daemon; quoting paused",
+                wallet_height,
+                sync.lag_blocks
+            );
+        }
+        *self.wallet_sync.write().await = Some(sync);
+        Ok(())
+    }
+
+    fn max_wallet_lag(&self) -> u64 {
+        self.config.monero.max_wallet_lag_blocks.unwrap_or(3)
+    }
+
+    async fn quoting_paused(&self) -> bool {
+        self.wallet_sync
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|sync| sync.is_lagging(self.max_wallet_lag()))
+    }
+
+    async fn ensure_wallet_synced(&self) -> Result<()> {
+        if let Some(sync) = self.wallet_sync.read().await.as_ref() {
+            if sync.is_lagging(self.max_wallet_lag()) {
+                return Err(anyhow::anyhow!(
+                    "Quoting is paused while the Monero wallet syncs ({} blocks behind)",
+                    sync.lag_blocks
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn health(&self) -> EngineHealth {
+        let (solana, wallet_height) = tokio::join!(
+            self.solana_client.health_check(),
+            self.monero_client.get_height(),
+        );
+        let daemon_height = match &self.daemon {
+            Some(daemon) => Some(daemon.get_height().await),
+            None => None,
+        };
+        let wallet_height = wallet_height.ok();
+        let daemon_connected = daemon_height.as_ref().map(|height| height.is_ok());
+        let daemon_height = daemon_height.and_then(|height| height.ok());
+
+        EngineHealth {
+            solana_connected: solana.unwrap_or(false),
+            monero_connected: wallet_height.is_some(),
+            daemon_connected,
+            wallet_height,
+            daemon_height,
+            wallet_lag_blocks: wallet_height.zip(daemon_height).map(|(wallet, daemon)| daemon.saturating_sub(wallet)),
+            quoting_paused: self.quoting_paused().await,
+        }
+    }
+
+    async fn refresh_inventory(&self) -> Result<()> {
+        let balances = self.inventory
+            .refresh(&self.monero_client, &self.solana_client)
+            .await?;
+        self.metrics.set_monero_balance(balances.xmr_unlocked + balances.xmr_locked);
+        self.metrics.set_solana_balance(balances.usdc);
+        Ok(())
+    }
+
+    /// Split a large output when the wallet runs short of payout-sized ones.
+    async fn maintain_outputs(&self) -> Result<()> {
+        if !self.outputs.is_enabled() {
+            return Ok(());
         }
+        let outputs = match self.inventory.outputs().await {
+            Some(outputs) => outputs,
+            None => return Ok(()),
+        };
+        let xmr_reserved = self.inventory.snapshot().await.xmr_reserved;
+        let split = match self.outputs.plan(&outputs, xmr_reserved) {
+            Some(split) => split,
+            None => return Ok(()),
+        };
 
+        // Don't spend inputs a built but unrelayed payout may be using
+        let _payout_guard = self.payout_lock.lock().await;
+        let address = self.monero_client.get_primary_address().await?;
+        let txid = self.monero_client
+            .sweep_single(&split.key_image, &address, split.outputs)
+            .await?;
+        tracing::info!(
+            "Splitting a {} piconero output into {} outputs in {}",
+            split.amount,
+            split.outputs,
+            txid
+        );
+        self.outputs.record_split(txid);
+        self.inventory.mark_stale().await;
         Ok(())
     }
 
-    async fn process_pending_swaps(&self) -> Result<()> {
-        let pending_swaps: Vec<SwapTrade> = {
+    /// Match incoming transfers on each XMR→USDC swap's subaddress to the
+    /// swap, and lock the swap once the deposit is confirmed. Expired swaps
+    /// stay watched for a while so late deposits are recorded.
+    async fn process_xmr_deposits(&self) -> Result<()> {
+        let now = self.scheduler.now();
+        let watched: Vec<SwapTrade> = {
             let active_swaps = self.active_swaps.read().await;
             active_swaps
                 .values()
-                .filter(|swap| 
-                    swap.state == SwapState::LockedUsdc || swap.state == SwapState::LockedXmr)
+                .filter(|swap| watches_deposits(swap, now))
                 .cloned()
                 .collect()
         };
 
-        for swap in pending_swaps {
-            self.process_swap_completion(&swap).await?;
+        let indices: Vec<u32> = watched.iter().filter_map(|swap| swap.monero_subaddr_index).collect();
+        let transfers = self.monero_client.get_incoming_transfers(&indices).await?;
+
+        for swap in watched {
+            let required_confirmations = self.confirmation_requirement(&swap).monero_confirmations;
+            let index = swap.monero_subaddr_index.unwrap_or_default();
+            let mut received: Vec<IncomingTransfer> = transfers
+                .iter()
+                .filter(|t| t.subaddr_index == index)
+                .cloned()
+                .collect();
+
+            // Stray deposits an operator credited to this swap count as
+            // confirmed transfers made when the credit was given
+            for credit in self.strays.credits_for(swap.swap_id).await? {
+                received.push(IncomingTransfer {
+                    txid: format!("stray:{}", credit.id),
+                    amount: credit.amount,
+                    confirmations: required_confirmations,
+                    height: 0,
+                    subaddr_index: index,
+                    in_pool: false,
+                    timestamp: credit.resolved_at.unwrap_or(credit.detected_at).timestamp(),
+                    double_spend_seen: false,
+                });
+            }
+
+            let deposit = XmrDeposit::assess(&received, swap.xmr_amount, swap.expires_at, required_confirmations);
+            if let Some(deposit) = &deposit {
+                self.classify_stray_deposit(&swap, deposit).await?;
+            }
+            if deposit.is_none() || deposit == swap.xmr_deposit {
+                continue;
+            }
+
+            let mut locked = None;
+            let mut active_swaps = self.active_swaps.write().await;
+            if let Some(swap) = active_swaps.get_mut(&swap.swap_id) {
+                if let Some(deposit) = &deposit {
+                    tracing::info!(
+                        "XMR deposit for swap {}: {:?}, {} of {} piconero, {}/{} confirmations",
+                        hex::encode(swap.swap_id),
+                        deposit.state,
+                        deposit.amount,
+                        swap.xmr_amount,
+                        deposit.confirmations,
+                        deposit.required_confirmations,
+                    );
+                    if let Some(confirmations) = swap.confirmations.as_mut() {
+                        confirmations.monero_confirmations = deposit.confirmations;
+                    }
+                    if swap.state == SwapState::Quoted && deposit.is_settled() {
+                        swap.state = SwapState::LockedXmr;
+                        swap.monero_txid = deposit.txids.first().cloned();
+                        locked = Some(deposit.txids.clone());
+                    }
+                }
+                swap.xmr_deposit = deposit;
+                self.persist_swap(swap).await?;
+            }
+            drop(active_swaps);
+
+            // Credited stray deposits were checked when they were first seen
+            for txid in locked.iter().flatten().filter(|txid| !txid.starts_with("stray:")) {
+                self.record_observation(&swap, Chain::Monero, txid, SwapState::LockedXmr, SwapState::Quoted).await?;
+            }
+            if locked.is_some() {
+                self.scheduler.wake(swap.swap_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record whatever part of a swap's deposit will not be used for it.
+    async fn classify_stray_deposit(&self, swap: &SwapTrade, deposit: &XmrDeposit) -> Result<()> {
+        let index = swap.monero_subaddr_index.unwrap_or_default();
+        for part in stray_parts(deposit, swap.xmr_amount, swap.state == SwapState::Quoted) {
+            self.strays
+                .record(Some(swap.swap_id), index, &part.txids, part.amount, part.reason)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Record confirmed deposits that no outstanding quote or watched swap
+    /// accounts for, such as one to a reused old deposit address or to the
+    /// subaddress of a swap that already completed.
+    async fn scan_unmatched_deposits(&self) -> Result<()> {
+        let height = self.monero_client.get_height().await?;
+        let transfers = self.monero_client
+            .get_incoming_transfers_since(height.saturating_sub(STRAY_RESCAN_BLOCKS))
+            .await?;
+        let required_confirmations = self.confirmation_policy.baseline().monero_confirmations;
+
+        let now = self.scheduler.now();
+        let mut watched: std::collections::HashSet<u32> = self.quotes.subaddr_indices().await.into_iter().collect();
+        let active_swaps = self.active_swaps.read().await;
+        watched.extend(
+            active_swaps
+                .values()
+                .filter(|swap| watches_deposits(swap, now))
+                .filter_map(|swap| swap.monero_subaddr_index),
+        );
+        let claimed: std::collections::HashSet<&str> = active_swaps
+            .values()
+            .filter_map(|swap| swap.xmr_deposit.as_ref())
+            .flat_map(|deposit| deposit.txids.iter().chain(&deposit.late_txids))
+            .map(String::as_str)
+            .collect();
+        let unmatched: Vec<IncomingTransfer> = transfers
+            .into_iter()
+            .filter(|transfer| is_unmatched(transfer, &watched, &claimed, required_confirmations))
+            .collect();
+        drop(active_swaps);
+
+        for transfer in unmatched {
+            self.strays
+                .record(None, transfer.subaddr_index, std::slice::from_ref(&transfer.txid), transfer.amount, StrayReason::Unmatched)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_stray_deposits(&self, status: Option<&str>) -> Result<Vec<StrayDeposit>> {
+        self.strays.list(status).await
+    }
+
+    pub async fn get_stray_deposit(&self, id: uuid::Uuid) -> Result<(StrayDeposit, Vec<StrayAuditEntry>)> {
+        Ok((self.strays.get(id).await?, self.strays.audit_log(id).await?))
+    }
+
+    /// Return a stray deposit to `address`, less the network fee. The refund
+    /// goes through the payout outbox like a swap's payout, so a retry or a
+    /// restart never sends it twice. Retrying a refund that was interrupted
+    /// resumes it, to the address it was started with.
+    pub async fn refund_stray_deposit(&self, id: uuid::Uuid, address: &str, actor: &str) -> Result<String> {
+        let network = self.config.monero.network.as_deref().unwrap_or("mainnet");
+        destination::validate_monero_address(&self.monero_client, address, network).await?;
+
+        let deposit = self.strays.get(id).await?;
+        let deposit = match (deposit.status.as_str(), deposit.refund_address.as_deref()) {
+            ("refunding", Some(started)) if started == address => deposit,
+            ("refunding", started) => {
+                return Err(StrayDepositError::RefundInProgress(started.unwrap_or_default().to_string()).into())
+            }
+            _ => {
+                let deposit = self.strays.begin_refund(id, address, actor).await?;
+                // Clear an unbuilt refund left from an earlier attempt, so
+                // this one goes to the new address
+                self.outbox.discard_intent(stray_refund_key(id)).await?;
+                deposit
+            }
+        };
+
+        let _payout_guard = self.payout_lock.lock().await;
+        let refund = self.outbox.record_stray_refund(id, address, deposit.amount).await?;
+        match self.send_payout(refund, self.fee_policy.normal_priority()).await {
+            Ok(refund) => {
+                let txid = refund.tx_hash.unwrap_or_default();
+                self.strays.finish_refund(id, &txid, actor).await?;
+                self.inventory.mark_stale().await;
+                Ok(txid)
+            }
+            Err(e) => {
+                // A built refund may be on the network already; it stays
+                // refunding and reconcile_payouts relays or finishes it.
+                // With nothing built, the deposit can be handled afresh.
+                if self.outbox.discard_intent(stray_refund_key(id)).await? {
+                    self.strays.abort_refund(id, &e.to_string(), actor).await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Settle stray refunds a restart interrupted. One that never got as
+    /// far as a built transaction reopens; one relayed is recorded
+    /// refunded. Built ones are left to `reconcile_payouts`.
+    async fn reconcile_stray_refunds(&self) -> Result<()> {
+        for deposit in self.strays.list(Some("refunding")).await? {
+            let key = stray_refund_key(deposit.id);
+            match self.outbox.get(key).await? {
+                None => {
+                    self.strays
+                        .abort_refund(deposit.id, "Interrupted before the refund was built", "system")
+                        .await?
+                }
+                Some(refund) if refund.state == PayoutState::Intent => {
+                    self.outbox.discard_intent(key).await?;
+                    self.strays
+                        .abort_refund(deposit.id, "Interrupted before the refund was built", "system")
+                        .await?
+                }
+                Some(refund) if matches!(refund.state, PayoutState::Relayed | PayoutState::Confirmed) => {
+                    self.finish_stray_refund(&refund).await?
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the stray deposit a relayed refund payout returns as refunded.
+    async fn finish_stray_refund(&self, refund: &Payout) -> Result<()> {
+        if let Some(id) = refund.stray_id {
+            self.strays
+                .finish_refund(id, refund.tx_hash.as_deref().unwrap_or_default(), "system")
+                .await?;
+            self.inventory.mark_stale().await;
+        }
+        Ok(())
+    }
+
+    /// Count a stray deposit towards an XMR→USDC swap that is still waiting
+    /// for Alice's XMR, typically one re-quoted for the same user.
+    pub async fn credit_stray_deposit(&self, id: uuid::Uuid, swap_id: [u8; 32], actor: &str) -> Result<StrayDeposit> {
+        let creditable = self.active_swaps
+            .read()
+            .await
+            .get(&swap_id)
+            .map(|swap| {
+                swap.direction == Direction::XmrToUsdc
+                    && swap.state == SwapState::Quoted
+                    && swap.monero_subaddr_index.is_some()
+                    && Utc::now() < swap.expires_at
+            })
+            .unwrap_or(false);
+        if !creditable {
+            return Err(StrayDepositError::NotCreditable(hex::encode(swap_id)).into());
         }
 
+        self.strays.credit(id, swap_id, actor).await
+    }
+
+    /// Expire or advance one swap, whichever is due.
+    async fn process_swap(&self, swap: &SwapTrade) -> Result<()> {
+        if is_expired(swap, self.scheduler.now()) {
+            // Refund on-chain first, so a refund the program rejects for now
+            // (not yet expired by its clock) is tried again at the next poll,
+            // and one it will always reject fails the swap with the reason.
+            // The swap ends only once the refund has confirmed.
+            if self.hold_unescrowed_deposit(swap).await? {
+                return Ok(());
+            }
+            return match self.trigger_onchain_refund(swap).await {
+                Ok(true) => self.refund_swap(swap.swap_id).await,
+                Ok(false) => Ok(()),
+                Err(e) => match e.downcast_ref::<SolanaTxError>() {
+                    Some(rejected) if !rejected.is_retryable() => {
+                        self.fail_swap(swap.swap_id, format!("On-chain refund failed: {}", rejected)).await
+                    }
+                    _ => Err(e),
+                },
+            };
+        }
+        if matches!(swap.state, SwapState::LockedUsdc | SwapState::LockedXmr) {
+            self.process_swap_completion(swap).await?;
+        }
         Ok(())
     }
 
+    /// Process due swaps, each in its own task so an error or panic in one
+    /// doesn't hold up or abort the others, then schedule each again.
+    async fn process_due_swaps(&self, due: Vec<[u8; 32]>) {
+        let now = self.scheduler.now();
+        let limit = Arc::new(tokio::sync::Semaphore::new(
+            self.engine_setting(|e| e.max_concurrent_swaps, 64).max(1) as usize,
+        ));
+        let mut tasks = Vec::new();
+        for swap_id in due {
+            let swap = match self.get_swap_status(swap_id).await {
+                Some(swap) if !swap.state.is_terminal() => swap,
+                _ => {
+                    if let Some(subscriptions) = &self.subscriptions {
+                        subscriptions.unwatch(swap_id);
+                    }
+                    continue;
+                }
+            };
+            if let Some(errors) = self.error_budget.get(swap_id).await {
+                if now < errors.retry_after {
+                    self.scheduler.schedule(swap_id, errors.retry_after);
+                    continue;
+                }
+            }
+
+            let engine = self.clone();
+            let limit = limit.clone();
+            tasks.push((swap_id, tokio::spawn(async move {
+                let _permit = limit.acquire_owned().await?;
+                engine.process_swap(&swap).await
+            })));
+        }
+
+        for (swap_id, task) in tasks {
+            let error = match task.await {
+                Ok(Ok(())) => {
+                    self.error_budget.record_success(swap_id).await;
+                    self.schedule_next(swap_id).await;
+                    continue;
+                }
+                // The program will take the transaction later, e.g. a refund
+                // once its clock, which can trail ours, passes the expiry.
+                // That is waiting, not failing, so the budget is untouched.
+                Ok(Err(e)) if e.downcast_ref::<SolanaTxError>().is_some_and(SolanaTxError::is_retryable) => {
+                    tracing::info!("Swap {} waiting on the program: {}", hex::encode(swap_id), e);
+                    self.schedule_next(swap_id).await;
+                    continue;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(e) => format!("task panicked: {}", e),
+            };
+            if let Err(e) = self.record_swap_error(swap_id, &error).await {
+                tracing::error!("Failed to record error for swap {}: {}", hex::encode(swap_id), e);
+            }
+        }
+    }
+
+    /// Schedule the swap's next action, or stop following it once it has
+    /// none.
+    async fn schedule_next(&self, swap_id: [u8; 32]) {
+        let Some(swap) = self.get_swap_status(swap_id).await else {
+            return;
+        };
+        match self.next_action_at(&swap, self.scheduler.now()) {
+            Some(at) => self.scheduler.schedule(swap_id, at),
+            None => {
+                if let Some(subscriptions) = &self.subscriptions {
+                    subscriptions.unwatch(swap_id);
+                }
+            }
+        }
+    }
+
+    /// Count a failed attempt against the swap's error budget, failing the
+    /// swap once the budget is spent.
+    async fn record_swap_error(&self, swap_id: [u8; 32], error: &str) -> Result<()> {
+        match self.error_budget.record_failure(swap_id, error, self.scheduler.now()).await {
+            RetryDecision::RetryAt(retry_after) => {
+                tracing::warn!(
+                    "Swap {} failed: {}; retrying at {}",
+                    hex::encode(swap_id),
+                    error,
+                    retry_after.to_rfc3339()
+                );
+                self.scheduler.schedule(swap_id, retry_after);
+                Ok(())
+            }
+            RetryDecision::GiveUp(reason) => self.fail_swap(swap_id, reason).await,
+        }
+    }
+
+    async fn fail_swap(&self, swap_id: [u8; 32], reason: String) -> Result<()> {
+        let swap = {
+            let mut active_swaps = self.active_swaps.write().await;
+            match active_swaps.get_mut(&swap_id) {
+                Some(swap) if !swap.state.is_terminal() => {
+                    swap.state = SwapState::Failed;
+                    swap.failure_reason = Some(reason);
+                    self.persist_swap(swap).await?;
+                    swap.clone()
+                }
+                _ => return Ok(()),
+            }
+        };
+
+        tracing::error!("Swap {} failed: {}", hex::encode(swap_id), swap.failure_reason.as_deref().unwrap_or_default());
+        self.inventory.release(swap.quote_id).await;
+        self.metrics.increment_swaps_failed();
+        self.emit_failed_event(&swap).await
+    }
+
     async fn process_swap_completion(&self, swap: &SwapTrade) -> Result<()> {
         match swap.direction {
             Direction::UsdcToXmr => self.process_usdc_to_xmr_completion(swap).await,
@@ -178,27 +1198,43 @@ impl SwapEngine {
     async fn process_usdc_to_xmr_completion(&self, swap: &SwapTrade) -> Result<()> {
         match swap.state {
             SwapState::LockedUsdc => {
-                // Monitor Monero blockchain for XMR lock
-                if let Some(monero_txid) = &swap.monero_txid {
-                    if let Some(confirmed) = self.check_monero_deposit(monero_txid, swap.xmr_amount).await? {
-                        if confirmed {
-                            // Update state to LockedXmr
-                            let mut active_swaps = self.active_swaps.write().await;
-                            if let Some(swap) = active_swaps.get_mut(&swap.swap_id) {
-                                swap.state = SwapState::LockedXmr;
-                                self.persist_swap(swap).await?;
-                            }
+                // Send Alice's XMR, then monitor it until confirmed
+                let Some(monero_txid) = &swap.monero_txid else {
+                    return self.lock_xmr(swap).await;
+                };
+                if self.check_monero_deposit(swap, monero_txid).await? {
+                    // Record the payout on-chain for Alice to check, and move
+                    // on only once the record has landed
+                    let instruction = self.solana_client.record_monero_lock_proof_instruction(swap.swap_id, monero_txid)?;
+                    if !self
+                        .submit_until_confirmed(swap.swap_id, SolanaTxKind::RecordProof, instruction, swap.expires_at)
+                        .await?
+                    {
+                        return Ok(());
+                    }
+
+                    // Update state to LockedXmr
+                    {
+                        let mut active_swaps = self.active_swaps.write().await;
+                        if let Some(swap) = active_swaps.get_mut(&swap.swap_id) {
+                            swap.state = SwapState::LockedXmr;
+                            self.persist_swap(swap).await?;
                         }
                     }
+                    self.record_observation(swap, Chain::Monero, monero_txid, SwapState::LockedXmr, SwapState::LockedUsdc).await?;
                 }
             },
             SwapState::LockedXmr => {
-                // Monitor for Alice's USDC redemption or timeout
-                let now = Utc::now();
-                if now > swap.expires_at {
-                    // Alice can now claim USDC with adaptor signature
-                    self.check_adaptor_redeemption(swap).await?;
+                // Claim the USDC, then monitor for the redemption past the
+                // deadline too: the XMR is out, so only the redeem or
+                // Alice's refund ends it
+                if let Err(e) = self.redeem_usdc(swap).await {
+                    tracing::warn!("Failed to submit redeem of {}: {}", hex::encode(swap.swap_id), e);
+                }
+                if self.check_adaptor_redeemption(swap).await? != Finalization::Redeemed {
+                    return Ok(());
                 }
+                self.complete_swap(swap.swap_id).await?;
             },
             _ => {},
         }
@@ -208,28 +1244,43 @@ impl SwapEngine {
     async fn process_xmr_to_usdc_completion(&self, swap: &SwapTrade) -> Result<()> {
         match swap.state {
             SwapState::LockedXmr => {
-                // Monitor Solana for Bob's USDC lock
-                if let Ok(Some(onchain_swap)) = self.solana_client.get_swap(swap.swap_id).await {
-                    if onchain_swap.usdc_amount == swap.usdc_amount {
-                        // Update state to LockedUsdc
+                // Monitor Solana for Bob's USDC lock: the vault, not the
+                // account's amount field, shows the USDC is escrowed
+                let usdc_mint = &self.config.solana.usdc_mint;
+                let locked = self
+                    .observe_onchain_swap(swap, |onchain| usdc_locked(onchain, usdc_mint, swap.usdc_amount))
+                    .await?;
+                if let Some(onchain) = locked {
+                    // Update state to LockedUsdc
+                    {
                         let mut active_swaps = self.active_swaps.write().await;
                         if let Some(swap) = active_swaps.get_mut(&swap.swap_id) {
                             swap.state = SwapState::LockedUsdc;
                             self.persist_swap(swap).await?;
                         }
                     }
+                    // Bob's USDC is escrowed from here, and gone from the
+                    // balance the next refresh reads
+                    self.inventory.settle(swap.quote_id).await;
+                    self.presign_escrow_refund(swap.swap_id, &onchain).await;
+                    let signatures = self.solana_client.get_signatures_for_address(&onchain.address, 1).await?;
+                    if let Some((signature, _)) = signatures.first() {
+                        self.record_observation(swap, Chain::Solana, signature, SwapState::LockedUsdc, SwapState::LockedXmr).await?;
+                    }
                 }
             },
             SwapState::LockedUsdc => {
-                // Monitor for redemption or timeout
-                let _now = Utc::now();
-                if let Some(_alice_pubkey) = &swap.alice_solana {
-                    // Check if Alice has redeemed with adaptor signature
-                    if self.check_adaptor_redeemption(swap).await? {
-                        // Alice has revealed the secret, Bob can unlock XMR
-                        self.unlock_xmr(swap).await?;
-                    }
+                // Alice redeeming with the adaptor signature completes the
+                // swap. Her XMR is already in Bob's sub-address, so nothing
+                // is paid out; it only waits out any reorg of the redeem.
+                if swap.alice_solana.is_none() || self.check_adaptor_redeemption(swap).await? != Finalization::Redeemed {
+                    return Ok(());
                 }
+                if !self.observations.all_final(swap.swap_id).await? {
+                    tracing::debug!("Swap {} waiting for its chain observations to be final", hex::encode(swap.swap_id));
+                    return Ok(());
+                }
+                self.complete_swap(swap.swap_id).await?;
             },
             _ => {},
         }
@@ -240,68 +1291,299 @@ impl SwapEngine {
         let mut swap = {
             let active_swaps = self.active_swaps.read().await;
             match active_swaps.get(&swap_id) {
-                Some(swap) => swap.clone(),
-                None => return Ok(()),
+                Some(swap) if is_expired(swap, self.scheduler.now()) => swap.clone(),
+                _ => return Ok(()),
             }
         };
 
         swap.state = SwapState::Refunded;
         swap.failure_reason = Some("Swap expired".to_string());
 
+        self.persist_swap(&swap).await?;
         {
             let mut active_swaps = self.active_swaps.write().await;
             active_swaps.insert(swap_id, swap.clone());
         }
 
+        self.inventory.release(swap.quote_id).await;
         self.metrics.increment_swaps_failed();
         let _ = self.emit_failed_event(&swap).await;
 
         Ok(())
     }
 
-    fn validate_trade_parameters(&self, _direction: Direction, usdc_amount: u64, _xmr_amount: u64) -> Result<()> {
+    fn validate_trade_parameters(&self, _direction: Direction, usdc_amount: u64, xmr_amount: u64) -> Result<()> {
         if usdc_amount < self.config.quoting.min_usdc || usdc_amount > self.config.quoting.max_usdc {
             return Err(anyhow::anyhow!("USDC amount out of allowed range"));
         }
+        if xmr_amount == 0 {
+            return Err(anyhow::anyhow!("XMR amount must be non-zero"));
+        }
         Ok(())
     }
 
-    async fn check_monero_deposit(&self, txid: &str, amount: u64) -> Result<Option<bool>> {
-        if let Some(transfer) = self.monero_client.get_transfers(txid).await? {
-            let received_amount = transfer["amount"].as_u64().unwrap_or(0);
-            let confirmations = transfer["confirmations"].as_u64().unwrap_or(0);
-            
-            if confirmations >= 10 && received_amount >= amount {
-                return Ok(Some(true));
+    /// Whether `txid` pays at least the swap's XMR amount with the swap's
+    /// required confirmations. The count is recorded on the swap.
+    async fn check_monero_deposit(&self, swap: &SwapTrade, txid: &str) -> Result<bool> {
+        let required = self.confirmation_requirement(swap);
+        let transfer = match self.monero_client.get_transfers(txid).await? {
+            Some(transfer) => transfer,
+            None => return Ok(false),
+        };
+        let received_amount = transfer.amount;
+        let confirmations = transfer.confirmations;
+
+        {
+            let mut active_swaps = self.active_swaps.write().await;
+            if let Some(tracked) = active_swaps.get_mut(&swap.swap_id) {
+                tracked.confirmations
+                    .get_or_insert_with(|| SwapConfirmations::new(required.clone()))
+                    .monero_confirmations = confirmations;
             }
         }
-        
-        Ok(None)
+
+        Ok(confirmations >= required.monero_confirmations && received_amount >= swap.xmr_amount)
+    }
+
+    /// Record the transaction a swap just advanced on so it is re-verified
+    /// until final. Skipped, with a warning, if the chain can't place it.
+    async fn record_observation(
+        &self,
+        swap: &SwapTrade,
+        chain: Chain,
+        reference: &str,
+        advanced_to: SwapState,
+        rollback_to: SwapState,
+    ) -> Result<()> {
+        let inclusion = match self.locate(chain, reference).await? {
+            Some(inclusion) => inclusion,
+            None => {
+                tracing::warn!(
+                    "Swap {} advanced on {} {} which is not in a block",
+                    hex::encode(swap.swap_id),
+                    chain.as_str(),
+                    reference
+                );
+                return Ok(());
+            }
+        };
+        self.observations
+            .record(swap.swap_id, chain, reference, &inclusion, advanced_to, rollback_to)
+            .await
+    }
+
+    async fn locate(&self, chain: Chain, reference: &str) -> Result<Option<Inclusion>> {
+        match chain {
+            Chain::Monero => self.monero_chain.locate(reference).await,
+            Chain::Solana => self.solana_chain.locate(reference).await,
+        }
+    }
+
+    /// Re-check every pending observation against the chain, rolling back
+    /// swaps whose observations a reorg has dropped.
+    async fn reverify_observations(&self) -> Result<()> {
+        for observation in self.observations.pending().await? {
+            let verdict = match observation.chain {
+                Chain::Monero => {
+                    let depth = match self.get_swap_status(observation.swap_id).await {
+                        Some(swap) => self.confirmation_requirement(&swap).monero_confirmations,
+                        None => self.confirmation_policy.baseline().monero_confirmations,
+                    };
+                    chain::reverify(&self.monero_chain, &observation, depth).await
+                }
+                Chain::Solana => chain::reverify(&self.solana_chain, &observation, SOLANA_FINALITY_SLOTS).await,
+            };
+            let verdict = match verdict {
+                Ok(verdict) => verdict,
+                Err(e) => {
+                    tracing::warn!("Failed to re-verify {} {}: {}", observation.chain.as_str(), observation.reference, e);
+                    continue;
+                }
+            };
+
+            match verdict {
+                Verdict::Pending => self.observations.mark_verified(observation.id).await?,
+                Verdict::Final => self.observations.mark_final(observation.id).await?,
+                Verdict::Moved(inclusion) => {
+                    tracing::warn!(
+                        "{} {} for swap {} moved from height {} to {}",
+                        observation.chain.as_str(),
+                        observation.reference,
+                        hex::encode(observation.swap_id),
+                        observation.height,
+                        inclusion.height
+                    );
+                    self.observations.update_inclusion(observation.id, &inclusion).await?;
+                }
+                Verdict::Invalidated(reason) => self.roll_back(&observation, &reason).await?,
+            }
+        }
+        Ok(())
     }
 
-    async fn trigger_onchain_refund(&self, swap_id: [u8; 32]) -> Result<()> {
-        self.solana_client.refund_usdc(swap_id).await?;
+    /// Undo the state change an invalidated observation caused.
+    async fn roll_back(&self, observation: &Observation, reason: &str) -> Result<()> {
+        self.observations.invalidate(observation, reason).await?;
+        self.metrics.increment_reorgs(observation.chain.as_str());
+        tracing::warn!("Reorg on swap {}: {}", hex::encode(observation.swap_id), reason);
+
+        let rolled_back = {
+            let mut active_swaps = self.active_swaps.write().await;
+            match active_swaps.get_mut(&observation.swap_id) {
+                Some(swap) if !swap.state.is_terminal() => {
+                    roll_back_swap(swap, observation, reason);
+                    self.persist_swap(swap).await?;
+                    Some(swap.clone())
+                }
+                _ => None,
+            }
+        };
+
+        match &rolled_back {
+            Some(swap) if swap.state == SwapState::Failed => {
+                self.inventory.release(swap.quote_id).await;
+                self.metrics.increment_swaps_failed();
+            }
+            Some(swap) => self.scheduler.wake(swap.swap_id),
+            None => tracing::error!(
+                "Swap {} can no longer be rolled back to {:?} after a reorg",
+                hex::encode(observation.swap_id),
+                observation.rollback_to
+            ),
+        }
+
+        self.emit_reorg_alert(observation, reason, rolled_back.is_some()).await
+    }
+
+    async fn emit_reorg_alert(&self, observation: &Observation, reason: &str, rolled_back: bool) -> Result<()> {
+        if let Ok(webhook_url) = std::env::var("FAIL_WEBHOOK_URL") {
+            let payload = serde_json::json!({
+                "event": "reorg",
+                "swap_id": hex::encode(observation.swap_id),
+                "chain": observation.chain.as_str(),
+                "reference": observation.reference,
+                "height": observation.height,
+                "block_hash": observation.block_hash,
+                "rolled_back_to": rolled_back.then(|| observation.rollback_to.as_str()),
+                "reason": reason,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+            });
+
+            let client = reqwest::Client::new();
+            if let Err(e) = client.post(&webhook_url).json(&payload).send().await {
+                tracing::error!("Failed to emit webhook: {}", e);
+            }
+        }
         Ok(())
     }
 
+    /// Reclaim the USDC Bob escrowed for an expired XMR -> USDC swap, if
+    /// it is still in the vault. Returns whether nothing is left to
+    /// reclaim; until the refund confirms it is resubmitted on each call.
+    async fn trigger_onchain_refund(&self, swap: &SwapTrade) -> Result<bool> {
+        if swap.direction != Direction::XmrToUsdc {
+            return Ok(true);
+        }
+        let Some(onchain) = self.solana_client.get_swap(swap.swap_id).await? else {
+            return Ok(true);
+        };
+        if !refunds_to_bob(&onchain, &self.solana_client.keypair().public_key()) {
+            return Ok(true);
+        }
+        let instruction = self.solana_client.refund_instruction(swap.swap_id)?;
+        self.submit_until_confirmed(swap.swap_id, SolanaTxKind::Refund, instruction, swap.expires_at).await
+    }
+
+    /// An expired XMR→USDC swap holding Alice's confirmed XMR that Bob never
+    /// escrowed USDC for has nothing on-chain to refund. Hold her XMR as an
+    /// unsettled stray deposit for the operator to return and fail the
+    /// swap, rather than ending it as refunded. Returns whether it did.
+    async fn hold_unescrowed_deposit(&self, swap: &SwapTrade) -> Result<bool> {
+        if swap.direction != Direction::XmrToUsdc || swap.state != SwapState::LockedXmr {
+            return Ok(false);
+        }
+        let Some(deposit) = &swap.xmr_deposit else {
+            return Ok(false);
+        };
+        let escrowed = self.solana_client.get_swap(swap.swap_id).await?.is_some_and(|onchain| {
+            usdc_locked(&onchain, &self.config.solana.usdc_mint, swap.usdc_amount)
+                || onchain.swap.is_redeemed
+                || onchain.swap.is_refunded
+        });
+        if escrowed {
+            return Ok(false);
+        }
+
+        self.strays
+            .record(
+                Some(swap.swap_id),
+                swap.monero_subaddr_index.unwrap_or_default(),
+                &deposit.txids,
+                deposit.amount.min(swap.xmr_amount),
+                StrayReason::Unsettled,
+            )
+            .await?;
+        self.fail_swap(
+            swap.swap_id,
+            "Expired before Bob escrowed its USDC; Alice's XMR is held as an unsettled stray deposit".to_string(),
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Submit the swap's `kind` transaction, or follow the one already
+    /// sent, and return whether it has confirmed. One that expired or
+    /// failed without confirming is signed afresh on the next call.
+    async fn submit_until_confirmed(
+        &self,
+        swap_id: [u8; 32],
+        kind: SolanaTxKind,
+        instruction: Instruction,
+        deadline: DateTime<Utc>,
+    ) -> Result<bool> {
+        if let Some(tx) = self.transactions.active(swap_id, kind).await? {
+            return Ok(tx.state == SolanaTxState::Confirmed);
+        }
+        let signature = self.transactions.submit(swap_id, kind, vec![instruction], deadline).await?;
+        tracing::info!("Submitted {} of swap {}: {}", kind.as_str(), hex::encode(swap_id), signature);
+        Ok(false)
+    }
+
+    /// Resume swaps that were live at shutdown and re-reserve the inventory
+    /// they still hold. A swap whose side Bob pays, the XMR of a USDC→XMR
+    /// swap or the USDC escrow of an XMR→USDC one, already settled its
+    /// reservation. Swaps that ended within the late-deposit window are
+    /// reloaded without a reservation so late deposits are still matched.
     async fn load_persisted_swaps(&self) -> Result<()> {
-        // Load saved swaps from database
-        // This would query SQLite to restore state after restart
-        tracing::info!("Loading persisted swaps from database...");
+        let ended_since = Utc::now() - Duration::hours(LATE_DEPOSIT_WATCH_HOURS);
+        let swaps = self.swaps.resumable(ended_since).await?;
+        let mut active_swaps = self.active_swaps.write().await;
+        for swap in swaps {
+            let settled = swap.state.is_terminal()
+                || match swap.direction {
+                    Direction::UsdcToXmr => swap.monero_txid.is_some(),
+                    Direction::XmrToUsdc => swap.state == SwapState::LockedUsdc,
+                };
+            if !settled {
+                self.inventory
+                    .restore(swap.quote_id, swap.direction, swap.usdc_amount, swap.xmr_amount, swap.expires_at)
+                    .await;
+                self.inventory.bind(swap.quote_id, swap.swap_id, swap.expires_at).await;
+            }
+            active_swaps.insert(swap.swap_id, swap);
+        }
+        tracing::info!("Resumed {} swaps", active_swaps.len());
         Ok(())
     }
 
     async fn persist_swap(&self, swap: &SwapTrade) -> Result<()> {
-        // Persist swap state to database
-        // This would insert/update into SQLite
-        tracing::debug!("Persisting swap: {} ", hex::encode(&swap.swap_id));
-        Ok(())
+        self.swaps.save(swap).await
     }
 
     async fn emit_failed_event(&self, swap: &SwapTrade) -> Result<()> {
         if let Ok(webhook_url) = std::env::var("FAIL_WEBHOOK_URL") {
             let payload = serde_json::json!({
-                "swap_id": hex::encode(&swap.swap_id),
+                "swap_id": hex::encode(swap.swap_id),
                 "state": format!("{:?}", swap.state),
                 "failure_reason": swap.failure_reason,
                 "timestamp": chrono::Utc::now().to_rfc3339(),
@@ -315,43 +1597,661 @@ impl SwapEngine {
         Ok(())
     }
 
-    async fn unlock_xmr(&self, swap: &SwapTrade) -> Result<()> {
-        let monero_address = Self::bytes_to_address_str(&swap.monero_sub_address);
-        let tx_hash = self.monero_client.send_transfer(
-            &monero_address,
-            swap.xmr_amount
-        ).await?;
-        
+    /// Direction A, step 2: once the USDC lock can no longer be reorged
+    /// away, send `xmr_amount` to `A_sub`, Alice's address tagged for the
+    /// swap, and record the transaction as the swap's `monero_txid`.
+    async fn lock_xmr(&self, swap: &SwapTrade) -> Result<()> {
+        let destination = swap.destination
+            .as_deref()
+            .ok_or(DestinationError::Missing(destination_kind(swap.direction)))?;
+        if !self.observations.all_final(swap.swap_id).await? {
+            tracing::debug!("Swap {} waiting for its USDC lock to be final", hex::encode(swap.swap_id));
+            return Ok(());
+        }
+
+        if !self.can_redeem_usdc(swap.swap_id).await? {
+            tracing::warn!("Not paying out swap {}: Bob could not redeem its USDC", hex::encode(swap.swap_id));
+            return Ok(());
+        }
+
+        let address = destination::payout_address(destination, &swap.swap_id)?;
+        let payout = self.pay_out(swap, &address).await?;
+        self.inventory.settle(swap.quote_id).await;
+        if let Some(fee) = payout.fee {
+            self.metrics.add_monero_fee(fee);
+        }
+
         let mut active_swaps = self.active_swaps.write().await;
         if let Some(swap) = active_swaps.get_mut(&swap.swap_id) {
-            swap.state = SwapState::Redeemed;
-            swap.solana_signature = Some(tx_hash);
+            swap.monero_txid = payout.tx_hash;
+            swap.monero_fee = payout.fee;
+            self.persist_swap(swap).await?;
         }
-        
-        self.metrics.increment_swaps_refunded();
-        
         Ok(())
     }
 
-    fn bytes_to_address_str(bytes: &[u8; 64]) -> String {
-        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
-        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    /// Whether the XMR of a USDC-to-XMR swap may go out: only once Bob holds
+    /// the swap's secret and can redeem its USDC with it.
+    async fn can_redeem_usdc(&self, swap_id: [u8; 32]) -> Result<bool> {
+        self.secrets.contains(swap_id).await
+    }
+
+    /// Direction A, step 3: claim the vault with `redeem_usdc`, signing the
+    /// swap's `secret_hash` with its secret. Submitted once; the transaction
+    /// manager rebroadcasts it until it lands or Alice's refund opens.
+    async fn redeem_usdc(&self, swap: &SwapTrade) -> Result<()> {
+        if self.transactions.active(swap.swap_id, SolanaTxKind::Redeem).await?.is_some() {
+            return Ok(());
+        }
+        let secret = self
+            .secrets
+            .get(swap.swap_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No stored secret for swap {}", hex::encode(swap.swap_id)))?;
+        let signed = security::sign_redeem(&secret, &swap.secret_hash)?;
+        let proof = RedeemProof {
+            adaptor_sig: signed.signature,
+            parity: signed.recovery_id,
+            curve_point: signed.curve_point,
+        };
+        let instruction = self.solana_client.redeem_usdc_instruction(swap.swap_id, &proof)?;
+        self.submit_until_confirmed(swap.swap_id, SolanaTxKind::Redeem, instruction, swap.expires_at)
+            .await?;
+        Ok(())
+    }
+
+    /// Mark a swap redeemed and spend its reservation. A swap that already
+    /// ended, and whose reservation was released, is left alone.
+    async fn complete_swap(&self, swap_id: [u8; 32]) -> Result<()> {
+        let swap = {
+            let mut active_swaps = self.active_swaps.write().await;
+            match active_swaps.get_mut(&swap_id) {
+                Some(swap) if !swap.state.is_terminal() => {
+                    swap.state = SwapState::Redeemed;
+                    self.persist_swap(swap).await?;
+                    swap.clone()
+                }
+                _ => return Ok(()),
+            }
+        };
+
+        self.inventory.settle(swap.quote_id).await;
+        self.metrics.increment_swaps_redeemed();
+        Ok(())
+    }
+
+    /// Pay the swap's XMR through the outbox: record the intent, build and
+    /// store the transaction, then relay it. A retry, or a restart, resumes
+    /// from the last recorded step, so the swap is paid exactly once.
+    async fn pay_out(&self, swap: &SwapTrade, address: &str) -> Result<Payout> {
+        // A built transaction's inputs aren't marked spent until it is
+        // relayed, so concurrent builds could pick the same ones
+        let _payout_guard = self.payout_lock.lock().await;
+
+        let payout = self.outbox.record_intent(swap.swap_id, address, swap.xmr_amount).await?;
+        self.send_payout(payout, self.fee_policy.priority(swap.expires_at, Utc::now())).await
+    }
+
+    /// Take a recorded payout from its last step to relayed. The caller
+    /// holds `payout_lock`.
+    async fn send_payout(&self, mut payout: Payout, priority: u32) -> Result<Payout> {
+        loop {
+            match payout.state {
+                PayoutState::Intent => {
+                    let built = self.build_payout(&payout, priority).await?;
+                    if !self.outbox.mark_built(payout.swap_id, &built).await? {
+                        // Another build was stored first; only that one may
+                        // ever be relayed, and this one never leaves the wallet
+                        tracing::warn!(
+                            "Dropping payout {} for {}: another build was stored first",
+                            built.tx_hash,
+                            hex::encode(payout.swap_id)
+                        );
+                    }
+                }
+                PayoutState::Built => self.relay_payout(&payout).await?,
+                PayoutState::Relayed | PayoutState::Confirmed => return Ok(payout),
+            }
+            payout = self.outbox
+                .get(payout.swap_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Payout for {} vanished", hex::encode(payout.swap_id)))?;
+        }
+    }
+
+    /// Build the payout at `priority` within the fee cap, a stray refund
+    /// with the fee taken out of it. An urgent payout that would break the
+    /// cap falls back to normal priority before giving up.
+    async fn build_payout(&self, payout: &Payout, mut priority: u32) -> Result<BuiltTransfer> {
+        loop {
+            let built = match payout.stray_id {
+                Some(_) => self.monero_client.build_refund(&payout.destination, payout.amount, priority).await?,
+                None => self.monero_client.build_transfer(&payout.destination, payout.amount, priority).await?,
+            };
+            match self.fee_policy.rebuild_priority(priority, built.fee)? {
+                None => return Ok(built),
+                Some(normal) => {
+                    tracing::warn!(
+                        "Payout for {} at priority {}: fee {} over the cap; falling back to normal priority",
+                        hex::encode(payout.swap_id),
+                        priority,
+                        built.fee
+                    );
+                    priority = normal;
+                }
+            }
+        }
+    }
+
+    /// Relay a built payout, unless it already reached the network.
+    async fn relay_payout(&self, payout: &Payout) -> Result<()> {
+        let tx_hash = payout.tx_hash.as_deref().unwrap_or_default();
+        if self.payout_seen(tx_hash).await?.is_some() {
+            return self.outbox.mark_relayed(payout.swap_id).await;
+        }
+
+        let tx_metadata = payout.tx_metadata
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Built payout {} has no metadata", tx_hash))?;
+        match self.monero_client.relay_tx(tx_metadata).await {
+            Ok(_) => self.outbox.mark_relayed(payout.swap_id).await,
+            // Only the daemon can tell a transaction spent elsewhere from
+            // this one already mined; the wallet may not have caught up
+            Err(e) if e.is_double_spend() && self.daemon.is_some() => {
+                tracing::warn!(
+                    "Payout {} for swap {} rejected as a double spend; rebuilding",
+                    tx_hash,
+                    hex::encode(payout.swap_id)
+                );
+                self.outbox.discard_built(payout.swap_id).await?;
+                Err(e.into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Height of a payout transaction if the network has it, 0 while it is
+    /// in the pool. The daemon is asked when configured, else the wallet.
+    async fn payout_seen(&self, tx_hash: &str) -> Result<Option<u64>> {
+        if let Some(daemon) = &self.daemon {
+            return Ok(daemon
+                .get_transactions(&[tx_hash])
+                .await?
+                .into_iter()
+                .find(|tx| tx.tx_hash == tx_hash)
+                .map(|tx| tx.block_height));
+        }
+        Ok(self.monero_client
+            .get_transfers(tx_hash)
+            .await?
+            .filter(|transfer| transfer.kind != "failed")
+            .map(|transfer| transfer.height))
     }
 
-    async fn check_adaptor_redeemption(&self, swap: &SwapTrade) -> Result<bool> {
-        if let Ok(Some(onchain_swap)) = self.solana_client.get_swap(swap.swap_id).await {
-            if onchain_swap.is_redeemed {
-                return Ok(true);
+    /// Finish payouts a restart interrupted between building and relaying,
+    /// and follow relayed ones until mined, by their stored hash.
+    async fn reconcile_payouts(&self) -> Result<()> {
+        for payout in self.outbox.unfinished().await? {
+            let tx_hash = payout.tx_hash.clone().unwrap_or_default();
+            match reconcile_payout(&payout, self.payout_seen(&tx_hash).await?) {
+                Reconcile::Relay => {
+                    let _payout_guard = self.payout_lock.lock().await;
+                    let relayable = match payout.stray_id {
+                        // An operator started the refund; it goes unless
+                        // the deposit was resolved some other way
+                        Some(id) => self.strays.get(id).await?.status == "refunding",
+                        None => match self.get_swap_status(payout.swap_id).await {
+                            Some(swap) => payout_relayable(&swap, self.scheduler.now()) && self.can_redeem_usdc(swap.swap_id).await?,
+                            None => false,
+                        },
+                    };
+                    if !relayable {
+                        // Alice can take her USDC back, or Bob could never
+                        // claim it, so the XMR must not go
+                        tracing::warn!(
+                            "Discarding payout {} for swap {}, which has ended, expired or can't be redeemed",
+                            tx_hash,
+                            hex::encode(payout.swap_id)
+                        );
+                        self.outbox.discard_built(payout.swap_id).await?;
+                        continue;
+                    }
+                    tracing::info!("Relaying payout {} left built by a restart", tx_hash);
+                    self.relay_payout(&payout).await?;
+                    self.finish_stray_refund(&payout).await?;
+                }
+                Reconcile::Seen(height) => {
+                    self.outbox.mark_seen(payout.swap_id, height).await?;
+                    self.finish_stray_refund(&payout).await?;
+                }
+                Reconcile::RelayAgain => {
+                    // Dropped from the pool; the same transaction may go again
+                    tracing::warn!("Payout {} dropped from the pool; relaying again", tx_hash);
+                    if let Some(tx_metadata) = &payout.tx_metadata {
+                        self.monero_client.relay_tx(tx_metadata).await?;
+                    }
+                }
+                Reconcile::Wait => {}
             }
-            if onchain_swap.is_refunded {
-                let mut active_swaps = self.active_swaps.write().await;
-                if let Some(swap) = active_swaps.get_mut(&swap.swap_id) {
+        }
+        Ok(())
+    }
+
+    async fn validate_destination(&self, direction: Direction, destination: &str) -> Result<(), DestinationError> {
+        let network = self.config.monero.network.as_deref().unwrap_or("mainnet");
+        destination::validate_destination(&self.monero_client, network, direction, destination).await
+    }
+
+    /// Signed terms; the destination is empty when Alice supplies it only at accept.
+    fn quote_terms(quote: &SwapTrade) -> QuoteTerms<'_> {
+        QuoteTerms {
+            quote_id: quote.quote_id,
+            direction: quote.direction,
+            usdc_amount: quote.usdc_amount,
+            xmr_amount: quote.xmr_amount,
+            secret_hash: quote.secret_hash,
+            destination: quote.destination.as_deref().unwrap_or(""),
+            expiry: quote.expires_at.timestamp(),
+        }
+    }
+
+    /// Whether the program has redeemed or refunded the swap. A refund ends
+    /// the swap here and releases its reservation.
+    async fn check_adaptor_redeemption(&self, swap: &SwapTrade) -> Result<Finalization> {
+        let finalized = self
+            .observe_onchain_swap(swap, |onchain| onchain.swap.is_redeemed || onchain.swap.is_refunded)
+            .await?;
+        let Some(onchain) = finalized else {
+            return Ok(Finalization::Pending);
+        };
+        if onchain.swap.is_redeemed {
+            return Ok(Finalization::Redeemed);
+        }
+
+        let refunded = {
+            let mut active_swaps = self.active_swaps.write().await;
+            match active_swaps.get_mut(&swap.swap_id) {
+                Some(swap) if !swap.state.is_terminal() => {
                     swap.state = SwapState::Refunded;
                     swap.failure_reason = Some("Refunded".to_string());
-                    return Ok(true);
+                    self.persist_swap(swap).await?;
+                    Some(swap.clone())
                 }
+                _ => None,
             }
+        };
+        if let Some(swap) = refunded {
+            self.inventory.release(swap.quote_id).await;
+            self.metrics.increment_swaps_refunded();
         }
-        Ok(false)
+        Ok(Finalization::Refunded)
+    }
+}
+
+/// What the program has done with a swap's vault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Finalization {
+    Redeemed,
+    Refunded,
+    Pending,
+}
+
+/// Whether deposits to the swap's subaddress are matched to it: while it
+/// awaits Alice's XMR, and for a while after it ended without it so late
+/// deposits are recorded against the swap.
+fn watches_deposits(swap: &SwapTrade, now: DateTime<Utc>) -> bool {
+    swap.direction == Direction::XmrToUsdc
+        && swap.monero_subaddr_index.is_some()
+        && (swap.state == SwapState::Quoted
+            || (matches!(swap.state, SwapState::Refunded | SwapState::Failed)
+                && now < swap.expires_at + Duration::hours(LATE_DEPOSIT_WATCH_HOURS)))
+}
+
+/// Whether the swap is past its deadline with nothing of Bob's committed.
+/// Once Bob has paid out a USDC-to-XMR swap's XMR it never expires here:
+/// it ends when the program records the redeem or Alice's refund.
+fn is_expired(swap: &SwapTrade, now: DateTime<Utc>) -> bool {
+    let expirable = matches!(swap.state, SwapState::Quoted | SwapState::LockedUsdc | SwapState::LockedXmr);
+    let xmr_paid = swap.direction == Direction::UsdcToXmr && swap.monero_txid.is_some();
+    expirable && !xmr_paid && now > swap.expires_at
+}
+
+/// Whether a payout built for the swap may still be relayed: the swap is
+/// live and Alice can't yet refund the USDC that pays for it.
+fn payout_relayable(swap: &SwapTrade, now: DateTime<Utc>) -> bool {
+    !swap.state.is_terminal() && !is_expired(swap, now)
+}
+
+/// Return a swap to the state it was in before an invalidated observation
+/// advanced it, forgetting progress made on top of that observation.
+fn roll_back_swap(swap: &mut SwapTrade, observation: &Observation, reason: &str) {
+    swap.state = observation.rollback_to;
+    if observation.chain == Chain::Monero {
+        swap.xmr_deposit = None;
+        if swap.direction == Direction::XmrToUsdc {
+            swap.monero_txid = None;
+        }
+    }
+    if let Some(confirmations) = swap.confirmations.as_mut() {
+        *confirmations = SwapConfirmations::new(confirmations.required.clone());
+    }
+    if swap.state == SwapState::Failed {
+        swap.failure_reason = Some(reason.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::SimulatedChain;
+    use crate::clients::mock_rpc::{self, MockRpc};
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn observations() -> ObservationStore {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        ObservationStore::new(db)
+    }
+
+    fn swap(direction: Direction, state: SwapState) -> SwapTrade {
+        let required = ConfirmationRequirement {
+            monero_confirmations: 10,
+            solana_commitment: "finalized".to_string(),
+            solana_slot_depth: 0,
+        };
+        SwapTrade {
+            swap_id: [3; 32],
+            quote_id: uuid::Uuid::new_v4(),
+            direction,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            secret_hash: [4; 32],
+            monero_sub_address: "8".repeat(95),
+            monero_subaddr_index: Some(1),
+            destination: None,
+            alice_solana: None,
+            state,
+            created_at: Utc::now(),
+            expires_at: Utc::now() + Duration::hours(24),
+            monero_txid: Some("deposit".to_string()),
+            monero_fee: None,
+            solana_signature: None,
+            failure_reason: None,
+            xmr_deposit: None,
+            confirmations: Some(SwapConfirmations {
+                monero_confirmations: 4,
+                solana_observed_slot: Some(100),
+                solana_slot_depth: 2,
+                ..SwapConfirmations::new(required)
+            }),
+        }
+    }
+
+    /// Mine `reference`, record the swap advancing on it, reorg it away and
+    /// return the invalidated observation and the reason.
+    async fn reorged(chain: Chain, swap: &SwapTrade, advanced_to: SwapState, rollback_to: SwapState) -> (Observation, String) {
+        let store = observations().await;
+        let chain = SimulatedChain::new(chain);
+        chain.advance(3);
+        chain.mine(&["deposit"]);
+        let inclusion = chain.locate("deposit").await.unwrap().unwrap();
+        store
+            .record(swap.swap_id, chain.chain(), "deposit", &inclusion, advanced_to, rollback_to)
+            .await
+            .unwrap();
+
+        chain.reorg(1);
+        chain.advance(2);
+
+        let observation = store.pending().await.unwrap().remove(0);
+        let Verdict::Invalidated(reason) = chain::reverify(&chain, &observation, 10).await.unwrap() else {
+            panic!("expected the reorg to invalidate the observation");
+        };
+        store.invalidate(&observation, &reason).await.unwrap();
+        assert!(store.pending().await.unwrap().is_empty());
+        (observation, reason)
+    }
+
+    #[tokio::test]
+    async fn reorged_xmr_deposit_rolls_swap_back_and_forgets_it() {
+        let mut swap = swap(Direction::XmrToUsdc, SwapState::LockedXmr);
+        let (observation, reason) = reorged(Chain::Monero, &swap, SwapState::LockedXmr, SwapState::Quoted).await;
+
+        roll_back_swap(&mut swap, &observation, &reason);
+
+        assert_eq!(swap.state, SwapState::Quoted);
+        assert_eq!(swap.monero_txid, None);
+        assert_eq!(swap.failure_reason, None);
+        let confirmations = swap.confirmations.unwrap();
+        assert_eq!(confirmations.monero_confirmations, 0);
+        assert_eq!(confirmations.solana_observed_slot, None);
+        assert_eq!(confirmations.required.monero_confirmations, 10);
+    }
+
+    #[tokio::test]
+    async fn reorged_payout_keeps_the_sent_txid() {
+        let mut swap = swap(Direction::UsdcToXmr, SwapState::LockedXmr);
+        let (observation, reason) = reorged(Chain::Monero, &swap, SwapState::LockedXmr, SwapState::LockedUsdc).await;
+
+        roll_back_swap(&mut swap, &observation, &reason);
+
+        // Bob's payout is already out; only the state goes back
+        assert_eq!(swap.state, SwapState::LockedUsdc);
+        assert_eq!(swap.monero_txid.as_deref(), Some("deposit"));
+    }
+
+    #[tokio::test]
+    async fn reorged_swap_creation_fails_the_swap() {
+        let mut swap = swap(Direction::UsdcToXmr, SwapState::LockedUsdc);
+        let (observation, reason) = reorged(Chain::Solana, &swap, SwapState::LockedUsdc, SwapState::Failed).await;
+
+        roll_back_swap(&mut swap, &observation, &reason);
+
+        assert_eq!(swap.state, SwapState::Failed);
+        assert_eq!(swap.failure_reason, Some(reason));
+        assert_eq!(swap.monero_txid.as_deref(), Some("deposit"));
+    }
+
+    #[test]
+    fn swap_with_xmr_paid_out_does_not_expire_past_its_deadline() {
+        let past_deadline = Utc::now() + Duration::hours(25);
+
+        let mut paid = swap(Direction::UsdcToXmr, SwapState::LockedXmr);
+        assert!(!is_expired(&paid, past_deadline));
+        paid.state = SwapState::LockedUsdc;
+        assert!(!is_expired(&paid, past_deadline));
+
+        let mut unpaid = swap(Direction::UsdcToXmr, SwapState::LockedUsdc);
+        unpaid.monero_txid = None;
+        assert!(!is_expired(&unpaid, Utc::now()));
+        assert!(is_expired(&unpaid, past_deadline));
+
+        // Alice's deposit is hers, so direction B still expires
+        assert!(is_expired(&swap(Direction::XmrToUsdc, SwapState::LockedXmr), past_deadline));
+        assert!(!is_expired(&swap(Direction::UsdcToXmr, SwapState::Redeemed), past_deadline));
+    }
+
+    #[test]
+    fn deposits_are_matched_until_the_late_deposit_window_closes() {
+        let mut swap = swap(Direction::XmrToUsdc, SwapState::Quoted);
+        let window_end = swap.expires_at + Duration::hours(LATE_DEPOSIT_WATCH_HOURS);
+        assert!(watches_deposits(&swap, Utc::now()));
+        assert!(watches_deposits(&swap, window_end));
+
+        for state in [SwapState::Refunded, SwapState::Failed] {
+            swap.state = state;
+            assert!(watches_deposits(&swap, window_end - Duration::seconds(1)));
+            assert!(!watches_deposits(&swap, window_end));
+        }
+
+        // Past its deposit, anything else sent to the swap is stray
+        for state in [SwapState::LockedXmr, SwapState::LockedUsdc, SwapState::Redeemed] {
+            swap.state = state;
+            assert!(!watches_deposits(&swap, Utc::now()));
+        }
+        assert!(!watches_deposits(&self::swap(Direction::UsdcToXmr, SwapState::Quoted), Utc::now()));
+    }
+
+    #[test]
+    fn built_payout_is_relayed_only_for_a_live_unexpired_swap() {
+        let now = Utc::now();
+        let past_deadline = now + Duration::hours(25);
+        let mut swap = swap(Direction::UsdcToXmr, SwapState::LockedUsdc);
+        swap.monero_txid = None;
+
+        assert!(payout_relayable(&swap, now));
+        assert!(!payout_relayable(&swap, past_deadline));
+        for state in [SwapState::Refunded, SwapState::Failed, SwapState::Redeemed] {
+            swap.state = state;
+            assert!(!payout_relayable(&swap, now));
+        }
+    }
+
+    /// An engine whose wallet-rpc looks up every transaction on
+    /// subaddress 1 unless `lookup` fails, and takes `refresh_delay` to
+    /// fail each refresh. Its Solana RPC fails every call.
+    async fn notified_engine(lookup: Result<(), i64>, refresh_delay: std::time::Duration) -> (SwapEngine, MockRpc, MockRpc) {
+        let wallet = MockRpc::wallet(move |method, params| match method {
+            "get_transfer_by_txid" => match lookup {
+                Ok(()) => Ok(serde_json::json!({ "transfer": {
+                    "txid": params["txid"],
+                    "amount": 1_000_000_000_000u64,
+                    "subaddr_index": { "major": 0, "minor": 1 },
+                    "type": "pool",
+                }})),
+                Err(code) => Err((code, "wallet busy".to_string())),
+            },
+            "refresh" => {
+                std::thread::sleep(refresh_delay);
+                Err((-1, "refresh failed".to_string()))
+            }
+            _ => Err((-32601, format!("unexpected {}", method))),
+        })
+        .await;
+        let solana = MockRpc::start(|method, _| Err((-32601, format!("unexpected {}", method)))).await;
+        (mock_rpc::swap_engine(&wallet, &solana).await, wallet, solana)
+    }
+
+    async fn track(engine: &SwapEngine, swap_id: [u8; 32], mut swap: SwapTrade) {
+        swap.swap_id = swap_id;
+        engine.active_swaps.write().await.insert(swap_id, swap);
+    }
+
+    fn due(engine: &SwapEngine) -> Vec<[u8; 32]> {
+        engine.scheduler.pop_due(engine.scheduler.now())
+    }
+
+    #[tokio::test]
+    async fn notified_deposit_wakes_the_swap_on_its_subaddress() {
+        let (engine, wallet, _solana) = notified_engine(Ok(()), std::time::Duration::ZERO).await;
+        let mut quoted = swap(Direction::XmrToUsdc, SwapState::Quoted);
+        quoted.monero_txid = None;
+        track(&engine, [3; 32], quoted.clone()).await;
+        quoted.monero_subaddr_index = Some(2);
+        track(&engine, [5; 32], quoted).await;
+
+        engine.notify_monero_tx("incoming").await;
+
+        assert_eq!(wallet.calls("get_transfer_by_txid"), vec![serde_json::json!({ "txid": "incoming" })]);
+        assert_eq!(due(&engine), vec![[3; 32]]);
+        assert!(engine.scheduler.take_sweep_request());
+    }
+
+    #[tokio::test]
+    async fn failed_lookup_still_wakes_the_swap_by_txid_and_leaves_the_rest_to_the_sweep() {
+        let (engine, _wallet, _solana) = notified_engine(Err(-1), std::time::Duration::ZERO).await;
+        track(&engine, [3; 32], swap(Direction::UsdcToXmr, SwapState::LockedUsdc)).await;
+        let mut quoted = swap(Direction::XmrToUsdc, SwapState::Quoted);
+        quoted.monero_txid = None;
+        track(&engine, [5; 32], quoted).await;
+
+        engine.notify_monero_tx("deposit").await;
+
+        // The deposit scan finds whatever the lookup would have
+        assert_eq!(due(&engine), vec![[3; 32]]);
+        assert!(engine.scheduler.take_sweep_request());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn block_notification_does_not_wait_for_the_wallet_refresh() {
+        let (engine, wallet, _solana) = notified_engine(Ok(()), std::time::Duration::from_millis(500)).await;
+        track(&engine, [3; 32], swap(Direction::UsdcToXmr, SwapState::LockedUsdc)).await;
+
+        tokio::time::timeout(std::time::Duration::from_millis(200), engine.notify_monero_block())
+            .await
+            .expect("the notification waited on the refresh");
+        assert_eq!(due(&engine), vec![[3; 32]]);
+        assert!(engine.scheduler.take_sweep_request());
+
+        // The failed refresh asks for another sweep once it gives up
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !engine.scheduler.take_sweep_request() {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("no sweep after the failed refresh");
+        assert_eq!(wallet.calls("refresh").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn slot_depth_counts_from_the_transaction_that_settled_the_swap() {
+        use crate::clients::solana_program::SwapAccount;
+        use base64::Engine;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let settled = SwapAccount {
+            direction: Direction::UsdcToXmr,
+            swap_id: [3; 32],
+            alice: [2; 32],
+            bob: mock_rpc::solana_authority(),
+            secret_hash: [4; 32],
+            expiry: 0,
+            relayer_fee: 0,
+            is_redeemed: true,
+            is_refunded: false,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            monero_sub_address: [0; 64],
+            monero_lock_txid: [0; 32],
+            alice_solana: [2; 32],
+            bump: 255,
+            vtc_opened: false,
+            bob_collateral_locked: true,
+            alice_collateral_locked: false,
+            bounty_claimed: false,
+        };
+        // The redeem landed in slot 100; the engine first reads the account
+        // at slot 104
+        let context_slot = Arc::new(AtomicU64::new(104));
+        let slot = context_slot.clone();
+        let solana = MockRpc::start(move |method, params| match method {
+            "getAccountInfo" if params[1]["encoding"] == "base64" => Ok(serde_json::json!({
+                "context": { "slot": slot.load(Ordering::SeqCst) },
+                "value": { "data": [base64::engine::general_purpose::STANDARD.encode(settled.encode()), "base64"] },
+            })),
+            "getAccountInfo" => Ok(serde_json::json!({ "context": { "slot": slot.load(Ordering::SeqCst) }, "value": null })),
+            "getSignaturesForAddress" => Ok(serde_json::json!([{ "signature": "redeem", "slot": 100, "err": null }])),
+            _ => Err((-32601, format!("unexpected {}", method))),
+        })
+        .await;
+        let wallet = MockRpc::wallet(|method, _| Err((-32601, format!("unexpected {}", method)))).await;
+        let engine = mock_rpc::swap_engine(&wallet, &solana).await;
+
+        let mut tracked = swap(Direction::UsdcToXmr, SwapState::LockedXmr);
+        let mut confirmations = tracked.confirmations.take().unwrap();
+        confirmations.required.solana_slot_depth = 5;
+        confirmations.solana_observed_slot = None;
+        tracked.confirmations = Some(confirmations);
+        track(&engine, [3; 32], tracked.clone()).await;
+        let redeemed = |onchain: &OnchainSwapInfo| onchain.swap.is_redeemed;
+
+        assert!(engine.observe_onchain_swap(&tracked, redeemed).await.unwrap().is_none());
+        let progress = engine.get_swap_status([3; 32]).await.unwrap().confirmations.unwrap();
+        assert_eq!((progress.solana_observed_slot, progress.solana_slot_depth), (Some(100), 4));
+
+        context_slot.store(105, Ordering::SeqCst);
+        assert!(engine.observe_onchain_swap(&tracked, redeemed).await.unwrap().is_some());
+        assert_eq!(solana.calls("getSignaturesForAddress").len(), 1);
     }
 }
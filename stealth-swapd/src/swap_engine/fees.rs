@@ -0,0 +1,125 @@
+use crate::config::FeeConfig;
+
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FeeError {
+    #[error("Monero fee {fee} exceeds the per-swap cap of {cap} piconero")]
+    ExceedsCap { fee: u64, cap: u64 },
+}
+
+/// Chooses the priority of a Monero payout and caps what it may pay.
+#[derive(Debug, Clone)]
+pub struct FeePolicy {
+    normal_priority: u32,
+    urgent_priority: u32,
+    urgent_within: Duration,
+    max_fee: Option<u64>,
+}
+
+impl FeePolicy {
+    pub fn new(config: Option<&FeeConfig>) -> Self {
+        Self {
+            normal_priority: config.and_then(|c| c.normal_priority).unwrap_or(1),
+            urgent_priority: config.and_then(|c| c.urgent_priority).unwrap_or(3),
+            urgent_within: Duration::minutes(config.and_then(|c| c.urgent_within_minutes).unwrap_or(30) as i64),
+            max_fee: config.and_then(|c| c.max_fee_per_swap),
+        }
+    }
+
+    pub fn normal_priority(&self) -> u32 {
+        self.normal_priority
+    }
+
+    /// Urgent priority once the swap is within `urgent_within` of expiry,
+    /// so a congested pool doesn't push the payout past the deadline.
+    pub fn priority(&self, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> u32 {
+        if expires_at - now <= self.urgent_within {
+            self.urgent_priority
+        } else {
+            self.normal_priority
+        }
+    }
+
+    pub fn check(&self, fee: u64) -> Result<(), FeeError> {
+        match self.max_fee {
+            Some(cap) if fee > cap => Err(FeeError::ExceedsCap { fee, cap }),
+            _ => Ok(()),
+        }
+    }
+
+    /// What to do with a payout built (but not relayed) at `priority` for
+    /// `fee`: nothing when the fee is within the cap, rebuild at normal
+    /// priority when an urgent fee broke it, and give up otherwise.
+    pub fn rebuild_priority(&self, priority: u32, fee: u64) -> Result<Option<u32>, FeeError> {
+        match self.check(fee) {
+            Ok(()) => Ok(None),
+            Err(_) if priority > self.normal_priority => Ok(Some(self.normal_priority)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_fee: Option<u64>) -> FeePolicy {
+        FeePolicy::new(Some(&FeeConfig {
+            normal_priority: Some(1),
+            urgent_priority: Some(3),
+            urgent_within_minutes: Some(30),
+            max_fee_per_swap: max_fee,
+        }))
+    }
+
+    #[test]
+    fn priority_turns_urgent_within_the_window_before_expiry() {
+        let policy = policy(None);
+        let expires_at = Utc::now();
+        let table = [
+            (Duration::hours(24), 1),
+            (Duration::minutes(31), 1),
+            (Duration::minutes(30) + Duration::seconds(1), 1),
+            (Duration::minutes(30), 3),
+            (Duration::minutes(1), 3),
+            (Duration::zero(), 3),
+            (-Duration::minutes(5), 3),
+        ];
+        for (remaining, priority) in table {
+            assert_eq!(policy.priority(expires_at, expires_at - remaining), priority, "{} left", remaining);
+        }
+    }
+
+    #[test]
+    fn defaults_to_priority_1_and_3_within_30_minutes() {
+        let policy = FeePolicy::new(None);
+        let expires_at = Utc::now();
+        assert_eq!(policy.normal_priority(), 1);
+        assert_eq!(policy.priority(expires_at, expires_at - Duration::minutes(31)), 1);
+        assert_eq!(policy.priority(expires_at, expires_at - Duration::minutes(30)), 3);
+        assert!(policy.check(u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn estimates_above_the_cap_are_rejected() {
+        let policy = policy(Some(50_000_000));
+        assert!(policy.check(50_000_000).is_ok());
+        assert!(matches!(
+            policy.check(50_000_001),
+            Err(FeeError::ExceedsCap { fee: 50_000_001, cap: 50_000_000 })
+        ));
+
+        // (built at, fee, outcome)
+        let table = [
+            (3, 40_000_000, Ok(None)),
+            (1, 50_000_000, Ok(None)),
+            (3, 60_000_000, Ok(Some(1))),
+            (1, 60_000_000, Err(())),
+        ];
+        for (priority, fee, outcome) in table {
+            let result = policy.rebuild_priority(priority, fee).map_err(|_| ());
+            assert_eq!(result, outcome, "priority {} fee {}", priority, fee);
+        }
+    }
+}
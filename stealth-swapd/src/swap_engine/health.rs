@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Wallet sync height against the daemon's, from the last check.
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletSync {
+    pub wallet_height: u64,
+    pub daemon_height: u64,
+    pub lag_blocks: u64,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl WalletSync {
+    pub fn new(wallet_height: u64, daemon_height: u64, checked_at: DateTime<Utc>) -> Self {
+        Self {
+            wallet_height,
+            daemon_height,
+            lag_blocks: daemon_height.saturating_sub(wallet_height),
+            checked_at,
+        }
+    }
+
+    /// Whether the wallet is further behind the daemon than quoting allows.
+    pub fn is_lagging(&self, max_lag_blocks: u64) -> bool {
+        self.lag_blocks > max_lag_blocks
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineHealth {
+    pub solana_connected: bool,
+    pub monero_connected: bool,
+    /// `None` when no daemon is configured.
+    pub daemon_connected: Option<bool>,
+    pub wallet_height: Option<u64>,
+    pub daemon_height: Option<u64>,
+    pub wallet_lag_blocks: Option<u64>,
+    pub quoting_paused: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wallet_lags_only_beyond_the_allowed_blocks() {
+        let table = [
+            (100, 100, 0, false),
+            (97, 100, 3, false),
+            (96, 100, 4, true),
+            (0, 100, 100, true),
+            // A wallet ahead of a lagging daemon is not behind
+            (105, 100, 0, false),
+        ];
+        for (wallet_height, daemon_height, lag_blocks, lagging) in table {
+            let sync = WalletSync::new(wallet_height, daemon_height, Utc::now());
+            assert_eq!(sync.lag_blocks, lag_blocks, "wallet {} daemon {}", wallet_height, daemon_height);
+            assert_eq!(sync.is_lagging(3), lagging, "wallet {} daemon {}", wallet_height, daemon_height);
+        }
+        assert!(!WalletSync::new(90, 100, Utc::now()).is_lagging(10));
+        assert!(WalletSync::new(100, 101, Utc::now()).is_lagging(0));
+    }
+}
@@ -1,5 +1,35 @@
 mod models;
 mod engine;
+mod store;
+mod destination;
+mod deposit;
+mod confirmations;
+mod stray;
+mod verification;
+mod retry;
+mod scheduler;
+mod health;
+mod fees;
+mod outbox;
+mod transactions;
+mod presigned;
+mod compute_budget;
+mod secrets;
 
 pub use models::*;
-pub use engine::*;
\ No newline at end of file
+pub use engine::*;
+pub use store::*;
+pub use destination::*;
+pub use deposit::*;
+pub use confirmations::*;
+pub use stray::*;
+pub use verification::*;
+pub use retry::*;
+pub use scheduler::*;
+pub use health::*;
+pub use fees::*;
+pub use outbox::*;
+pub use transactions::*;
+pub use presigned::*;
+pub use compute_budget::*;
+pub use secrets::*;
@@ -1,3 +1,5 @@
+use super::{SwapConfirmations, XmrDeposit};
+
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
@@ -7,6 +9,23 @@ pub enum Direction {
     XmrToUsdc,
 }
 
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::UsdcToXmr => "usdc_to_xmr",
+            Direction::XmrToUsdc => "xmr_to_usdc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "usdc_to_xmr" => Some(Direction::UsdcToXmr),
+            "xmr_to_usdc" => Some(Direction::XmrToUsdc),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SwapState {
     Quoted,
@@ -17,6 +36,35 @@ pub enum SwapState {
     Failed,
 }
 
+impl SwapState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwapState::Quoted => "quoted",
+            SwapState::LockedUsdc => "locked_usdc",
+            SwapState::LockedXmr => "locked_xmr",
+            SwapState::Redeemed => "redeemed",
+            SwapState::Refunded => "refunded",
+            SwapState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "quoted" => Some(SwapState::Quoted),
+            "locked_usdc" => Some(SwapState::LockedUsdc),
+            "locked_xmr" => Some(SwapState::LockedXmr),
+            "redeemed" => Some(SwapState::Redeemed),
+            "refunded" => Some(SwapState::Refunded),
+            "failed" => Some(SwapState::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, SwapState::Redeemed | SwapState::Refunded | SwapState::Failed)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SwapTrade {
     pub swap_id: [u8; 32],
@@ -25,15 +73,25 @@ pub struct SwapTrade {
     pub usdc_amount: u64,
     pub xmr_amount: u64,
     pub secret_hash: [u8; 32],
-    #[serde(with = "serde_bytes")]
-    pub monero_sub_address: [u8; 64],
+    /// Full Monero sub-address Alice pays XMR to.
+    pub monero_sub_address: String,
+    /// Wallet subaddress index of `monero_sub_address`, used to match deposits.
+    pub monero_subaddr_index: Option<u32>,
+    /// Alice's payout address: Monero for USDC→XMR, Solana for XMR→USDC.
+    pub destination: Option<String>,
     pub alice_solana: Option<String>,
     pub state: SwapState,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub monero_txid: Option<String>,
+    /// Fee paid by Bob's Monero payout, in piconero.
+    pub monero_fee: Option<u64>,
     pub solana_signature: Option<String>,
     pub failure_reason: Option<String>,
+    /// Alice's XMR deposit, for XMR→USDC swaps.
+    pub xmr_deposit: Option<XmrDeposit>,
+    /// Finality required for this swap and progress towards it; set at accept.
+    pub confirmations: Option<SwapConfirmations>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,16 +99,81 @@ pub struct QuoteRequest {
     pub direction: Direction,
     pub usdc_amount: u64,
     pub xmr_amount: u64,
+    pub destination: Option<String>,
+}
+
+/// `AcceptRequest` from spec.txt §2.1: the terms Alice locked on-chain,
+/// plus her payout destination and the quote signature she is accepting.
+#[derive(Debug, Clone)]
+pub struct AcceptRequest {
+    pub quote_id: uuid::Uuid,
+    pub swap_id: [u8; 32],
+    pub direction: Direction,
+    pub usdc_amount: u64,
+    pub xmr_amount: u64,
+    pub secret_hash: [u8; 32],
+    pub counterparty_pubkey: String,
+    /// Unix timestamp of the on-chain swap expiry.
+    pub expiry: i64,
+    pub destination: Option<String>,
+    pub quote_signature: String,
+}
+
+fn serialize_hex<S: serde::Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct QuoteResponse {
     pub quote_id: uuid::Uuid,
+    /// Id Alice must use for the on-chain `Swap` PDA, hex like accept takes it.
+    #[serde(serialize_with = "serialize_hex")]
+    pub swap_id: [u8; 32],
     pub expires_at: DateTime<Utc>,
     pub usdc_amount: u64,
     pub xmr_amount: u64,
+    #[serde(serialize_with = "serialize_hex")]
     pub secret_hash: [u8; 32],
+    /// Full Monero sub-address Alice pays XMR to.
+    pub monero_sub_address: String,
+    /// `monero_sub_address` as written to the `Swap` account, which only
+    /// holds its first 64 bytes.
     #[serde(with = "serde_bytes")]
-    pub monero_sub_address: [u8; 64],
+    pub onchain_sub_address: [u8; 64],
+    pub destination: Option<String>,
+    /// `A_sub` derived from a USDC→XMR quote's `destination`, where Bob
+    /// pays the XMR (protocol.md §4.1), for Alice to confirm.
+    pub payout_address: Option<String>,
     pub solana_address: String,
-}
\ No newline at end of file
+    /// Hex Ed25519 signature by `solana_address` over the canonical quote
+    /// terms (see `security::QuoteTerms`).
+    pub signature: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_ids_and_hashes_serialize_as_hex() {
+        let quote = QuoteResponse {
+            quote_id: uuid::Uuid::nil(),
+            swap_id: [0xab; 32],
+            expires_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            secret_hash: [0x01; 32],
+            monero_sub_address: "8".repeat(95),
+            onchain_sub_address: [b'8'; 64],
+            destination: None,
+            payout_address: None,
+            solana_address: "bob".to_string(),
+            signature: "00".to_string(),
+        };
+
+        let json = serde_json::to_value(&quote).unwrap();
+        assert_eq!(json["swap_id"], "ab".repeat(32));
+        assert_eq!(json["secret_hash"], "01".repeat(32));
+        assert_eq!(hex::decode(json["swap_id"].as_str().unwrap()).unwrap(), quote.swap_id);
+    }
+}
@@ -0,0 +1,479 @@
+use crate::clients::monero::BuiltTransfer;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutState {
+    /// Recorded; no transaction built yet, so building one is safe.
+    Intent,
+    /// Built and signed but possibly not relayed. Only this transaction
+    /// may ever be relayed for the swap.
+    Built,
+    /// Handed to the daemon.
+    Relayed,
+    /// Mined.
+    Confirmed,
+}
+
+impl PayoutState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutState::Intent => "intent",
+            PayoutState::Built => "built",
+            PayoutState::Relayed => "relayed",
+            PayoutState::Confirmed => "confirmed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "intent" => Some(PayoutState::Intent),
+            "built" => Some(PayoutState::Built),
+            "relayed" => Some(PayoutState::Relayed),
+            "confirmed" => Some(PayoutState::Confirmed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Payout {
+    /// The swap paid, or for a stray refund `stray_refund_key` of the
+    /// deposit.
+    pub swap_id: [u8; 32],
+    /// The stray deposit this payout refunds, if it refunds one.
+    pub stray_id: Option<uuid::Uuid>,
+    pub destination: String,
+    pub amount: u64,
+    pub state: PayoutState,
+    pub tx_hash: Option<String>,
+    #[serde(skip)]
+    pub tx_key: Option<String>,
+    #[serde(skip)]
+    pub tx_blob: Option<String>,
+    #[serde(skip)]
+    pub tx_metadata: Option<String>,
+    pub fee: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outbox key of the refund of a stray deposit, apart from any swap's.
+pub fn stray_refund_key(stray_id: uuid::Uuid) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"stray-refund");
+    hasher.update(stray_id.as_bytes());
+    hasher.finalize().into()
+}
+
+/// What a restart does with an unfinished payout, by whether the network
+/// has seen its transaction: `Some(0)` in the pool, `Some(height)` mined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconcile {
+    /// Built and never seen: relay the stored transaction, if the swap may
+    /// still be paid, and never a new one.
+    Relay,
+    /// Seen at this height: record how far it got.
+    Seen(u64),
+    /// Relayed but gone from the pool: the same transaction may go again.
+    RelayAgain,
+    /// Relayed and still waiting in the pool.
+    Wait,
+}
+
+pub fn reconcile_payout(payout: &Payout, seen: Option<u64>) -> Reconcile {
+    match (payout.state, seen) {
+        (PayoutState::Relayed, Some(0)) => Reconcile::Wait,
+        (_, Some(height)) => Reconcile::Seen(height),
+        (PayoutState::Relayed, None) => Reconcile::RelayAgain,
+        (_, None) => Reconcile::Relay,
+    }
+}
+
+/// SQLite outbox of XMR payouts. The intent is written before the wallet
+/// is asked for anything and each step after it is recorded before the
+/// next is taken, so a crash at any point resumes without paying twice.
+#[derive(Clone)]
+pub struct PayoutOutbox {
+    db: SqlitePool,
+}
+
+impl PayoutOutbox {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// The swap's payout, recording the intent first if there is none yet.
+    /// An existing entry wins, whatever destination and amount it has.
+    pub async fn record_intent(&self, swap_id: [u8; 32], destination: &str, amount: u64) -> Result<Payout> {
+        self.insert_intent(swap_id, None, destination, amount).await
+    }
+
+    /// The refund of a stray deposit, recording its intent first like a
+    /// swap's payout.
+    pub async fn record_stray_refund(&self, stray_id: uuid::Uuid, destination: &str, amount: u64) -> Result<Payout> {
+        self.insert_intent(stray_refund_key(stray_id), Some(stray_id), destination, amount).await
+    }
+
+    async fn insert_intent(
+        &self,
+        swap_id: [u8; 32],
+        stray_id: Option<uuid::Uuid>,
+        destination: &str,
+        amount: u64,
+    ) -> Result<Payout> {
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO payout_outbox (swap_id, stray_id, destination, amount, state, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, 'intent', ?, ?) ON CONFLICT (swap_id) DO NOTHING",
+        )
+        .bind(swap_id.to_vec())
+        .bind(stray_id.map(|id| id.to_string()))
+        .bind(destination)
+        .bind(amount as i64)
+        .bind(now)
+        .bind(now)
+        .execute(&self.db)
+        .await?;
+
+        self.get(swap_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Payout for {} vanished", hex::encode(swap_id)))
+    }
+
+    pub async fn get(&self, swap_id: [u8; 32]) -> Result<Option<Payout>> {
+        let row = sqlx::query(
+            "SELECT swap_id, stray_id, destination, amount, state, tx_hash, tx_key, tx_blob, tx_metadata, fee, created_at \
+             FROM payout_outbox WHERE swap_id = ?",
+        )
+        .bind(swap_id.to_vec())
+        .fetch_optional(&self.db)
+        .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    /// Payouts built or relayed but not yet seen mined.
+    pub async fn unfinished(&self) -> Result<Vec<Payout>> {
+        let rows = sqlx::query(
+            "SELECT swap_id, stray_id, destination, amount, state, tx_hash, tx_key, tx_blob, tx_metadata, fee, created_at \
+             FROM payout_outbox WHERE state IN ('built', 'relayed') ORDER BY created_at",
+        )
+        .fetch_all(&self.db)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// Store the signed transaction. Only an intent can be built, so a
+    /// second build racing the first is discarded.
+    pub async fn mark_built(&self, swap_id: [u8; 32], built: &BuiltTransfer) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE payout_outbox SET state = 'built', tx_hash = ?, tx_key = ?, tx_blob = ?, tx_metadata = ?, \
+             fee = ?, updated_at = ? WHERE swap_id = ? AND state = 'intent'",
+        )
+        .bind(&built.tx_hash)
+        .bind(&built.tx_key)
+        .bind(&built.tx_blob)
+        .bind(&built.tx_metadata)
+        .bind(built.fee as i64)
+        .bind(Utc::now())
+        .bind(swap_id.to_vec())
+        .execute(&self.db)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn mark_relayed(&self, swap_id: [u8; 32]) -> Result<()> {
+        self.set_state(swap_id, PayoutState::Built, PayoutState::Relayed).await
+    }
+
+    pub async fn mark_confirmed(&self, swap_id: [u8; 32]) -> Result<()> {
+        self.set_state(swap_id, PayoutState::Relayed, PayoutState::Confirmed).await
+    }
+
+    /// Record a payout's transaction seen at `height`: relayed once in the
+    /// pool, confirmed once mined.
+    pub async fn mark_seen(&self, swap_id: [u8; 32], height: u64) -> Result<()> {
+        self.mark_relayed(swap_id).await?;
+        if height > 0 {
+            self.mark_confirmed(swap_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Forget a built transaction the daemon rejected as a double spend. Its
+    /// inputs are spent elsewhere, so it can never be mined and a new one
+    /// may be built in its place.
+    pub async fn discard_built(&self, swap_id: [u8; 32]) -> Result<()> {
+        sqlx::query(
+            "UPDATE payout_outbox SET state = 'intent', tx_hash = NULL, tx_key = NULL, tx_blob = NULL, \
+             tx_metadata = NULL, fee = NULL, updated_at = ? WHERE swap_id = ? AND state = 'built'",
+        )
+        .bind(Utc::now())
+        .bind(swap_id.to_vec())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Forget a payout nothing was built for yet, so it can be started
+    /// afresh. Returns whether there was one; a built payout stays.
+    pub async fn discard_intent(&self, swap_id: [u8; 32]) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM payout_outbox WHERE swap_id = ? AND state = 'intent'")
+            .bind(swap_id.to_vec())
+            .execute(&self.db)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn set_state(&self, swap_id: [u8; 32], from: PayoutState, to: PayoutState) -> Result<()> {
+        sqlx::query("UPDATE payout_outbox SET state = ?, updated_at = ? WHERE swap_id = ? AND state = ?")
+            .bind(to.as_str())
+            .bind(Utc::now())
+            .bind(swap_id.to_vec())
+            .bind(from.as_str())
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Payout> {
+        let swap_id: Vec<u8> = row.try_get("swap_id")?;
+        let stray_id: Option<String> = row.try_get("stray_id")?;
+        let state: String = row.try_get("state")?;
+
+        Ok(Payout {
+            swap_id: swap_id.try_into().map_err(|_| anyhow::anyhow!("Corrupt swap_id in payout_outbox"))?,
+            stray_id: stray_id.as_deref().map(uuid::Uuid::parse_str).transpose()?,
+            destination: row.try_get("destination")?,
+            amount: row.try_get::<i64, _>("amount")? as u64,
+            state: PayoutState::parse(&state).ok_or_else(|| anyhow::anyhow!("Unknown payout state {}", state))?,
+            tx_hash: row.try_get("tx_hash")?,
+            tx_key: row.try_get("tx_key")?,
+            tx_blob: row.try_get("tx_blob")?,
+            tx_metadata: row.try_get("tx_metadata")?,
+            fee: row.try_get::<Option<i64>, _>("fee")?.map(|fee| fee as u64),
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::HashMap;
+
+    const SWAP: [u8; 32] = [1; 32];
+
+    async fn outbox() -> PayoutOutbox {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        PayoutOutbox::new(db)
+    }
+
+    fn built(tx_hash: &str) -> BuiltTransfer {
+        BuiltTransfer {
+            tx_hash: tx_hash.to_string(),
+            tx_key: format!("{}-key", tx_hash),
+            tx_blob: format!("{}-blob", tx_hash),
+            tx_metadata: format!("{}-metadata", tx_hash),
+            fee: 30_000_000,
+        }
+    }
+
+    async fn state(outbox: &PayoutOutbox) -> PayoutState {
+        outbox.get(SWAP).await.unwrap().unwrap().state
+    }
+
+    /// Replay a restart's pass over the unfinished payouts against what the
+    /// network has seen, relaying through `relay`.
+    async fn restart(outbox: &PayoutOutbox, seen: &HashMap<String, u64>, relay: &mut Vec<String>) {
+        for payout in outbox.unfinished().await.unwrap() {
+            let tx_hash = payout.tx_hash.clone().unwrap();
+            match reconcile_payout(&payout, seen.get(&tx_hash).copied()) {
+                Reconcile::Relay => {
+                    relay.push(tx_hash);
+                    outbox.mark_relayed(payout.swap_id).await.unwrap();
+                }
+                Reconcile::Seen(height) => outbox.mark_seen(payout.swap_id, height).await.unwrap(),
+                Reconcile::RelayAgain => relay.push(tx_hash),
+                Reconcile::Wait => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn a_payout_moves_from_intent_through_built_and_relayed_to_confirmed() {
+        let outbox = outbox().await;
+
+        let payout = outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        assert_eq!(payout.state, PayoutState::Intent);
+        assert!(payout.tx_hash.is_none());
+
+        assert!(outbox.mark_built(SWAP, &built("aa")).await.unwrap());
+        let payout = outbox.get(SWAP).await.unwrap().unwrap();
+        assert_eq!(payout.state, PayoutState::Built);
+        assert_eq!(payout.tx_hash.as_deref(), Some("aa"));
+        assert_eq!(payout.tx_metadata.as_deref(), Some("aa-metadata"));
+        assert_eq!(payout.fee, Some(30_000_000));
+
+        outbox.mark_relayed(SWAP).await.unwrap();
+        assert_eq!(state(&outbox).await, PayoutState::Relayed);
+        outbox.mark_confirmed(SWAP).await.unwrap();
+        assert_eq!(state(&outbox).await, PayoutState::Confirmed);
+        assert!(outbox.unfinished().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn steps_out_of_order_change_nothing() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+
+        // Nothing built yet, so nothing can be relayed or confirmed
+        outbox.mark_relayed(SWAP).await.unwrap();
+        outbox.mark_confirmed(SWAP).await.unwrap();
+        assert_eq!(state(&outbox).await, PayoutState::Intent);
+
+        outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+        outbox.mark_confirmed(SWAP).await.unwrap();
+        assert_eq!(state(&outbox).await, PayoutState::Built);
+    }
+
+    #[tokio::test]
+    async fn the_first_intent_wins() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+
+        let payout = outbox.record_intent(SWAP, "4other", 2_000).await.unwrap();
+        assert_eq!(payout.destination, "4dest");
+        assert_eq!(payout.amount, 1_000);
+    }
+
+    #[tokio::test]
+    async fn discarding_a_built_payout_returns_it_to_intent() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+
+        outbox.discard_built(SWAP).await.unwrap();
+        let payout = outbox.get(SWAP).await.unwrap().unwrap();
+        assert_eq!(payout.state, PayoutState::Intent);
+        assert!(payout.tx_hash.is_none() && payout.tx_metadata.is_none() && payout.fee.is_none());
+
+        // And a fresh build may take its place
+        assert!(outbox.mark_built(SWAP, &built("bb")).await.unwrap());
+        assert_eq!(outbox.get(SWAP).await.unwrap().unwrap().tx_hash.as_deref(), Some("bb"));
+    }
+
+    #[tokio::test]
+    async fn a_relayed_payout_is_never_discarded() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+        outbox.mark_relayed(SWAP).await.unwrap();
+
+        outbox.discard_built(SWAP).await.unwrap();
+        let payout = outbox.get(SWAP).await.unwrap().unwrap();
+        assert_eq!(payout.state, PayoutState::Relayed);
+        assert_eq!(payout.tx_hash.as_deref(), Some("aa"));
+    }
+
+    #[tokio::test]
+    async fn a_second_build_loses_the_race_and_leaves_the_first() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+
+        assert!(outbox.mark_built(SWAP, &built("aa")).await.unwrap());
+        assert!(!outbox.mark_built(SWAP, &built("bb")).await.unwrap());
+
+        let payout = outbox.get(SWAP).await.unwrap().unwrap();
+        assert_eq!(payout.tx_hash.as_deref(), Some("aa"));
+        assert_eq!(payout.tx_metadata.as_deref(), Some("aa-metadata"));
+    }
+
+    #[tokio::test]
+    async fn a_stray_refund_is_kept_apart_from_swap_payouts() {
+        let outbox = outbox().await;
+        let stray_id = uuid::Uuid::new_v4();
+
+        let refund = outbox.record_stray_refund(stray_id, "4refund", 500).await.unwrap();
+        assert_eq!(refund.swap_id, stray_refund_key(stray_id));
+        assert_eq!(refund.stray_id, Some(stray_id));
+        assert_eq!(outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap().stray_id, None);
+    }
+
+    #[tokio::test]
+    async fn only_an_unbuilt_intent_is_discarded() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        assert!(outbox.discard_intent(SWAP).await.unwrap());
+        assert!(outbox.get(SWAP).await.unwrap().is_none());
+
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+        assert!(!outbox.discard_intent(SWAP).await.unwrap());
+        assert_eq!(state(&outbox).await, PayoutState::Built);
+    }
+
+    #[tokio::test]
+    async fn a_crash_before_the_build_leaves_only_the_intent() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+
+        // Nothing to relay on restart: the swap builds again from the intent
+        assert!(outbox.unfinished().await.unwrap().is_empty());
+        assert_eq!(outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap().state, PayoutState::Intent);
+    }
+
+    #[tokio::test]
+    async fn a_crash_after_the_build_relays_the_stored_transaction() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+
+        let mut relayed = Vec::new();
+        restart(&outbox, &HashMap::new(), &mut relayed).await;
+        assert_eq!(relayed, ["aa"]);
+        assert_eq!(state(&outbox).await, PayoutState::Relayed);
+    }
+
+    #[tokio::test]
+    async fn a_crash_after_the_relay_before_recording_it_does_not_relay_again() {
+        for (height, expected) in [(0, PayoutState::Relayed), (120, PayoutState::Confirmed)] {
+            let outbox = outbox().await;
+            outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+            outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+
+            let mut relayed = Vec::new();
+            restart(&outbox, &HashMap::from([("aa".to_string(), height)]), &mut relayed).await;
+            assert!(relayed.is_empty());
+            assert_eq!(state(&outbox).await, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_crash_after_recording_the_relay_follows_it_until_mined() {
+        let outbox = outbox().await;
+        outbox.record_intent(SWAP, "4dest", 1_000).await.unwrap();
+        outbox.mark_built(SWAP, &built("aa")).await.unwrap();
+        outbox.mark_relayed(SWAP).await.unwrap();
+
+        let mut relayed = Vec::new();
+        restart(&outbox, &HashMap::from([("aa".to_string(), 0)]), &mut relayed).await;
+        assert!(relayed.is_empty());
+        assert_eq!(state(&outbox).await, PayoutState::Relayed);
+
+        // Dropped from the pool: the same transaction goes again
+        restart(&outbox, &HashMap::new(), &mut relayed).await;
+        assert_eq!(relayed, ["aa"]);
+        assert_eq!(state(&outbox).await, PayoutState::Relayed);
+
+        restart(&outbox, &HashMap::from([("aa".to_string(), 120)]), &mut relayed).await;
+        assert_eq!(state(&outbox).await, PayoutState::Confirmed);
+        assert!(outbox.unfinished().await.unwrap().is_empty());
+    }
+}
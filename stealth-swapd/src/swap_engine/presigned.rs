@@ -0,0 +1,328 @@
+use crate::clients::solana_program::encode_pubkey;
+use crate::clients::solana_tx::SignedTransaction;
+use crate::security::{self, KeyDerivation};
+use crate::swap_engine::SolanaTxKind;
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use secrecy::SecretString;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresignedState {
+    /// Held for broadcast after the deadline; its nonce account is reserved.
+    Stored,
+    /// Handed to the transaction manager.
+    Submitted,
+    /// The swap settled another way, so the transaction can never succeed.
+    Void,
+}
+
+impl PresignedState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PresignedState::Stored => "stored",
+            PresignedState::Submitted => "submitted",
+            PresignedState::Void => "void",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stored" => Some(PresignedState::Stored),
+            "submitted" => Some(PresignedState::Submitted),
+            "void" => Some(PresignedState::Void),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedTx {
+    pub swap_id: [u8; 32],
+    pub kind: SolanaTxKind,
+    pub signature: String,
+    pub nonce_account: String,
+    pub blockhash: String,
+    /// Unix time before which the program rejects the transaction.
+    pub not_before: i64,
+    pub state: PresignedState,
+    #[serde(skip)]
+    sealed_tx: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A pre-signed transaction anyone can broadcast with `sendTransaction`
+/// once `not_before` has passed.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryTransaction {
+    pub swap_id: String,
+    pub kind: SolanaTxKind,
+    pub signature: String,
+    pub not_before: i64,
+    pub nonce_account: String,
+    pub tx_base64: String,
+}
+
+/// Everything needed to reclaim Bob's escrowed USDC without the daemon.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryBundle {
+    pub generated_at: DateTime<Utc>,
+    pub transactions: Vec<RecoveryTransaction>,
+}
+
+/// Transactions signed ahead of their deadline, encrypted at rest. Each is
+/// signed against a durable nonce account it holds until it is submitted
+/// or void, so it stays valid however long the daemon is away.
+#[derive(Clone)]
+pub struct PresignedStore {
+    db: SqlitePool,
+    key: Option<KeyDerivation>,
+}
+
+impl PresignedStore {
+    /// Pre-signing is disabled without a passphrase.
+    pub fn new(db: SqlitePool, passphrase: Option<SecretString>) -> Self {
+        Self {
+            db,
+            key: passphrase.map(KeyDerivation::new),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub async fn insert(
+        &self,
+        swap_id: [u8; 32],
+        kind: SolanaTxKind,
+        signed: &SignedTransaction,
+        nonce_account: &str,
+        nonce: &[u8; 32],
+        not_before: i64,
+    ) -> Result<()> {
+        let key = self.key()?;
+        let sealed = security::seal(key.encryption_key(), &Self::context(swap_id, kind), &signed.bytes);
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO presigned_transactions \
+             (swap_id, kind, signature, sealed_tx, nonce_account, blockhash, not_before, state, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, 'stored', ?, ?)",
+        )
+        .bind(swap_id.to_vec())
+        .bind(kind.as_str())
+        .bind(&signed.signature)
+        .bind(sealed)
+        .bind(nonce_account)
+        .bind(encode_pubkey(nonce))
+        .bind(not_before)
+        .bind(now)
+        .bind(now)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, swap_id: [u8; 32], kind: SolanaTxKind) -> Result<Option<PresignedTx>> {
+        let row = sqlx::query("SELECT * FROM presigned_transactions WHERE swap_id = ? AND kind = ?")
+            .bind(swap_id.to_vec())
+            .bind(kind.as_str())
+            .fetch_optional(&self.db)
+            .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    pub async fn stored(&self) -> Result<Vec<PresignedTx>> {
+        let rows = sqlx::query("SELECT * FROM presigned_transactions WHERE state = 'stored' ORDER BY not_before")
+            .fetch_all(&self.db)
+            .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// The signed transaction's wire bytes.
+    pub fn decrypt(&self, tx: &PresignedTx) -> Result<Vec<u8>> {
+        let key = self.key()?;
+        Ok(security::open(key.encryption_key(), &Self::context(tx.swap_id, tx.kind), &tx.sealed_tx)?)
+    }
+
+    pub async fn mark_submitted(&self, swap_id: [u8; 32], kind: SolanaTxKind) -> Result<()> {
+        self.set_state(swap_id, kind, PresignedState::Submitted).await
+    }
+
+    pub async fn void(&self, swap_id: [u8; 32], kind: SolanaTxKind) -> Result<()> {
+        self.set_state(swap_id, kind, PresignedState::Void).await
+    }
+
+    /// Decrypt every stored transaction for export.
+    pub async fn recovery_bundle(&self) -> Result<RecoveryBundle> {
+        let transactions = self
+            .stored()
+            .await?
+            .into_iter()
+            .map(|tx| {
+                Ok(RecoveryTransaction {
+                    tx_base64: base64::engine::general_purpose::STANDARD.encode(self.decrypt(&tx)?),
+                    swap_id: hex::encode(tx.swap_id),
+                    kind: tx.kind,
+                    signature: tx.signature,
+                    not_before: tx.not_before,
+                    nonce_account: tx.nonce_account,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RecoveryBundle {
+            generated_at: Utc::now(),
+            transactions,
+        })
+    }
+
+    fn key(&self) -> Result<&KeyDerivation> {
+        self.key
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Pre-signing is disabled: solana.presign_key_env is unset or empty"))
+    }
+
+    /// Binds a sealed transaction to its row.
+    fn context(swap_id: [u8; 32], kind: SolanaTxKind) -> Vec<u8> {
+        let mut context = swap_id.to_vec();
+        context.extend_from_slice(kind.as_str().as_bytes());
+        context
+    }
+
+    async fn set_state(&self, swap_id: [u8; 32], kind: SolanaTxKind, state: PresignedState) -> Result<()> {
+        sqlx::query(
+            "UPDATE presigned_transactions SET state = ?, updated_at = ? \
+             WHERE swap_id = ? AND kind = ? AND state = 'stored'",
+        )
+        .bind(state.as_str())
+        .bind(Utc::now())
+        .bind(swap_id.to_vec())
+        .bind(kind.as_str())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<PresignedTx> {
+        let swap_id: Vec<u8> = row.try_get("swap_id")?;
+        let kind: String = row.try_get("kind")?;
+        let state: String = row.try_get("state")?;
+
+        Ok(PresignedTx {
+            swap_id: swap_id.try_into().map_err(|_| anyhow::anyhow!("Corrupt swap_id in presigned_transactions"))?,
+            kind: SolanaTxKind::parse(&kind).ok_or_else(|| anyhow::anyhow!("Unknown transaction kind {}", kind))?,
+            signature: row.try_get("signature")?,
+            nonce_account: row.try_get("nonce_account")?,
+            blockhash: row.try_get("blockhash")?,
+            not_before: row.try_get("not_before")?,
+            state: PresignedState::parse(&state).ok_or_else(|| anyhow::anyhow!("Unknown presigned state {}", state))?,
+            sealed_tx: row.try_get("sealed_tx")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn store(passphrase: Option<&str>) -> PresignedStore {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        PresignedStore::new(db, passphrase.map(|p| SecretString::new(p.to_string())))
+    }
+
+    fn signed(byte: u8) -> SignedTransaction {
+        SignedTransaction {
+            signature: format!("sig-{}", byte),
+            bytes: vec![byte; 200],
+        }
+    }
+
+    async fn insert(store: &PresignedStore, byte: u8, not_before: i64) {
+        store
+            .insert([byte; 32], SolanaTxKind::Refund, &signed(byte), &format!("nonce-{}", byte), &[byte; 32], not_before)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn stored_transaction_decrypts_to_the_signed_bytes() {
+        let store = store(Some("passphrase")).await;
+        insert(&store, 1, 1_700_000_000).await;
+
+        let tx = store.get([1; 32], SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!(tx.state, PresignedState::Stored);
+        assert_eq!((tx.signature.as_str(), tx.nonce_account.as_str()), ("sig-1", "nonce-1"));
+        assert_eq!(tx.blockhash, encode_pubkey(&[1; 32]));
+        assert_ne!(tx.sealed_tx, signed(1).bytes);
+        assert_eq!(store.decrypt(&tx).unwrap(), signed(1).bytes);
+        assert!(store.get([1; 32], SolanaTxKind::Redeem).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn another_key_or_a_tampered_row_does_not_decrypt() {
+        let store = store(Some("passphrase")).await;
+        insert(&store, 1, 1_700_000_000).await;
+        let tx = store.get([1; 32], SolanaTxKind::Refund).await.unwrap().unwrap();
+
+        let other = PresignedStore::new(store.db.clone(), Some(SecretString::new("other".to_string())));
+        assert!(other.decrypt(&tx).is_err());
+        assert!(other.recovery_bundle().await.is_err());
+
+        // Sealed for another swap's row
+        let moved = PresignedTx { swap_id: [2; 32], ..tx.clone() };
+        assert!(store.decrypt(&moved).is_err());
+
+        let mut tampered = tx;
+        let last = tampered.sealed_tx.len() - 1;
+        tampered.sealed_tx[last] ^= 1;
+        assert!(store.decrypt(&tampered).is_err());
+
+        assert!(PresignedStore::new(store.db.clone(), None).decrypt(&tampered).is_err());
+    }
+
+    #[tokio::test]
+    async fn recovery_bundle_holds_every_stored_refund() {
+        let store = store(Some("passphrase")).await;
+        for (byte, not_before) in [(3, 1_700_000_300), (1, 1_700_000_100), (2, 1_700_000_200), (4, 1_700_000_400)] {
+            insert(&store, byte, not_before).await;
+        }
+        store.mark_submitted([2; 32], SolanaTxKind::Refund).await.unwrap();
+        store.void([4; 32], SolanaTxKind::Refund).await.unwrap();
+
+        let bundle = store.recovery_bundle().await.unwrap();
+        let swap_ids: Vec<String> = bundle.transactions.iter().map(|tx| tx.swap_id.clone()).collect();
+        assert_eq!(swap_ids, vec![hex::encode([1; 32]), hex::encode([3; 32])]);
+        for tx in &bundle.transactions {
+            let byte = hex::decode(&tx.swap_id).unwrap()[0];
+            assert_eq!(tx.kind, SolanaTxKind::Refund);
+            assert_eq!(tx.signature, format!("sig-{}", byte));
+            assert_eq!(tx.nonce_account, format!("nonce-{}", byte));
+            assert_eq!(tx.not_before, 1_700_000_000 + 100 * byte as i64);
+            assert_eq!(base64::engine::general_purpose::STANDARD.decode(&tx.tx_base64).unwrap(), signed(byte).bytes);
+        }
+
+        // Submitted and void transactions leave the stored state for good
+        store.void([2; 32], SolanaTxKind::Refund).await.unwrap();
+        let submitted = store.get([2; 32], SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!(submitted.state, PresignedState::Submitted);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_presigned_without_a_passphrase() {
+        let store = store(None).await;
+        assert!(!store.is_enabled());
+        assert!(store
+            .insert([1; 32], SolanaTxKind::Refund, &signed(1), "nonce-1", &[1; 32], 1_700_000_000)
+            .await
+            .is_err());
+        assert!(store.recovery_bundle().await.unwrap().transactions.is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::config::EngineConfig;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+/// How often a swap is retried after an error, and when to give up on it.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: Option<&EngineConfig>) -> Self {
+        let config = config.cloned().unwrap_or(EngineConfig {
+            max_attempts: None,
+            retry_base_seconds: None,
+            retry_max_seconds: None,
+            monero_poll_seconds: None,
+            solana_poll_seconds: None,
+            sweep_interval_seconds: None,
+            max_concurrent_swaps: None,
+        });
+        Self {
+            max_attempts: config.max_attempts.unwrap_or(10),
+            base: Duration::seconds(config.retry_base_seconds.unwrap_or(5) as i64),
+            max: Duration::seconds(config.retry_max_seconds.unwrap_or(600) as i64),
+        }
+    }
+
+    /// Delay before the next attempt after `failures` consecutive failures.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(30);
+        self.base
+            .checked_mul(1i32 << exponent)
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+/// A swap's consecutive failures since it last made progress.
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapErrors {
+    pub attempts: u32,
+    pub last_error: String,
+    pub retry_after: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetryDecision {
+    RetryAt(DateTime<Utc>),
+    /// The swap has used up its attempts; the reason says why it failed.
+    GiveUp(String),
+}
+
+/// Per-swap error budget shared by the engine's swap tasks.
+#[derive(Clone)]
+pub struct ErrorBudget {
+    policy: RetryPolicy,
+    errors: Arc<RwLock<HashMap<[u8; 32], SwapErrors>>>,
+}
+
+impl ErrorBudget {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            errors: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Whether the swap is out of backoff and may be processed at `now`.
+    pub async fn ready(&self, swap_id: [u8; 32], now: DateTime<Utc>) -> bool {
+        self.errors
+            .read()
+            .await
+            .get(&swap_id)
+            .is_none_or(|errors| now >= errors.retry_after)
+    }
+
+    pub async fn get(&self, swap_id: [u8; 32]) -> Option<SwapErrors> {
+        self.errors.read().await.get(&swap_id).cloned()
+    }
+
+    pub async fn record_failure(&self, swap_id: [u8; 32], error: &str, now: DateTime<Utc>) -> RetryDecision {
+        let mut errors = self.errors.write().await;
+        let entry = errors.entry(swap_id).or_insert_with(|| SwapErrors {
+            attempts: 0,
+            last_error: String::new(),
+            retry_after: now,
+        });
+        entry.attempts += 1;
+        entry.last_error = error.to_string();
+
+        if entry.attempts >= self.policy.max_attempts {
+            let reason = format!("Gave up after {} attempts: {}", entry.attempts, error);
+            errors.remove(&swap_id);
+            return RetryDecision::GiveUp(reason);
+        }
+
+        entry.retry_after = now + self.policy.backoff(entry.attempts);
+        RetryDecision::RetryAt(entry.retry_after)
+    }
+
+    /// Reset the budget once an attempt at the swap succeeds.
+    pub async fn record_success(&self, swap_id: [u8; 32]) {
+        self.errors.write().await.remove(&swap_id);
+    }
+}
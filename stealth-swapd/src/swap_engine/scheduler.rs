@@ -0,0 +1,321 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::Notify;
+
+/// Source of the current time, so scheduling can be driven by a test clock.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock that only moves when told to.
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(now) }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Due time, sequence number and swap of a queued entry.
+type Entry = (DateTime<Utc>, u64, [u8; 32]);
+
+#[derive(Default)]
+struct Queue {
+    /// Min-heap of (due, sequence, swap). Entries superseded by a later
+    /// `schedule` stay in the heap and are skipped when popped.
+    heap: BinaryHeap<Reverse<Entry>>,
+    /// Live entry per swap: its due time and sequence.
+    entries: HashMap<[u8; 32], (DateTime<Utc>, u64)>,
+    sequence: u64,
+}
+
+/// Priority queue of swaps keyed by when each next needs attention: its
+/// expiry, its next confirmation check or the end of its retry backoff.
+/// Chain events wake it early.
+pub struct Scheduler {
+    clock: Arc<dyn Clock>,
+    queue: Mutex<Queue>,
+    notify: Notify,
+    sweep_requested: AtomicBool,
+}
+
+impl Scheduler {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            queue: Mutex::new(Queue::default()),
+            notify: Notify::new(),
+            sweep_requested: AtomicBool::new(false),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Schedule the swap for `at`, unless it is already due sooner.
+    pub fn schedule(&self, swap_id: [u8; 32], at: DateTime<Utc>) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if matches!(queue.entries.get(&swap_id), Some((due, _)) if *due <= at) {
+            return;
+        }
+        queue.sequence += 1;
+        let sequence = queue.sequence;
+        queue.entries.insert(swap_id, (at, sequence));
+        queue.heap.push(Reverse((at, sequence, swap_id)));
+        drop(queue);
+        self.notify.notify_one();
+    }
+
+    /// Make the swap due now, e.g. because a chain event concerns it.
+    pub fn wake(&self, swap_id: [u8; 32]) {
+        self.schedule(swap_id, self.clock.now());
+    }
+
+    /// Ask for the engine-wide sweeps (deposit scans, re-verification) to
+    /// run now rather than at their next interval.
+    pub fn request_sweep(&self) {
+        self.sweep_requested.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+
+    pub fn take_sweep_request(&self) -> bool {
+        self.sweep_requested.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn cancel(&self, swap_id: [u8; 32]) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.entries.remove(&swap_id);
+    }
+
+    /// Remove and return every swap due at or before `now`, earliest first.
+    pub fn pop_due(&self, now: DateTime<Utc>) -> Vec<[u8; 32]> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        let mut due = Vec::new();
+        while let Some(Reverse((at, sequence, swap_id))) = queue.heap.peek().copied() {
+            if at > now {
+                break;
+            }
+            queue.heap.pop();
+            if queue.entries.get(&swap_id).map(|(_, live)| *live) == Some(sequence) {
+                queue.entries.remove(&swap_id);
+                due.push(swap_id);
+            }
+        }
+        due
+    }
+
+    /// When the earliest scheduled swap is due.
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(Reverse((at, sequence, swap_id))) = queue.heap.peek().copied() {
+            if queue.entries.get(&swap_id).map(|(_, live)| *live) == Some(sequence) {
+                return Some(at);
+            }
+            queue.heap.pop();
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sleep until the next swap is due or `limit`, whichever is sooner,
+    /// returning early when a swap is scheduled or a sweep requested.
+    pub async fn wait(&self, limit: DateTime<Utc>) {
+        let until = self.next_due().map_or(limit, |due| due.min(limit));
+        let delay = (until - self.clock.now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = self.notify.notified() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swap_engine::{ErrorBudget, RetryDecision, RetryPolicy};
+
+    const A: [u8; 32] = [1; 32];
+    const B: [u8; 32] = [2; 32];
+
+    fn scheduler() -> (Arc<ManualClock>, Scheduler) {
+        let clock = Arc::new(ManualClock::new(DateTime::from_timestamp(1_700_000_000, 0).unwrap()));
+        let scheduler = Scheduler::new(clock.clone());
+        (clock, scheduler)
+    }
+
+    #[test]
+    fn swaps_come_due_at_their_deadlines_in_order() {
+        let (clock, scheduler) = scheduler();
+        let start = clock.now();
+        scheduler.schedule(A, start + Duration::minutes(10));
+        scheduler.schedule(B, start + Duration::minutes(5));
+
+        assert_eq!(scheduler.next_due(), Some(start + Duration::minutes(5)));
+        assert!(scheduler.pop_due(clock.now()).is_empty());
+
+        clock.advance(Duration::minutes(5));
+        assert_eq!(scheduler.pop_due(clock.now()), vec![B]);
+        assert_eq!(scheduler.next_due(), Some(start + Duration::minutes(10)));
+
+        clock.advance(Duration::minutes(20));
+        assert_eq!(scheduler.pop_due(clock.now()), vec![A]);
+        assert!(scheduler.is_empty());
+        assert_eq!(scheduler.next_due(), None);
+    }
+
+    #[test]
+    fn scheduling_never_postpones_a_swap() {
+        let (clock, scheduler) = scheduler();
+        let start = clock.now();
+        scheduler.schedule(A, start + Duration::minutes(10));
+        scheduler.schedule(A, start + Duration::minutes(30));
+        assert_eq!(scheduler.next_due(), Some(start + Duration::minutes(10)));
+
+        // An earlier time supersedes the queued entry, which is then skipped
+        scheduler.schedule(A, start + Duration::minutes(1));
+        assert_eq!(scheduler.len(), 1);
+        clock.advance(Duration::minutes(10));
+        assert_eq!(scheduler.pop_due(clock.now()), vec![A]);
+        assert!(scheduler.pop_due(clock.now() + Duration::hours(1)).is_empty());
+    }
+
+    #[test]
+    fn wake_makes_a_swap_due_now_and_cancel_drops_it() {
+        let (clock, scheduler) = scheduler();
+        scheduler.schedule(A, clock.now() + Duration::hours(1));
+        scheduler.schedule(B, clock.now() + Duration::hours(1));
+
+        scheduler.wake(A);
+        assert_eq!(scheduler.pop_due(clock.now()), vec![A]);
+
+        scheduler.cancel(B);
+        assert!(scheduler.is_empty());
+        assert!(scheduler.pop_due(clock.now() + Duration::hours(2)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn wait_returns_when_a_swap_is_scheduled() {
+        let (clock, scheduler) = scheduler();
+        let scheduler = Arc::new(scheduler);
+        let limit = clock.now() + Duration::hours(1);
+
+        let waiter = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.wait(limit).await }
+        });
+        tokio::task::yield_now().await;
+        scheduler.wake(A);
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), waiter)
+            .await
+            .expect("wait should return once a swap is woken")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn wait_returns_at_once_when_a_swap_is_already_due() {
+        let (clock, scheduler) = scheduler();
+        scheduler.schedule(A, clock.now() - Duration::seconds(1));
+        tokio::time::timeout(std::time::Duration::from_secs(5), scheduler.wait(clock.now() + Duration::hours(1)))
+            .await
+            .expect("wait should not sleep past a due swap");
+    }
+
+    #[tokio::test]
+    async fn failing_swap_backs_off_and_exhausts_its_budget() {
+        let (clock, scheduler) = scheduler();
+        let budget = ErrorBudget::new(RetryPolicy {
+            max_attempts: 3,
+            base: Duration::seconds(5),
+            max: Duration::seconds(600),
+        });
+
+        let RetryDecision::RetryAt(retry_at) = budget.record_failure(A, "rpc down", clock.now()).await else {
+            panic!("first failure should be retried");
+        };
+        assert_eq!(retry_at, clock.now() + Duration::seconds(5));
+        assert!(!budget.ready(A, clock.now()).await);
+
+        scheduler.schedule(A, retry_at);
+        clock.advance(Duration::seconds(4));
+        assert!(scheduler.pop_due(clock.now()).is_empty());
+        clock.advance(Duration::seconds(1));
+        assert_eq!(scheduler.pop_due(clock.now()), vec![A]);
+        assert!(budget.ready(A, clock.now()).await);
+
+        let RetryDecision::RetryAt(retry_at) = budget.record_failure(A, "rpc down", clock.now()).await else {
+            panic!("second failure should be retried");
+        };
+        assert_eq!(retry_at, clock.now() + Duration::seconds(10));
+        assert_eq!(budget.get(A).await.unwrap().attempts, 2);
+
+        clock.advance(Duration::seconds(10));
+        assert_eq!(
+            budget.record_failure(A, "rpc down", clock.now()).await,
+            RetryDecision::GiveUp("Gave up after 3 attempts: rpc down".to_string())
+        );
+        assert!(budget.get(A).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_budget() {
+        let (clock, _) = scheduler();
+        let budget = ErrorBudget::new(RetryPolicy {
+            max_attempts: 2,
+            base: Duration::seconds(5),
+            max: Duration::seconds(600),
+        });
+
+        budget.record_failure(A, "timeout", clock.now()).await;
+        budget.record_success(A).await;
+        assert!(budget.ready(A, clock.now()).await);
+        assert!(matches!(budget.record_failure(A, "timeout", clock.now()).await, RetryDecision::RetryAt(_)));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base: Duration::seconds(5),
+            max: Duration::seconds(60),
+        };
+        let delays: Vec<i64> = (1..=6).map(|failures| policy.backoff(failures).num_seconds()).collect();
+        assert_eq!(delays, vec![5, 10, 20, 40, 60, 60]);
+        assert_eq!(policy.backoff(100), Duration::seconds(60));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::security::{self, KeyDerivation};
+
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret, SecretString};
+use sqlx::SqlitePool;
+use anyhow::Result;
+
+/// Adaptor secrets of quoted swaps, encrypted at rest with the presign key.
+/// Without the secret Bob can never claim a swap's USDC, so nothing is paid
+/// out for a swap whose secret isn't stored here.
+#[derive(Clone)]
+pub struct SwapSecretStore {
+    db: SqlitePool,
+    key: Option<KeyDerivation>,
+}
+
+impl SwapSecretStore {
+    /// Secrets are not stored without a passphrase.
+    pub fn new(db: SqlitePool, passphrase: Option<SecretString>) -> Self {
+        Self {
+            db,
+            key: passphrase.map(KeyDerivation::new),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Store the swap's secret, keeping the first one stored for it.
+    pub async fn insert(&self, swap_id: [u8; 32], secret: &Secret<[u8; 32]>) -> Result<()> {
+        let key = self.key()?;
+        let sealed = security::seal(key.encryption_key(), &Self::context(swap_id), secret.expose_secret());
+        sqlx::query("INSERT OR IGNORE INTO swap_secrets (swap_id, sealed_secret, created_at) VALUES (?, ?, ?)")
+            .bind(swap_id.to_vec())
+            .bind(sealed)
+            .bind(Utc::now())
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, swap_id: [u8; 32]) -> Result<Option<Secret<[u8; 32]>>> {
+        let sealed: Option<Vec<u8>> = sqlx::query_scalar("SELECT sealed_secret FROM swap_secrets WHERE swap_id = ?")
+            .bind(swap_id.to_vec())
+            .fetch_optional(&self.db)
+            .await?;
+        let Some(sealed) = sealed else {
+            return Ok(None);
+        };
+
+        let secret: [u8; 32] = security::open(self.key()?.encryption_key(), &Self::context(swap_id), &sealed)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Corrupt secret for swap {}", hex::encode(swap_id)))?;
+        Ok(Some(Secret::new(secret)))
+    }
+
+    /// Whether the swap's secret is stored and opens with the current key.
+    pub async fn contains(&self, swap_id: [u8; 32]) -> Result<bool> {
+        if !self.is_enabled() {
+            return Ok(false);
+        }
+        Ok(self.get(swap_id).await?.is_some())
+    }
+
+    fn key(&self) -> Result<&KeyDerivation> {
+        self.key
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Swap secrets are not stored: solana.presign_key_env is unset or empty"))
+    }
+
+    /// Binds a sealed secret to its swap.
+    fn context(swap_id: [u8; 32]) -> Vec<u8> {
+        let mut context = swap_id.to_vec();
+        context.extend_from_slice(b"adaptor_secret");
+        context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn store(passphrase: Option<&str>) -> SwapSecretStore {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        SwapSecretStore::new(db, passphrase.map(|p| SecretString::new(p.to_string())))
+    }
+
+    #[tokio::test]
+    async fn stored_secret_opens_for_its_swap_only() {
+        let store = store(Some("passphrase")).await;
+        let secret = KeyDerivation::generate_adaptor_secret();
+        store.insert([1; 32], &secret).await.unwrap();
+
+        let opened = store.get([1; 32]).await.unwrap().unwrap();
+        assert_eq!(opened.expose_secret(), secret.expose_secret());
+        assert!(store.contains([1; 32]).await.unwrap());
+        assert!(!store.contains([2; 32]).await.unwrap());
+
+        // The first secret stored for a swap is kept
+        store.insert([1; 32], &KeyDerivation::generate_adaptor_secret()).await.unwrap();
+        assert_eq!(store.get([1; 32]).await.unwrap().unwrap().expose_secret(), secret.expose_secret());
+    }
+
+    #[tokio::test]
+    async fn secret_sealed_under_another_key_does_not_open() {
+        let store = store(Some("passphrase")).await;
+        store.insert([1; 32], &KeyDerivation::generate_adaptor_secret()).await.unwrap();
+
+        let other = SwapSecretStore::new(store.db.clone(), Some(SecretString::new("other".to_string())));
+        assert!(other.get([1; 32]).await.is_err());
+        assert!(other.contains([1; 32]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn nothing_is_stored_without_a_passphrase() {
+        let store = store(None).await;
+        assert!(!store.is_enabled());
+        assert!(store.insert([1; 32], &KeyDerivation::generate_adaptor_secret()).await.is_err());
+        assert!(!store.contains([1; 32]).await.unwrap());
+    }
+}
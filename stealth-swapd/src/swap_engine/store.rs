@@ -0,0 +1,192 @@
+use crate::swap_engine::{Direction, SwapState, SwapTrade};
+
+use chrono::{DateTime, Utc};
+use sqlx::{Row, SqlitePool};
+use anyhow::Result;
+
+const COLUMNS: &str = "swap_id, quote_id, direction, usdc_amount, xmr_amount, secret_hash, monero_sub_address, \
+                       monero_subaddr_index, destination, alice_solana, state, created_at, expires_at, \
+                       monero_txid, monero_fee, solana_signature, failure_reason";
+
+/// Accepted swaps in SQLite, so they are resumed after a restart and their
+/// payouts and fees stay on record once they end.
+#[derive(Clone)]
+pub struct SwapStore {
+    db: SqlitePool,
+}
+
+impl SwapStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Insert a swap, or overwrite the stored row for its `swap_id`.
+    pub async fn save(&self, swap: &SwapTrade) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO swaps ({}, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(swap_id) DO UPDATE SET \
+             destination = excluded.destination, alice_solana = excluded.alice_solana, state = excluded.state, \
+             expires_at = excluded.expires_at, monero_txid = excluded.monero_txid, monero_fee = excluded.monero_fee, \
+             solana_signature = excluded.solana_signature, failure_reason = excluded.failure_reason, \
+             updated_at = excluded.updated_at",
+            COLUMNS
+        ))
+        .bind(swap.swap_id.to_vec())
+        .bind(swap.quote_id.to_string())
+        .bind(swap.direction.as_str())
+        .bind(swap.usdc_amount as i64)
+        .bind(swap.xmr_amount as i64)
+        .bind(swap.secret_hash.to_vec())
+        .bind(&swap.monero_sub_address)
+        .bind(swap.monero_subaddr_index.map(|index| index as i64))
+        .bind(swap.destination.as_deref())
+        .bind(swap.alice_solana.as_deref())
+        .bind(swap.state.as_str())
+        .bind(swap.created_at)
+        .bind(swap.expires_at)
+        .bind(swap.monero_txid.as_deref())
+        .bind(swap.monero_fee.map(|fee| fee as i64))
+        .bind(swap.solana_signature.as_deref())
+        .bind(swap.failure_reason.as_deref())
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Swaps to resume after a restart: those that have not reached a
+    /// terminal state, plus refunded or failed ones last updated after
+    /// `ended_since`, whose subaddresses are still watched for late deposits.
+    /// Deposit progress and confirmation counts are not stored; they are
+    /// re-read from the chains.
+    pub async fn resumable(&self, ended_since: DateTime<Utc>) -> Result<Vec<SwapTrade>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM swaps \
+             WHERE state NOT IN ('redeemed', 'refunded', 'failed') \
+             OR (state IN ('refunded', 'failed') AND updated_at > ?) \
+             ORDER BY created_at",
+            COLUMNS
+        ))
+        .bind(ended_since)
+        .fetch_all(&self.db)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<SwapTrade> {
+        let swap_id: Vec<u8> = row.try_get("swap_id")?;
+        let quote_id: String = row.try_get("quote_id")?;
+        let direction: String = row.try_get("direction")?;
+        let secret_hash: Vec<u8> = row.try_get("secret_hash")?;
+        let state: String = row.try_get("state")?;
+
+        Ok(SwapTrade {
+            swap_id: swap_id.try_into().map_err(|_| anyhow::anyhow!("Corrupt swap_id for quote {}", quote_id))?,
+            quote_id: uuid::Uuid::parse_str(&quote_id)?,
+            direction: Direction::parse(&direction).ok_or_else(|| anyhow::anyhow!("Unknown direction {}", direction))?,
+            usdc_amount: row.try_get::<i64, _>("usdc_amount")? as u64,
+            xmr_amount: row.try_get::<i64, _>("xmr_amount")? as u64,
+            secret_hash: secret_hash.try_into().map_err(|_| anyhow::anyhow!("Corrupt secret_hash for quote {}", quote_id))?,
+            monero_sub_address: row.try_get("monero_sub_address")?,
+            monero_subaddr_index: row.try_get::<Option<i64>, _>("monero_subaddr_index")?.map(|index| index as u32),
+            destination: row.try_get("destination")?,
+            alice_solana: row.try_get("alice_solana")?,
+            state: SwapState::parse(&state).ok_or_else(|| anyhow::anyhow!("Unknown swap state {}", state))?,
+            created_at: row.try_get::<DateTime<Utc>, _>("created_at")?,
+            expires_at: row.try_get::<DateTime<Utc>, _>("expires_at")?,
+            monero_txid: row.try_get("monero_txid")?,
+            monero_fee: row.try_get::<Option<i64>, _>("monero_fee")?.map(|fee| fee as u64),
+            solana_signature: row.try_get("solana_signature")?,
+            failure_reason: row.try_get("failure_reason")?,
+            xmr_deposit: None,
+            confirmations: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn store() -> SwapStore {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        SwapStore::new(db)
+    }
+
+    fn swap(state: SwapState) -> SwapTrade {
+        SwapTrade {
+            swap_id: [7; 32],
+            quote_id: uuid::Uuid::new_v4(),
+            direction: Direction::UsdcToXmr,
+            usdc_amount: 150_000_000,
+            xmr_amount: 1_000_000_000_000,
+            secret_hash: [9; 32],
+            monero_sub_address: format!("8{}", "a".repeat(94)),
+            monero_subaddr_index: Some(3),
+            destination: Some("4alice".to_string()),
+            alice_solana: Some("alice".to_string()),
+            state,
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            expires_at: DateTime::from_timestamp(1_700_086_400, 0).unwrap(),
+            monero_txid: None,
+            monero_fee: None,
+            solana_signature: None,
+            failure_reason: None,
+            xmr_deposit: None,
+            confirmations: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn save_updates_the_row_and_keeps_the_monero_fee() {
+        let store = store().await;
+        let mut swap = swap(SwapState::LockedUsdc);
+        store.save(&swap).await.unwrap();
+
+        swap.state = SwapState::LockedXmr;
+        swap.monero_txid = Some("ab".repeat(32));
+        swap.monero_fee = Some(30_000_000);
+        store.save(&swap).await.unwrap();
+
+        let active = store.resumable(Utc::now()).await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].swap_id, swap.swap_id);
+        assert_eq!(active[0].state, SwapState::LockedXmr);
+        assert_eq!(active[0].monero_txid, swap.monero_txid);
+        assert_eq!(active[0].monero_fee, Some(30_000_000));
+        assert_eq!(active[0].monero_sub_address, swap.monero_sub_address);
+        assert_eq!(active[0].expires_at, swap.expires_at);
+    }
+
+    #[tokio::test]
+    async fn redeemed_swaps_are_not_resumed() {
+        let store = store().await;
+        let mut swap = swap(SwapState::LockedXmr);
+        store.save(&swap).await.unwrap();
+
+        swap.state = SwapState::Redeemed;
+        store.save(&swap).await.unwrap();
+
+        let day_ago = Utc::now() - chrono::Duration::hours(24);
+        assert!(store.resumable(day_ago).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refunded_swaps_are_resumed_while_watched() {
+        let store = store().await;
+        let mut swap = swap(SwapState::Refunded);
+        swap.direction = Direction::XmrToUsdc;
+        store.save(&swap).await.unwrap();
+
+        let day_ago = Utc::now() - chrono::Duration::hours(24);
+        let resumed = store.resumable(day_ago).await.unwrap();
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].state, SwapState::Refunded);
+
+        // Last updated before the window
+        assert!(store.resumable(Utc::now() + chrono::Duration::seconds(1)).await.unwrap().is_empty());
+    }
+}
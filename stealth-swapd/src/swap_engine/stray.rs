@@ -0,0 +1,550 @@
+use crate::clients::monero::IncomingTransfer;
+use super::{DepositState, XmrDeposit};
+
+use std::collections::HashSet;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use anyhow::Result;
+
+/// Blocks behind the wallet tip scanned for deposits to addresses no swap
+/// is watching (about one day).
+pub const STRAY_RESCAN_BLOCKS: u64 = 720;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StrayDepositError {
+    #[error("Stray deposit not found")]
+    NotFound,
+
+    #[error("Stray deposit is already {0}")]
+    AlreadyResolved(String),
+
+    #[error("Stray deposit is already being refunded to {0}")]
+    RefundInProgress(String),
+
+    #[error("Swap {0} cannot take a credit: it must be an XMR→USDC swap awaiting its deposit")]
+    NotCreditable(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrayReason {
+    /// Less than quoted arrived before the swap expired.
+    Underpaid,
+    /// The part of a deposit above the quoted amount.
+    Overpaid,
+    /// Arrived after the swap expired.
+    Late,
+    /// Sent to a subaddress no live quote or swap is using.
+    Unmatched,
+    /// Counted towards an XMR→USDC swap that ended before Bob escrowed
+    /// its USDC.
+    Unsettled,
+}
+
+impl StrayReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StrayReason::Underpaid => "underpaid",
+            StrayReason::Overpaid => "overpaid",
+            StrayReason::Late => "late",
+            StrayReason::Unmatched => "unmatched",
+            StrayReason::Unsettled => "unsettled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "underpaid" => Some(StrayReason::Underpaid),
+            "overpaid" => Some(StrayReason::Overpaid),
+            "late" => Some(StrayReason::Late),
+            "unmatched" => Some(StrayReason::Unmatched),
+            "unsettled" => Some(StrayReason::Unsettled),
+            _ => None,
+        }
+    }
+}
+
+/// Part of a swap's deposit that will not be used for the swap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrayPart {
+    pub reason: StrayReason,
+    pub txids: Vec<String>,
+    pub amount: u64,
+}
+
+/// Split off whatever part of a swap's deposit will not be used for it.
+/// A short payment only becomes stray once the swap stops awaiting its
+/// deposit, since Alice may still top it up.
+pub fn stray_parts(deposit: &XmrDeposit, expected: u64, awaiting_deposit: bool) -> Vec<StrayPart> {
+    let mut parts = Vec::new();
+    if deposit.late_amount > 0 {
+        parts.push(StrayPart {
+            reason: StrayReason::Late,
+            txids: deposit.late_txids.clone(),
+            amount: deposit.late_amount,
+        });
+    }
+    match deposit.state {
+        DepositState::Overpaid => parts.push(StrayPart {
+            reason: StrayReason::Overpaid,
+            txids: deposit.txids.clone(),
+            amount: deposit.amount - expected,
+        }),
+        DepositState::Underpaid if !awaiting_deposit => parts.push(StrayPart {
+            reason: StrayReason::Underpaid,
+            txids: deposit.txids.clone(),
+            amount: deposit.amount,
+        }),
+        _ => {}
+    }
+    parts
+}
+
+/// Whether a confirmed transfer belongs to no swap: sent to a subaddress
+/// other than Bob's primary one (index 0) that no quote or swap is watching,
+/// and not already counted towards a swap's deposit.
+pub fn is_unmatched(
+    transfer: &IncomingTransfer,
+    watched: &HashSet<u32>,
+    claimed: &HashSet<&str>,
+    required_confirmations: u64,
+) -> bool {
+    transfer.subaddr_index != 0
+        && !watched.contains(&transfer.subaddr_index)
+        && !claimed.contains(transfer.txid.as_str())
+        && transfer.confirmations >= required_confirmations
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrayDeposit {
+    pub id: uuid::Uuid,
+    /// Hex swap id the deposit was sent for, if it matched one.
+    pub swap_id: Option<String>,
+    pub subaddr_index: u32,
+    pub txids: Vec<String>,
+    pub amount: u64,
+    pub reason: StrayReason,
+    /// "open", "refunding", "refunded" or "credited".
+    pub status: String,
+    pub refund_address: Option<String>,
+    pub refund_txid: Option<String>,
+    pub credited_swap_id: Option<String>,
+    pub detected_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrayAuditEntry {
+    pub action: String,
+    pub actor: String,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// SQLite-backed register of stray XMR deposits and what operators did
+/// with them. Every change is appended to `stray_deposit_audit`.
+#[derive(Clone)]
+pub struct StrayDepositStore {
+    db: SqlitePool,
+}
+
+const COLUMNS: &str = "id, swap_id, subaddr_index, txids, amount, reason, status, refund_address, \
+                       refund_txid, credited_swap_id, detected_at, resolved_at";
+
+impl StrayDepositStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Record a stray deposit, or update the amount of an open one already
+    /// recorded for the same swap and reason.
+    pub async fn record(
+        &self,
+        swap_id: Option<[u8; 32]>,
+        subaddr_index: u32,
+        txids: &[String],
+        amount: u64,
+        reason: StrayReason,
+    ) -> Result<()> {
+        let dedup_key = match swap_id {
+            Some(swap_id) => format!("swap:{}:{}", hex::encode(swap_id), reason.as_str()),
+            None => format!("tx:{}:{}", txids.join(","), subaddr_index),
+        };
+        let txids = txids.join(",");
+
+        let existing = sqlx::query("SELECT id, amount, status FROM stray_deposits WHERE dedup_key = ?")
+            .bind(&dedup_key)
+            .fetch_optional(&self.db)
+            .await?;
+
+        match existing {
+            None => {
+                let id = uuid::Uuid::new_v4().to_string();
+                sqlx::query(
+                    "INSERT INTO stray_deposits (id, dedup_key, swap_id, subaddr_index, txids, amount, reason, detected_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&id)
+                .bind(&dedup_key)
+                .bind(swap_id.map(|swap_id| swap_id.to_vec()))
+                .bind(subaddr_index as i64)
+                .bind(&txids)
+                .bind(amount as i64)
+                .bind(reason.as_str())
+                .bind(Utc::now())
+                .execute(&self.db)
+                .await?;
+
+                tracing::warn!("Stray XMR deposit {} ({}): {} piconero in {}", id, reason.as_str(), amount, txids);
+                self.audit(&id, "detected", "system", Some(format!("{} piconero in {}", amount, txids))).await
+            }
+            Some(row) => {
+                let id: String = row.try_get("id")?;
+                let status: String = row.try_get("status")?;
+                let recorded = row.try_get::<i64, _>("amount")? as u64;
+                if status != "open" || recorded == amount {
+                    return Ok(());
+                }
+
+                sqlx::query("UPDATE stray_deposits SET amount = ?, txids = ? WHERE id = ?")
+                    .bind(amount as i64)
+                    .bind(&txids)
+                    .bind(&id)
+                    .execute(&self.db)
+                    .await?;
+                self.audit(&id, "updated", "system", Some(format!("{} -> {} piconero", recorded, amount))).await
+            }
+        }
+    }
+
+    pub async fn list(&self, status: Option<&str>) -> Result<Vec<StrayDeposit>> {
+        let rows = match status {
+            Some(status) => {
+                sqlx::query(&format!("SELECT {} FROM stray_deposits WHERE status = ? ORDER BY detected_at", COLUMNS))
+                    .bind(status)
+                    .fetch_all(&self.db)
+                    .await?
+            }
+            None => {
+                sqlx::query(&format!("SELECT {} FROM stray_deposits ORDER BY detected_at", COLUMNS))
+                    .fetch_all(&self.db)
+                    .await?
+            }
+        };
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    pub async fn get(&self, id: uuid::Uuid) -> Result<StrayDeposit> {
+        let row = sqlx::query(&format!("SELECT {} FROM stray_deposits WHERE id = ?", COLUMNS))
+            .bind(id.to_string())
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or(StrayDepositError::NotFound)?;
+        Self::from_row(&row)
+    }
+
+    pub async fn audit_log(&self, id: uuid::Uuid) -> Result<Vec<StrayAuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT action, actor, detail, created_at FROM stray_deposit_audit WHERE deposit_id = ? ORDER BY id",
+        )
+        .bind(id.to_string())
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(StrayAuditEntry {
+                action: row.try_get("action")?,
+                actor: row.try_get("actor")?,
+                detail: row.try_get("detail")?,
+                created_at: row.try_get("created_at")?,
+            }))
+            .collect()
+    }
+
+    /// Move an open deposit to `refunding`, so two operators can't refund it
+    /// twice. Fails if it is not open.
+    pub async fn begin_refund(&self, id: uuid::Uuid, address: &str, actor: &str) -> Result<StrayDeposit> {
+        let deposit = self.get(id).await?;
+        let claimed = sqlx::query(
+            "UPDATE stray_deposits SET status = 'refunding', refund_address = ? WHERE id = ? AND status = 'open'",
+        )
+        .bind(address)
+        .bind(id.to_string())
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Err(StrayDepositError::AlreadyResolved(deposit.status).into());
+        }
+
+        self.audit(&id.to_string(), "refund_started", actor, Some(format!("to {}", address))).await?;
+        Ok(deposit)
+    }
+
+    /// Record the refund relayed. Only a deposit still refunding is
+    /// finished, so a restart resuming it records it once.
+    pub async fn finish_refund(&self, id: uuid::Uuid, txid: &str, actor: &str) -> Result<()> {
+        let finished = sqlx::query(
+            "UPDATE stray_deposits SET status = 'refunded', refund_txid = ?, resolved_at = ? \
+             WHERE id = ? AND status = 'refunding'",
+        )
+        .bind(txid)
+        .bind(Utc::now())
+        .bind(id.to_string())
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        if finished == 0 {
+            return Ok(());
+        }
+        self.audit(&id.to_string(), "refunded", actor, Some(format!("txid {}", txid))).await
+    }
+
+    pub async fn abort_refund(&self, id: uuid::Uuid, error: &str, actor: &str) -> Result<()> {
+        sqlx::query("UPDATE stray_deposits SET status = 'open', refund_address = NULL WHERE id = ? AND status = 'refunding'")
+            .bind(id.to_string())
+            .execute(&self.db)
+            .await?;
+        self.audit(&id.to_string(), "refund_failed", actor, Some(error.to_string())).await
+    }
+
+    pub async fn credit(&self, id: uuid::Uuid, swap_id: [u8; 32], actor: &str) -> Result<StrayDeposit> {
+        let deposit = self.get(id).await?;
+        let credited = sqlx::query(
+            "UPDATE stray_deposits SET status = 'credited', credited_swap_id = ?, resolved_at = ? \
+             WHERE id = ? AND status = 'open'",
+        )
+        .bind(swap_id.to_vec())
+        .bind(Utc::now())
+        .bind(id.to_string())
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        if credited == 0 {
+            return Err(StrayDepositError::AlreadyResolved(deposit.status).into());
+        }
+
+        self.audit(&id.to_string(), "credited", actor, Some(format!("to swap {}", hex::encode(swap_id)))).await?;
+        self.get(id).await
+    }
+
+    /// Deposits credited to a swap, which count towards its XMR deposit.
+    pub async fn credits_for(&self, swap_id: [u8; 32]) -> Result<Vec<StrayDeposit>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM stray_deposits WHERE credited_swap_id = ? AND status = 'credited'",
+            COLUMNS
+        ))
+        .bind(swap_id.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn audit(&self, id: &str, action: &str, actor: &str, detail: Option<String>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO stray_deposit_audit (deposit_id, action, actor, detail, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(action)
+        .bind(actor)
+        .bind(detail)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<StrayDeposit> {
+        let id: String = row.try_get("id")?;
+        let reason: String = row.try_get("reason")?;
+        let txids: String = row.try_get("txids")?;
+
+        Ok(StrayDeposit {
+            id: uuid::Uuid::parse_str(&id)?,
+            swap_id: row.try_get::<Option<Vec<u8>>, _>("swap_id")?.map(hex::encode),
+            subaddr_index: row.try_get::<i64, _>("subaddr_index")? as u32,
+            txids: txids.split(',').filter(|t| !t.is_empty()).map(String::from).collect(),
+            amount: row.try_get::<i64, _>("amount")? as u64,
+            reason: StrayReason::parse(&reason)
+                .ok_or_else(|| anyhow::anyhow!("Unknown stray deposit reason {}", reason))?,
+            status: row.try_get("status")?,
+            refund_address: row.try_get("refund_address")?,
+            refund_txid: row.try_get("refund_txid")?,
+            credited_swap_id: row.try_get::<Option<Vec<u8>>, _>("credited_swap_id")?.map(hex::encode),
+            detected_at: row.try_get("detected_at")?,
+            resolved_at: row.try_get("resolved_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const XMR: u64 = 1_000_000_000_000;
+
+    async fn store() -> StrayDepositStore {
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        StrayDepositStore::new(db)
+    }
+
+    fn deposit(state: DepositState, amount: u64, late_amount: u64) -> XmrDeposit {
+        XmrDeposit {
+            state,
+            txids: if amount > 0 { vec!["on-time".to_string()] } else { Vec::new() },
+            amount,
+            late_amount,
+            late_txids: if late_amount > 0 { vec!["late".to_string()] } else { Vec::new() },
+            confirmations: 10,
+            required_confirmations: 10,
+        }
+    }
+
+    fn part(reason: StrayReason, txid: &str, amount: u64) -> StrayPart {
+        StrayPart { reason, txids: vec![txid.to_string()], amount }
+    }
+
+    fn transfer(txid: &str, subaddr_index: u32, confirmations: u64) -> IncomingTransfer {
+        IncomingTransfer {
+            txid: txid.to_string(),
+            amount: XMR,
+            confirmations,
+            height: 3_000_000,
+            subaddr_index,
+            in_pool: false,
+            timestamp: 1_700_000_000,
+            double_spend_seen: false,
+        }
+    }
+
+    #[test]
+    fn unused_parts_of_a_swap_deposit_are_stray() {
+        let table = [
+            ("exact", deposit(DepositState::Confirmed, XMR, 0), false, vec![]),
+            ("excess", deposit(DepositState::Overpaid, XMR + 5, 0), false, vec![part(StrayReason::Overpaid, "on-time", 5)]),
+            ("short, still awaited", deposit(DepositState::Underpaid, XMR / 2, 0), true, vec![]),
+            ("short, swap expired", deposit(DepositState::Underpaid, XMR / 2, 0), false, vec![part(StrayReason::Underpaid, "on-time", XMR / 2)]),
+            ("after expiry", deposit(DepositState::Late, 0, XMR), false, vec![part(StrayReason::Late, "late", XMR)]),
+            (
+                "short, then the rest after expiry",
+                deposit(DepositState::Underpaid, XMR / 2, XMR / 2),
+                false,
+                vec![part(StrayReason::Late, "late", XMR / 2), part(StrayReason::Underpaid, "on-time", XMR / 2)],
+            ),
+        ];
+        for (case, deposit, awaiting_deposit, expected) in table {
+            assert_eq!(stray_parts(&deposit, XMR, awaiting_deposit), expected, "{}", case);
+        }
+    }
+
+    #[test]
+    fn transfers_no_swap_accounts_for_are_unmatched() {
+        // Subaddress 1 belongs to a live quote; 2 to a completed swap whose
+        // deposit was "counted"
+        let watched = HashSet::from([1]);
+        let claimed = HashSet::from(["counted"]);
+
+        let table = [
+            ("unknown subaddress", transfer("a", 9, 10), true),
+            ("completed swap's subaddress", transfer("b", 2, 10), true),
+            ("completed swap's own deposit", transfer("counted", 2, 10), false),
+            ("watched subaddress", transfer("c", 1, 10), false),
+            ("Bob's primary address", transfer("d", 0, 10), false),
+            ("unconfirmed", transfer("e", 9, 9), false),
+        ];
+        for (case, transfer, unmatched) in table {
+            assert_eq!(is_unmatched(&transfer, &watched, &claimed, 10), unmatched, "{}", case);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_deposit_is_recorded_once_per_swap_and_reason() {
+        let store = store().await;
+        let txids = ["a".to_string()];
+        store.record(Some([1; 32]), 3, &txids, XMR, StrayReason::Late).await.unwrap();
+        store.record(Some([1; 32]), 3, &txids, XMR, StrayReason::Late).await.unwrap();
+        store.record(Some([1; 32]), 3, &["a".to_string(), "b".to_string()], 2 * XMR, StrayReason::Late).await.unwrap();
+        store.record(None, 9, &["c".to_string()], XMR, StrayReason::Unmatched).await.unwrap();
+        store.record(None, 9, &["c".to_string()], XMR, StrayReason::Unmatched).await.unwrap();
+
+        let deposits = store.list(Some("open")).await.unwrap();
+        assert_eq!(deposits.len(), 2);
+        let late = deposits.iter().find(|d| d.reason == StrayReason::Late).unwrap();
+        assert_eq!((late.amount, late.txids.len()), (2 * XMR, 2));
+        assert_eq!(late.swap_id, Some(hex::encode([1; 32])));
+
+        let actions: Vec<String> = store.audit_log(late.id).await.unwrap().into_iter().map(|e| e.action).collect();
+        assert_eq!(actions, vec!["detected", "updated"]);
+    }
+
+    #[tokio::test]
+    async fn an_unsettled_deposit_is_held_for_refund() {
+        let store = store().await;
+        store.record(Some([2; 32]), 4, &["a".to_string()], XMR, StrayReason::Unsettled).await.unwrap();
+
+        let deposit = &store.list(Some("open")).await.unwrap()[0];
+        assert_eq!(deposit.reason, StrayReason::Unsettled);
+        store.begin_refund(deposit.id, "4refund", "ops").await.unwrap();
+        let actions: Vec<String> = store.audit_log(deposit.id).await.unwrap().into_iter().map(|e| e.action).collect();
+        assert_eq!(actions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_stray_deposit_is_refunded_only_once() {
+        let store = store().await;
+        store.record(None, 9, &["a".to_string()], XMR, StrayReason::Unmatched).await.unwrap();
+        let id = store.list(None).await.unwrap()[0].id;
+
+        store.begin_refund(id, "4refund", "alice-ops").await.unwrap();
+        let error = store.begin_refund(id, "4other", "bob-ops").await.unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(StrayDepositError::AlreadyResolved(status)) if status == "refunding"));
+        assert!(store.credit(id, [2; 32], "bob-ops").await.is_err());
+
+        store.finish_refund(id, "refund-tx", "alice-ops").await.unwrap();
+        let refunded = store.get(id).await.unwrap();
+        assert_eq!(refunded.status, "refunded");
+        assert_eq!(refunded.refund_address.as_deref(), Some("4refund"));
+        assert_eq!(refunded.refund_txid.as_deref(), Some("refund-tx"));
+        assert!(store.begin_refund(id, "4refund", "alice-ops").await.is_err());
+        // A restart resuming the refund finds it already finished
+        store.finish_refund(id, "refund-tx", "system").await.unwrap();
+
+        // A refunded deposit seen again by the rescan stays refunded
+        store.record(None, 9, &["a".to_string()], XMR, StrayReason::Unmatched).await.unwrap();
+        assert_eq!(store.get(id).await.unwrap().status, "refunded");
+
+        let actions: Vec<String> = store.audit_log(id).await.unwrap().into_iter().map(|e| e.action).collect();
+        assert_eq!(actions, vec!["detected", "refund_started", "refunded"]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_refund_reopens_the_deposit_for_a_single_credit() {
+        let store = store().await;
+        store.record(None, 9, &["a".to_string()], XMR, StrayReason::Unmatched).await.unwrap();
+        let id = store.list(None).await.unwrap()[0].id;
+
+        store.begin_refund(id, "4refund", "ops").await.unwrap();
+        store.abort_refund(id, "wallet busy", "ops").await.unwrap();
+        let reopened = store.get(id).await.unwrap();
+        assert_eq!((reopened.status.as_str(), reopened.refund_address), ("open", None));
+
+        let credited = store.credit(id, [2; 32], "ops").await.unwrap();
+        assert_eq!(credited.status, "credited");
+        assert_eq!(credited.credited_swap_id, Some(hex::encode([2; 32])));
+        assert!(credited.resolved_at.is_some());
+
+        let error = store.credit(id, [3; 32], "ops").await.unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(StrayDepositError::AlreadyResolved(status)) if status == "credited"));
+        assert!(store.begin_refund(id, "4refund", "ops").await.is_err());
+
+        assert_eq!(store.credits_for([2; 32]).await.unwrap().len(), 1);
+        assert!(store.credits_for([3; 32]).await.unwrap().is_empty());
+        assert!(matches!(
+            store.credit(uuid::Uuid::new_v4(), [2; 32], "ops").await.unwrap_err().downcast_ref(),
+            Some(StrayDepositError::NotFound)
+        ));
+    }
+}
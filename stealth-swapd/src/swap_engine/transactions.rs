@@ -0,0 +1,815 @@
+use crate::clients::SolanaClient;
+use crate::clients::solana::TransactionFailure;
+use crate::clients::solana_program::{decode_pubkey, encode_pubkey};
+use crate::clients::solana_tx::{
+    advance_nonce_instruction, set_compute_unit_limit, sign_transaction, Instruction, SignedTransaction, MAX_COMPUTE_UNITS,
+};
+use crate::swap_engine::{ComputeBudget, PresignedState, PresignedStore, PriorityFeePolicy};
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use secrecy::SecretString;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolanaTxKind {
+    Refund,
+    Redeem,
+    RecordProof,
+}
+
+impl SolanaTxKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SolanaTxKind::Refund => "refund",
+            SolanaTxKind::Redeem => "redeem",
+            SolanaTxKind::RecordProof => "record_proof",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "refund" => Some(SolanaTxKind::Refund),
+            "redeem" => Some(SolanaTxKind::Redeem),
+            "record_proof" => Some(SolanaTxKind::RecordProof),
+            _ => None,
+        }
+    }
+
+    /// Refunds and redeems race a deadline, so they take a durable nonce
+    /// when one is free rather than a blockhash that expires in a minute.
+    pub fn uses_nonce(&self) -> bool {
+        matches!(self, SolanaTxKind::Refund | SolanaTxKind::Redeem)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolanaTxState {
+    /// Signed and stored; sent at least once or about to be.
+    Pending,
+    Confirmed,
+    /// Can no longer land: its blockhash expired or its nonce advanced.
+    Expired,
+    /// Landed with an error.
+    Failed,
+}
+
+impl SolanaTxState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SolanaTxState::Pending => "pending",
+            SolanaTxState::Confirmed => "confirmed",
+            SolanaTxState::Expired => "expired",
+            SolanaTxState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(SolanaTxState::Pending),
+            "confirmed" => Some(SolanaTxState::Confirmed),
+            "expired" => Some(SolanaTxState::Expired),
+            "failed" => Some(SolanaTxState::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SolanaTxError {
+    #[error("{} rejected in simulation: {failure}", kind.as_str())]
+    Rejected {
+        kind: SolanaTxKind,
+        failure: TransactionFailure,
+        logs: Vec<String>,
+    },
+}
+
+impl SolanaTxError {
+    /// Whether the same transaction may succeed if submitted again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SolanaTxError::Rejected { failure, .. } => failure.is_retryable(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SolanaTx {
+    pub id: i64,
+    pub swap_id: [u8; 32],
+    pub kind: SolanaTxKind,
+    pub signature: String,
+    #[serde(skip)]
+    pub tx_base64: String,
+    pub blockhash: String,
+    /// `None` for durable nonce transactions.
+    pub last_valid_block_height: Option<u64>,
+    pub nonce_account: Option<String>,
+    pub state: SolanaTxState,
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub confirmed_slot: Option<u64>,
+    /// Compute units the simulation before the first send consumed.
+    pub compute_units: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Sends the daemon's Solana transactions. Each is signed and stored with
+/// its signature before it is first sent, then resent until it confirms,
+/// fails or can no longer land, so a retry or restart never signs a second
+/// transaction for the same action while the first might still land.
+#[derive(Clone)]
+pub struct SolanaTxManager {
+    client: SolanaClient,
+    db: SqlitePool,
+    nonce_accounts: Vec<String>,
+    presigned: PresignedStore,
+    fees: PriorityFeePolicy,
+    interval: Duration,
+    /// Serializes submissions, so two can't claim the same nonce account
+    /// or sign twice for one swap.
+    submit_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl SolanaTxManager {
+    pub fn new(client: SolanaClient, db: SqlitePool, presign_key: Option<SecretString>, fees: PriorityFeePolicy) -> Self {
+        Self {
+            fees,
+            nonce_accounts: client.config.nonce_accounts.clone().unwrap_or_default(),
+            presigned: PresignedStore::new(db.clone(), presign_key),
+            interval: Duration::from_secs(client.config.rebroadcast_seconds.unwrap_or(2)),
+            client,
+            db,
+            submit_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub fn presigned(&self) -> &PresignedStore {
+        &self.presigned
+    }
+
+    /// Sign the swap's `kind` transaction now and store it encrypted for
+    /// broadcast after `not_before`. `None` when pre-signing is disabled or
+    /// no nonce account is free to hold it. It is simulated when submitted,
+    /// not now: until the deadline the program rejects it as not expired,
+    /// so its compute limit is the configured default.
+    pub async fn presign(
+        &self,
+        swap_id: [u8; 32],
+        kind: SolanaTxKind,
+        instructions: Vec<Instruction>,
+        not_before: i64,
+    ) -> Result<Option<String>> {
+        if !self.presigned.is_enabled() {
+            return Ok(None);
+        }
+        let _guard = self.submit_lock.lock().await;
+        if let Some(existing) = self.presigned.get(swap_id, kind).await? {
+            return Ok(Some(existing.signature));
+        }
+
+        let Some((address, nonce)) = self.free_nonce_account().await? else {
+            tracing::warn!("No free nonce account to pre-sign {} of {}", kind.as_str(), hex::encode(swap_id));
+            return Ok(None);
+        };
+        let budget = self.budget(swap_id, kind, &instructions, None, Utc::now()).await?;
+        let signed = self.sign(Some(&address), nonce, Some(&budget), &instructions)?;
+        self.presigned.insert(swap_id, kind, &signed, &address, &nonce, not_before).await?;
+        tracing::info!(
+            "Pre-signed {} for swap {}: {} (at most {} lamports)",
+            kind.as_str(),
+            hex::encode(swap_id),
+            signed.signature,
+            budget.max_cost()
+        );
+        Ok(Some(signed.signature))
+    }
+
+    /// Signature of the swap's `kind` transaction: the one already pending
+    /// or confirmed if there is one, the pre-signed one if it was stored,
+    /// otherwise a newly signed one. Each is simulated first and nothing is
+    /// stored or sent if the simulation fails; a failed first send is only
+    /// logged, as the rebroadcast loop retries it.
+    ///
+    /// A new transaction's compute limit is sized from a simulation of it
+    /// at the maximum limit, and its price escalates with each earlier
+    /// attempt that expired or failed once `deadline` is near.
+    pub async fn submit(
+        &self,
+        swap_id: [u8; 32],
+        kind: SolanaTxKind,
+        instructions: Vec<Instruction>,
+        deadline: DateTime<Utc>,
+    ) -> Result<String> {
+        let _guard = self.submit_lock.lock().await;
+        if let Some(existing) = self.active(swap_id, kind).await? {
+            return Ok(existing.signature);
+        }
+
+        let presigned = self
+            .presigned
+            .get(swap_id, kind)
+            .await?
+            .filter(|presigned| presigned.state == PresignedState::Stored);
+        let (blockhash, last_valid, nonce_account) = match &presigned {
+            Some(presigned) => (decode_pubkey(&presigned.blockhash)?, None, Some(presigned.nonce_account.clone())),
+            None => {
+                let nonce_account = match kind.uses_nonce() {
+                    true => self.free_nonce_account().await?,
+                    false => None,
+                };
+                match nonce_account {
+                    Some((address, nonce)) => (nonce, None, Some(address)),
+                    None => {
+                        if kind.uses_nonce() {
+                            tracing::warn!("No free nonce account for {} of {}; using a recent blockhash", kind.as_str(), hex::encode(swap_id));
+                        }
+                        let (blockhash, last_valid) = self.client.get_latest_blockhash().await?;
+                        (blockhash, Some(last_valid), None)
+                    }
+                }
+            }
+        };
+
+        let draft = match &presigned {
+            Some(presigned) => SignedTransaction {
+                bytes: self.presigned.decrypt(presigned)?,
+                signature: presigned.signature.clone(),
+            },
+            None => self.sign(nonce_account.as_deref(), blockhash, None, &instructions)?,
+        };
+        let simulation = self.client.simulate_transaction(&draft.bytes).await?;
+        if let Some(failure) = simulation.err {
+            tracing::warn!(
+                "{} for swap {} failed simulation: {}\n{}",
+                kind.as_str(),
+                hex::encode(swap_id),
+                failure,
+                simulation.logs.join("\n")
+            );
+            return Err(SolanaTxError::Rejected { kind, failure, logs: simulation.logs }.into());
+        }
+
+        let (signed, budget) = match presigned {
+            Some(_) => (draft, None),
+            None => {
+                let budget = self.budget(swap_id, kind, &instructions, simulation.units_consumed, deadline).await?;
+                (self.sign(nonce_account.as_deref(), blockhash, Some(&budget), &instructions)?, Some(budget))
+            }
+        };
+
+        self.insert(swap_id, kind, &signed, &blockhash, last_valid, nonce_account.as_deref()).await?;
+        self.record_simulation(&signed.signature, simulation.units_consumed, &simulation.logs, budget.as_ref()).await?;
+        if budget.is_none() {
+            self.presigned.mark_submitted(swap_id, kind).await?;
+        }
+        tracing::info!(
+            "Submitting {} for swap {}: {} ({} compute units{})",
+            kind.as_str(),
+            hex::encode(swap_id),
+            signed.signature,
+            simulation.units_consumed.unwrap_or_default(),
+            budget.map(|budget| format!(", at most {} lamports", budget.max_cost())).unwrap_or_default()
+        );
+        self.send(&signed.signature, &signed.bytes).await;
+        Ok(signed.signature)
+    }
+
+    /// The swap's pending or confirmed `kind` transaction.
+    pub async fn active(&self, swap_id: [u8; 32], kind: SolanaTxKind) -> Result<Option<SolanaTx>> {
+        let row = sqlx::query(
+            "SELECT * FROM solana_transactions WHERE swap_id = ? AND kind = ? AND state IN ('pending', 'confirmed') \
+             ORDER BY id DESC LIMIT 1",
+        )
+        .bind(swap_id.to_vec())
+        .bind(kind.as_str())
+        .fetch_optional(&self.db)
+        .await?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    pub async fn pending(&self) -> Result<Vec<SolanaTx>> {
+        let rows = sqlx::query("SELECT * FROM solana_transactions WHERE state = 'pending' ORDER BY id")
+            .fetch_all(&self.db)
+            .await?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    /// Settle or resend each pending transaction.
+    pub async fn rebroadcast_pending(&self) -> Result<()> {
+        let pending = self.pending().await?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let block_height = self.client.get_block_height().await?;
+
+        for tx in pending {
+            if self.settle(&tx).await? {
+                continue;
+            }
+            if self.expired(&tx, block_height).await? {
+                // The nonce may have been advanced by this very transaction
+                // landing since its status was read
+                if !self.settle(&tx).await? {
+                    tracing::warn!(
+                        "{} for swap {} expired unconfirmed: {}",
+                        tx.kind.as_str(),
+                        hex::encode(tx.swap_id),
+                        tx.signature
+                    );
+                    self.set_state(tx.id, SolanaTxState::Expired, None, None).await?;
+                }
+                continue;
+            }
+
+            let bytes = base64::engine::general_purpose::STANDARD.decode(&tx.tx_base64)?;
+            self.send(&tx.signature, &bytes).await;
+        }
+        Ok(())
+    }
+
+    /// Rebroadcast pending transactions until the task is aborted.
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.rebroadcast_pending().await {
+                tracing::warn!("Failed to rebroadcast Solana transactions: {}", e);
+            }
+        }
+    }
+
+    /// Record the transaction's outcome if it has one.
+    async fn settle(&self, tx: &SolanaTx) -> Result<bool> {
+        let Some(status) = self.client.get_transaction_status(&tx.signature).await? else {
+            return Ok(false);
+        };
+        if let Some(err) = status.err {
+            tracing::error!("{} for swap {} failed: {}", tx.kind.as_str(), hex::encode(tx.swap_id), err);
+            self.set_state(tx.id, SolanaTxState::Failed, Some(&err.to_string()), None).await?;
+            return Ok(true);
+        }
+        match status.confirmation_status.as_deref() {
+            Some("confirmed") | Some("finalized") => {
+                self.set_state(tx.id, SolanaTxState::Confirmed, None, Some(status.slot)).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Whether the transaction can no longer land.
+    async fn expired(&self, tx: &SolanaTx, block_height: u64) -> Result<bool> {
+        if let Some(last_valid) = tx.last_valid_block_height {
+            return Ok(block_height > last_valid);
+        }
+        let Some(address) = &tx.nonce_account else {
+            return Ok(false);
+        };
+        match self.client.get_nonce_account(address).await? {
+            Some(account) => Ok(encode_pubkey(&account.nonce) != tx.blockhash),
+            None => Ok(true),
+        }
+    }
+
+    /// Sign `instructions` against a durable nonce when `nonce_account` is
+    /// given, otherwise against a recent blockhash. Without a budget the
+    /// limit is the maximum and the price zero, for sizing by simulation.
+    fn sign(
+        &self,
+        nonce_account: Option<&str>,
+        blockhash: [u8; 32],
+        budget: Option<&ComputeBudget>,
+        instructions: &[Instruction],
+    ) -> Result<SignedTransaction> {
+        let authority = self.client.keypair().public_key();
+        let mut all = match nonce_account {
+            Some(address) => vec![advance_nonce_instruction(decode_pubkey(address)?, authority)?],
+            None => Vec::new(),
+        };
+        match budget {
+            Some(budget) => all.extend(budget.instructions()?),
+            None => all.push(set_compute_unit_limit(MAX_COMPUTE_UNITS)?),
+        }
+        all.extend_from_slice(instructions);
+        sign_transaction(self.client.keypair(), &all, blockhash)
+    }
+
+    /// Compute budget for `instructions`, priced from recent fees on the
+    /// accounts they write.
+    async fn budget(
+        &self,
+        swap_id: [u8; 32],
+        kind: SolanaTxKind,
+        instructions: &[Instruction],
+        consumed: Option<u64>,
+        deadline: DateTime<Utc>,
+    ) -> Result<ComputeBudget> {
+        let mut accounts: Vec<String> = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .filter(|meta| meta.is_writable)
+            .map(|meta| encode_pubkey(&meta.pubkey))
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+
+        let recent_fees = match self.client.get_recent_prioritization_fees(&accounts).await {
+            Ok(fees) => fees,
+            Err(e) => {
+                tracing::warn!("Failed to fetch recent prioritization fees: {}", e);
+                Vec::new()
+            }
+        };
+        let retries: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM solana_transactions WHERE swap_id = ? AND kind = ? AND state IN ('expired', 'failed')",
+        )
+        .bind(swap_id.to_vec())
+        .bind(kind.as_str())
+        .fetch_one(&self.db)
+        .await?;
+
+        let urgent = self.fees.is_urgent(deadline, Utc::now());
+        Ok(self.fees.budget(self.fees.unit_limit(consumed), &recent_fees, retries as u32, urgent)?)
+    }
+
+    /// A configured nonce account neither a pending nor a stored
+    /// pre-signed transaction holds, with its current nonce.
+    async fn free_nonce_account(&self) -> Result<Option<(String, [u8; 32])>> {
+        if self.nonce_accounts.is_empty() {
+            return Ok(None);
+        }
+        let in_use: Vec<String> = sqlx::query_scalar(
+            "SELECT nonce_account FROM solana_transactions WHERE state = 'pending' AND nonce_account IS NOT NULL \
+             UNION SELECT nonce_account FROM presigned_transactions WHERE state = 'stored'",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let authority = self.client.keypair().public_key();
+        for address in self.nonce_accounts.iter().filter(|address| !in_use.contains(address)) {
+            match self.client.get_nonce_account(address).await {
+                Ok(Some(account)) if account.authority == authority => return Ok(Some((address.clone(), account.nonce))),
+                Ok(Some(_)) => tracing::warn!("Nonce account {} has another authority", address),
+                Ok(None) => tracing::warn!("Nonce account {} does not exist", address),
+                Err(e) => tracing::warn!("Failed to read nonce account {}: {}", address, e),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn send(&self, signature: &str, bytes: &[u8]) {
+        if let Err(e) = self.client.send_transaction(bytes).await {
+            tracing::warn!("Failed to send {}: {}", signature, e);
+        }
+        let result = sqlx::query("UPDATE solana_transactions SET attempts = attempts + 1, last_sent_at = ? WHERE signature = ?")
+            .bind(Utc::now())
+            .bind(signature)
+            .execute(&self.db)
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to record send of {}: {}", signature, e);
+        }
+    }
+
+    async fn insert(
+        &self,
+        swap_id: [u8; 32],
+        kind: SolanaTxKind,
+        signed: &SignedTransaction,
+        blockhash: &[u8; 32],
+        last_valid: Option<u64>,
+        nonce_account: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO solana_transactions \
+             (swap_id, kind, signature, tx_base64, blockhash, last_valid_block_height, nonce_account, state, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, 'pending', ?)",
+        )
+        .bind(swap_id.to_vec())
+        .bind(kind.as_str())
+        .bind(&signed.signature)
+        .bind(base64::engine::general_purpose::STANDARD.encode(&signed.bytes))
+        .bind(encode_pubkey(blockhash))
+        .bind(last_valid.map(|height| height as i64))
+        .bind(nonce_account)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_simulation(
+        &self,
+        signature: &str,
+        units: Option<u64>,
+        logs: &[String],
+        budget: Option<&ComputeBudget>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE solana_transactions SET compute_units = ?, simulation_logs = ?, compute_unit_limit = ?, \
+             compute_unit_price = ? WHERE signature = ?",
+        )
+        .bind(units.map(|units| units as i64))
+        .bind(logs.join("\n"))
+        .bind(budget.map(|budget| budget.unit_limit as i64))
+        .bind(budget.map(|budget| budget.unit_price as i64))
+        .bind(signature)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_state(&self, id: i64, state: SolanaTxState, error: Option<&str>, slot: Option<u64>) -> Result<()> {
+        sqlx::query(
+            "UPDATE solana_transactions SET state = ?, error = ?, confirmed_slot = ? WHERE id = ? AND state = 'pending'",
+        )
+        .bind(state.as_str())
+        .bind(error)
+        .bind(slot.map(|slot| slot as i64))
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<SolanaTx> {
+        let swap_id: Vec<u8> = row.try_get("swap_id")?;
+        let kind: String = row.try_get("kind")?;
+        let state: String = row.try_get("state")?;
+
+        Ok(SolanaTx {
+            id: row.try_get("id")?,
+            swap_id: swap_id.try_into().map_err(|_| anyhow::anyhow!("Corrupt swap_id in solana_transactions"))?,
+            kind: SolanaTxKind::parse(&kind).ok_or_else(|| anyhow::anyhow!("Unknown transaction kind {}", kind))?,
+            signature: row.try_get("signature")?,
+            tx_base64: row.try_get("tx_base64")?,
+            blockhash: row.try_get("blockhash")?,
+            last_valid_block_height: row.try_get::<Option<i64>, _>("last_valid_block_height")?.map(|height| height as u64),
+            nonce_account: row.try_get("nonce_account")?,
+            state: SolanaTxState::parse(&state).ok_or_else(|| anyhow::anyhow!("Unknown transaction state {}", state))?,
+            error: row.try_get("error")?,
+            attempts: row.try_get::<i64, _>("attempts")? as u32,
+            confirmed_slot: row.try_get::<Option<i64>, _>("confirmed_slot")?.map(|slot| slot as u64),
+            compute_units: row.try_get::<Option<i64>, _>("compute_units")?.map(|units| units as u64),
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::mock_rpc::{solana_authority as authority, MockRpc};
+    use serde_json::{json, Value};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// What the mocked cluster reports, changed by tests as it moves on.
+    #[derive(Default)]
+    struct Cluster {
+        block_height: u64,
+        /// Nonce account address to `(authority, nonce)`.
+        nonces: HashMap<String, ([u8; 32], [u8; 32])>,
+        /// Signature to its `getSignatureStatuses` entry.
+        statuses: HashMap<String, Value>,
+    }
+
+    fn nonce_data(authority: [u8; 32], nonce: [u8; 32]) -> String {
+        let mut data = vec![0u8; 80];
+        data[4..8].copy_from_slice(&1u32.to_le_bytes());
+        data[8..40].copy_from_slice(&authority);
+        data[40..72].copy_from_slice(&nonce);
+        base64::engine::general_purpose::STANDARD.encode(data)
+    }
+
+    fn respond(cluster: &Mutex<Cluster>, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let cluster = cluster.lock().unwrap();
+        Ok(match method {
+            "getLatestBlockhash" => json!({ "value": {
+                "blockhash": encode_pubkey(&[9; 32]),
+                "lastValidBlockHeight": cluster.block_height + 150,
+            } }),
+            "getBlockHeight" => json!(cluster.block_height),
+            "getAccountInfo" => match cluster.nonces.get(params[0].as_str().unwrap_or_default()) {
+                Some((authority, nonce)) => json!({
+                    "context": { "slot": 1 },
+                    "value": { "data": [nonce_data(*authority, *nonce), "base64"] },
+                }),
+                None => json!({ "context": { "slot": 1 }, "value": null }),
+            },
+            "simulateTransaction" => json!({ "value": { "err": null, "logs": ["ok"], "unitsConsumed": 5_000 } }),
+            "getRecentPrioritizationFees" => json!([]),
+            "sendTransaction" => json!("sent"),
+            "getSignatureStatuses" => {
+                let signature = params[0][0].as_str().unwrap_or_default();
+                json!({ "value": [cluster.statuses.get(signature).cloned().unwrap_or(Value::Null)] })
+            }
+            other => return Err((-32601, format!("unexpected {}", other))),
+        })
+    }
+
+    fn nonce_account(byte: u8) -> String {
+        encode_pubkey(&[byte; 32])
+    }
+
+    /// A manager against a mocked cluster whose configured nonce accounts
+    /// are `nonce_accounts`, with pre-signing enabled.
+    async fn manager(cluster: &Arc<Mutex<Cluster>>, nonce_accounts: &[u8]) -> (SolanaTxManager, MockRpc) {
+        let state = cluster.clone();
+        let rpc = MockRpc::start(move |method, params| respond(&state, method, params)).await;
+
+        let mut client = rpc.solana_client().await;
+        client.config.nonce_accounts = Some(nonce_accounts.iter().map(|byte| nonce_account(*byte)).collect());
+
+        let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        let manager = SolanaTxManager::new(
+            client,
+            db,
+            Some(SecretString::new("passphrase".to_string())),
+            PriorityFeePolicy::new(None, 1_000_000),
+        );
+        (manager, rpc)
+    }
+
+    fn instruction(manager: &SolanaTxManager, swap_id: [u8; 32]) -> Vec<Instruction> {
+        vec![manager.client.refund_instruction(swap_id).unwrap()]
+    }
+
+    fn deadline() -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::hours(1)
+    }
+
+    async fn rows(manager: &SolanaTxManager) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM solana_transactions").fetch_one(&manager.db).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn submitting_the_same_swap_and_kind_twice_sends_one_transaction() {
+        let cluster = Arc::new(Mutex::new(Cluster { block_height: 100, ..Default::default() }));
+        let (manager, rpc) = manager(&cluster, &[]).await;
+        let swap_id = [1; 32];
+
+        let proof = || vec![manager.client.record_monero_lock_proof_instruction(swap_id, &"ab".repeat(32)).unwrap()];
+
+        let first = manager.submit(swap_id, SolanaTxKind::RecordProof, proof(), deadline()).await.unwrap();
+        let second = manager.submit(swap_id, SolanaTxKind::RecordProof, proof(), deadline()).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(rows(&manager).await, 1);
+        assert_eq!(rpc.calls("sendTransaction").len(), 1);
+
+        // Another kind for the same swap is its own transaction
+        let refund = manager.submit(swap_id, SolanaTxKind::Refund, instruction(&manager, swap_id), deadline()).await.unwrap();
+        assert_ne!(refund, first);
+        assert_eq!(rows(&manager).await, 2);
+    }
+
+    #[tokio::test]
+    async fn nonce_accounts_are_claimed_once_and_freed_when_settled() {
+        let cluster = Arc::new(Mutex::new(Cluster { block_height: 100, ..Default::default() }));
+        {
+            let mut cluster = cluster.lock().unwrap();
+            // Account 1 answers to another authority and is never used
+            cluster.nonces.insert(nonce_account(1), ([5; 32], [11; 32]));
+            cluster.nonces.insert(nonce_account(2), (authority(), [12; 32]));
+            cluster.nonces.insert(nonce_account(3), (authority(), [13; 32]));
+        }
+        let (manager, _rpc) = manager(&cluster, &[1, 2, 3, 4]).await;
+        assert_eq!(manager.free_nonce_account().await.unwrap(), Some((nonce_account(2), [12; 32])));
+
+        manager.submit([1; 32], SolanaTxKind::Refund, instruction(&manager, [1; 32]), deadline()).await.unwrap();
+        manager.submit([2; 32], SolanaTxKind::Redeem, instruction(&manager, [2; 32]), deadline()).await.unwrap();
+        let first = manager.active([1; 32], SolanaTxKind::Refund).await.unwrap().unwrap();
+        let second = manager.active([2; 32], SolanaTxKind::Redeem).await.unwrap().unwrap();
+        assert_eq!((first.nonce_account.as_deref(), first.last_valid_block_height), (Some(nonce_account(2).as_str()), None));
+        assert_eq!(first.blockhash, encode_pubkey(&[12; 32]));
+        assert_eq!(second.nonce_account, Some(nonce_account(3)));
+        assert_eq!(manager.free_nonce_account().await.unwrap(), None);
+
+        // With every nonce held, a refund falls back to a recent blockhash
+        manager.submit([3; 32], SolanaTxKind::Refund, instruction(&manager, [3; 32]), deadline()).await.unwrap();
+        let third = manager.active([3; 32], SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!((third.nonce_account, third.last_valid_block_height), (None, Some(250)));
+
+        // Settling the first frees its account, at its advanced nonce
+        cluster.lock().unwrap().nonces.insert(nonce_account(2), (authority(), [22; 32]));
+        cluster.lock().unwrap().statuses.insert(first.signature.clone(), json!({
+            "slot": 90, "confirmationStatus": "confirmed", "err": null,
+        }));
+        manager.rebroadcast_pending().await.unwrap();
+        assert_eq!(manager.free_nonce_account().await.unwrap(), Some((nonce_account(2), [22; 32])));
+
+        // A stored pre-signed transaction holds its account too
+        manager.presign([4; 32], SolanaTxKind::Refund, instruction(&manager, [4; 32]), 0).await.unwrap().unwrap();
+        assert_eq!(manager.free_nonce_account().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_presigned_refund_is_stored_at_once_and_sent_as_signed() {
+        let cluster = Arc::new(Mutex::new(Cluster { block_height: 100, ..Default::default() }));
+        cluster.lock().unwrap().nonces.insert(nonce_account(1), (authority(), [11; 32]));
+        let (manager, rpc) = manager(&cluster, &[1]).await;
+        let swap_id = [1; 32];
+        let expiry = 1_700_000_000;
+
+        let signature = manager.presign(swap_id, SolanaTxKind::Refund, instruction(&manager, swap_id), expiry).await.unwrap().unwrap();
+        let stored = manager.presigned().get(swap_id, SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!((stored.signature.as_str(), stored.state, stored.not_before), (signature.as_str(), PresignedState::Stored, expiry));
+        assert_eq!((stored.nonce_account, stored.blockhash), (nonce_account(1), encode_pubkey(&[11; 32])));
+        assert!(rpc.calls("sendTransaction").is_empty());
+        let bundle = manager.presigned().recovery_bundle().await.unwrap();
+        assert_eq!(bundle.transactions.len(), 1);
+
+        // Signing again keeps the stored one, and the refund sent at expiry is it
+        let again = manager.presign(swap_id, SolanaTxKind::Refund, instruction(&manager, swap_id), expiry).await.unwrap();
+        assert_eq!(again.as_deref(), Some(signature.as_str()));
+        let sent = manager.submit(swap_id, SolanaTxKind::Refund, instruction(&manager, swap_id), deadline()).await.unwrap();
+        assert_eq!(sent, signature);
+        let stored = manager.presigned().get(swap_id, SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!(stored.state, PresignedState::Submitted);
+    }
+
+    #[tokio::test]
+    async fn active_is_the_pending_or_confirmed_transaction() {
+        let cluster = Arc::new(Mutex::new(Cluster { block_height: 100, ..Default::default() }));
+        let (manager, _rpc) = manager(&cluster, &[]).await;
+        let swap_id = [1; 32];
+        assert!(manager.active(swap_id, SolanaTxKind::Refund).await.unwrap().is_none());
+
+        let signature = manager.submit(swap_id, SolanaTxKind::Refund, instruction(&manager, swap_id), deadline()).await.unwrap();
+        let pending = manager.active(swap_id, SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!((pending.signature.as_str(), pending.state, pending.attempts), (signature.as_str(), SolanaTxState::Pending, 1));
+        assert_eq!(pending.compute_units, Some(5_000));
+        assert!(manager.active(swap_id, SolanaTxKind::Redeem).await.unwrap().is_none());
+
+        cluster.lock().unwrap().statuses.insert(signature.clone(), json!({
+            "slot": 120, "confirmationStatus": "finalized", "err": null,
+        }));
+        manager.rebroadcast_pending().await.unwrap();
+        let confirmed = manager.active(swap_id, SolanaTxKind::Refund).await.unwrap().unwrap();
+        assert_eq!((confirmed.state, confirmed.confirmed_slot), (SolanaTxState::Confirmed, Some(120)));
+
+        // A failed transaction is no longer active, so the next submit signs afresh
+        let failing = [2; 32];
+        let signature = manager.submit(failing, SolanaTxKind::Refund, instruction(&manager, failing), deadline()).await.unwrap();
+        cluster.lock().unwrap().statuses.insert(signature.clone(), json!({
+            "slot": 121, "confirmationStatus": "confirmed", "err": { "InstructionError": [2, { "Custom": 6001 }] },
+        }));
+        manager.rebroadcast_pending().await.unwrap();
+        assert!(manager.active(failing, SolanaTxKind::Refund).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rebroadcast_settles_expires_and_resends_pending_transactions() {
+        let cluster = Arc::new(Mutex::new(Cluster { block_height: 100, ..Default::default() }));
+        cluster.lock().unwrap().nonces.insert(nonce_account(1), (authority(), [11; 32]));
+        let (manager, rpc) = manager(&cluster, &[1]).await;
+
+        let durable = manager.submit([1; 32], SolanaTxKind::Refund, instruction(&manager, [1; 32]), deadline()).await.unwrap();
+        let confirming = manager.submit([2; 32], SolanaTxKind::RecordProof, instruction(&manager, [2; 32]), deadline()).await.unwrap();
+        let expiring = manager.submit([3; 32], SolanaTxKind::RecordProof, instruction(&manager, [3; 32]), deadline()).await.unwrap();
+        assert_eq!(rpc.calls("sendTransaction").len(), 3);
+
+        // Nothing has landed and nothing expired: all are sent again
+        manager.rebroadcast_pending().await.unwrap();
+        assert_eq!(rpc.calls("sendTransaction").len(), 6);
+
+        // One confirms; the blockhash of the others is past its last valid
+        // height, which only expires the one that carries it
+        {
+            let mut cluster = cluster.lock().unwrap();
+            cluster.statuses.insert(confirming.clone(), json!({ "slot": 130, "confirmationStatus": "confirmed", "err": null }));
+            cluster.block_height = 251;
+        }
+        manager.rebroadcast_pending().await.unwrap();
+        assert_eq!(rpc.calls("sendTransaction").len(), 7);
+        let states: Vec<(String, SolanaTxState)> = sqlx::query("SELECT signature, state FROM solana_transactions ORDER BY id")
+            .fetch_all(&manager.db)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| (row.get("signature"), SolanaTxState::parse(row.get("state")).unwrap()))
+            .collect();
+        assert_eq!(states, vec![
+            (durable.clone(), SolanaTxState::Pending),
+            (confirming, SolanaTxState::Confirmed),
+            (expiring, SolanaTxState::Expired),
+        ]);
+
+        // The durable one expires once its nonce advances without it
+        cluster.lock().unwrap().nonces.insert(nonce_account(1), (authority(), [21; 32]));
+        manager.rebroadcast_pending().await.unwrap();
+        assert!(manager.pending().await.unwrap().is_empty());
+        assert!(manager.active([1; 32], SolanaTxKind::Refund).await.unwrap().is_none());
+        assert_eq!(rpc.calls("sendTransaction").len(), 7);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::clients::solana::OnchainSwapInfo;
+use crate::clients::solana_program::{encode_pubkey, Pubkey};
+use crate::swap_engine::{AcceptRequest, Direction, SwapTrade};
+
+/// Earliest on-chain expiry the program accepts (`InvalidExpiry` below this).
+pub const MIN_SWAP_EXPIRY_HOURS: i64 = 24;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AcceptError {
+    #[error("Accept request does not match the quote: {field}")]
+    QuoteMismatch { field: &'static str },
+
+    #[error("Swap account not found on-chain for swap {0}")]
+    SwapNotFound(String),
+
+    #[error("Swap account is only {depth} slots deep, {required} required")]
+    NotFinal { depth: u64, required: u64 },
+
+    #[error("On-chain {field} does not match the quote: expected {expected}, found {actual}")]
+    OnchainMismatch {
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("On-chain swap is already redeemed or refunded")]
+    AlreadyFinalized,
+
+    #[error("On-chain expiry {expiry} is outside the accepted window [{min}, {max}]")]
+    ExpiryOutOfRange { expiry: i64, min: i64, max: i64 },
+
+    #[error("Swap vault holds {mint}, expected USDC ({expected})")]
+    WrongMint { mint: String, expected: String },
+
+    #[error("Swap vault is not owned by the swap account")]
+    WrongVaultOwner,
+
+    #[error("Swap vault holds {actual} USDC base units, expected at least {expected}")]
+    Underfunded { expected: u64, actual: u64 },
+
+    #[error("Bob's collateral is not locked in the swap")]
+    CollateralNotLocked,
+
+    /// The program lets any signer take an XMR→USDC vault with
+    /// `redeem_usdc_alice`, which checks no signature, so Bob never funds
+    /// one.
+    #[error("XMR→USDC swaps are not offered: the program cannot hold Bob's USDC escrow safely")]
+    EscrowUnsupported,
+}
+
+/// The parts of the accepted terms that the daemon itself decides rather
+/// than reading from the request.
+pub struct SwapExpectations<'a> {
+    pub bob: &'a str,
+    pub usdc_mint: &'a str,
+    pub destination: &'a str,
+    pub now: i64,
+    pub max_expiry_hours: i64,
+}
+
+/// Check Alice's `AcceptRequest` against the quote she was issued.
+pub fn verify_accept_request(quote: &SwapTrade, request: &AcceptRequest) -> Result<(), AcceptError> {
+    let mismatch = |field| Err(AcceptError::QuoteMismatch { field });
+
+    if request.swap_id != quote.swap_id {
+        return mismatch("swap_id");
+    }
+    if request.direction != quote.direction {
+        return mismatch("direction");
+    }
+    if request.usdc_amount != quote.usdc_amount {
+        return mismatch("usdc_amount");
+    }
+    if request.xmr_amount != quote.xmr_amount {
+        return mismatch("xmr_amount");
+    }
+    if request.secret_hash != quote.secret_hash {
+        return mismatch("secret_hash");
+    }
+    Ok(())
+}
+
+/// Check that a swap expiring at `expiry` leaves the program's minimum
+/// time to settle, and no more than `max_expiry_hours`.
+pub fn verify_expiry(expiry: i64, now: i64, max_expiry_hours: i64) -> Result<(), AcceptError> {
+    let min = now + MIN_SWAP_EXPIRY_HOURS * 3600;
+    let max = now + max_expiry_hours * 3600;
+    if expiry < min || expiry > max {
+        return Err(AcceptError::ExpiryOutOfRange { expiry, min, max });
+    }
+    Ok(())
+}
+
+/// Whether the swap's vault escrows at least `usdc_amount` of USDC for the
+/// swap: it exists, holds `usdc_mint` and is owned by the `Swap` PDA.
+pub fn usdc_locked(onchain: &OnchainSwapInfo, usdc_mint: &str, usdc_amount: u64) -> bool {
+    onchain.vault.as_ref().is_some_and(|vault| {
+        vault.mint == usdc_mint && vault.owner == onchain.address && vault.amount >= usdc_amount
+    })
+}
+
+/// Whether the daemon refunds the swap's escrow to Bob after expiry. The
+/// program pays a refund to whoever signs it, so Bob only refunds, or
+/// pre-signs the refund of, an unsettled XMR→USDC escrow of his own and
+/// never Alice's USDC.
+pub fn refunds_to_bob(onchain: &OnchainSwapInfo, bob: &Pubkey) -> bool {
+    onchain.swap.direction == Direction::XmrToUsdc
+        && onchain.swap.bob == *bob
+        && !onchain.swap.is_redeemed
+        && !onchain.swap.is_refunded
+}
+
+/// Check the on-chain `Swap` PDA and its vault against the accepted terms.
+/// Nothing about the swap may advance unless this passes.
+pub fn verify_onchain_swap(
+    request: &AcceptRequest,
+    onchain: &OnchainSwapInfo,
+    expected: &SwapExpectations<'_>,
+) -> Result<(), AcceptError> {
+    let swap = &onchain.swap;
+
+    fn check<T: PartialEq + std::fmt::Display>(field: &'static str, expected: T, actual: T) -> Result<(), AcceptError> {
+        if expected == actual {
+            return Ok(());
+        }
+        Err(AcceptError::OnchainMismatch {
+            field,
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        })
+    }
+
+    check("swap_id", hex::encode(request.swap_id), hex::encode(swap.swap_id))?;
+    check("direction", request.direction.as_str(), swap.direction.as_str())?;
+    check("usdc_amount", request.usdc_amount, swap.usdc_amount)?;
+    check("xmr_amount", request.xmr_amount, swap.xmr_amount)?;
+    check("secret_hash", hex::encode(request.secret_hash), hex::encode(swap.secret_hash))?;
+    check("expiry", request.expiry, swap.expiry)?;
+    check("alice", request.counterparty_pubkey.clone(), encode_pubkey(&swap.alice))?;
+    check("bob", expected.bob.to_string(), encode_pubkey(&swap.bob))?;
+    if request.direction == Direction::XmrToUsdc {
+        check("alice_solana", expected.destination.to_string(), encode_pubkey(&swap.alice_solana))?;
+    }
+
+    if swap.is_redeemed || swap.is_refunded {
+        return Err(AcceptError::AlreadyFinalized);
+    }
+
+    verify_expiry(swap.expiry, expected.now, expected.max_expiry_hours)?;
+
+    // Alice's USDC and Bob's collateral are escrowed when a USDC→XMR swap is
+    // created; an XMR→USDC swap has no vault until Bob funds it.
+    match (&onchain.vault, request.direction) {
+        (Some(vault), _) => {
+            if vault.mint != expected.usdc_mint {
+                return Err(AcceptError::WrongMint {
+                    mint: vault.mint.clone(),
+                    expected: expected.usdc_mint.to_string(),
+                });
+            }
+            if vault.owner != onchain.address {
+                return Err(AcceptError::WrongVaultOwner);
+            }
+            if request.direction == Direction::UsdcToXmr && vault.amount < request.usdc_amount {
+                return Err(AcceptError::Underfunded { expected: request.usdc_amount, actual: vault.amount });
+            }
+        }
+        (None, Direction::UsdcToXmr) => {
+            return Err(AcceptError::Underfunded { expected: request.usdc_amount, actual: 0 });
+        }
+        (None, Direction::XmrToUsdc) => {}
+    }
+
+    if request.direction == Direction::UsdcToXmr && !swap.bob_collateral_locked {
+        return Err(AcceptError::CollateralNotLocked);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::solana::VaultInfo;
+    use crate::clients::solana_program::SwapAccount;
+
+    const MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn onchain(vault: Option<VaultInfo>) -> OnchainSwapInfo {
+        OnchainSwapInfo {
+            address: "SwapPda".to_string(),
+            slot: 10,
+            swap: SwapAccount {
+                direction: Direction::XmrToUsdc,
+                swap_id: [1; 32],
+                alice: [2; 32],
+                bob: [3; 32],
+                secret_hash: [4; 32],
+                expiry: 0,
+                relayer_fee: 0,
+                is_redeemed: false,
+                is_refunded: false,
+                usdc_amount: 150_000_000,
+                xmr_amount: 1_000_000_000_000,
+                monero_sub_address: [0; 64],
+                monero_lock_txid: [0; 32],
+                alice_solana: [2; 32],
+                bump: 255,
+                vtc_opened: false,
+                bob_collateral_locked: false,
+                alice_collateral_locked: false,
+                bounty_claimed: false,
+            },
+            vault,
+        }
+    }
+
+    fn vault(mint: &str, owner: &str, amount: u64) -> Option<VaultInfo> {
+        Some(VaultInfo {
+            address: "Vault".to_string(),
+            mint: mint.to_string(),
+            owner: owner.to_string(),
+            amount,
+        })
+    }
+
+    #[test]
+    fn usdc_is_locked_only_in_a_funded_usdc_vault_the_swap_owns() {
+        assert!(usdc_locked(&onchain(vault(MINT, "SwapPda", 150_000_000)), MINT, 150_000_000));
+        assert!(usdc_locked(&onchain(vault(MINT, "SwapPda", 150_000_001)), MINT, 150_000_000));
+
+        // The account alone, with its amount field, is not a lock
+        assert!(!usdc_locked(&onchain(None), MINT, 150_000_000));
+        assert!(!usdc_locked(&onchain(vault(MINT, "SwapPda", 149_999_999)), MINT, 150_000_000));
+        assert!(!usdc_locked(&onchain(vault("OtherMint", "SwapPda", 150_000_000)), MINT, 150_000_000));
+        assert!(!usdc_locked(&onchain(vault(MINT, "Bob", 150_000_000)), MINT, 150_000_000));
+    }
+
+    #[test]
+    fn bob_refunds_only_his_own_unsettled_xmr_to_usdc_escrow() {
+        let escrow = onchain(vault(MINT, "SwapPda", 150_000_000));
+        assert!(refunds_to_bob(&escrow, &[3; 32]));
+        assert!(!refunds_to_bob(&escrow, &[2; 32]));
+
+        // Alice's USDC would be paid to Bob if he signed its refund
+        let mut alices = escrow.clone();
+        alices.swap.direction = Direction::UsdcToXmr;
+        assert!(!refunds_to_bob(&alices, &[3; 32]));
+
+        let mut settled = escrow.clone();
+        settled.swap.is_redeemed = true;
+        assert!(!refunds_to_bob(&settled, &[3; 32]));
+        settled.swap.is_redeemed = false;
+        settled.swap.is_refunded = true;
+        assert!(!refunds_to_bob(&settled, &[3; 32]));
+    }
+
+    #[test]
+    fn expiry_must_fall_in_the_settlement_window() {
+        let now = 1_700_000_000;
+        assert!(verify_expiry(now + 24 * 3600, now, 48).is_ok());
+        assert!(verify_expiry(now + 48 * 3600, now, 48).is_ok());
+        assert!(matches!(
+            verify_expiry(now + 24 * 3600 - 1, now, 48),
+            Err(AcceptError::ExpiryOutOfRange { .. })
+        ));
+        assert!(matches!(
+            verify_expiry(now + 48 * 3600 + 1, now, 48),
+            Err(AcceptError::ExpiryOutOfRange { .. })
+        ));
+    }
+}
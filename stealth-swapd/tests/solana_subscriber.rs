@@ -0,0 +1,218 @@
+//! Subscriber reconnect and backfill against a local validator.
+//!
+//! Ignored by default. Start a validator and run:
+//!
+//! ```text
+//! solana-test-validator --reset
+//! cargo test --test solana_subscriber -- --ignored
+//! ```
+//!
+//! `STEALTH_SWAPD_TEST_RPC_URL` and `STEALTH_SWAPD_TEST_WS_URL` override the
+//! validator endpoints and `SOLANA_KEYPAIR` the fee payer, which is funded by
+//! airdrop. The program need not be deployed: the test sends transfers that
+//! mention the program id, which `logsSubscribe` and
+//! `getSignaturesForAddress` both match on.
+
+use stealth_swapd::chain::{ProgramEvent, SolanaSubscriber};
+use stealth_swapd::clients::solana_program::{decode_pubkey, DEFAULT_PROGRAM_ID};
+use stealth_swapd::clients::solana_tx::{sign_transaction, AccountMeta, Instruction, SYSTEM_PROGRAM_ID};
+use stealth_swapd::clients::SolanaClient;
+use stealth_swapd::config::SolanaConfig;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+/// TCP proxy in front of the validator's websocket that can cut every open
+/// connection and refuse new ones until restored.
+struct Proxy {
+    port: u16,
+    up: Arc<AtomicBool>,
+    accepted: Arc<AtomicUsize>,
+    connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Proxy {
+    async fn start(upstream: String) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Self {
+            port: listener.local_addr().unwrap().port(),
+            up: Arc::new(AtomicBool::new(true)),
+            accepted: Arc::new(AtomicUsize::new(0)),
+            connections: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let (up, accepted, connections) = (proxy.up.clone(), proxy.accepted.clone(), proxy.connections.clone());
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                if !up.load(Ordering::SeqCst) {
+                    continue;
+                }
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let upstream = upstream.clone();
+                let connection = tokio::spawn(async move {
+                    if let Ok(mut server) = TcpStream::connect(upstream).await {
+                        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                    }
+                });
+                connections.lock().unwrap().push(connection);
+            }
+        });
+        proxy
+    }
+
+    fn cut(&self) {
+        self.up.store(false, Ordering::SeqCst);
+        for connection in self.connections.lock().unwrap().drain(..) {
+            connection.abort();
+        }
+    }
+
+    fn restore(&self) {
+        self.up.store(true, Ordering::SeqCst);
+    }
+
+    fn accepted(&self) -> usize {
+        self.accepted.load(Ordering::SeqCst)
+    }
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    tokio::time::timeout(TIMEOUT, async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for {}", what));
+}
+
+async fn airdrop(rpc_url: &str, address: &str) {
+    let response: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "requestAirdrop",
+            "params": [address, 1_000_000_000u64],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(response["error"].is_null(), "airdrop failed: {}", response["error"]);
+}
+
+/// Send a small transfer that lists the program id as an extra account and
+/// wait for it to confirm. Returns its signature.
+async fn send_mentioning_program(client: &SolanaClient) -> String {
+    let mut data = 2u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&1_000u64.to_le_bytes());
+    let transfer = Instruction {
+        program_id: decode_pubkey(SYSTEM_PROGRAM_ID).unwrap(),
+        accounts: vec![
+            AccountMeta::writable(client.keypair().public_key(), true),
+            AccountMeta::writable([7; 32], false),
+            AccountMeta::readonly(client.program_id().unwrap(), false),
+        ],
+        data,
+    };
+
+    let (blockhash, _) = client.get_latest_blockhash().await.unwrap();
+    let signed = sign_transaction(client.keypair(), &[transfer], blockhash).unwrap();
+    client.send_transaction(&signed.bytes).await.unwrap();
+
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Some(status) = client.get_transaction_status(&signed.signature).await.unwrap() {
+                assert!(status.err.is_none(), "transfer failed: {:?}", status.err);
+                if matches!(status.confirmation_status.as_deref(), Some("confirmed" | "finalized")) {
+                    return;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("transfer did not confirm");
+    signed.signature
+}
+
+async fn expect_transaction(events: &mut mpsc::Receiver<ProgramEvent>, signature: &str) {
+    tokio::time::timeout(TIMEOUT, async {
+        while let Some(event) = events.recv().await {
+            if matches!(&event, ProgramEvent::Transaction(tx) if tx.signature == signature) {
+                return;
+            }
+        }
+        panic!("subscriber stopped");
+    })
+    .await
+    .unwrap_or_else(|_| panic!("no event for {}", signature));
+}
+
+#[tokio::test]
+#[ignore = "needs a local solana-test-validator"]
+async fn subscriber_backfills_transactions_missed_while_disconnected() {
+    let rpc_url = env_or("STEALTH_SWAPD_TEST_RPC_URL", "http://127.0.0.1:8899");
+    let ws_url = env_or("STEALTH_SWAPD_TEST_WS_URL", "ws://127.0.0.1:8900");
+    let keypair_path = std::env::var("SOLANA_KEYPAIR").map(PathBuf::from).unwrap_or_else(|_| {
+        PathBuf::from(std::env::var("HOME").unwrap()).join(".config/solana/id.json")
+    });
+
+    let upstream = ws_url.split_once("://").map_or(ws_url.as_str(), |(_, rest)| rest).trim_end_matches('/');
+    let proxy = Proxy::start(upstream.to_string()).await;
+
+    let client = SolanaClient::new(&SolanaConfig {
+        rpc_url: rpc_url.clone(),
+        keypair_path,
+        usdc_mint: SYSTEM_PROGRAM_ID.to_string(),
+        commitment: Some("confirmed".to_string()),
+        program_id: Some(DEFAULT_PROGRAM_ID.to_string()),
+        ws_url: Some(format!("ws://127.0.0.1:{}", proxy.port)),
+        nonce_accounts: None,
+        rebroadcast_seconds: None,
+        presign_key_env: None,
+        priority_fees: None,
+    })
+    .await
+    .unwrap();
+    airdrop(&rpc_url, &client.pubkey()).await;
+
+    let (subscriber, _handle) = SolanaSubscriber::new(client.clone()).unwrap();
+    let (sender, mut events) = mpsc::channel(64);
+    let subscriber = tokio::spawn(subscriber.run(sender));
+
+    // Live: delivered by logsSubscribe, which also moves the backfill cursor
+    wait_until("the first connection", || proxy.accepted() == 1).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let live = send_mentioning_program(&client).await;
+    expect_transaction(&mut events, &live).await;
+
+    // Missed while disconnected: delivered by the backfill on reconnect
+    proxy.cut();
+    let missed = send_mentioning_program(&client).await;
+    proxy.restore();
+    wait_until("a reconnect", || proxy.accepted() >= 2).await;
+    expect_transaction(&mut events, &missed).await;
+
+    // Live again after the reconnect
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let after = send_mentioning_program(&client).await;
+    expect_transaction(&mut events, &after).await;
+
+    drop(events);
+    subscriber.abort();
+}